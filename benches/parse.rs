@@ -0,0 +1,68 @@
+//! Parse times of a multi-megabyte IDL: serde_json parsing it the way it
+//! used to, the json parse alone, and the parse and lowering into the
+//! program model through a `Value` and straight from the bytes.
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use parse_idl::{frontend, parse_idl_value, Sighash};
+use serde_json::{json, Value};
+
+/// Instructions and types of the big IDL, each a renamed copy of those of
+/// the legacy fixture.
+const COPIES: usize = 2000;
+
+/// The legacy fixture with its instructions and types repeated, about the
+/// size of the largest IDLs in use.
+fn large_idl() -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/legacy.json");
+    let mut idl: Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    let renamed = |items: &Value, i: usize| -> Vec<Value> {
+        items
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                let mut item = item.clone();
+                let name = format!("{}{}", item["name"].as_str().unwrap(), i);
+                item["name"] = json!(name);
+                item
+            })
+            .collect()
+    };
+    let instructions = &idl["instructions"].clone();
+    let types = &idl["types"].clone();
+    idl["instructions"] = (0..COPIES).flat_map(|i| renamed(instructions, i)).collect();
+    idl["types"] = types
+        .as_array()
+        .unwrap()
+        .iter()
+        .cloned()
+        .chain((0..COPIES).flat_map(|i| renamed(types, i)))
+        .collect();
+    serde_json::to_vec_pretty(&idl).unwrap()
+}
+
+fn parse(c: &mut Criterion) {
+    let bytes = large_idl();
+    let path = Path::new("large.json");
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.sample_size(20);
+    group.bench_function("serde_json", |b| {
+        b.iter(|| serde_json::from_slice::<Value>(&bytes).unwrap())
+    });
+    group.bench_function("parse_idl_value", |b| {
+        b.iter(|| parse_idl_value(path, &bytes).unwrap())
+    });
+    group.bench_function("lower", |b| {
+        b.iter(|| frontend::lower(parse_idl_value(path, &bytes).unwrap()).unwrap())
+    });
+    group.bench_function("lower_slice", |b| {
+        b.iter(|| frontend::lower_slice(&bytes, &Sighash::default()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "Vau1t11111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 2] = [
+	([245, 7, 108, 117, 95, 196, 54, 217], "configure"),
+	([181, 184, 224, 203, 193, 29, 177, 224], "set_status"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Configure {
+	mode: Mode,
+	fee: FeeSchedule,
+	fallback: Option<Mode>,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SetStatus {
+	status: Status,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Mode {
+	Paused = 0,
+	Active = 1,
+	WithdrawOnly = 2,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum FeeSchedule {
+	Free,
+	Flat(u64),
+	Tiered {
+		threshold: u64,
+		bps: u16,
+	},
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Status {
+	Open = 0,
+	Closed = 1,
+	Liquidating = 2,
+	Settled = 3,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Mode::Paused as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Mode::Paused).unwrap(), [0]);
+		assert_eq!(Mode::Active as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Mode::Active).unwrap(), [1]);
+		assert_eq!(Mode::WithdrawOnly as u8, 2);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Mode::WithdrawOnly).unwrap(), [2]);
+		let bytes = borsh::BorshSerialize::try_to_vec(&FeeSchedule::Free).unwrap();
+		assert_eq!(bytes[0], 0);
+		let decoded: FeeSchedule = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&decoded).unwrap(), bytes);
+		let bytes = borsh::BorshSerialize::try_to_vec(&FeeSchedule::Flat(Default::default())).unwrap();
+		assert_eq!(bytes[0], 1);
+		let decoded: FeeSchedule = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&decoded).unwrap(), bytes);
+		let bytes = borsh::BorshSerialize::try_to_vec(&FeeSchedule::Tiered { threshold: Default::default(), bps: Default::default() }).unwrap();
+		assert_eq!(bytes[0], 2);
+		let decoded: FeeSchedule = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&decoded).unwrap(), bytes);
+		assert_eq!(Status::Open as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Status::Open).unwrap(), [0]);
+		assert_eq!(Status::Closed as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Status::Closed).unwrap(), [1]);
+		assert_eq!(Status::Liquidating as u8, 2);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Status::Liquidating).unwrap(), [2]);
+		assert_eq!(Status::Settled as u8, 3);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Status::Settled).unwrap(), [3]);
+	}
+}
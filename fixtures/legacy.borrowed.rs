@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
+
+fn take_bytes<'a>(data: &mut &'a [u8]) -> std::io::Result<&'a [u8]> {
+    let len = <u32 as borsh::BorshDeserialize>::deserialize(data)? as usize;
+    if data.len() < len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "unexpected end of data",
+        ));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+fn take_str<'a>(data: &mut &'a [u8]) -> std::io::Result<&'a str> {
+    std::str::from_utf8(take_bytes(data)?)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+/// Decoding of a value borrowing from the input buffer.
+pub trait DecodeRef<'a>: Sized {
+    /// Decodes a value from the start of `data` and advances it past the value.
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self>;
+}
+impl<'a> DecodeRef<'a> for &'a [u8] {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        take_bytes(data)
+    }
+}
+impl<'a> DecodeRef<'a> for &'a str {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        take_str(data)
+    }
+}
+impl<'a, T: DecodeRef<'a>> DecodeRef<'a> for Option<T> {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        match <u8 as borsh::BorshDeserialize>::deserialize(data)? {
+            0 => Ok(None),
+            _ => T::decode_ref(data).map(Some),
+        }
+    }
+}
+/// A borsh encoded `Vec` of borrowed elements, checked when decoded and
+/// decoded again one at a time as it is iterated, without allocating.
+pub struct RefSeq<'a, T> {
+    data: &'a [u8],
+    len: usize,
+    _item: std::marker::PhantomData<T>,
+}
+impl<'a, T> Clone for RefSeq<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for RefSeq<'a, T> {}
+impl<'a, T: DecodeRef<'a>> RefSeq<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> RefIter<'a, T> {
+        RefIter {
+            data: self.data,
+            remaining: self.len,
+            _item: std::marker::PhantomData,
+        }
+    }
+}
+impl<'a, T: DecodeRef<'a>> DecodeRef<'a> for RefSeq<'a, T> {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        let len = <u32 as borsh::BorshDeserialize>::deserialize(data)? as usize;
+        let start = *data;
+        for _ in 0..len {
+            T::decode_ref(data)?;
+        }
+        Ok(Self {
+            data: &start[..start.len() - data.len()],
+            len,
+            _item: std::marker::PhantomData,
+        })
+    }
+}
+impl<'a, T: DecodeRef<'a> + std::fmt::Debug> std::fmt::Debug for RefSeq<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+impl<'a, T: DecodeRef<'a>> IntoIterator for RefSeq<'a, T> {
+    type Item = T;
+    type IntoIter = RefIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+/// Iterator over the elements of a [`RefSeq`].
+pub struct RefIter<'a, T> {
+    data: &'a [u8],
+    remaining: usize,
+    _item: std::marker::PhantomData<T>,
+}
+impl<'a, T: DecodeRef<'a>> Iterator for RefIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(T::decode_ref(&mut self.data).expect("checked when the RefSeq was decoded"))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, T: DecodeRef<'a>> ExactSizeIterator for RefIter<'a, T> {}
+#[derive(Debug, Clone)]
+pub struct InitializePoolRef<'a> {
+	pub fee_rate: u16,
+	pub config: PoolConfigRef<'a>,
+}
+impl<'a> InitializePoolRef<'a> {
+	/// Decodes a value from the start of `data` and advances it past the value.
+	pub fn decode(data: &mut &'a [u8]) -> std::io::Result<Self> {
+		Ok(Self {
+			fee_rate: <u16 as borsh::BorshDeserialize>::deserialize(data)?,
+			config: DecodeRef::decode_ref(data)?,
+		})
+	}
+}
+impl<'a> DecodeRef<'a> for InitializePoolRef<'a> {
+	fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+		Self::decode(data)
+	}
+}
+#[derive(Debug, Clone)]
+pub struct PoolConfigRef<'a> {
+	pub max_amount: Option<u64>,
+	pub tags: RefSeq<'a, &'a str>,
+}
+impl<'a> PoolConfigRef<'a> {
+	/// Decodes a value from the start of `data` and advances it past the value.
+	pub fn decode(data: &mut &'a [u8]) -> std::io::Result<Self> {
+		Ok(Self {
+			max_amount: <Option<u64> as borsh::BorshDeserialize>::deserialize(data)?,
+			tags: DecodeRef::decode_ref(data)?,
+		})
+	}
+}
+impl<'a> DecodeRef<'a> for PoolConfigRef<'a> {
+	fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+		Self::decode(data)
+	}
+}
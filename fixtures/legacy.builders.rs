@@ -0,0 +1,488 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+impl InitializePool {
+	/// The args of `initializePool`.
+	pub fn new(fee_rate: u16, config: PoolConfig) -> Self {
+		Self {
+			fee_rate,
+			config,
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+impl Swap {
+	/// The args of `swap`.
+	pub fn new(amount_in: u64, min_out: u64, side: Side) -> Self {
+		Self {
+			amount_in,
+			min_out,
+			side,
+		}
+	}
+}
+
+/// Why a decoder, builder or RPC call of the bindings failed.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+	#[error(transparent)]
+	Accounts(#[from] AccountsError),
+}
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([187, 249, 99, 112, 228, 16, 100, 204, 142, 133, 1, 211, 212, 198, 216, 93, 138, 244, 73, 87, 131, 170, 226, 139, 10, 203, 195, 64, 0, 0, 0, 0]);
+
+/// Compute unit limit and price of a transaction, `None` leaves the
+/// defaults of the runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+	pub unit_limit: Option<u32>,
+	/// Priority fee, in micro-lamports per compute unit.
+	pub unit_price: Option<u64>,
+}
+impl ComputeBudget {
+	pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0]);
+
+	/// The `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions of
+	/// the budget.
+	pub fn instructions(&self) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut ixs = Vec::new();
+		if let Some(limit) = self.unit_limit {
+			let mut data = vec![2];
+			data.extend_from_slice(&limit.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		if let Some(price) = self.unit_price {
+			let mut data = vec![3];
+			data.extend_from_slice(&price.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		ixs
+	}
+
+	/// `ixs` preceded by the instructions of the budget.
+	pub fn prepend(
+		&self,
+		ixs: impl IntoIterator<Item = anchor_lang::solana_program::instruction::Instruction>,
+	) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut all = self.instructions();
+		all.extend(ixs);
+		all
+	}
+}
+
+/// Accounts of an instruction breaking the constraints of the IDL.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AccountsError {
+	/// `account` must be `expected`, the address the IDL declares.
+	#[error("account {account} of {instruction} must be {expected}, got {actual}")]
+	WrongAddress {
+		instruction: &'static str,
+		account: &'static str,
+		expected: Pubkey,
+		actual: Pubkey,
+	},
+	/// `account` signs the instruction, but `key` is none of the signers.
+	#[error("account {account} of {instruction} must sign, but {key} is not a signer")]
+	MissingSigner {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// `account` is written by the instruction, but `key` is read-only.
+	#[error("account {account} of {instruction} must be writable, but {key} is read-only")]
+	NotWritable {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// The instruction takes at least `expected` accounts.
+	#[error("{instruction} takes at least {expected} accounts, got {actual}")]
+	TooFewAccounts {
+		instruction: &'static str,
+		expected: usize,
+		actual: usize,
+	},
+}
+
+/// An account of an instruction, as the IDL declares it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSpec {
+	pub name: &'static str,
+	pub is_signer: bool,
+	pub is_writable: bool,
+	pub is_optional: bool,
+	/// The address the IDL declares the account must have.
+	pub address: Option<Pubkey>,
+}
+
+/// Checks the account `metas` of `instruction` against its `accounts`:
+/// their count, and that the signer and writable accounts are and those
+/// with a fixed address have it. The metas past the accounts are remaining
+/// accounts, which aren't checked. When `omitted` optional accounts may be
+/// left out, which shifts the accounts after them, so only those before
+/// the first optional account are checked; otherwise the program ID takes
+/// the place of an optional account left out.
+fn check_account_metas(
+	instruction: &'static str,
+	accounts: &[AccountSpec],
+	metas: &[anchor_lang::solana_program::instruction::AccountMeta],
+	omitted: bool,
+) -> std::result::Result<(), AccountsError> {
+	let expected = accounts.iter().filter(|a| !(omitted && a.is_optional)).count();
+	if metas.len() < expected {
+		return Err(AccountsError::TooFewAccounts {
+			instruction,
+			expected,
+			actual: metas.len(),
+		});
+	}
+	for (account, meta) in accounts.iter().zip(metas) {
+		if account.is_optional && omitted {
+			break;
+		}
+		if account.is_optional && meta.pubkey == PROGRAM_ID {
+			continue;
+		}
+		if let Some(expected) = account.address.filter(|key| *key != meta.pubkey) {
+			return Err(AccountsError::WrongAddress {
+				instruction,
+				account: account.name,
+				expected,
+				actual: meta.pubkey,
+			});
+		}
+		if account.is_signer && !meta.is_signer {
+			return Err(AccountsError::MissingSigner {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+		if account.is_writable && !meta.is_writable {
+			return Err(AccountsError::NotWritable {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+	}
+	Ok(())
+}
+/// Accounts of the `initializePool` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitializePoolAccounts {
+	pub pool: Pubkey,
+	pub authority: Pubkey,
+	pub system_program: Pubkey,
+}
+impl InitializePoolAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.authority, true),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.system_program, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("authority", Some(self.authority))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "initializePool",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "authority",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "systemProgram",
+			is_signer: false,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("initializePool", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `initializePool` instruction.
+pub fn initialize_pool_ix(accounts: &InitializePoolAccounts, args: &InitializePool) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [95, 180, 10, 172, 84, 174, 232, 40].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `swap` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapAccounts {
+	pub pool: Pubkey,
+	pub user: Pubkey,
+}
+impl SwapAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.user, true),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("user", Some(self.user))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "swap",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "user",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("swap", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `swap` instruction.
+pub fn swap_ix(accounts: &SwapAccounts, args: &Swap) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [248, 198, 158, 145, 225, 117, 135, 200].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `close` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseAccounts {
+	pub pool: Pubkey,
+}
+impl CloseAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		let _ = signers;
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("close", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `close` instruction.
+pub fn close_ix(accounts: &CloseAccounts) -> anchor_lang::solana_program::instruction::Instruction {
+	let data = [98, 165, 201, 177, 108, 65, 206, 96].to_vec();
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+impl PoolConfig {
+	/// A `PoolConfig`, the optional fields `None`.
+	pub fn new(tags: Vec<String>) -> Self {
+		Self {
+			max_amount: None,
+			tags,
+		}
+	}
+
+	/// Sets `max_amount`.
+	pub fn with_max_amount(mut self, max_amount: u64) -> Self {
+		self.max_amount = Some(max_amount);
+		self
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
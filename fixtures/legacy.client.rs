@@ -0,0 +1,908 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+impl InitializePool {
+	/// The args of `initializePool`.
+	pub fn new(fee_rate: u16, config: PoolConfig) -> Self {
+		Self {
+			fee_rate,
+			config,
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+impl Swap {
+	/// The args of `swap`.
+	pub fn new(amount_in: u64, min_out: u64, side: Side) -> Self {
+		Self {
+			amount_in,
+			min_out,
+			side,
+		}
+	}
+}
+
+/// Why a decoder, builder or RPC call of the bindings failed.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+	#[error(transparent)]
+	Accounts(#[from] AccountsError),
+	#[error("rpc request failed: {0}")]
+	RpcError(#[from] solana_client::client_error::ClientError),
+	#[error("transaction failed: {0}")]
+	Transaction(#[from] solana_sdk::transaction::TransactionError),
+	#[error("signing failed: {0}")]
+	Signer(#[from] solana_sdk::signer::SignerError),
+	#[error("v0 message not compiled: {0}")]
+	Compile(#[from] solana_sdk::message::CompileError),
+	#[error("transaction not confirmed after {attempts} attempts")]
+	NotConfirmed { attempts: usize },
+	#[error("{0} is not an address lookup table")]
+	NotLookupTable(Pubkey),
+}
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([187, 249, 99, 112, 228, 16, 100, 204, 142, 133, 1, 211, 212, 198, 216, 93, 138, 244, 73, 87, 131, 170, 226, 139, 10, 203, 195, 64, 0, 0, 0, 0]);
+
+/// Compute unit limit and price of a transaction, `None` leaves the
+/// defaults of the runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+	pub unit_limit: Option<u32>,
+	/// Priority fee, in micro-lamports per compute unit.
+	pub unit_price: Option<u64>,
+}
+impl ComputeBudget {
+	pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0]);
+
+	/// The `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions of
+	/// the budget.
+	pub fn instructions(&self) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut ixs = Vec::new();
+		if let Some(limit) = self.unit_limit {
+			let mut data = vec![2];
+			data.extend_from_slice(&limit.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		if let Some(price) = self.unit_price {
+			let mut data = vec![3];
+			data.extend_from_slice(&price.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		ixs
+	}
+
+	/// `ixs` preceded by the instructions of the budget.
+	pub fn prepend(
+		&self,
+		ixs: impl IntoIterator<Item = anchor_lang::solana_program::instruction::Instruction>,
+	) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut all = self.instructions();
+		all.extend(ixs);
+		all
+	}
+}
+
+/// Accounts of an instruction breaking the constraints of the IDL.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AccountsError {
+	/// `account` must be `expected`, the address the IDL declares.
+	#[error("account {account} of {instruction} must be {expected}, got {actual}")]
+	WrongAddress {
+		instruction: &'static str,
+		account: &'static str,
+		expected: Pubkey,
+		actual: Pubkey,
+	},
+	/// `account` signs the instruction, but `key` is none of the signers.
+	#[error("account {account} of {instruction} must sign, but {key} is not a signer")]
+	MissingSigner {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// `account` is written by the instruction, but `key` is read-only.
+	#[error("account {account} of {instruction} must be writable, but {key} is read-only")]
+	NotWritable {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// The instruction takes at least `expected` accounts.
+	#[error("{instruction} takes at least {expected} accounts, got {actual}")]
+	TooFewAccounts {
+		instruction: &'static str,
+		expected: usize,
+		actual: usize,
+	},
+}
+
+/// An account of an instruction, as the IDL declares it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSpec {
+	pub name: &'static str,
+	pub is_signer: bool,
+	pub is_writable: bool,
+	pub is_optional: bool,
+	/// The address the IDL declares the account must have.
+	pub address: Option<Pubkey>,
+}
+
+/// Checks the account `metas` of `instruction` against its `accounts`:
+/// their count, and that the signer and writable accounts are and those
+/// with a fixed address have it. The metas past the accounts are remaining
+/// accounts, which aren't checked. When `omitted` optional accounts may be
+/// left out, which shifts the accounts after them, so only those before
+/// the first optional account are checked; otherwise the program ID takes
+/// the place of an optional account left out.
+fn check_account_metas(
+	instruction: &'static str,
+	accounts: &[AccountSpec],
+	metas: &[anchor_lang::solana_program::instruction::AccountMeta],
+	omitted: bool,
+) -> std::result::Result<(), AccountsError> {
+	let expected = accounts.iter().filter(|a| !(omitted && a.is_optional)).count();
+	if metas.len() < expected {
+		return Err(AccountsError::TooFewAccounts {
+			instruction,
+			expected,
+			actual: metas.len(),
+		});
+	}
+	for (account, meta) in accounts.iter().zip(metas) {
+		if account.is_optional && omitted {
+			break;
+		}
+		if account.is_optional && meta.pubkey == PROGRAM_ID {
+			continue;
+		}
+		if let Some(expected) = account.address.filter(|key| *key != meta.pubkey) {
+			return Err(AccountsError::WrongAddress {
+				instruction,
+				account: account.name,
+				expected,
+				actual: meta.pubkey,
+			});
+		}
+		if account.is_signer && !meta.is_signer {
+			return Err(AccountsError::MissingSigner {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+		if account.is_writable && !meta.is_writable {
+			return Err(AccountsError::NotWritable {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+	}
+	Ok(())
+}
+/// Accounts of the `initializePool` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitializePoolAccounts {
+	pub pool: Pubkey,
+	pub authority: Pubkey,
+	pub system_program: Pubkey,
+}
+impl InitializePoolAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.authority, true),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.system_program, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("authority", Some(self.authority))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "initializePool",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "authority",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "systemProgram",
+			is_signer: false,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("initializePool", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `initializePool` instruction.
+pub fn initialize_pool_ix(accounts: &InitializePoolAccounts, args: &InitializePool) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [95, 180, 10, 172, 84, 174, 232, 40].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `swap` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapAccounts {
+	pub pool: Pubkey,
+	pub user: Pubkey,
+}
+impl SwapAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.user, true),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("user", Some(self.user))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "swap",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "user",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("swap", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `swap` instruction.
+pub fn swap_ix(accounts: &SwapAccounts, args: &Swap) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [248, 198, 158, 145, 225, 117, 135, 200].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `close` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseAccounts {
+	pub pool: Pubkey,
+}
+impl CloseAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		let _ = signers;
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("close", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `close` instruction.
+pub fn close_ix(accounts: &CloseAccounts) -> anchor_lang::solana_program::instruction::Instruction {
+	let data = [98, 165, 201, 177, 108, 65, 206, 96].to_vec();
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Fetches and parses the address lookup table at `address`.
+pub fn fetch_lookup_table(
+	rpc: &solana_client::rpc_client::RpcClient,
+	address: &Pubkey,
+) -> std::result::Result<solana_sdk::address_lookup_table_account::AddressLookupTableAccount, BindingsError> {
+	// the addresses follow the 56 byte metadata of the table
+	let data = rpc.get_account_data(address)?;
+	let addresses = data
+		.get(56..)
+		.filter(|a| a.len() % 32 == 0)
+		.ok_or(BindingsError::NotLookupTable(*address))?
+		.chunks(32)
+		.map(|a| Pubkey::new_from_array(a.try_into().expect("chunks of 32")))
+		.collect();
+	Ok(solana_sdk::address_lookup_table_account::AddressLookupTableAccount {
+		key: *address,
+		addresses,
+	})
+}
+/// A transaction of `ixs` paid by `payer` and signed by `signers`, any
+/// [`Signer`](solana_sdk::signer::Signer) such as a keypair, a hardware
+/// wallet or a remote signer. With lookup tables it is a v0 transaction
+/// referencing the accounts found in them through them, otherwise a legacy
+/// one.
+pub fn versioned_transaction(
+	payer: &Pubkey,
+	ixs: &[anchor_lang::solana_program::instruction::Instruction],
+	lookup_tables: &[solana_sdk::address_lookup_table_account::AddressLookupTableAccount],
+	signers: &[&dyn solana_sdk::signer::Signer],
+	blockhash: solana_sdk::hash::Hash,
+) -> std::result::Result<solana_sdk::transaction::VersionedTransaction, BindingsError> {
+	let message = if lookup_tables.is_empty() {
+		solana_sdk::message::VersionedMessage::Legacy(solana_sdk::message::Message::new_with_blockhash(ixs, Some(payer), &blockhash))
+	} else {
+		solana_sdk::message::VersionedMessage::V0(solana_sdk::message::v0::Message::try_compile(payer, ixs, lookup_tables, blockhash)?)
+	};
+	Ok(solana_sdk::transaction::VersionedTransaction::try_new(message, signers)?)
+}
+/// The accounts of `ixs` a lookup table can hold: all but the signers and
+/// the invoked programs, in order of first use.
+pub fn lookup_table_candidates(ixs: &[anchor_lang::solana_program::instruction::Instruction]) -> Vec<Pubkey> {
+	let excluded: std::collections::HashSet<Pubkey> = ixs
+		.iter()
+		.flat_map(|ix| {
+			ix.accounts
+				.iter()
+				.filter(|meta| meta.is_signer)
+				.map(|meta| meta.pubkey)
+				.chain([ix.program_id])
+		})
+		.collect();
+	let mut candidates = Vec::new();
+	for meta in ixs.iter().flat_map(|ix| ix.accounts.iter()) {
+		if !excluded.contains(&meta.pubkey) && !candidates.contains(&meta.pubkey) {
+			candidates.push(meta.pubkey);
+		}
+	}
+	candidates
+}
+/// How the client sends and confirms transactions.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+	/// Commitment of the RPC calls and of the confirmation.
+	pub commitment: solana_sdk::commitment_config::CommitmentConfig,
+	/// Compute budget instructions prepended to every transaction.
+	pub compute_budget: ComputeBudget,
+	/// Lookup tables making transactions v0 ones, see [`fetch_lookup_table`].
+	pub lookup_tables: Vec<solana_sdk::address_lookup_table_account::AddressLookupTableAccount>,
+	/// Times a transaction is sent again after it failed to send or to
+	/// confirm. Preflight and program errors are not retried.
+	pub max_retries: usize,
+	/// Wait before the first retry, doubled after every one.
+	pub retry_backoff: std::time::Duration,
+	/// Sign a retry with a fresh blockhash once the blockhash of the
+	/// previous attempt expired, as until then that transaction can still
+	/// land and a new one would run the instructions twice. Otherwise every
+	/// attempt resends the first transaction, which can't land twice but
+	/// never lands after its blockhash expired.
+	pub refresh_blockhash: bool,
+	/// How long an attempt waits for the confirmation.
+	pub confirm_timeout: std::time::Duration,
+	/// Interval of the signature status polls while confirming.
+	pub poll_interval: std::time::Duration,
+}
+impl Default for ClientConfig {
+	fn default() -> Self {
+		Self {
+			commitment: solana_sdk::commitment_config::CommitmentConfig::confirmed(),
+			compute_budget: ComputeBudget::default(),
+			lookup_tables: Vec::new(),
+			max_retries: 3,
+			retry_backoff: std::time::Duration::from_millis(500),
+			refresh_blockhash: true,
+			confirm_timeout: std::time::Duration::from_secs(60),
+			poll_interval: std::time::Duration::from_millis(500),
+		}
+	}
+}
+/// Sends the instructions of the program over RPC.
+pub struct DemoClient {
+	rpc: solana_client::rpc_client::RpcClient,
+	config: ClientConfig,
+}
+impl DemoClient {
+	pub fn new(url: impl ToString, config: ClientConfig) -> Self {
+		let rpc = solana_client::rpc_client::RpcClient::new_with_commitment(url.to_string(), config.commitment);
+		Self { rpc, config }
+	}
+
+	pub fn rpc(&self) -> &solana_client::rpc_client::RpcClient {
+		&self.rpc
+	}
+
+	pub fn config(&self) -> &ClientConfig {
+		&self.config
+	}
+
+	/// Sends `ixs` after the compute budget instructions, paid by the first
+	/// of `signers`, and waits for the confirmation, retrying as configured.
+	pub fn send(
+		&self,
+		ixs: &[anchor_lang::solana_program::instruction::Instruction],
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {
+		let payer = signers.first().ok_or(BindingsError::MissingAccount { name: "payer" })?.pubkey();
+		let ixs = self.config.compute_budget.prepend(ixs.iter().cloned());
+		let send_config = solana_client::rpc_config::RpcSendTransactionConfig {
+			preflight_commitment: Some(self.config.commitment.commitment),
+			max_retries: Some(0),
+			..Default::default()
+		};
+		let mut backoff = self.config.retry_backoff;
+		// the last transaction sent and its blockhash
+		let mut sent: Option<(solana_sdk::transaction::VersionedTransaction, solana_sdk::hash::Hash)> = None;
+		for attempt in 0..=self.config.max_retries {
+			if attempt > 0 {
+				std::thread::sleep(backoff);
+				backoff *= 2;
+			}
+			if let Some((tx, blockhash)) = sent.take().filter(|_| self.config.refresh_blockhash) {
+				// the previous transaction can land until its blockhash expires
+				let signature = tx.signatures[0];
+				loop {
+					let valid = self.rpc.is_blockhash_valid(&blockhash, self.config.commitment)?;
+					match self.rpc.get_signature_status_with_commitment(&signature, self.config.commitment)? {
+						Some(Ok(())) => return Ok(signature),
+						Some(Err(e)) => return Err(e.into()),
+						None if valid => std::thread::sleep(self.config.poll_interval),
+						None => break,
+					}
+				}
+			}
+			if sent.is_none() {
+				let (blockhash, _) =
+					self.rpc.get_latest_blockhash_with_commitment(self.config.commitment)?;
+				let tx = versioned_transaction(&payer, &ixs, &self.config.lookup_tables, signers, blockhash)?;
+				sent = Some((tx, blockhash));
+			}
+			let (tx, _) = sent.as_ref().expect("built above");
+			let signature = match self.rpc.send_transaction_with_config(tx, send_config) {
+				Ok(signature) => signature,
+				// preflight and program errors fail the same way again
+				Err(e)
+					if attempt < self.config.max_retries
+						&& matches!(
+							e.get_transaction_error(),
+							None | Some(solana_sdk::transaction::TransactionError::BlockhashNotFound)
+						) =>
+				{
+					continue;
+				}
+				Err(e) => return Err(e.into()),
+			};
+			let started = std::time::Instant::now();
+			while started.elapsed() < self.config.confirm_timeout {
+				let status = self.rpc.get_signature_status_with_commitment(&signature, self.config.commitment)?;
+				match status {
+					Some(Ok(())) => return Ok(signature),
+					Some(Err(e)) => return Err(e.into()),
+					None => std::thread::sleep(self.config.poll_interval),
+				}
+			}
+		}
+		Err(BindingsError::NotConfirmed {
+			attempts: self.config.max_retries + 1,
+		})
+	}
+
+	/// Simulates `ixs` after the compute budget instructions, paid by the
+	/// first of `signers`, without sending them.
+	pub fn simulate(
+		&self,
+		ixs: &[anchor_lang::solana_program::instruction::Instruction],
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<Simulation, BindingsError> {
+		let payer = signers.first().ok_or(BindingsError::MissingAccount { name: "payer" })?.pubkey();
+		let ixs = self.config.compute_budget.prepend(ixs.iter().cloned());
+		let blockhash = self.rpc.get_latest_blockhash()?;
+		let tx = versioned_transaction(&payer, &ixs, &self.config.lookup_tables, signers, blockhash)?;
+		let simulate_config = solana_client::rpc_config::RpcSimulateTransactionConfig {
+			commitment: Some(self.config.commitment),
+			..Default::default()
+		};
+		let result = self.rpc.simulate_transaction_with_config(&tx, simulate_config)?.value;
+		let logs = result.logs.unwrap_or_default();
+		Ok(Simulation {
+			error: match &result.err {
+				// errors of the instructions of the program only, not of those
+				// it invoked
+				Some(solana_sdk::transaction::TransactionError::InstructionError(
+					index,
+					solana_sdk::instruction::InstructionError::Custom(code),
+				)) if ixs.get(*index as usize).map(|ix| ix.program_id) == Some(PROGRAM_ID) => ErrorCode::from_code(*code),
+				_ => None,
+			},
+			units_consumed: result.units_consumed,
+			logs,
+			err: result.err,
+		})
+	}
+
+	/// Sends `initializePool`, paid by the first of `signers`.
+	pub fn send_initialize_pool(
+		&self,
+		accounts: &InitializePoolAccounts, args: &InitializePool,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {
+		accounts.validate()?;
+		accounts.check_signers(&signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>())?;
+		let ix = initialize_pool_ix(accounts, args);
+		InitializePoolAccounts::check_metas(&ix.accounts)?;
+		self.send(&[ix], signers)
+	}
+
+	/// Simulates `initializePool`, paid by the first of `signers`.
+	pub fn simulate_initialize_pool(
+		&self,
+		accounts: &InitializePoolAccounts, args: &InitializePool,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<Simulation, BindingsError> {
+		accounts.validate()?;
+		let ix = initialize_pool_ix(accounts, args);
+		InitializePoolAccounts::check_metas(&ix.accounts)?;
+		self.simulate(&[ix], signers)
+	}
+
+	/// Sends `swap`, paid by the first of `signers`.
+	pub fn send_swap(
+		&self,
+		accounts: &SwapAccounts, args: &Swap,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {
+		accounts.validate()?;
+		accounts.check_signers(&signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>())?;
+		let ix = swap_ix(accounts, args);
+		SwapAccounts::check_metas(&ix.accounts)?;
+		self.send(&[ix], signers)
+	}
+
+	/// Simulates `swap`, paid by the first of `signers`.
+	pub fn simulate_swap(
+		&self,
+		accounts: &SwapAccounts, args: &Swap,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<Simulation, BindingsError> {
+		accounts.validate()?;
+		let ix = swap_ix(accounts, args);
+		SwapAccounts::check_metas(&ix.accounts)?;
+		self.simulate(&[ix], signers)
+	}
+
+	/// Sends `close`, paid by the first of `signers`.
+	pub fn send_close(
+		&self,
+		accounts: &CloseAccounts,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {
+		accounts.validate()?;
+		accounts.check_signers(&signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>())?;
+		let ix = close_ix(accounts);
+		CloseAccounts::check_metas(&ix.accounts)?;
+		self.send(&[ix], signers)
+	}
+
+	/// Simulates `close`, paid by the first of `signers`.
+	pub fn simulate_close(
+		&self,
+		accounts: &CloseAccounts,
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<Simulation, BindingsError> {
+		accounts.validate()?;
+		let ix = close_ix(accounts);
+		CloseAccounts::check_metas(&ix.accounts)?;
+		self.simulate(&[ix], signers)
+	}
+}
+/// Outcome of a simulation by the client.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+	/// Compute units the transaction consumed, if the node reports them.
+	pub units_consumed: Option<u64>,
+	pub logs: Vec<String>,
+	/// Why the transaction failed, if it did.
+	pub err: Option<solana_sdk::transaction::TransactionError>,
+	/// The error of the program the transaction failed with, if any.
+	pub error: Option<ErrorCode>,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Pool {
+	authority: Pubkey,
+	fee_rate: u16,
+	reserves: [u64; 2],
+	config: PoolConfig,
+}
+impl Pool {
+	/// A `Pool`.
+	pub fn new(authority: Pubkey, fee_rate: u16, reserves: [u64; 2], config: PoolConfig) -> Self {
+		Self {
+			authority,
+			fee_rate,
+			reserves,
+			config,
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+impl PoolConfig {
+	/// A `PoolConfig`, the optional fields `None`.
+	pub fn new(tags: Vec<String>) -> Self {
+		Self {
+			max_amount: None,
+			tags,
+		}
+	}
+
+	/// Sets `max_amount`.
+	pub fn with_max_amount(mut self, max_amount: u64) -> Self {
+		self.max_amount = Some(max_amount);
+		self
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
+
+/// Decodes a `T` from the data of an account, which starts with its
+/// `discriminator` and holds `size` bytes at least if `T` has a fixed size.
+fn decode_account<T: borsh::BorshDeserialize>(
+	data: &[u8],
+	discriminator: [u8; 8],
+	size: Option<usize>,
+) -> std::result::Result<T, BindingsError> {
+	if data.len() < 8 {
+		return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+	}
+	let (found, mut fields) = data.split_at(8);
+	if found != discriminator {
+		return Err(BindingsError::InvalidDiscriminator(found.try_into().expect("split at 8")));
+	}
+	if let Some(expected) = size.filter(|size| data.len() < *size) {
+		return Err(BindingsError::TruncatedData { expected, got: data.len() });
+	}
+	Ok(T::deserialize(&mut fields)?)
+}
+
+impl Pool {
+	/// Decodes the `Pool` account from its data, discriminator included.
+	pub fn from_account_data(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		decode_account(data, [241, 154, 109, 4, 17, 177, 109, 188], None)
+	}
+}
+
+/// Fetches the `Pool` accounts of the program matching `extra_filters`,
+/// decoded, through `getProgramAccounts` filtered by their discriminator.
+pub fn fetch_all_pools(
+	rpc: &solana_client::rpc_client::RpcClient,
+	extra_filters: Vec<solana_client::rpc_filter::RpcFilterType>,
+) -> std::result::Result<Vec<(Pubkey, Pool)>, BindingsError> {
+	let mut filters = vec![
+		solana_client::rpc_filter::RpcFilterType::Memcmp(solana_client::rpc_filter::Memcmp::new_raw_bytes(0, vec![241, 154, 109, 4, 17, 177, 109, 188])),
+	];
+	filters.extend(extra_filters);
+	let config = solana_client::rpc_config::RpcProgramAccountsConfig {
+		filters: Some(filters),
+		account_config: solana_client::rpc_config::RpcAccountInfoConfig {
+			encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+			..Default::default()
+		},
+		..Default::default()
+	};
+	rpc.get_program_accounts_with_config(&PROGRAM_ID, config)?
+		.into_iter()
+		.map(|(address, account)| {
+			let decoded = Pool::from_account_data(&account.data)?;
+			Ok((address, decoded))
+		})
+		.collect()
+}
+
+/// `getProgramAccounts` filters on the fields of the `Pool` accounts.
+pub struct PoolFilters;
+impl PoolFilters {
+	/// The accounts whose `authority` is `value`, compared at byte 8.
+	pub fn authority(value: &Pubkey) -> solana_client::rpc_filter::RpcFilterType {
+		Self::memcmp(8, value)
+	}
+
+	/// The accounts whose `feeRate` is `value`, compared at byte 40.
+	pub fn fee_rate(value: &u16) -> solana_client::rpc_filter::RpcFilterType {
+		Self::memcmp(40, value)
+	}
+
+	/// The accounts whose `reserves` is `value`, compared at byte 42.
+	pub fn reserves(value: &[u64; 2]) -> solana_client::rpc_filter::RpcFilterType {
+		Self::memcmp(42, value)
+	}
+
+	/// The accounts whose `config` is `value`, compared at byte 58.
+	pub fn config(value: &PoolConfig) -> solana_client::rpc_filter::RpcFilterType {
+		Self::memcmp(58, value)
+	}
+
+	fn memcmp(offset: usize, value: &impl borsh::BorshSerialize) -> solana_client::rpc_filter::RpcFilterType {
+		let bytes = value.try_to_vec().expect("serializing into a Vec");
+		solana_client::rpc_filter::RpcFilterType::Memcmp(solana_client::rpc_filter::Memcmp::new_raw_bytes(offset, bytes))
+	}
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use solana_program::pubkey::Pubkey;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+
+/// Why a decoder, builder or RPC call of the bindings failed.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+
+/// Instruction discriminator of the self-invocation `emit_cpi!` carries an
+/// event in, followed by the event's own discriminator and fields.
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// An event with the instruction of the transaction that emitted it.
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions the top-level instruction invoked of
+    /// the instruction executing when the event was emitted, `None` for the
+    /// top-level instruction itself.
+    pub inner_index: Option<usize>,
+    pub event: ProgramEvent,
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes().take_while(|c| *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = ((acc << 6) | v as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+const PROGRAM_ADDRESS: &str = "DemoPoo1111111111111111111111111111111111111";
+
+/// The events of the program.
+#[derive(Debug, Clone)]
+pub enum ProgramEvent {
+	SwapEvent(SwapEvent),
+}
+impl ProgramEvent {
+	/// Decodes an event from its discriminator and fields, `None` if it
+	/// fails, see [`Self::try_decode`].
+	pub fn decode(data: &[u8]) -> Option<Self> {
+		Self::try_decode(data).ok()
+	}
+
+	/// Decodes an event from its discriminator and fields.
+	pub fn try_decode(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		if data.len() < 8 {
+			return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+		}
+		let (discriminator, mut data) = data.split_at(8);
+		let discriminator: [u8; 8] = discriminator.try_into().expect("split at 8");
+		match discriminator {
+			[64, 198, 205, 232, 38, 8, 113, 226] => Ok(Self::SwapEvent(borsh::BorshDeserialize::deserialize(&mut data)?)),
+			_ => Err(BindingsError::InvalidDiscriminator(discriminator)),
+		}
+	}
+
+	/// Decodes the event carried by an inner instruction of `program_id`,
+	/// if it is an `emit_cpi!` self-invocation of the program.
+	pub fn from_inner_instruction(program_id: &str, data: &[u8]) -> Option<Self> {
+		if program_id != PROGRAM_ADDRESS {
+			return None;
+		}
+		Self::decode(data.strip_prefix(&EVENT_IX_TAG)?)
+	}
+
+	/// Events the program logged with `emit!`, from the log messages of a
+	/// transaction, with the instruction executing when each was logged.
+	/// Nothing after a truncated log can be attributed.
+	pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<EmittedEvent> {
+		let mut events = Vec::new();
+		// (program, inner index) of every instruction being executed
+		let mut stack: Vec<(&str, Option<usize>)> = Vec::new();
+		let mut index = None;
+		let mut invoked = 0;
+		for line in logs.iter().map(AsRef::as_ref) {
+			if line == "Log truncated" {
+				break;
+			}
+			let Some(rest) = line.strip_prefix("Program ") else {
+				continue;
+			};
+			if let Some(data) = rest.strip_prefix("data: ") {
+				let (Some(index), Some((program, inner_index))) = (index, stack.last()) else {
+					continue;
+				};
+				if *program != PROGRAM_ADDRESS {
+					continue;
+				}
+				// `sol_log_data` logs each slice separately
+				let data: Option<Vec<Vec<u8>>> = data.split_whitespace().map(decode_base64).collect();
+				if let Some(event) = data.and_then(|data| Self::decode(&data.concat())) {
+					events.push(EmittedEvent {
+						index,
+						inner_index: *inner_index,
+						event,
+					});
+				}
+				continue;
+			}
+			let Some((program, status)) = rest.split_once(' ') else {
+				continue;
+			};
+			if status == "invoke [1]" {
+				index = Some(index.map_or(0, |i: usize| i + 1));
+				invoked = 0;
+				stack = vec![(program, None)];
+			} else if status.starts_with("invoke [") {
+				stack.push((program, Some(invoked)));
+				invoked += 1;
+			} else if status == "success" || status.starts_with("failed") {
+				stack.pop();
+			}
+		}
+		events
+	}
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
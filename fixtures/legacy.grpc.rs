@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+
+/// Why a decoder, builder or RPC call of the bindings failed.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+}
+
+impl From<&InitializePool> for proto::InitializePool {
+	fn from(value: &InitializePool) -> Self {
+		Self {
+			fee_rate: value.fee_rate as u32,
+			config: borsh::BorshSerialize::try_to_vec(&value.config).expect("writing to a Vec can't fail"),
+		}
+	}
+}
+
+impl From<&Swap> for proto::Swap {
+	fn from(value: &Swap) -> Self {
+		Self {
+			amount_in: value.amount_in,
+			min_out: value.min_out,
+			side: borsh::BorshSerialize::try_to_vec(&value.side).expect("writing to a Vec can't fail"),
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+
+/// Instruction discriminator of the self-invocation `emit_cpi!` carries an
+/// event in, followed by the event's own discriminator and fields.
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// An event with the instruction of the transaction that emitted it.
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions the top-level instruction invoked of
+    /// the instruction executing when the event was emitted, `None` for the
+    /// top-level instruction itself.
+    pub inner_index: Option<usize>,
+    pub event: ProgramEvent,
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes().take_while(|c| *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = ((acc << 6) | v as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+const PROGRAM_ADDRESS: &str = "DemoPoo1111111111111111111111111111111111111";
+
+/// The events of the program.
+#[derive(Debug, Clone)]
+pub enum ProgramEvent {
+	SwapEvent(SwapEvent),
+}
+impl ProgramEvent {
+	/// Decodes an event from its discriminator and fields, `None` if it
+	/// fails, see [`Self::try_decode`].
+	pub fn decode(data: &[u8]) -> Option<Self> {
+		Self::try_decode(data).ok()
+	}
+
+	/// Decodes an event from its discriminator and fields.
+	pub fn try_decode(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		if data.len() < 8 {
+			return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+		}
+		let (discriminator, mut data) = data.split_at(8);
+		let discriminator: [u8; 8] = discriminator.try_into().expect("split at 8");
+		match discriminator {
+			[64, 198, 205, 232, 38, 8, 113, 226] => Ok(Self::SwapEvent(borsh::BorshDeserialize::deserialize(&mut data)?)),
+			_ => Err(BindingsError::InvalidDiscriminator(discriminator)),
+		}
+	}
+
+	/// Decodes the event carried by an inner instruction of `program_id`,
+	/// if it is an `emit_cpi!` self-invocation of the program.
+	pub fn from_inner_instruction(program_id: &str, data: &[u8]) -> Option<Self> {
+		if program_id != PROGRAM_ADDRESS {
+			return None;
+		}
+		Self::decode(data.strip_prefix(&EVENT_IX_TAG)?)
+	}
+
+	/// Events the program logged with `emit!`, from the log messages of a
+	/// transaction, with the instruction executing when each was logged.
+	/// Nothing after a truncated log can be attributed.
+	pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<EmittedEvent> {
+		let mut events = Vec::new();
+		// (program, inner index) of every instruction being executed
+		let mut stack: Vec<(&str, Option<usize>)> = Vec::new();
+		let mut index = None;
+		let mut invoked = 0;
+		for line in logs.iter().map(AsRef::as_ref) {
+			if line == "Log truncated" {
+				break;
+			}
+			let Some(rest) = line.strip_prefix("Program ") else {
+				continue;
+			};
+			if let Some(data) = rest.strip_prefix("data: ") {
+				let (Some(index), Some((program, inner_index))) = (index, stack.last()) else {
+					continue;
+				};
+				if *program != PROGRAM_ADDRESS {
+					continue;
+				}
+				// `sol_log_data` logs each slice separately
+				let data: Option<Vec<Vec<u8>>> = data.split_whitespace().map(decode_base64).collect();
+				if let Some(event) = data.and_then(|data| Self::decode(&data.concat())) {
+					events.push(EmittedEvent {
+						index,
+						inner_index: *inner_index,
+						event,
+					});
+				}
+				continue;
+			}
+			let Some((program, status)) = rest.split_once(' ') else {
+				continue;
+			};
+			if status == "invoke [1]" {
+				index = Some(index.map_or(0, |i: usize| i + 1));
+				invoked = 0;
+				stack = vec![(program, None)];
+			} else if status.starts_with("invoke [") {
+				stack.push((program, Some(invoked)));
+				invoked += 1;
+			} else if status == "success" || status.starts_with("failed") {
+				stack.pop();
+			}
+		}
+		events
+	}
+}
+
+impl From<&SwapEvent> for proto::SwapEvent {
+	fn from(value: &SwapEvent) -> Self {
+		Self {
+			amount_in: value.amount_in,
+			user: value.user.to_string(),
+		}
+	}
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Pool {
+	authority: Pubkey,
+	fee_rate: u16,
+	reserves: [u64; 2],
+	config: PoolConfig,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
+
+/// Decodes a `T` from the data of an account, which starts with its
+/// `discriminator` and holds `size` bytes at least if `T` has a fixed size.
+fn decode_account<T: borsh::BorshDeserialize>(
+	data: &[u8],
+	discriminator: [u8; 8],
+	size: Option<usize>,
+) -> std::result::Result<T, BindingsError> {
+	if data.len() < 8 {
+		return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+	}
+	let (found, mut fields) = data.split_at(8);
+	if found != discriminator {
+		return Err(BindingsError::InvalidDiscriminator(found.try_into().expect("split at 8")));
+	}
+	if let Some(expected) = size.filter(|size| data.len() < *size) {
+		return Err(BindingsError::TruncatedData { expected, got: data.len() });
+	}
+	Ok(T::deserialize(&mut fields)?)
+}
+
+impl Pool {
+	/// Decodes the `Pool` account from its data, discriminator included.
+	pub fn from_account_data(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		decode_account(data, [241, 154, 109, 4, 17, 177, 109, 188], None)
+	}
+}
+
+impl From<&Pool> for proto::Pool {
+	fn from(value: &Pool) -> Self {
+		Self {
+			authority: value.authority.to_string(),
+			fee_rate: value.fee_rate as u32,
+			reserves: value.reserves.to_vec(),
+			config: borsh::BorshSerialize::try_to_vec(&value.config).expect("writing to a Vec can't fail"),
+		}
+	}
+}
+
+/// The protobuf schema of the [`DecoderServer`] and its messages.
+pub const PROTO_SCHEMA: &str = r#"syntax = "proto3";
+
+package demo;
+
+// Decodes the instructions, accounts and events of the program.
+service Decoder {
+  // Decodes one of them.
+  rpc Decode(DecodeRequest) returns (Decoded);
+  // Decodes a stream of them in order, ending at the first that fails.
+  rpc DecodeStream(stream DecodeRequest) returns (stream Decoded);
+}
+
+message DecodeRequest {
+  enum Kind {
+    KIND_INSTRUCTION = 0;
+    KIND_ACCOUNT = 1;
+    KIND_EVENT = 2;
+  }
+  Kind kind = 1;
+  // The data, discriminator included.
+  bytes data = 2;
+}
+
+message Decoded {
+  oneof item {
+    InitializePool instruction_initialize_pool = 1;
+    Swap instruction_swap = 2;
+    Close instruction_close = 3;
+    Pool account_pool = 4;
+    SwapEvent event_swap_event = 5;
+  }
+}
+
+// The args of the `initializePool` instruction.
+message InitializePool {
+  uint32 fee_rate = 1;
+  // borsh encoded
+  bytes config = 2;
+}
+
+// The args of the `swap` instruction.
+message Swap {
+  uint64 amount_in = 1;
+  uint64 min_out = 2;
+  // borsh encoded
+  bytes side = 3;
+}
+
+// The args of the `close` instruction.
+message Close {
+}
+
+// The `Pool` account.
+message Pool {
+  string authority = 1;
+  uint32 fee_rate = 2;
+  repeated uint64 reserves = 3;
+  // borsh encoded
+  bytes config = 4;
+}
+
+// The `SwapEvent` event.
+message SwapEvent {
+  uint64 amount_in = 1;
+  string user = 2;
+}
+"#;
+
+/// The messages of [`PROTO_SCHEMA`], as prost generates them.
+pub mod proto {
+	/// The data of an instruction, account or event to decode.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct DecodeRequest {
+		#[prost(enumeration = "decode_request::Kind", tag = "1")]
+		pub kind: i32,
+		/// The data, discriminator included.
+		#[prost(bytes = "vec", tag = "2")]
+		pub data: Vec<u8>,
+	}
+	pub mod decode_request {
+		/// What the data of a [`DecodeRequest`](super::DecodeRequest) is.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+		#[repr(i32)]
+		pub enum Kind {
+			Instruction = 0,
+			Account = 1,
+			Event = 2,
+		}
+	}
+	/// A decoded instruction, account or event.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct Decoded {
+		#[prost(oneof = "decoded::Item", tags = "1, 2, 3, 4, 5")]
+		pub item: Option<decoded::Item>,
+	}
+	pub mod decoded {
+		#[derive(Clone, PartialEq, prost::Oneof)]
+		pub enum Item {
+			#[prost(message, tag = "1")]
+			InstructionInitializePool(super::InitializePool),
+			#[prost(message, tag = "2")]
+			InstructionSwap(super::Swap),
+			#[prost(message, tag = "3")]
+			InstructionClose(super::Close),
+			#[prost(message, tag = "4")]
+			AccountPool(super::Pool),
+			#[prost(message, tag = "5")]
+			EventSwapEvent(super::SwapEvent),
+		}
+	}
+	/// The args of the `initializePool` instruction.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct InitializePool {
+		#[prost(uint32, tag = "1")]
+		pub fee_rate: u32,
+		#[prost(bytes = "vec", tag = "2")]
+		pub config: Vec<u8>,
+	}
+	/// The args of the `swap` instruction.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct Swap {
+		#[prost(uint64, tag = "1")]
+		pub amount_in: u64,
+		#[prost(uint64, tag = "2")]
+		pub min_out: u64,
+		#[prost(bytes = "vec", tag = "3")]
+		pub side: Vec<u8>,
+	}
+	/// The args of the `close` instruction.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct Close {
+	}
+	/// The `Pool` account.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct Pool {
+		#[prost(string, tag = "1")]
+		pub authority: String,
+		#[prost(uint32, tag = "2")]
+		pub fee_rate: u32,
+		#[prost(uint64, repeated, tag = "3")]
+		pub reserves: Vec<u64>,
+		#[prost(bytes = "vec", tag = "4")]
+		pub config: Vec<u8>,
+	}
+	/// The `SwapEvent` event.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct SwapEvent {
+		#[prost(uint64, tag = "1")]
+		pub amount_in: u64,
+		#[prost(string, tag = "2")]
+		pub user: String,
+	}
+}
+
+/// Decodes the instruction, account or event of `request`.
+fn decode_proto(request: &proto::DecodeRequest) -> std::result::Result<proto::Decoded, tonic::Status> {
+	use proto::decode_request::Kind;
+	use proto::decoded::Item;
+	let data = request.data.as_slice();
+	if data.len() < 8 {
+		return Err(tonic::Status::invalid_argument("data shorter than a discriminator"));
+	}
+	let (discriminator, mut rest) = data.split_at(8);
+	let item = match request.kind() {
+		Kind::Instruction => match discriminator {
+			[95, 180, 10, 172, 84, 174, 232, 40] => Item::InstructionInitializePool((&<InitializePool as borsh::BorshDeserialize>::deserialize(&mut rest).map_err(invalid)?).into()),
+			[248, 198, 158, 145, 225, 117, 135, 200] => Item::InstructionSwap((&<Swap as borsh::BorshDeserialize>::deserialize(&mut rest).map_err(invalid)?).into()),
+			[98, 165, 201, 177, 108, 65, 206, 96] => Item::InstructionClose(proto::Close {}),
+			_ => return Err(tonic::Status::not_found("none of the program's instructions")),
+		},
+		Kind::Account => match discriminator {
+			[241, 154, 109, 4, 17, 177, 109, 188] => Item::AccountPool((&Pool::from_account_data(data).map_err(invalid)?).into()),
+			_ => return Err(tonic::Status::not_found("none of the program's accounts")),
+		},
+		// the events of `emit_cpi!` come in their instruction
+		Kind::Event => match ProgramEvent::try_decode(data.strip_prefix(&EVENT_IX_TAG).unwrap_or(data)).map_err(invalid)? {
+			ProgramEvent::SwapEvent(event) => Item::EventSwapEvent((&event).into()),
+		},
+	};
+	Ok(proto::Decoded { item: Some(item) })
+}
+
+fn invalid(e: impl ToString) -> tonic::Status {
+	tonic::Status::invalid_argument(e.to_string())
+}
+
+/// The `demo.Decoder` service of [`PROTO_SCHEMA`], decoding the instructions,
+/// accounts and events of the program, to add to a `tonic` server:
+///
+/// - `Decode` decodes one of them
+/// - `DecodeStream` decodes a stream of them in order, ending at the first
+///   that fails with its status
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecoderServer;
+
+impl tonic::server::NamedService for DecoderServer {
+	const NAME: &'static str = "demo.Decoder";
+}
+
+impl<B> tonic::codegen::Service<tonic::codegen::http::Request<B>> for DecoderServer
+where
+	B: tonic::codegen::Body + Send + 'static,
+	B::Error: Into<tonic::codegen::StdError> + Send + 'static,
+{
+	type Response = tonic::codegen::http::Response<tonic::body::BoxBody>;
+	type Error = std::convert::Infallible;
+	type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+	fn poll_ready(
+		&mut self,
+		_cx: &mut std::task::Context<'_>,
+	) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+		std::task::Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, request: tonic::codegen::http::Request<B>) -> Self::Future {
+		match request.uri().path() {
+			"/demo.Decoder/Decode" => Box::pin(async move {
+				let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+				Ok(grpc.unary(DecodeCall, request).await)
+			}),
+			"/demo.Decoder/DecodeStream" => Box::pin(async move {
+				let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+				Ok(grpc.streaming(DecodeStreamCall, request).await)
+			}),
+			path => {
+				let status = tonic::Status::unimplemented(format!("no method {}", path));
+				Box::pin(async move { Ok(status.into_http()) })
+			}
+		}
+	}
+}
+
+struct DecodeCall;
+
+impl tonic::server::UnaryService<proto::DecodeRequest> for DecodeCall {
+	type Response = proto::Decoded;
+	type Future = tonic::codegen::BoxFuture<tonic::Response<proto::Decoded>, tonic::Status>;
+
+	fn call(&mut self, request: tonic::Request<proto::DecodeRequest>) -> Self::Future {
+		let decoded = decode_proto(request.get_ref()).map(tonic::Response::new);
+		Box::pin(async move { decoded })
+	}
+}
+
+struct DecodeStreamCall;
+
+impl tonic::server::StreamingService<proto::DecodeRequest> for DecodeStreamCall {
+	type Response = proto::Decoded;
+	type ResponseStream = tonic::codegen::BoxStream<proto::Decoded>;
+	type Future = tonic::codegen::BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+	fn call(&mut self, request: tonic::Request<tonic::Streaming<proto::DecodeRequest>>) -> Self::Future {
+		let decoded = futures::StreamExt::map(request.into_inner(), |request| decode_proto(&request?));
+		// the stream ends at the first error
+		let decoded = futures::StreamExt::scan(decoded, false, |failed, decoded| {
+			let item = (!*failed).then_some(decoded);
+			*failed = item.as_ref().is_some_and(|d| d.is_err());
+			futures::future::ready(item)
+		});
+		Box::pin(async move { Ok(tonic::Response::new(Box::pin(decoded) as Self::ResponseStream)) })
+	}
+}
+
+/// Serves the [`DecoderServer`] on `addr`, until it fails.
+pub async fn serve_grpc_decoder(addr: std::net::SocketAddr) -> std::result::Result<(), tonic::transport::Error> {
+	tonic::transport::Server::builder()
+		.add_service(DecoderServer)
+		.serve(addr)
+		.await
+}
@@ -0,0 +1,1068 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "DemoPoo1111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 3] = [
+	([95, 180, 10, 172, 84, 174, 232, 40], "initialize_pool"),
+	([248, 198, 158, 145, 225, 117, 135, 200], "swap"),
+	([98, 165, 201, 177, 108, 65, 206, 96], "close"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct InitializePool {
+	fee_rate: u16,
+	config: PoolConfig,
+}
+impl InitializePool {
+	/// The args of `initializePool`.
+	pub fn new(fee_rate: u16, config: PoolConfig) -> Self {
+		Self {
+			fee_rate,
+			config,
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Swap {
+	amount_in: u64,
+	min_out: u64,
+	side: Side,
+}
+impl Swap {
+	/// The args of `swap`.
+	pub fn new(amount_in: u64, min_out: u64, side: Side) -> Self {
+		Self {
+			amount_in,
+			min_out,
+			side,
+		}
+	}
+}
+
+/// Why a decoder, builder or RPC call of the bindings failed.
+#[derive(Debug, thiserror::Error)]
+pub enum BindingsError {
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+	#[error(transparent)]
+	Accounts(#[from] AccountsError),
+}
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([187, 249, 99, 112, 228, 16, 100, 204, 142, 133, 1, 211, 212, 198, 216, 93, 138, 244, 73, 87, 131, 170, 226, 139, 10, 203, 195, 64, 0, 0, 0, 0]);
+
+/// Compute unit limit and price of a transaction, `None` leaves the
+/// defaults of the runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+	pub unit_limit: Option<u32>,
+	/// Priority fee, in micro-lamports per compute unit.
+	pub unit_price: Option<u64>,
+}
+impl ComputeBudget {
+	pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0]);
+
+	/// The `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions of
+	/// the budget.
+	pub fn instructions(&self) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut ixs = Vec::new();
+		if let Some(limit) = self.unit_limit {
+			let mut data = vec![2];
+			data.extend_from_slice(&limit.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		if let Some(price) = self.unit_price {
+			let mut data = vec![3];
+			data.extend_from_slice(&price.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		ixs
+	}
+
+	/// `ixs` preceded by the instructions of the budget.
+	pub fn prepend(
+		&self,
+		ixs: impl IntoIterator<Item = anchor_lang::solana_program::instruction::Instruction>,
+	) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut all = self.instructions();
+		all.extend(ixs);
+		all
+	}
+}
+
+/// Accounts of an instruction breaking the constraints of the IDL.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AccountsError {
+	/// `account` must be `expected`, the address the IDL declares.
+	#[error("account {account} of {instruction} must be {expected}, got {actual}")]
+	WrongAddress {
+		instruction: &'static str,
+		account: &'static str,
+		expected: Pubkey,
+		actual: Pubkey,
+	},
+	/// `account` signs the instruction, but `key` is none of the signers.
+	#[error("account {account} of {instruction} must sign, but {key} is not a signer")]
+	MissingSigner {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// `account` is written by the instruction, but `key` is read-only.
+	#[error("account {account} of {instruction} must be writable, but {key} is read-only")]
+	NotWritable {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// The instruction takes at least `expected` accounts.
+	#[error("{instruction} takes at least {expected} accounts, got {actual}")]
+	TooFewAccounts {
+		instruction: &'static str,
+		expected: usize,
+		actual: usize,
+	},
+}
+
+/// An account of an instruction, as the IDL declares it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSpec {
+	pub name: &'static str,
+	pub is_signer: bool,
+	pub is_writable: bool,
+	pub is_optional: bool,
+	/// The address the IDL declares the account must have.
+	pub address: Option<Pubkey>,
+}
+
+/// Checks the account `metas` of `instruction` against its `accounts`:
+/// their count, and that the signer and writable accounts are and those
+/// with a fixed address have it. The metas past the accounts are remaining
+/// accounts, which aren't checked. When `omitted` optional accounts may be
+/// left out, which shifts the accounts after them, so only those before
+/// the first optional account are checked; otherwise the program ID takes
+/// the place of an optional account left out.
+fn check_account_metas(
+	instruction: &'static str,
+	accounts: &[AccountSpec],
+	metas: &[anchor_lang::solana_program::instruction::AccountMeta],
+	omitted: bool,
+) -> std::result::Result<(), AccountsError> {
+	let expected = accounts.iter().filter(|a| !(omitted && a.is_optional)).count();
+	if metas.len() < expected {
+		return Err(AccountsError::TooFewAccounts {
+			instruction,
+			expected,
+			actual: metas.len(),
+		});
+	}
+	for (account, meta) in accounts.iter().zip(metas) {
+		if account.is_optional && omitted {
+			break;
+		}
+		if account.is_optional && meta.pubkey == PROGRAM_ID {
+			continue;
+		}
+		if let Some(expected) = account.address.filter(|key| *key != meta.pubkey) {
+			return Err(AccountsError::WrongAddress {
+				instruction,
+				account: account.name,
+				expected,
+				actual: meta.pubkey,
+			});
+		}
+		if account.is_signer && !meta.is_signer {
+			return Err(AccountsError::MissingSigner {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+		if account.is_writable && !meta.is_writable {
+			return Err(AccountsError::NotWritable {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+	}
+	Ok(())
+}
+/// Accounts of the `initializePool` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitializePoolAccounts {
+	pub pool: Pubkey,
+	pub authority: Pubkey,
+	pub system_program: Pubkey,
+}
+impl InitializePoolAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.authority, true),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.system_program, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("authority", Some(self.authority))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "initializePool",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "authority",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "systemProgram",
+			is_signer: false,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("initializePool", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `initializePool` instruction.
+pub fn initialize_pool_ix(accounts: &InitializePoolAccounts, args: &InitializePool) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [95, 180, 10, 172, 84, 174, 232, 40].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `swap` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapAccounts {
+	pub pool: Pubkey,
+	pub user: Pubkey,
+}
+impl SwapAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+			anchor_lang::solana_program::instruction::AccountMeta::new_readonly(self.user, true),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		for (account, key) in [("user", Some(self.user))] {
+			if let Some(key) = key.filter(|key| !signers.contains(key)) {
+				return Err(AccountsError::MissingSigner {
+					instruction: "swap",
+					account,
+					key,
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+		AccountSpec {
+			name: "user",
+			is_signer: true,
+			is_writable: false,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("swap", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `swap` instruction.
+pub fn swap_ix(accounts: &SwapAccounts, args: &Swap) -> anchor_lang::solana_program::instruction::Instruction {
+	let mut data = [248, 198, 158, 145, 225, 117, 135, 200].to_vec();
+	borsh::BorshSerialize::serialize(args, &mut data).expect("writing to a Vec can't fail");
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+/// Accounts of the `close` instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseAccounts {
+	pub pool: Pubkey,
+}
+impl CloseAccounts {
+	/// The account metas, in the order the program expects them.
+	pub fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {
+		vec![
+			anchor_lang::solana_program::instruction::AccountMeta::new(self.pool, false),
+		]
+	}
+	/// Checks the accounts with an address the IDL fixes.
+	pub fn validate(&self) -> std::result::Result<(), AccountsError> {
+		Ok(())
+	}
+
+	/// Checks that the signer accounts are among `signers`.
+	pub fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {
+		let _ = signers;
+		Ok(())
+	}
+
+	/// The accounts of the instruction the IDL declares, in order.
+	pub const ACCOUNTS: &[AccountSpec] = &[
+		AccountSpec {
+			name: "pool",
+			is_signer: false,
+			is_writable: true,
+			is_optional: false,
+			address: None,
+		},
+	];
+
+	/// Checks account metas of the instruction, such as ones assembled by
+	/// hand, against [`Self::ACCOUNTS`].
+	pub fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {
+		check_account_metas("close", Self::ACCOUNTS, metas, false)
+	}
+}
+/// Builds the `close` instruction.
+pub fn close_ix(accounts: &CloseAccounts) -> anchor_lang::solana_program::instruction::Instruction {
+	let data = [98, 165, 201, 177, 108, 65, 206, 96].to_vec();
+	anchor_lang::solana_program::instruction::Instruction {
+		program_id: PROGRAM_ID,
+		accounts: accounts.metas(),
+		data,
+	}
+}
+
+/// The tables of the indexer, created if missing by [`run_indexer`].
+pub const MIGRATION: &str = r#"CREATE TABLE IF NOT EXISTS "demo_ix_initialize_pool" (
+	signature TEXT NOT NULL,
+	slot BIGINT NOT NULL,
+	ix_index INTEGER NOT NULL,
+	inner_index INTEGER,
+	"pool" TEXT,
+	"authority" TEXT,
+	"system_program" TEXT,
+	"fee_rate" INTEGER NOT NULL,
+	"config" BYTEA NOT NULL,
+	UNIQUE NULLS NOT DISTINCT (signature, ix_index, inner_index)
+);
+CREATE TABLE IF NOT EXISTS "demo_ix_swap" (
+	signature TEXT NOT NULL,
+	slot BIGINT NOT NULL,
+	ix_index INTEGER NOT NULL,
+	inner_index INTEGER,
+	"pool" TEXT,
+	"user" TEXT,
+	"amount_in" NUMERIC NOT NULL,
+	"min_out" NUMERIC NOT NULL,
+	"side" BYTEA NOT NULL,
+	UNIQUE NULLS NOT DISTINCT (signature, ix_index, inner_index)
+);
+CREATE TABLE IF NOT EXISTS "demo_ix_close" (
+	signature TEXT NOT NULL,
+	slot BIGINT NOT NULL,
+	ix_index INTEGER NOT NULL,
+	inner_index INTEGER,
+	"pool" TEXT,
+	UNIQUE NULLS NOT DISTINCT (signature, ix_index, inner_index)
+);
+CREATE TABLE IF NOT EXISTS "demo_event_swap_event" (
+	event_index INTEGER NOT NULL,
+	signature TEXT NOT NULL,
+	slot BIGINT NOT NULL,
+	ix_index INTEGER NOT NULL,
+	inner_index INTEGER,
+	"amount_in" NUMERIC NOT NULL,
+	"user" TEXT NOT NULL,
+	PRIMARY KEY (signature, event_index)
+);
+CREATE TABLE IF NOT EXISTS "demo_account_pool" (
+	address TEXT PRIMARY KEY,
+	slot BIGINT NOT NULL,
+	"authority" TEXT NOT NULL,
+	"fee_rate" INTEGER NOT NULL,
+	"reserves" BYTEA NOT NULL,
+	"config" BYTEA NOT NULL
+);
+"#;
+
+/// Where an indexed instruction or event comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+	pub signature: solana_sdk::signature::Signature,
+	pub slot: u64,
+	/// Position of the top-level instruction in the message.
+	pub index: usize,
+	/// Position among the instructions the top-level instruction invoked,
+	/// `None` for the top-level instruction itself.
+	pub inner_index: Option<usize>,
+}
+
+/// Where [`run_indexer`] reads the program's transactions from.
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+	pub rpc_url: String,
+	pub ws_url: String,
+	pub commitment: solana_sdk::commitment_config::CommitmentConfig,
+	/// First slot of the backfill, `None` to index new transactions only.
+	pub from_slot: Option<u64>,
+}
+
+/// Creates the tables, indexes the accounts of the program, backfills its
+/// transactions from `config.from_slot` on and then indexes the new ones as
+/// their logs are notified. Runs until a transaction can't be indexed.
+pub async fn run_indexer(
+	pool: &sqlx::PgPool,
+	config: &IndexerConfig,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::Executor::execute(pool, MIGRATION).await?;
+	let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+	index_accounts(pool, &rpc).await?;
+
+	// subscribed first, so that nothing falls between the backfill and the
+	// notifications
+	let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+	let (ws_url, commitment) = (config.ws_url.clone(), config.commitment);
+	tokio::spawn(async move {
+		let mut backoff = std::time::Duration::from_millis(500);
+		while let Err(_e) = forward_signatures(&ws_url, commitment, &sender).await {
+			tokio::time::sleep(backoff).await;
+			backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+		}
+	});
+	if let Some(from_slot) = config.from_slot {
+		backfill(pool, &rpc, from_slot).await?;
+	}
+	while let Some(signature) = receiver.recv().await {
+		index_signature(pool, &rpc, signature).await?;
+	}
+	Err("the subscription task ended".into())
+}
+
+/// Indexes the successful transactions of the program from `from_slot` on,
+/// oldest first.
+pub async fn backfill(
+	pool: &sqlx::PgPool,
+	rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+	from_slot: u64,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let mut signatures = Vec::new();
+	let mut before = None;
+	'pages: loop {
+		let config = solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config {
+			before,
+			until: None,
+			limit: None,
+			commitment: Some(rpc.commitment()),
+		};
+		let page = rpc.get_signatures_for_address_with_config(&PROGRAM_ID, config).await?;
+		let Some(last) = page.last() else {
+			break;
+		};
+		before = Some(last.signature.parse()?);
+		for info in page {
+			if info.slot < from_slot {
+				break 'pages;
+			}
+			if info.err.is_none() {
+				signatures.push(info.signature.parse()?);
+			}
+		}
+	}
+	for signature in signatures.into_iter().rev() {
+		index_signature(pool, rpc, signature).await?;
+	}
+	Ok(())
+}
+
+/// Fetches and indexes the transaction `signature`, retrying while the
+/// node doesn't have it yet.
+pub async fn index_signature(
+	pool: &sqlx::PgPool,
+	rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+	signature: solana_sdk::signature::Signature,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let config = solana_client::rpc_config::RpcTransactionConfig {
+		encoding: Some(solana_transaction_status::UiTransactionEncoding::Base64),
+		commitment: Some(rpc.commitment()),
+		max_supported_transaction_version: Some(0),
+	};
+	let mut attempt = 0;
+	let tx = loop {
+		match rpc.get_transaction_with_config(&signature, config).await {
+			Ok(tx) => break tx,
+			Err(_e) if attempt < 5 => {
+				attempt += 1;
+				tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+			}
+			Err(e) => return Err(e.into()),
+		}
+	};
+	index_transaction(pool, signature, &tx).await
+}
+
+/// Indexes the instructions of the program in `tx`, inner ones included,
+/// and the events it logged, in one database transaction. Failed
+/// transactions are skipped.
+pub async fn index_transaction(
+	pool: &sqlx::PgPool,
+	signature: solana_sdk::signature::Signature,
+	tx: &solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use solana_transaction_status::option_serializer::OptionSerializer;
+	let meta = tx.transaction.meta.as_ref().ok_or("transaction without status")?;
+	if meta.err.is_some() {
+		return Ok(());
+	}
+	let decoded = tx.transaction.transaction.decode().ok_or("transaction not in a binary encoding")?;
+	let mut keys = decoded.message.static_account_keys().to_vec();
+	if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+		for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+			keys.push(key.parse()?);
+		}
+	}
+
+	let mut db = pool.begin().await?;
+	for (index, ix) in decoded.message.instructions().iter().enumerate() {
+		let location = Location { signature, slot: tx.slot, index, inner_index: None };
+		index_instruction(&mut db, &location, &keys, ix.program_id_index, &ix.accounts, &ix.data).await?;
+	}
+	if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+		for group in inner {
+			for (inner_index, ix) in group.instructions.iter().enumerate() {
+				let solana_transaction_status::UiInstruction::Compiled(ix) = ix else {
+					continue;
+				};
+				let location = Location {
+					signature,
+					slot: tx.slot,
+					index: group.index as usize,
+					inner_index: Some(inner_index),
+				};
+				let data = solana_sdk::bs58::decode(&ix.data).into_vec()?;
+				index_instruction(&mut db, &location, &keys, ix.program_id_index, &ix.accounts, &data).await?;
+			}
+		}
+	}
+	// events of `emit_cpi!` are only seen in the logs when also `emit!`ed
+	if let OptionSerializer::Some(logs) = &meta.log_messages {
+		for (event_index, emitted) in ProgramEvent::from_logs(logs).into_iter().enumerate() {
+			let location = Location {
+				signature,
+				slot: tx.slot,
+				index: emitted.index,
+				inner_index: emitted.inner_index,
+			};
+			match &emitted.event {
+				ProgramEvent::SwapEvent(event) => insert_swap_event(&mut *db, &location, event_index, event).await?,
+			}
+		}
+	}
+	db.commit().await?;
+	Ok(())
+}
+
+/// Fails when the subscription does, `Ok` once nobody receives the
+/// signatures anymore.
+async fn forward_signatures(
+	ws_url: &str,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+	sender: &tokio::sync::mpsc::UnboundedSender<solana_sdk::signature::Signature>,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use futures::StreamExt;
+	let client = solana_client::nonblocking::pubsub_client::PubsubClient::new(ws_url).await?;
+	let (mut notifications, unsubscribe) = client
+		.logs_subscribe(
+			solana_client::rpc_config::RpcTransactionLogsFilter::Mentions(vec![PROGRAM_ID.to_string()]),
+			solana_client::rpc_config::RpcTransactionLogsConfig { commitment: Some(commitment) },
+		)
+		.await?;
+	while let Some(notification) = notifications.next().await {
+		if notification.value.err.is_some() {
+			continue;
+		}
+		let Ok(signature) = notification.value.signature.parse() else {
+			continue;
+		};
+		if sender.send(signature).is_err() {
+			drop(notifications);
+			unsubscribe().await;
+			return Ok(());
+		}
+	}
+	Err("subscription closed".into())
+}
+
+/// Indexes the accounts the program owns, at the slot they are fetched at.
+pub async fn index_accounts(
+	pool: &sqlx::PgPool,
+	rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let slot = rpc.get_slot().await?;
+	for (address, account) in rpc.get_program_accounts(&PROGRAM_ID).await? {
+		let Some((discriminator, mut data)) = (account.data.len() >= 8).then(|| account.data.split_at(8)) else {
+			continue;
+		};
+		match discriminator {
+			[241, 154, 109, 4, 17, 177, 109, 188] => {
+				let account = <Pool as borsh::BorshDeserialize>::deserialize(&mut data)?;
+				insert_pool(pool, &address, slot, &account).await?;
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+async fn index_instruction(
+	db: &mut sqlx::PgConnection,
+	location: &Location,
+	keys: &[Pubkey],
+	program_id_index: u8,
+	accounts: &[u8],
+	data: &[u8],
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	if keys.get(program_id_index as usize) != Some(&PROGRAM_ID) || data.len() < 8 {
+		return Ok(());
+	}
+	let accounts: Vec<Pubkey> = accounts.iter().filter_map(|i| keys.get(*i as usize).copied()).collect();
+	let (discriminator, mut data) = data.split_at(8);
+	match discriminator {
+		[95, 180, 10, 172, 84, 174, 232, 40] => {
+			let args = <InitializePool as borsh::BorshDeserialize>::deserialize(&mut data)?;
+			insert_initialize_pool(db, location, &accounts, &args).await?;
+		}
+		[248, 198, 158, 145, 225, 117, 135, 200] => {
+			let args = <Swap as borsh::BorshDeserialize>::deserialize(&mut data)?;
+			insert_swap(db, location, &accounts, &args).await?;
+		}
+		[98, 165, 201, 177, 108, 65, 206, 96] => insert_close(db, location, &accounts).await?,
+		_ => {}
+	}
+	Ok(())
+}
+
+/// Inserts a decoded `initializePool` instruction with its `accounts`, in order, unless it is indexed already.
+pub async fn insert_initialize_pool(
+	executor: impl sqlx::PgExecutor<'_>,
+	location: &Location,
+	accounts: &[Pubkey],
+	args: &InitializePool,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::query("INSERT INTO \"demo_ix_initialize_pool\" (signature, slot, ix_index, inner_index, \"pool\", \"authority\", \"system_program\", \"fee_rate\", \"config\") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT DO NOTHING")
+		.bind(location.signature.to_string())
+		.bind(location.slot as i64)
+		.bind(location.index as i32)
+		.bind(location.inner_index.map(|i| i as i32))
+		.bind(accounts.get(0).map(|a| a.to_string()))
+		.bind(accounts.get(1).map(|a| a.to_string()))
+		.bind(accounts.get(2).map(|a| a.to_string()))
+		.bind(args.fee_rate as i32)
+		.bind(borsh::BorshSerialize::try_to_vec(&args.config)?)
+		.execute(executor)
+		.await?;
+	Ok(())
+}
+
+/// Inserts a decoded `swap` instruction with its `accounts`, in order, unless it is indexed already.
+pub async fn insert_swap(
+	executor: impl sqlx::PgExecutor<'_>,
+	location: &Location,
+	accounts: &[Pubkey],
+	args: &Swap,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::query("INSERT INTO \"demo_ix_swap\" (signature, slot, ix_index, inner_index, \"pool\", \"user\", \"amount_in\", \"min_out\", \"side\") VALUES ($1, $2, $3, $4, $5, $6, $7::NUMERIC, $8::NUMERIC, $9) ON CONFLICT DO NOTHING")
+		.bind(location.signature.to_string())
+		.bind(location.slot as i64)
+		.bind(location.index as i32)
+		.bind(location.inner_index.map(|i| i as i32))
+		.bind(accounts.get(0).map(|a| a.to_string()))
+		.bind(accounts.get(1).map(|a| a.to_string()))
+		.bind(args.amount_in.to_string())
+		.bind(args.min_out.to_string())
+		.bind(borsh::BorshSerialize::try_to_vec(&args.side)?)
+		.execute(executor)
+		.await?;
+	Ok(())
+}
+
+/// Inserts a decoded `close` instruction with its `accounts`, in order, unless it is indexed already.
+pub async fn insert_close(
+	executor: impl sqlx::PgExecutor<'_>,
+	location: &Location,
+	accounts: &[Pubkey],
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::query("INSERT INTO \"demo_ix_close\" (signature, slot, ix_index, inner_index, \"pool\") VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING")
+		.bind(location.signature.to_string())
+		.bind(location.slot as i64)
+		.bind(location.index as i32)
+		.bind(location.inner_index.map(|i| i as i32))
+		.bind(accounts.get(0).map(|a| a.to_string()))
+		.execute(executor)
+		.await?;
+	Ok(())
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SwapEvent {
+	amount_in: u64,
+	user: Pubkey,
+}
+
+/// Instruction discriminator of the self-invocation `emit_cpi!` carries an
+/// event in, followed by the event's own discriminator and fields.
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// An event with the instruction of the transaction that emitted it.
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions the top-level instruction invoked of
+    /// the instruction executing when the event was emitted, `None` for the
+    /// top-level instruction itself.
+    pub inner_index: Option<usize>,
+    pub event: ProgramEvent,
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes().take_while(|c| *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = ((acc << 6) | v as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+const PROGRAM_ADDRESS: &str = "DemoPoo1111111111111111111111111111111111111";
+
+/// The events of the program.
+#[derive(Debug, Clone)]
+pub enum ProgramEvent {
+	SwapEvent(SwapEvent),
+}
+impl ProgramEvent {
+	/// Decodes an event from its discriminator and fields, `None` if it
+	/// fails, see [`Self::try_decode`].
+	pub fn decode(data: &[u8]) -> Option<Self> {
+		Self::try_decode(data).ok()
+	}
+
+	/// Decodes an event from its discriminator and fields.
+	pub fn try_decode(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		if data.len() < 8 {
+			return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+		}
+		let (discriminator, mut data) = data.split_at(8);
+		let discriminator: [u8; 8] = discriminator.try_into().expect("split at 8");
+		match discriminator {
+			[64, 198, 205, 232, 38, 8, 113, 226] => Ok(Self::SwapEvent(borsh::BorshDeserialize::deserialize(&mut data)?)),
+			_ => Err(BindingsError::InvalidDiscriminator(discriminator)),
+		}
+	}
+
+	/// Decodes the event carried by an inner instruction of `program_id`,
+	/// if it is an `emit_cpi!` self-invocation of the program.
+	pub fn from_inner_instruction(program_id: &str, data: &[u8]) -> Option<Self> {
+		if program_id != PROGRAM_ADDRESS {
+			return None;
+		}
+		Self::decode(data.strip_prefix(&EVENT_IX_TAG)?)
+	}
+
+	/// Events the program logged with `emit!`, from the log messages of a
+	/// transaction, with the instruction executing when each was logged.
+	/// Nothing after a truncated log can be attributed.
+	pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<EmittedEvent> {
+		let mut events = Vec::new();
+		// (program, inner index) of every instruction being executed
+		let mut stack: Vec<(&str, Option<usize>)> = Vec::new();
+		let mut index = None;
+		let mut invoked = 0;
+		for line in logs.iter().map(AsRef::as_ref) {
+			if line == "Log truncated" {
+				break;
+			}
+			let Some(rest) = line.strip_prefix("Program ") else {
+				continue;
+			};
+			if let Some(data) = rest.strip_prefix("data: ") {
+				let (Some(index), Some((program, inner_index))) = (index, stack.last()) else {
+					continue;
+				};
+				if *program != PROGRAM_ADDRESS {
+					continue;
+				}
+				// `sol_log_data` logs each slice separately
+				let data: Option<Vec<Vec<u8>>> = data.split_whitespace().map(decode_base64).collect();
+				if let Some(event) = data.and_then(|data| Self::decode(&data.concat())) {
+					events.push(EmittedEvent {
+						index,
+						inner_index: *inner_index,
+						event,
+					});
+				}
+				continue;
+			}
+			let Some((program, status)) = rest.split_once(' ') else {
+				continue;
+			};
+			if status == "invoke [1]" {
+				index = Some(index.map_or(0, |i: usize| i + 1));
+				invoked = 0;
+				stack = vec![(program, None)];
+			} else if status.starts_with("invoke [") {
+				stack.push((program, Some(invoked)));
+				invoked += 1;
+			} else if status == "success" || status.starts_with("failed") {
+				stack.pop();
+			}
+		}
+		events
+	}
+}
+
+/// Inserts a decoded `SwapEvent` event, the `event_index`th of its transaction, unless it is indexed already.
+pub async fn insert_swap_event(
+	executor: impl sqlx::PgExecutor<'_>,
+	location: &Location,
+	event_index: usize,
+	event: &SwapEvent,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::query("INSERT INTO \"demo_event_swap_event\" (event_index, signature, slot, ix_index, inner_index, \"amount_in\", \"user\") VALUES ($1, $2, $3, $4, $5, $6::NUMERIC, $7) ON CONFLICT DO NOTHING")
+		.bind(event_index as i32)
+		.bind(location.signature.to_string())
+		.bind(location.slot as i64)
+		.bind(location.index as i32)
+		.bind(location.inner_index.map(|i| i as i32))
+		.bind(event.amount_in.to_string())
+		.bind(event.user.to_string())
+		.execute(executor)
+		.await?;
+	Ok(())
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ErrorCode {
+	SlippageExceeded = 6000,
+}
+impl ErrorCode {
+	pub fn from_code(code: u32) -> Option<Self> {
+		match code {
+			6000 => Some(Self::SlippageExceeded),
+			_ => None,
+		}
+	}
+	pub fn msg(&self) -> &'static str {
+		match self {
+			Self::SlippageExceeded => "Slippage exceeded",
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Pool {
+	authority: Pubkey,
+	fee_rate: u16,
+	reserves: [u64; 2],
+	config: PoolConfig,
+}
+impl Pool {
+	/// A `Pool`.
+	pub fn new(authority: Pubkey, fee_rate: u16, reserves: [u64; 2], config: PoolConfig) -> Self {
+		Self {
+			authority,
+			fee_rate,
+			reserves,
+			config,
+		}
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PoolConfig {
+	max_amount: Option<u64>,
+	tags: Vec<String>,
+}
+impl PoolConfig {
+	/// A `PoolConfig`, the optional fields `None`.
+	pub fn new(tags: Vec<String>) -> Self {
+		Self {
+			max_amount: None,
+			tags,
+		}
+	}
+
+	/// Sets `max_amount`.
+	pub fn with_max_amount(mut self, max_amount: u64) -> Self {
+		self.max_amount = Some(max_amount);
+		self
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum Side {
+	Bid = 0,
+	Ask = 1,
+}
+
+#[cfg(test)]
+mod enum_discriminants {
+	use super::*;
+
+	#[test]
+	fn borsh_tags_are_discriminants() {
+		assert_eq!(Side::Bid as u8, 0);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Bid).unwrap(), [0]);
+		assert_eq!(Side::Ask as u8, 1);
+		assert_eq!(borsh::BorshSerialize::try_to_vec(&Side::Ask).unwrap(), [1]);
+	}
+}
+
+/// Decodes a `T` from the data of an account, which starts with its
+/// `discriminator` and holds `size` bytes at least if `T` has a fixed size.
+fn decode_account<T: borsh::BorshDeserialize>(
+	data: &[u8],
+	discriminator: [u8; 8],
+	size: Option<usize>,
+) -> std::result::Result<T, BindingsError> {
+	if data.len() < 8 {
+		return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+	}
+	let (found, mut fields) = data.split_at(8);
+	if found != discriminator {
+		return Err(BindingsError::InvalidDiscriminator(found.try_into().expect("split at 8")));
+	}
+	if let Some(expected) = size.filter(|size| data.len() < *size) {
+		return Err(BindingsError::TruncatedData { expected, got: data.len() });
+	}
+	Ok(T::deserialize(&mut fields)?)
+}
+
+impl Pool {
+	/// Decodes the `Pool` account from its data, discriminator included.
+	pub fn from_account_data(data: &[u8]) -> std::result::Result<Self, BindingsError> {
+		decode_account(data, [241, 154, 109, 4, 17, 177, 109, 188], None)
+	}
+}
+
+/// Inserts or updates the `Pool` account at `address`, unless a newer state is indexed.
+pub async fn insert_pool(
+	executor: impl sqlx::PgExecutor<'_>,
+	address: &Pubkey,
+	slot: u64,
+	account: &Pool,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::query("INSERT INTO \"demo_account_pool\" (address, slot, \"authority\", \"fee_rate\", \"reserves\", \"config\") VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (address) DO UPDATE SET slot = EXCLUDED.slot, \"authority\" = EXCLUDED.\"authority\", \"fee_rate\" = EXCLUDED.\"fee_rate\", \"reserves\" = EXCLUDED.\"reserves\", \"config\" = EXCLUDED.\"config\" WHERE \"demo_account_pool\".slot <= EXCLUDED.slot")
+		.bind(address.to_string())
+		.bind(slot as i64)
+		.bind(account.authority.to_string())
+		.bind(account.fee_rate as i32)
+		.bind(borsh::BorshSerialize::try_to_vec(&account.reserves)?)
+		.bind(borsh::BorshSerialize::try_to_vec(&account.config)?)
+		.execute(executor)
+		.await?;
+	Ok(())
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use solana_program::pubkey::Pubkey;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+
+/// Human-readable serde representations of the fields.
+#[cfg(feature = "serde")]
+pub mod serde_human {
+	struct AsString<'a, T>(&'a T);
+
+	impl<T: std::fmt::Display> serde::Serialize for AsString<'_, T> {
+		fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.collect_str(self.0)
+		}
+	}
+
+	fn parse<T, E>(s: &str) -> std::result::Result<T, E>
+	where
+		T: std::str::FromStr,
+		T::Err: std::fmt::Display,
+		E: serde::de::Error,
+	{
+		s.parse().map_err(E::custom)
+	}
+
+	fn to_hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|b| format!("{:02x}", b)).collect()
+	}
+
+	fn from_hex<T: TryFrom<Vec<u8>>, E: serde::de::Error>(hex: &str) -> std::result::Result<T, E> {
+		if hex.len() % 2 != 0 || !hex.is_ascii() {
+			return Err(E::custom(format!("invalid hex {:?}", hex)));
+		}
+		let bytes = (0..hex.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+			.collect::<std::result::Result<Vec<u8>, _>>()
+			.map_err(E::custom)?;
+		let len = bytes.len();
+		T::try_from(bytes).map_err(|_| E::invalid_length(len, &"the length of the field"))
+	}
+
+	/// A value as its `Display` string, parsed back with `FromStr`.
+	pub mod string {
+		pub fn serialize<T: std::fmt::Display, S: serde::Serializer>(value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.collect_str(value)
+		}
+
+		pub fn deserialize<'de, T, D>(deserializer: D) -> std::result::Result<T, D::Error>
+		where
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			super::parse(&<String as serde::Deserialize>::deserialize(deserializer)?)
+		}
+	}
+
+	/// An optional value as its [`string`] or null.
+	pub mod option_string {
+		pub fn serialize<T: std::fmt::Display, S: serde::Serializer>(value: &Option<T>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serde::Serialize::serialize(&value.as_ref().map(super::AsString), serializer)
+		}
+
+		pub fn deserialize<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+		where
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			<Option<String> as serde::Deserialize>::deserialize(deserializer)?
+				.map(|s| super::parse(&s))
+				.transpose()
+		}
+	}
+
+	/// A vector or array of values as a sequence of their [`string`]s.
+	pub mod seq_string {
+		pub fn serialize<C, T, S>(values: &C, serializer: S) -> std::result::Result<S::Ok, S::Error>
+		where
+			for<'a> &'a C: IntoIterator<Item = &'a T>,
+			T: std::fmt::Display,
+			S: serde::Serializer,
+		{
+			serializer.collect_seq(values.into_iter().map(super::AsString))
+		}
+
+		pub fn deserialize<'de, C, T, D>(deserializer: D) -> std::result::Result<C, D::Error>
+		where
+			C: TryFrom<Vec<T>>,
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			let values = <Vec<String> as serde::Deserialize>::deserialize(deserializer)?
+				.iter()
+				.map(|s| super::parse(s))
+				.collect::<std::result::Result<Vec<T>, D::Error>>()?;
+			let len = values.len();
+			C::try_from(values).map_err(|_| serde::de::Error::invalid_length(len, &"the length of the field"))
+		}
+	}
+
+	/// Bytes as a lowercase hex string.
+	pub mod hex {
+		pub fn serialize<T: AsRef<[u8]>, S: serde::Serializer>(bytes: &T, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.serialize_str(&super::to_hex(bytes.as_ref()))
+		}
+
+		pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<T, D::Error> {
+			super::from_hex(&<String as serde::Deserialize>::deserialize(deserializer)?)
+		}
+	}
+
+	/// Optional bytes as their [`hex`] string or null.
+	pub mod option_hex {
+		pub fn serialize<T: AsRef<[u8]>, S: serde::Serializer>(bytes: &Option<T>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serde::Serialize::serialize(&bytes.as_ref().map(|b| super::to_hex(b.as_ref())), serializer)
+		}
+
+		pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<T>, D::Error> {
+			<Option<String> as serde::Deserialize>::deserialize(deserializer)?
+				.map(|hex| super::from_hex(&hex))
+				.transpose()
+		}
+	}
+}
+
+/// Serde of the arrays longer than 32, as the sequence of their elements.
+#[cfg(feature = "serde")]
+mod big_array {
+	pub fn serialize<T: serde::Serialize, const N: usize, S: serde::Serializer>(values: &[T; N], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.collect_seq(values.iter())
+	}
+
+	pub fn deserialize<'de, T: serde::Deserialize<'de>, const N: usize, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<[T; N], D::Error> {
+		let values = <Vec<T> as serde::Deserialize>::deserialize(deserializer)?;
+		let len = values.len();
+		<[T; N] as std::convert::TryFrom<Vec<T>>>::try_from(values)
+			.map_err(|_| serde::de::Error::invalid_length(len, &"the length of the array"))
+	}
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Book {
+	#[cfg_attr(feature = "serde", serde(with = "serde_human::string"))]
+	market: Pubkey,
+	seq_num: u64,
+	#[cfg_attr(feature = "serde", serde(with = "big_array"))]
+	bids: [Order; 128],
+	#[cfg_attr(feature = "serde", serde(with = "big_array"))]
+	asks: [Order; 128],
+	#[cfg_attr(feature = "serde", serde(with = "big_array"))]
+	price_levels: [[u64; 4]; 64],
+	#[cfg_attr(feature = "serde", serde(with = "serde_human::hex"))]
+	padding: [u8; 256],
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Order {
+	#[cfg_attr(feature = "serde", serde(with = "serde_human::string"))]
+	owner: Pubkey,
+	price: u64,
+	quantity: u64,
+	#[cfg_attr(feature = "serde", serde(with = "serde_human::string"))]
+	client_id: u128,
+	flags: u8,
+	#[cfg_attr(feature = "serde", serde(with = "serde_human::hex"))]
+	reserved: [u8; 7],
+}
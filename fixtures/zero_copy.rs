@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+static ID: &str = "Book111111111111111111111111111111111111111";
+
+pub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); 2] = [
+	([51, 194, 155, 175, 109, 130, 96, 106], "place_order"),
+	([0, 232, 3, 195, 124, 117, 105, 53], "crank"),
+];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct PlaceOrder {
+	order: Order,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Crank {
+	limit: u16,
+}
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct Order {
+	owner: Pubkey,
+	price: u64,
+	quantity: u64,
+	client_id: u128,
+	flags: u8,
+	reserved: [u8; 7],
+}
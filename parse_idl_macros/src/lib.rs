@@ -0,0 +1,52 @@
+//! Compile time companion of parse_idl.
+
+use std::path::PathBuf;
+
+use parse_idl::{generate_tokens, read_idl, GeneratorConfig};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// Expands to the bindings generated from an IDL file.
+///
+/// The path is relative to the crate root (`CARGO_MANIFEST_DIR`), the crate
+/// is rebuilt whenever the IDL changes.
+///
+/// ```ignore
+/// parse_idl_macros::include_idl!("idls/whirlpool.json");
+/// ```
+#[proc_macro]
+pub fn include_idl(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    match expand(&path) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => syn::Error::new(path.span(), e).to_compile_error().into(),
+    }
+}
+
+fn expand(path: &LitStr) -> Result<proc_macro2::TokenStream, String> {
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    let full_path = PathBuf::from(root).join(path.value());
+
+    let idl = read_idl(&full_path).map_err(|e| error_chain(&e))?;
+    let bindings =
+        generate_tokens(&idl, &GeneratorConfig::default()).map_err(|e| error_chain(&e))?;
+
+    // include_bytes! makes cargo track the IDL file for rebuilds
+    let tracked = full_path.to_string_lossy().to_string();
+    Ok(quote! {
+        const _: &[u8] = include_bytes!(#tracked);
+        #bindings
+    })
+}
+
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        msg.push_str(": ");
+        msg.push_str(&e.to_string());
+        source = e.source();
+    }
+    msg
+}
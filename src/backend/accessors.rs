@@ -0,0 +1,166 @@
+//! Accessors of the fields in a known role: `chrono` times of timestamps,
+//! see [`GeneratorConfig::timestamps`], and UI amounts of token amounts,
+//! see [`GeneratorConfig::amounts`].
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use heck::ToSnakeCase;
+use log::warn;
+
+use super::rust::rust_type;
+use crate::config::{Decimals, GeneratorConfig};
+use crate::ir::Type;
+
+/// Whether `name` matches `pattern`, whose leading or trailing `*` matches
+/// any prefix or suffix.
+fn matches(pattern: &str, name: &str) -> bool {
+    match (pattern.strip_prefix('*'), pattern.strip_suffix('*')) {
+        (Some(suffix), _) if suffix.ends_with('*') => name.contains(suffix.trim_end_matches('*')),
+        (Some(suffix), _) => name.ends_with(suffix),
+        (None, Some(prefix)) => name.starts_with(prefix),
+        (None, None) => name == pattern,
+    }
+}
+
+/// A generated method: doc line, signature and body.
+struct Accessor {
+    doc: String,
+    signature: String,
+    body: String,
+}
+
+/// The `<field>_datetime` accessor of a timestamp field.
+fn timestamp(field: &str, ty: &str) -> Option<Accessor> {
+    // hooks and flags may have replaced the integer
+    let (body, none) = match ty {
+        "i64" => ("chrono::DateTime::from_timestamp(self.{}, 0)", "out of range"),
+        "u64" => (
+            "i64::try_from(self.{}).ok().and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))",
+            "out of range",
+        ),
+        "Option<i64>" => (
+            "self.{}.and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))",
+            "unset or out of range",
+        ),
+        "Option<u64>" => (
+            "self.{}.and_then(|secs| chrono::DateTime::from_timestamp(i64::try_from(secs).ok()?, 0))",
+            "unset or out of range",
+        ),
+        _ => return None,
+    };
+    Some(Accessor {
+        doc: format!("`{}` as a UTC time, `None` if it is {}.", field, none),
+        signature: format!(
+            "{}_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>>",
+            field
+        ),
+        body: body.replace("{}", field),
+    })
+}
+
+fn is_integer(ty: &str) -> bool {
+    matches!(
+        ty,
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+    )
+}
+
+/// The `<field>_ui` accessor of an amount field, `None` if its decimals
+/// can't be read. `decimals_field` is the rust name and type of the field
+/// of a [`Decimals::Field`].
+fn amount(
+    field: &str,
+    ty: &str,
+    decimals: &Decimals,
+    decimals_field: Option<(String, String)>,
+) -> Option<Accessor> {
+    let (scale, of) = match decimals {
+        Decimals::Constant(decimals) => {
+            (format!("1e{}", decimals), format!("{} decimals", decimals))
+        }
+        Decimals::Field(_) => {
+            let (name, decimals_ty) = decimals_field?;
+            if !is_integer(&decimals_ty) {
+                return None;
+            }
+            (
+                format!("10f64.powi(self.{} as i32)", name),
+                format!("`{}` decimals", name),
+            )
+        }
+    };
+    let (ret, body) = match ty.strip_prefix("Option<").and_then(|t| t.strip_suffix('>')) {
+        Some(inner) if is_integer(inner) => (
+            "Option<f64>",
+            format!("self.{}.map(|amount| amount as f64 / {})", field, scale),
+        ),
+        Some(_) => return None,
+        None if is_integer(ty) => ("f64", format!("self.{} as f64 / {}", field, scale)),
+        None => return None,
+    };
+    Some(Accessor {
+        doc: format!("`{}` in UI units, with {}.", field, of),
+        signature: format!("{}_ui(&self) -> {}", field, ret),
+        body,
+    })
+}
+
+/// Writes an impl of struct `name` with a `<field>_datetime` accessor per
+/// integer field matching one of the timestamp patterns, returning it as a
+/// UTC time, and a `<field>_ui` accessor per amount field, returning it
+/// divided by its decimals.
+pub(super) fn define_accessors<'f>(
+    output: &mut impl Write,
+    name: &str,
+    fields: impl IntoIterator<Item = (&'f str, &'f Type)>,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if config.timestamps.is_empty() && config.amounts.is_empty() {
+        return Ok(());
+    }
+    let fields: Vec<_> = fields
+        .into_iter()
+        .map(|(field, ty)| {
+            let rust = rust_type(ty, Some(field), config, &mut HashSet::new());
+            (field, config.naming.fields.apply(field), rust)
+        })
+        .collect();
+    let mut accessors = vec![];
+    for (field, rust_name, ty) in fields.iter() {
+        let snake = field.to_snake_case();
+        if config.timestamps.iter().any(|p| matches(p, &snake)) {
+            accessors.extend(timestamp(rust_name, ty));
+        }
+        if let Some(decimals) = config.amounts.get(*field) {
+            let decimals_field = match decimals {
+                Decimals::Constant(_) => None,
+                Decimals::Field(other) => fields
+                    .iter()
+                    .find(|(f, _, _)| f == other)
+                    .map(|(_, rust_name, ty)| (rust_name.clone(), ty.clone())),
+            };
+            match amount(rust_name, ty, decimals, decimals_field) {
+                Some(accessor) => accessors.push(accessor),
+                None => warn!(
+                    "{}.{} is not an integer amount with integer decimals, no UI amount",
+                    name, field
+                ),
+            }
+        }
+    }
+    if accessors.is_empty() {
+        return Ok(());
+    }
+    output.write_fmt(format_args!("impl {} {{\n", name))?;
+    for (i, accessor) in accessors.iter().enumerate() {
+        if i > 0 {
+            output.write_all(b"\n")?;
+        }
+        output.write_fmt(format_args!(
+            "\t/// {}\n\tpub fn {} {{\n\t\t{}\n\t}}\n",
+            accessor.doc, accessor.signature, accessor.body
+        ))?;
+    }
+    output.write_all(b"}\n")
+}
@@ -0,0 +1,71 @@
+//! anchor_lang account traits of the accounts, see
+//! [`GeneratorConfig::anchor_traits`].
+
+use std::io::Write;
+
+use log::warn;
+
+use crate::config::GeneratorConfig;
+use crate::ir::TypeDef;
+
+/// Writes the `Discriminator`, `Owner`, `AccountDeserialize` and
+/// `AccountSerialize` impls of the accounts among `emitted`, as anchor
+/// derives them for the accounts of a program, owned by the program at
+/// `address`. The (de)serialization ones are left out without the borsh
+/// derive they go through.
+pub(super) fn define_anchor_traits(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    address: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some())
+        .collect();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    let owner = match bs58::decode(address).into_vec() {
+        Ok(id) if id.len() == 32 => Some(id),
+        _ => {
+            warn!(
+                "program address {:?} is not a public key, no Owner impls",
+                address
+            );
+            None
+        }
+    };
+    let serializes = config.derives.iter().any(|d| d.ends_with("BorshSerialize"));
+    let deserializes = config
+        .derives
+        .iter()
+        .any(|d| d.ends_with("BorshDeserialize"));
+    for def in accounts {
+        let name = config.naming.types.apply(&def.name);
+        output.write_fmt(format_args!(
+            "\nimpl anchor_lang::Discriminator for {} {{\n\tconst DISCRIMINATOR: [u8; 8] = {:?};\n}}\n",
+            name,
+            def.discriminator.unwrap_or_default()
+        ))?;
+        if let Some(owner) = &owner {
+            output.write_fmt(format_args!(
+                "\nimpl anchor_lang::Owner for {} {{\n\tfn owner() -> Pubkey {{\n\t\tPubkey::new_from_array({:?})\n\t}}\n}}\n",
+                name, owner
+            ))?;
+        }
+        if deserializes {
+            output.write_fmt(format_args!(
+                "\nimpl anchor_lang::AccountDeserialize for {} {{\n\tfn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {{\n\t\tif buf.len() < 8 {{\n\t\t\treturn Err(anchor_lang::error::ErrorCode::AccountDiscriminatorNotFound.into());\n\t\t}}\n\t\tif buf[..8] != <Self as anchor_lang::Discriminator>::DISCRIMINATOR {{\n\t\t\treturn Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());\n\t\t}}\n\t\tSelf::try_deserialize_unchecked(buf)\n\t}}\n\n\tfn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {{\n\t\tlet mut data = buf.get(8..).unwrap_or_default();\n\t\tborsh::BorshDeserialize::deserialize(&mut data)\n\t\t\t.map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())\n\t}}\n}}\n",
+                name
+            ))?;
+        }
+        if serializes {
+            output.write_fmt(format_args!(
+                "\nimpl anchor_lang::AccountSerialize for {} {{\n\tfn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {{\n\t\twriter\n\t\t\t.write_all(&<Self as anchor_lang::Discriminator>::DISCRIMINATOR)\n\t\t\t.map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;\n\t\tborsh::BorshSerialize::serialize(self, writer)\n\t\t\t.map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotSerialize)?;\n\t\tOk(())\n\t}}\n}}\n",
+                name
+            ))?;
+        }
+    }
+    Ok(())
+}
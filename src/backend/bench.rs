@@ -0,0 +1,193 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::rust::emitted_definitions;
+use super::{Backend, GeneratedFile, ProgramContext};
+use crate::config::{Artifact, GeneratorConfig};
+use crate::error::Result;
+use crate::ir::{Program, Type, TypeDefKind};
+use crate::template::TemplateKind;
+
+/// Emits a criterion bench harness for the bindings generated by
+/// [`RustBackend`](super::RustBackend) with the same config.
+///
+/// The benches decode canned inputs: the smallest valid borsh encoding of
+/// every instruction's args, account and event (zeroes, empty vectors,
+/// `None`, first enum variant). Write the output to the `benches/` directory
+/// of the crate named by [`GeneratorConfig::crate_path`] and add
+/// `[[bench]] name = "<program>" harness = false` to its manifest.
+#[derive(Debug, Default)]
+pub struct BenchBackend {
+    config: GeneratorConfig,
+    module_name: String,
+    program: Option<Program>,
+}
+
+impl Backend for BenchBackend {
+    fn visit_program(&mut self, program: &ProgramContext<'_>) -> Result<()> {
+        self.config = program.config.clone();
+        self.module_name = program.module_name.to_string();
+        self.program = Some(program.program.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<Vec<GeneratedFile>> {
+        let Some(program) = self.program.take() else {
+            return Ok(vec![]);
+        };
+        let config = &self.config;
+        let module = &self.module_name;
+        let mut out = vec![];
+
+        writeln!(
+            out,
+            "use borsh::BorshDeserialize;\nuse criterion::{{black_box, criterion_group, criterion_main, Criterion}};\nuse {}::{}::*;\n",
+            config.crate_path, module
+        )?;
+
+        let mut groups = vec![];
+
+        if config.emits(Artifact::Discriminators) || config.emits(Artifact::InstructionArgs) {
+            groups.push("instructions");
+            writeln!(out, "fn instructions(c: &mut Criterion) {{")?;
+            for ix in program.instructions.iter() {
+                let mut data = ix.discriminator.to_vec();
+                let args = ix
+                    .args
+                    .iter()
+                    .map(|a| zero_encoding(&a.ty, Some(&a.name), &program, config))
+                    .collect::<Option<Vec<_>>>();
+                let Some(args) = args else {
+                    continue;
+                };
+                data.extend(args.concat());
+                writeln!(out, "\tlet data: &[u8] = &{:?};", data)?;
+                if config.emits(Artifact::Discriminators) {
+                    writeln!(
+                        out,
+                        "\tc.bench_function(\"{}/{}/discriminator\", |b| b.iter(|| instruction_name(black_box(data))));",
+                        module, ix.name
+                    )?;
+                }
+                let args_struct = config.emits(Artifact::InstructionArgs)
+                    && !config.templates.contains_key(&TemplateKind::Instruction)
+                    && !ix.args.is_empty();
+                if args_struct {
+                    writeln!(
+                        out,
+                        "\tc.bench_function(\"{}/{}/args\", |b| b.iter(|| {}::try_from_slice(black_box(&data[8..])).unwrap()));",
+                        module,
+                        ix.name,
+                        config.naming.instructions.apply(&ix.name)
+                    )?;
+                }
+            }
+            writeln!(out, "}}\n")?;
+        }
+
+        let emitted = emitted_definitions(&program, config);
+        let accounts: Vec<_> = program
+            .accounts
+            .iter()
+            .filter(|a| emitted.contains(&a.name))
+            .collect();
+        if !accounts.is_empty() {
+            groups.push("accounts");
+            writeln!(out, "fn accounts(c: &mut Criterion) {{")?;
+            for account in accounts {
+                let Some(data) = def_encoding(&account.name, &program, config) else {
+                    continue;
+                };
+                writeln!(
+                    out,
+                    "\tlet data: &[u8] = &{:?};\n\tc.bench_function(\"{}/{}\", |b| b.iter(|| {}::try_from_slice(black_box(data)).unwrap()));",
+                    data,
+                    module,
+                    account.name,
+                    config.naming.types.apply(&account.name)
+                )?;
+            }
+            writeln!(out, "}}\n")?;
+        }
+
+        if config.emits(Artifact::Events) && !program.events.is_empty() {
+            groups.push("events");
+            writeln!(out, "fn events(c: &mut Criterion) {{")?;
+            for event in program.events.iter() {
+                let fields = event
+                    .fields
+                    .iter()
+                    .map(|f| zero_encoding(&f.ty, Some(&f.name), &program, config))
+                    .collect::<Option<Vec<_>>>();
+                let Some(fields) = fields else {
+                    continue;
+                };
+                writeln!(
+                    out,
+                    "\tlet data: &[u8] = &{:?};\n\tc.bench_function(\"{}/{}\", |b| b.iter(|| {}::try_from_slice(black_box(data)).unwrap()));",
+                    fields.concat(),
+                    module,
+                    event.name,
+                    config.naming.types.apply(&event.name)
+                )?;
+            }
+            writeln!(out, "}}\n")?;
+        }
+
+        writeln!(
+            out,
+            "criterion_group!(benches, {});\ncriterion_main!(benches);",
+            groups.join(", ")
+        )?;
+
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.rs", module)),
+            contents: String::from_utf8(out)?,
+        }])
+    }
+}
+
+/// Smallest valid borsh encoding of a value of `ty`, `None` if the rust type
+/// is replaced by an override or hook and its encoding is unknown.
+fn zero_encoding(
+    ty: &Type,
+    field: Option<&str>,
+    program: &Program,
+    config: &GeneratorConfig,
+) -> Option<Vec<u8>> {
+    if config.type_hooks.iter().any(|h| h.map(ty, field).is_some()) {
+        return None;
+    }
+    let bytes = match ty {
+        Type::Bool | Type::U8 | Type::I8 | Type::Option(_) => vec![0],
+        Type::U16 | Type::I16 => vec![0; 2],
+        Type::U32 | Type::I32 | Type::F32 => vec![0; 4],
+        Type::U64 | Type::I64 | Type::F64 => vec![0; 8],
+        Type::U128 | Type::I128 => vec![0; 16],
+        // empty, only the u32 length
+        Type::Bytes | Type::String | Type::Vec(_) => vec![0; 4],
+        Type::PublicKey => vec![0; 32],
+        Type::Array(inner, len) => zero_encoding(inner, field, program, config)?.repeat(*len),
+        Type::Defined(name) => def_encoding(name, program, config)?,
+    };
+    Some(bytes)
+}
+
+fn def_encoding(name: &str, program: &Program, config: &GeneratorConfig) -> Option<Vec<u8>> {
+    let ty = Type::Defined(name.to_string());
+    if config.type_overrides.contains_key(name)
+        || config.type_hooks.iter().any(|h| h.map(&ty, None).is_some())
+    {
+        return None;
+    }
+    let bytes = match &program.find_type(name)?.kind {
+        TypeDefKind::Struct { fields } => fields
+            .iter()
+            .map(|f| zero_encoding(&f.ty, Some(&f.name), program, config))
+            .collect::<Option<Vec<_>>>()?
+            .concat(),
+        // the generated enums only have unit variants
+        TypeDefKind::Enum { .. } => vec![0],
+    };
+    Some(bytes)
+}
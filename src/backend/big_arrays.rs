@@ -0,0 +1,158 @@
+//! Serde of the arrays longer than 32, which serde implements its traits for
+//! only up to that length.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use super::rust::rust_type;
+use super::serde_human::{field_attribute, human_readable, serde_attribute};
+use crate::config::{Feature, GeneratorConfig};
+use crate::error::{ParseIdlError, Result};
+use crate::ir::{EnumFields, Event, Instruction, Type, TypeDef, TypeDefKind};
+
+/// The `big_array` module of the `#[serde(with)]` representation of long
+/// arrays, the sequence of their elements.
+const BIG_ARRAY: &str = r#"
+/// Serde of the arrays longer than 32, as the sequence of their elements.
+mod big_array {
+	pub fn serialize<T: serde::Serialize, const N: usize, S: serde::Serializer>(values: &[T; N], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		serializer.collect_seq(values.iter())
+	}
+
+	pub fn deserialize<'de, T: serde::Deserialize<'de>, const N: usize, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<[T; N], D::Error> {
+		let values = <Vec<T> as serde::Deserialize>::deserialize(deserializer)?;
+		let len = values.len();
+		<[T; N] as std::convert::TryFrom<Vec<T>>>::try_from(values)
+			.map_err(|_| serde::de::Error::invalid_length(len, &"the length of the array"))
+	}
+}
+"#;
+
+/// Writes the `big_array` module the field attributes refer to.
+pub(super) fn define_big_array(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if config.feature_gates {
+        let cfg = format!("#[cfg({})]\nmod big_array", Feature::Serde.predicate());
+        return output.write_all(BIG_ARRAY.replace("mod big_array", &cfg).as_bytes());
+    }
+    output.write_all(BIG_ARRAY.as_bytes())
+}
+
+/// Whether `ty` is an array serde doesn't implement its traits for.
+fn is_big_array(ty: &Type) -> bool {
+    matches!(ty, Type::Array(_, len) if *len > 32)
+}
+
+/// Whether `ty` is or contains a [big array](is_big_array).
+fn has_big_array(ty: &Type) -> bool {
+    match ty {
+        Type::Array(inner, _) => is_big_array(ty) || has_big_array(inner),
+        Type::Option(inner) | Type::Vec(inner) => has_big_array(inner),
+        _ => false,
+    }
+}
+
+/// The `#[serde(with = "big_array")]` attribute of the field `field` of type
+/// `ty`, if it is a big array of elements serde implements its traits for
+/// and isn't typed otherwise by the config.
+pub(super) fn big_array_attribute(
+    ty: &Type,
+    field: &str,
+    config: &GeneratorConfig,
+) -> Option<String> {
+    match ty {
+        Type::Array(inner, _) if is_big_array(ty) && !has_big_array(inner) => {}
+        _ => return None,
+    }
+    let mut unresolved = HashSet::new();
+    if rust_type(ty, Some(field), config, &mut unresolved)
+        != rust_type(ty, None, config, &mut unresolved)
+    {
+        return None;
+    }
+    Some(format!(
+        "\t{}\n",
+        serde_attribute("with = \"big_array\"", config)
+    ))
+}
+
+/// Whether the fields of the serializing types of a program need the
+/// `big_array` module. Fails for a big array serde can't derive for even
+/// with it: in an option, a vector, another array or a tuple variant.
+pub(super) fn uses_big_arrays(
+    instructions: &[Instruction],
+    events: &[Event],
+    definitions: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> Result<bool> {
+    let mut fields: Vec<(&str, Option<&str>, &Type)> = vec![];
+    for ix in instructions {
+        fields.extend(
+            ix.args
+                .iter()
+                .map(|f| (ix.name.as_str(), Some(f.name.as_str()), &f.ty)),
+        );
+    }
+    for event in events {
+        fields.extend(
+            event
+                .fields
+                .iter()
+                .map(|f| (event.name.as_str(), Some(f.name.as_str()), &f.ty)),
+        );
+    }
+    for def in definitions {
+        match &def.kind {
+            TypeDefKind::Struct { fields: named } => fields.extend(
+                named
+                    .iter()
+                    .map(|f| (def.name.as_str(), Some(f.name.as_str()), &f.ty)),
+            ),
+            TypeDefKind::Enum { variants } => {
+                for variant in variants {
+                    match &variant.fields {
+                        Some(EnumFields::Named(named)) => fields.extend(
+                            named
+                                .iter()
+                                .map(|f| (def.name.as_str(), Some(f.name.as_str()), &f.ty)),
+                        ),
+                        Some(EnumFields::Tuple(types)) => {
+                            fields.extend(types.iter().map(|ty| (def.name.as_str(), None, ty)))
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    let mut uses = false;
+    for (parent, field, ty) in fields {
+        if !has_big_array(ty) {
+            continue;
+        }
+        if let Some(field) = field {
+            if human_readable(config) && field_attribute(ty, field, config).is_some() {
+                continue;
+            }
+            if big_array_attribute(ty, field, config).is_some() {
+                uses = true;
+                continue;
+            }
+            let mut unresolved = HashSet::new();
+            if rust_type(ty, Some(field), config, &mut unresolved)
+                != rust_type(ty, None, config, &mut unresolved)
+            {
+                continue;
+            }
+        }
+        return Err(ParseIdlError::UnsupportedType(format!(
+            "{} of {}, serde can't be derived for an array longer than 32 in it",
+            rust_type(ty, None, config, &mut HashSet::new()),
+            field.map_or(parent.to_string(), |field| format!("{}.{}", parent, field)),
+        )));
+    }
+    Ok(uses)
+}
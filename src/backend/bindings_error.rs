@@ -0,0 +1,82 @@
+//! The error of the generated decoders, builders and client, see
+//! [`defines_bindings_error`](super::defines_bindings_error).
+
+use std::io::Write;
+
+use super::client::cfg;
+use super::rust::{defines_cli, defines_client};
+use crate::config::{Feature, GeneratorConfig};
+
+/// Variants every `BindingsError` has: those of the decoders.
+const DECODE_VARIANTS: &[u8] = br#"
+	/// The data starts with a discriminator of none of the expected items.
+	#[error("invalid discriminator {0:?}")]
+	InvalidDiscriminator([u8; 8]),
+	/// The data is shorter than the item it decodes to.
+	#[error("truncated data, expected {expected} bytes, got {got}")]
+	TruncatedData { expected: usize, got: usize },
+	/// An account the call needs is missing.
+	#[error("missing account {name}")]
+	MissingAccount { name: &'static str },
+	/// The fields after the discriminator don't decode.
+	#[error("undecodable data: {0}")]
+	Deserialize(#[from] std::io::Error),
+"#;
+
+/// Variants of the transactions the client and the CLI send.
+const SEND_VARIANTS: &[&str] = &[
+    "\t#[error(\"transaction failed: {0}\")]\n\tTransaction(#[from] solana_sdk::transaction::TransactionError),\n",
+    "\t#[error(\"signing failed: {0}\")]\n\tSigner(#[from] solana_sdk::signer::SignerError),\n",
+    "\t#[error(\"v0 message not compiled: {0}\")]\n\tCompile(#[from] solana_sdk::message::CompileError),\n",
+    "\t#[error(\"transaction not confirmed after {attempts} attempts\")]\n\tNotConfirmed { attempts: usize },\n",
+    "\t#[error(\"{0} is not an address lookup table\")]\n\tNotLookupTable(Pubkey),\n",
+];
+
+/// Writes the thiserror `BindingsError` enum, with variants for the RPC
+/// and transaction failures of the generated RPC code, gated by its
+/// features, and for the [`AccountsError`](super::client) of the builders
+/// when `builders`.
+pub(super) fn define_bindings_error(
+    output: &mut impl Write,
+    builders: bool,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut sends = vec![];
+    if defines_client(config) {
+        sends.push(Feature::Client);
+    }
+    if defines_cli(config) {
+        sends.push(Feature::Cli);
+    }
+    let mut rpc = sends.clone();
+    if config.account_scans {
+        rpc.push(Feature::Ws);
+    }
+    // the variants are indented, unlike the items `cfg` is for
+    let variant_cfg = |features: &[Feature]| match cfg(features, config) {
+        cfg if cfg.is_empty() => cfg,
+        cfg => format!("\t{}", cfg),
+    };
+
+    output.write_all(
+        b"\n/// Why a decoder, builder or RPC call of the bindings failed.\n#[derive(Debug, thiserror::Error)]\npub enum BindingsError {",
+    )?;
+    output.write_all(DECODE_VARIANTS)?;
+    if builders {
+        output.write_all(b"\t#[error(transparent)]\n\tAccounts(#[from] AccountsError),\n")?;
+    }
+    if !rpc.is_empty() {
+        output.write_fmt(format_args!(
+            "{}\t#[error(\"rpc request failed: {{0}}\")]\n\tRpcError(#[from] solana_client::client_error::ClientError),\n",
+            variant_cfg(&rpc)
+        ))?;
+    }
+    if !sends.is_empty() {
+        let cfg = variant_cfg(&sends);
+        for variant in SEND_VARIANTS {
+            output.write_all(cfg.as_bytes())?;
+            output.write_all(variant.as_bytes())?;
+        }
+    }
+    output.write_all(b"}\n")
+}
@@ -0,0 +1,257 @@
+//! Borrowed variants of the generated structs, see
+//! [`GeneratorConfig::borrowed`].
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use super::rust::rust_type;
+use crate::config::GeneratorConfig;
+use crate::ir::{Field, Type, TypeDef, TypeDefKind};
+
+/// Decoding helpers shared by the `decode` functions of a module.
+const HELPERS: &[u8] = br#"
+fn take_bytes<'a>(data: &mut &'a [u8]) -> std::io::Result<&'a [u8]> {
+    let len = <u32 as borsh::BorshDeserialize>::deserialize(data)? as usize;
+    if data.len() < len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "unexpected end of data",
+        ));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+fn take_str<'a>(data: &mut &'a [u8]) -> std::io::Result<&'a str> {
+    std::str::from_utf8(take_bytes(data)?)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+/// Decoding of a value borrowing from the input buffer.
+pub trait DecodeRef<'a>: Sized {
+    /// Decodes a value from the start of `data` and advances it past the value.
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self>;
+}
+impl<'a> DecodeRef<'a> for &'a [u8] {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        take_bytes(data)
+    }
+}
+impl<'a> DecodeRef<'a> for &'a str {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        take_str(data)
+    }
+}
+impl<'a, T: DecodeRef<'a>> DecodeRef<'a> for Option<T> {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        match <u8 as borsh::BorshDeserialize>::deserialize(data)? {
+            0 => Ok(None),
+            _ => T::decode_ref(data).map(Some),
+        }
+    }
+}
+/// A borsh encoded `Vec` of borrowed elements, checked when decoded and
+/// decoded again one at a time as it is iterated, without allocating.
+pub struct RefSeq<'a, T> {
+    data: &'a [u8],
+    len: usize,
+    _item: std::marker::PhantomData<T>,
+}
+impl<'a, T> Clone for RefSeq<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, T> Copy for RefSeq<'a, T> {}
+impl<'a, T: DecodeRef<'a>> RefSeq<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn iter(&self) -> RefIter<'a, T> {
+        RefIter {
+            data: self.data,
+            remaining: self.len,
+            _item: std::marker::PhantomData,
+        }
+    }
+}
+impl<'a, T: DecodeRef<'a>> DecodeRef<'a> for RefSeq<'a, T> {
+    fn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        let len = <u32 as borsh::BorshDeserialize>::deserialize(data)? as usize;
+        let start = *data;
+        for _ in 0..len {
+            T::decode_ref(data)?;
+        }
+        Ok(Self {
+            data: &start[..start.len() - data.len()],
+            len,
+            _item: std::marker::PhantomData,
+        })
+    }
+}
+impl<'a, T: DecodeRef<'a> + std::fmt::Debug> std::fmt::Debug for RefSeq<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+impl<'a, T: DecodeRef<'a>> IntoIterator for RefSeq<'a, T> {
+    type Item = T;
+    type IntoIter = RefIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+/// Iterator over the elements of a [`RefSeq`].
+pub struct RefIter<'a, T> {
+    data: &'a [u8],
+    remaining: usize,
+    _item: std::marker::PhantomData<T>,
+}
+impl<'a, T: DecodeRef<'a>> Iterator for RefIter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(T::decode_ref(&mut self.data).expect("checked when the RefSeq was decoded"))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+impl<'a, T: DecodeRef<'a>> ExactSizeIterator for RefIter<'a, T> {}
+"#;
+
+/// Writes the [`HELPERS`].
+pub(super) fn define_borrowed_helpers(output: &mut impl Write) -> std::io::Result<()> {
+    output.write_all(HELPERS)
+}
+
+pub(super) struct Borrowed<'c> {
+    config: &'c GeneratorConfig,
+    /// The emitted definitions, only these have a `Ref` variant.
+    defs: HashMap<&'c str, &'c TypeDef>,
+}
+
+impl<'c> Borrowed<'c> {
+    pub(super) fn new(config: &'c GeneratorConfig, defs: &[&'c TypeDef]) -> Self {
+        Self {
+            config,
+            defs: defs.iter().map(|d| (d.name.as_str(), *d)).collect(),
+        }
+    }
+
+    /// Whether a struct with these fields gets a `Ref` variant.
+    pub(super) fn any_borrows(&self, fields: &[Field]) -> bool {
+        fields
+            .iter()
+            .any(|f| self.borrows(&f.ty, Some(&f.name), &mut HashSet::new()))
+    }
+
+    fn hooked(&self, ty: &Type, field: Option<&str>) -> bool {
+        if let Type::Defined(name) = ty {
+            if self.config.type_overrides.contains_key(name) {
+                return true;
+            }
+        }
+        self.config
+            .type_hooks
+            .iter()
+            .any(|h| h.map(ty, field).is_some())
+    }
+
+    fn borrows(&self, ty: &Type, field: Option<&str>, seen: &mut HashSet<String>) -> bool {
+        if self.hooked(ty, field) {
+            return false;
+        }
+        match ty {
+            Type::Bytes | Type::String => true,
+            Type::Vec(inner) => **inner == Type::U8 || self.borrows(inner, field, seen),
+            Type::Option(inner) => self.borrows(inner, field, seen),
+            Type::Defined(name) => {
+                let Some(TypeDefKind::Struct { fields }) =
+                    self.defs.get(name.as_str()).map(|d| &d.kind)
+                else {
+                    return false;
+                };
+                if !seen.insert(name.clone()) {
+                    return false;
+                }
+                let borrows = fields
+                    .iter()
+                    .any(|f| self.borrows(&f.ty, Some(&f.name), seen));
+                seen.remove(name);
+                borrows
+            }
+            _ => false,
+        }
+    }
+
+    fn rust_type(&self, ty: &Type, field: Option<&str>) -> String {
+        if !self.borrows(ty, field, &mut HashSet::new()) {
+            return rust_type(ty, field, self.config, &mut HashSet::new());
+        }
+        match ty {
+            Type::String => "&'a str".to_string(),
+            Type::Option(inner) => format!("Option<{}>", self.rust_type(inner, field)),
+            Type::Vec(inner) if **inner != Type::U8 => {
+                format!("RefSeq<'a, {}>", self.rust_type(inner, field))
+            }
+            Type::Defined(name) => format!("{}Ref<'a>", self.config.naming.types.apply(name)),
+            _ => "&'a [u8]".to_string(),
+        }
+    }
+
+    /// Expression decoding a `ty` from `data: &mut &'a [u8]`.
+    fn decode(&self, ty: &Type, field: Option<&str>) -> String {
+        if !self.borrows(ty, field, &mut HashSet::new()) {
+            return format!(
+                "<{} as borsh::BorshDeserialize>::deserialize(data)?",
+                rust_type(ty, field, self.config, &mut HashSet::new())
+            );
+        }
+        "DecodeRef::decode_ref(data)?".to_string()
+    }
+
+    /// Writes `<name>Ref<'a>` and its `decode` function.
+    pub(super) fn write_struct(
+        &self,
+        output: &mut impl Write,
+        name: &str,
+        fields: &[Field],
+    ) -> std::io::Result<()> {
+        // the fields are the point of the borrowed variants, readable
+        // wherever they are
+        let vis = self.config.type_visibility.as_prefix();
+        output.write_fmt(format_args!(
+            "#[derive(Debug, Clone)]\n{}struct {}Ref<'a> {{\n",
+            vis, name
+        ))?;
+        for field in fields.iter() {
+            output.write_fmt(format_args!(
+                "\t{}{}: {},\n",
+                vis,
+                self.config.naming.fields.apply(&field.name),
+                self.rust_type(&field.ty, Some(&field.name))
+            ))?;
+        }
+        output.write_fmt(format_args!(
+            "}}\nimpl<'a> {}Ref<'a> {{\n\t/// Decodes a value from the start of `data` and advances it past the value.\n\t{}fn decode(data: &mut &'a [u8]) -> std::io::Result<Self> {{\n\t\tOk(Self {{\n",
+            name, vis
+        ))?;
+        for field in fields.iter() {
+            output.write_fmt(format_args!(
+                "\t\t\t{}: {},\n",
+                self.config.naming.fields.apply(&field.name),
+                self.decode(&field.ty, Some(&field.name))
+            ))?;
+        }
+        output.write_fmt(format_args!(
+            "\t\t}})\n\t}}\n}}\nimpl<'a> DecodeRef<'a> for {0}Ref<'a> {{\n\tfn decode_ref(data: &mut &'a [u8]) -> std::io::Result<Self> {{\n\t\tSelf::decode(data)\n\t}}\n}}\n",
+            name
+        ))
+    }
+}
@@ -0,0 +1,1036 @@
+//! Instruction builders and the instruction CLI, see
+//! [`GeneratorConfig::builders`] and [`GeneratorConfig::cli`].
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use heck::ToUpperCamelCase;
+use log::warn;
+
+use super::rust::{defines_cli, defines_client, rust_type};
+use crate::config::{Artifact, Feature, GeneratorConfig, Layout, OptionalAccounts};
+use crate::ir::{Instruction, InstructionAccount, Type};
+use crate::template::TemplateKind;
+
+/// Whether the args struct of `ix` is emitted for builders to take.
+fn has_args_struct(ix: &Instruction, config: &GeneratorConfig) -> bool {
+    ix.args.is_empty()
+        || (config.emits(Artifact::InstructionArgs)
+            && !config.templates.contains_key(&TemplateKind::Instruction))
+}
+
+/// Name of the accounts struct of `ix`.
+fn accounts_struct(ix: &Instruction, config: &GeneratorConfig) -> String {
+    format!("{}Accounts", config.naming.instructions.apply(&ix.name))
+}
+
+/// The accounts of `ix` with their field names, prefixed with the groups
+/// they are nested in.
+fn account_fields<'a>(
+    ix: &'a Instruction,
+    config: &GeneratorConfig,
+) -> Vec<(String, &'a InstructionAccount)> {
+    ix.qualified_accounts()
+        .into_iter()
+        .map(|(name, account)| (config.naming.fields.apply(&name.replace('.', "_")), account))
+        .collect()
+}
+
+/// Type of the address of `account`, its newtype if it has one.
+fn account_type<'c>(account: &InstructionAccount, config: &'c GeneratorConfig) -> &'c str {
+    config
+        .pubkey_newtypes
+        .get(&account.name)
+        .map_or("Pubkey", String::as_str)
+}
+
+/// The `Pubkey` of the accounts struct `field`, an `Option` of it for
+/// optional accounts.
+fn account_key(field: &str, account: &InstructionAccount, config: &GeneratorConfig) -> String {
+    let newtype = config.pubkey_newtypes.contains_key(&account.name);
+    match (account.is_optional, newtype) {
+        (false, true) => format!("self.{}.0", field),
+        (true, true) => format!("self.{}.map(|key| key.0)", field),
+        (_, false) => format!("self.{}", field),
+    }
+}
+
+/// `address` as the expression of a `Pubkey` constant.
+fn pubkey_expr(address: &str) -> Option<String> {
+    match bs58::decode(address).into_vec() {
+        Ok(key) if key.len() == 32 => Some(format!("Pubkey::new_from_array({:?})", key)),
+        _ => {
+            warn!(
+                "fixed account address {:?} is not a public key, not checked",
+                address
+            );
+            None
+        }
+    }
+}
+
+/// Name of the function building `ix`.
+fn builder_fn(ix: &Instruction, config: &GeneratorConfig) -> String {
+    format!("{}_ix", config.naming.fields.apply(&ix.name))
+}
+
+/// Program independent part: the compute budget instructions prepended to
+/// the built instructions.
+const BUDGET_HELPERS: &[u8] = br#"
+/// Compute unit limit and price of a transaction, `None` leaves the
+/// defaults of the runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+	pub unit_limit: Option<u32>,
+	/// Priority fee, in micro-lamports per compute unit.
+	pub unit_price: Option<u64>,
+}
+impl ComputeBudget {
+	pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187, 197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0]);
+
+	/// The `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions of
+	/// the budget.
+	pub fn instructions(&self) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut ixs = Vec::new();
+		if let Some(limit) = self.unit_limit {
+			let mut data = vec![2];
+			data.extend_from_slice(&limit.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		if let Some(price) = self.unit_price {
+			let mut data = vec![3];
+			data.extend_from_slice(&price.to_le_bytes());
+			ixs.push(anchor_lang::solana_program::instruction::Instruction {
+				program_id: Self::PROGRAM_ID,
+				accounts: Vec::new(),
+				data,
+			});
+		}
+		ixs
+	}
+
+	/// `ixs` preceded by the instructions of the budget.
+	pub fn prepend(
+		&self,
+		ixs: impl IntoIterator<Item = anchor_lang::solana_program::instruction::Instruction>,
+	) -> Vec<anchor_lang::solana_program::instruction::Instruction> {
+		let mut all = self.instructions();
+		all.extend(ixs);
+		all
+	}
+}
+"#;
+
+/// Program independent part: the error of the account checks of the
+/// accounts structs and the account specs they check against.
+const ACCOUNTS_ERROR: &[u8] = br#"
+/// Accounts of an instruction breaking the constraints of the IDL.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AccountsError {
+	/// `account` must be `expected`, the address the IDL declares.
+	#[error("account {account} of {instruction} must be {expected}, got {actual}")]
+	WrongAddress {
+		instruction: &'static str,
+		account: &'static str,
+		expected: Pubkey,
+		actual: Pubkey,
+	},
+	/// `account` signs the instruction, but `key` is none of the signers.
+	#[error("account {account} of {instruction} must sign, but {key} is not a signer")]
+	MissingSigner {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// `account` is written by the instruction, but `key` is read-only.
+	#[error("account {account} of {instruction} must be writable, but {key} is read-only")]
+	NotWritable {
+		instruction: &'static str,
+		account: &'static str,
+		key: Pubkey,
+	},
+	/// The instruction takes at least `expected` accounts.
+	#[error("{instruction} takes at least {expected} accounts, got {actual}")]
+	TooFewAccounts {
+		instruction: &'static str,
+		expected: usize,
+		actual: usize,
+	},
+}
+
+/// An account of an instruction, as the IDL declares it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSpec {
+	pub name: &'static str,
+	pub is_signer: bool,
+	pub is_writable: bool,
+	pub is_optional: bool,
+	/// The address the IDL declares the account must have.
+	pub address: Option<Pubkey>,
+}
+"#;
+
+/// The check of the account metas against the `ACCOUNTS` table of an
+/// instruction, with the program ID in place of the optional accounts left
+/// out.
+const CHECK_ACCOUNT_METAS: &[u8] = br#"
+/// Checks the account `metas` of `instruction` against its `accounts`:
+/// their count, and that the signer and writable accounts are and those
+/// with a fixed address have it. The metas past the accounts are remaining
+/// accounts, which aren't checked. When `omitted` optional accounts may be
+/// left out, which shifts the accounts after them, so only those before
+/// the first optional account are checked; otherwise the program ID takes
+/// the place of an optional account left out.
+fn check_account_metas(
+	instruction: &'static str,
+	accounts: &[AccountSpec],
+	metas: &[anchor_lang::solana_program::instruction::AccountMeta],
+	omitted: bool,
+) -> std::result::Result<(), AccountsError> {
+	let expected = accounts.iter().filter(|a| !(omitted && a.is_optional)).count();
+	if metas.len() < expected {
+		return Err(AccountsError::TooFewAccounts {
+			instruction,
+			expected,
+			actual: metas.len(),
+		});
+	}
+	for (account, meta) in accounts.iter().zip(metas) {
+		if account.is_optional && omitted {
+			break;
+		}
+		if account.is_optional && meta.pubkey == PROGRAM_ID {
+			continue;
+		}
+		if let Some(expected) = account.address.filter(|key| *key != meta.pubkey) {
+			return Err(AccountsError::WrongAddress {
+				instruction,
+				account: account.name,
+				expected,
+				actual: meta.pubkey,
+			});
+		}
+		if account.is_signer && !meta.is_signer {
+			return Err(AccountsError::MissingSigner {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+		if account.is_writable && !meta.is_writable {
+			return Err(AccountsError::NotWritable {
+				instruction,
+				account: account.name,
+				key: meta.pubkey,
+			});
+		}
+	}
+	Ok(())
+}
+"#;
+
+/// Writes `validate`, checking the accounts of `ix` with a fixed address,
+/// `check_signers`, checking its signer accounts sign, and the `ACCOUNTS`
+/// table of the IDL with `check_metas`, checking account metas against it.
+fn define_account_checks(
+    output: &mut impl Write,
+    ix: &Instruction,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let vis = config.type_visibility.as_prefix();
+    output.write_fmt(format_args!(
+        "\t/// Checks the accounts with an address the IDL fixes.\n\t{}fn validate(&self) -> std::result::Result<(), AccountsError> {{\n",
+        vis
+    ))?;
+    let accounts: Vec<_> = ix
+        .qualified_accounts()
+        .into_iter()
+        .zip(account_fields(ix, config))
+        .map(|((name, account), (field, _))| (name, field, account))
+        .collect();
+    let key = |field: &str, account: &InstructionAccount| {
+        let key = account_key(field, account, config);
+        if account.is_optional {
+            key
+        } else {
+            format!("Some({})", key)
+        }
+    };
+    let addresses: Vec<_> = accounts
+        .iter()
+        .map(|(_, _, account)| account.address.as_deref().and_then(pubkey_expr))
+        .collect();
+    for ((name, field, account), expected) in accounts.iter().zip(addresses.iter()) {
+        let Some(expected) = expected else {
+            continue;
+        };
+        output.write_fmt(format_args!(
+            "\t\tlet expected = {};\n\t\tif let Some(actual) = {}.filter(|key| *key != expected) {{\n\t\t\treturn Err(AccountsError::WrongAddress {{\n\t\t\t\tinstruction: {:?},\n\t\t\t\taccount: {:?},\n\t\t\t\texpected,\n\t\t\t\tactual,\n\t\t\t}});\n\t\t}}\n",
+            expected,
+            key(field, account),
+            ix.name,
+            name
+        ))?;
+    }
+    output.write_fmt(format_args!(
+        "\t\tOk(())\n\t}}\n\n\t/// Checks that the signer accounts are among `signers`.\n\t{}fn check_signers(&self, signers: &[Pubkey]) -> std::result::Result<(), AccountsError> {{\n",
+        vis
+    ))?;
+    let signers: Vec<_> = accounts
+        .iter()
+        .filter(|(_, _, account)| account.is_signer)
+        .map(|(name, field, account)| format!("({:?}, {})", name, key(field, account)))
+        .collect();
+    if signers.is_empty() {
+        output.write_all(b"\t\tlet _ = signers;\n")?;
+    } else {
+        output.write_fmt(format_args!(
+            "\t\tfor (account, key) in [{}] {{\n\t\t\tif let Some(key) = key.filter(|key| !signers.contains(key)) {{\n\t\t\t\treturn Err(AccountsError::MissingSigner {{\n\t\t\t\t\tinstruction: {:?},\n\t\t\t\t\taccount,\n\t\t\t\t\tkey,\n\t\t\t\t}});\n\t\t\t}}\n\t\t}}\n",
+            signers.join(", "),
+            ix.name
+        ))?;
+    }
+    output.write_fmt(format_args!(
+        "\t\tOk(())\n\t}}\n\n\t/// The accounts of the instruction the IDL declares, in order.\n\t{}const ACCOUNTS: &[AccountSpec] = &[\n",
+        vis
+    ))?;
+    for ((name, _, account), address) in accounts.iter().zip(addresses) {
+        output.write_fmt(format_args!(
+            "\t\tAccountSpec {{\n\t\t\tname: {:?},\n\t\t\tis_signer: {},\n\t\t\tis_writable: {},\n\t\t\tis_optional: {},\n\t\t\taddress: {},\n\t\t}},\n",
+            name,
+            account.is_signer,
+            account.is_mut,
+            account.is_optional,
+            address.map_or("None".to_string(), |a| format!("Some({})", a))
+        ))?;
+    }
+    output.write_fmt(format_args!(
+        "\t];\n\n\t/// Checks account metas of the instruction, such as ones assembled by\n\t/// hand, against [`Self::ACCOUNTS`].\n\t{}fn check_metas(metas: &[anchor_lang::solana_program::instruction::AccountMeta]) -> std::result::Result<(), AccountsError> {{\n\t\tcheck_account_metas({:?}, Self::ACCOUNTS, metas, {})\n\t}}\n",
+        vis,
+        ix.name,
+        config.optional_accounts == OptionalAccounts::Omit
+    ))
+}
+
+/// Writes the [`BUDGET_HELPERS`] and the [`ACCOUNTS_ERROR`] of the builders.
+pub(super) fn define_builder_helpers(output: &mut impl Write) -> std::io::Result<()> {
+    output.write_all(BUDGET_HELPERS)?;
+    output.write_all(ACCOUNTS_ERROR)
+}
+
+/// Writes the `PROGRAM_ID` constant of the program at `address`, unless it
+/// is not a public key, and returns whether it did.
+pub(super) fn define_program_id(
+    output: &mut impl Write,
+    address: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<bool> {
+    let id = match bs58::decode(address).into_vec() {
+        Ok(id) if id.len() == 32 => id,
+        _ => {
+            warn!(
+                "program address {:?} is not a public key, no PROGRAM_ID or builders",
+                address
+            );
+            return Ok(false);
+        }
+    };
+    output.write_fmt(format_args!(
+        "\n{}const PROGRAM_ID: Pubkey = Pubkey::new_from_array({:?});\n",
+        config.type_visibility.as_prefix(),
+        id
+    ))?;
+    Ok(true)
+}
+
+/// Writes the `ComputeBudget`, then for every instruction a
+/// `<Name>Accounts` struct of its account addresses and a `<name>_ix`
+/// function building the instruction from them, the args struct and the
+/// `PROGRAM_ID`.
+pub(super) fn define_builders(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    program: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let vis = config.type_visibility.as_prefix();
+    if !config.shared_support {
+        define_builder_helpers(output)?;
+    }
+    output.write_all(CHECK_ACCOUNT_METAS)?;
+
+    for ix in instructions.iter() {
+        if !has_args_struct(ix, config) {
+            warn!("no args struct for {}, no builder", ix.name);
+            continue;
+        }
+        let accounts = accounts_struct(ix, config);
+        output.write_fmt(format_args!(
+            "/// Accounts of the `{}` instruction.\n#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n{}struct {} {{\n",
+            ix.name, vis, accounts
+        ))?;
+        for (field, account) in account_fields(ix, config) {
+            let ty = account_type(account, config);
+            if account.is_optional {
+                output.write_fmt(format_args!("\tpub {}: Option<{}>,\n", field, ty))?;
+            } else {
+                output.write_fmt(format_args!("\tpub {}: {},\n", field, ty))?;
+            }
+        }
+        let omits = config.optional_accounts == OptionalAccounts::Omit
+            && ix.flat_accounts().iter().any(|a| a.is_optional);
+        output.write_fmt(format_args!(
+            "}}\nimpl {} {{\n\t/// The account metas, in the order the program expects them.\n\t{}fn metas(&self) -> Vec<anchor_lang::solana_program::instruction::AccountMeta> {{\n\t\t{}vec![\n",
+            accounts,
+            vis,
+            if omits { "let metas = " } else { "" }
+        ))?;
+        for (field, account) in account_fields(ix, config) {
+            let constructor = if account.is_mut {
+                "new"
+            } else {
+                "new_readonly"
+            };
+            let meta = format!(
+                "anchor_lang::solana_program::instruction::AccountMeta::{}({{}}, {})",
+                constructor, account.is_signer
+            );
+            let key = account_key(&field, account, config);
+            let meta = match (account.is_optional, omits) {
+                (false, false) => meta.replace("{}", &key),
+                (false, true) => format!("Some({})", meta.replace("{}", &key)),
+                (true, false) => format!(
+                    "match {} {{\n\t\t\t\tSome(key) => {},\n\t\t\t\tNone => anchor_lang::solana_program::instruction::AccountMeta::new_readonly(PROGRAM_ID, false),\n\t\t\t}}",
+                    key,
+                    meta.replace("{}", "key")
+                ),
+                (true, true) => format!("{}.map(|key| {})", key, meta.replace("{}", "key")),
+            };
+            output.write_fmt(format_args!("\t\t\t{},\n", meta))?;
+        }
+        if omits {
+            output.write_all(b"\t\t];\n\t\tmetas.into_iter().flatten().collect()\n\t}\n")?;
+        } else {
+            output.write_all(b"\t\t]\n\t}\n")?;
+        }
+        define_account_checks(output, ix, config)?;
+        output.write_all(b"}\n")?;
+
+        let args = if ix.args.is_empty() {
+            String::new()
+        } else {
+            format!(", args: &{}", config.naming.instructions.apply(&ix.name))
+        };
+        output.write_fmt(format_args!(
+            "/// Builds the `{}` instruction.\n{}{}fn {}(accounts: &{}{}) -> anchor_lang::solana_program::instruction::Instruction {{\n\tlet {}data = {:?}.to_vec();\n",
+            ix.name,
+            config.instrument(
+                "",
+                "trace",
+                &format!("program = {:?}, instruction = {:?}", program, ix.name)
+            ),
+            vis,
+            builder_fn(ix, config),
+            accounts,
+            args,
+            if ix.args.is_empty() { "" } else { "mut " },
+            ix.discriminator
+        ))?;
+        if !ix.args.is_empty() {
+            output.write_all(
+                b"\tborsh::BorshSerialize::serialize(args, &mut data).expect(\"writing to a Vec can't fail\");\n",
+            )?;
+        }
+        output.write_all(
+            b"\tanchor_lang::solana_program::instruction::Instruction {\n\t\tprogram_id: PROGRAM_ID,\n\t\taccounts: accounts.metas(),\n\t\tdata,\n\t}\n}\n",
+        )?;
+    }
+    Ok(())
+}
+
+/// How an arg is read from the command line.
+enum CliArg {
+    /// Parsed by clap, the rust type is the field type.
+    Flag(String),
+    /// A hex string of the borsh encoding, decoded into the rust type.
+    Borsh(String),
+}
+
+/// Clap parses primitives, strings and public keys, optional and repeated
+/// ones too, everything else goes through [`CliArg::Borsh`], as do the
+/// fields typed by their name, such as flags and newtypes.
+fn cli_arg(ty: &Type, field: &str, config: &GeneratorConfig) -> CliArg {
+    let simple = |ty: &Type| {
+        !matches!(
+            ty,
+            Type::Bytes | Type::Defined(_) | Type::Option(_) | Type::Vec(_) | Type::Array(..)
+        ) && config
+            .type_hooks
+            .iter()
+            .all(|h| h.map(ty, Some(field)).is_none())
+    };
+    let rust = rust_type(ty, Some(field), config, &mut HashSet::new());
+    if rust != rust_type(ty, None, config, &mut HashSet::new()) {
+        return CliArg::Borsh(rust);
+    }
+    match ty {
+        Type::Option(inner) | Type::Vec(inner) if simple(inner) => CliArg::Flag(rust),
+        ty if simple(ty) => CliArg::Flag(rust),
+        _ => CliArg::Borsh(rust),
+    }
+}
+
+/// Decodes the [`CliArg::Borsh`] args.
+const CLI_HELPERS: &[&str] = &[
+    r#"fn cli_borsh<T: borsh::BorshDeserialize>(hex: &str) -> std::result::Result<T, Box<dyn std::error::Error>> {
+	if hex.len() % 2 != 0 || !hex.is_ascii() {
+		return Err(format!("invalid hex {:?}", hex).into());
+	}
+	let bytes = (0..hex.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+		.collect::<std::result::Result<Vec<u8>, _>>()?;
+	Ok(T::try_from_slice(&bytes)?)
+}
+"#,
+];
+
+/// Program independent items of the CLI and the client: reading lookup
+/// tables and building transactions with them.
+const SEND_HELPERS: &[&str] = &[
+    r#"/// Fetches and parses the address lookup table at `address`.
+pub fn fetch_lookup_table(
+	rpc: &solana_client::rpc_client::RpcClient,
+	address: &Pubkey,
+) -> std::result::Result<solana_sdk::address_lookup_table_account::AddressLookupTableAccount, BindingsError> {
+	// the addresses follow the 56 byte metadata of the table
+	let data = rpc.get_account_data(address)?;
+	let addresses = data
+		.get(56..)
+		.filter(|a| a.len() % 32 == 0)
+		.ok_or(BindingsError::NotLookupTable(*address))?
+		.chunks(32)
+		.map(|a| Pubkey::new_from_array(a.try_into().expect("chunks of 32")))
+		.collect();
+	Ok(solana_sdk::address_lookup_table_account::AddressLookupTableAccount {
+		key: *address,
+		addresses,
+	})
+}
+"#,
+    r#"/// A transaction of `ixs` paid by `payer` and signed by `signers`, any
+/// [`Signer`](solana_sdk::signer::Signer) such as a keypair, a hardware
+/// wallet or a remote signer. With lookup tables it is a v0 transaction
+/// referencing the accounts found in them through them, otherwise a legacy
+/// one.
+pub fn versioned_transaction(
+	payer: &Pubkey,
+	ixs: &[anchor_lang::solana_program::instruction::Instruction],
+	lookup_tables: &[solana_sdk::address_lookup_table_account::AddressLookupTableAccount],
+	signers: &[&dyn solana_sdk::signer::Signer],
+	blockhash: solana_sdk::hash::Hash,
+) -> std::result::Result<solana_sdk::transaction::VersionedTransaction, BindingsError> {
+	let message = if lookup_tables.is_empty() {
+		solana_sdk::message::VersionedMessage::Legacy(solana_sdk::message::Message::new_with_blockhash(ixs, Some(payer), &blockhash))
+	} else {
+		solana_sdk::message::VersionedMessage::V0(solana_sdk::message::v0::Message::try_compile(payer, ixs, lookup_tables, blockhash)?)
+	};
+	Ok(solana_sdk::transaction::VersionedTransaction::try_new(message, signers)?)
+}
+"#,
+    r#"/// The accounts of `ixs` a lookup table can hold: all but the signers and
+/// the invoked programs, in order of first use.
+pub fn lookup_table_candidates(ixs: &[anchor_lang::solana_program::instruction::Instruction]) -> Vec<Pubkey> {
+	let excluded: std::collections::HashSet<Pubkey> = ixs
+		.iter()
+		.flat_map(|ix| {
+			ix.accounts
+				.iter()
+				.filter(|meta| meta.is_signer)
+				.map(|meta| meta.pubkey)
+				.chain([ix.program_id])
+		})
+		.collect();
+	let mut candidates = Vec::new();
+	for meta in ixs.iter().flat_map(|ix| ix.accounts.iter()) {
+		if !excluded.contains(&meta.pubkey) && !candidates.contains(&meta.pubkey) {
+			candidates.push(meta.pubkey);
+		}
+	}
+	candidates
+}
+"#,
+];
+
+/// The `cfg` attribute of items needing one of `features`, empty without
+/// feature gates.
+pub(super) fn cfg(features: &[Feature], config: &GeneratorConfig) -> String {
+    match features {
+        _ if !config.feature_gates => String::new(),
+        [feature] => format!("#[cfg({})]\n", feature.predicate()),
+        features => {
+            let predicates: Vec<_> = features.iter().map(Feature::predicate).collect();
+            format!("#[cfg(any({}))]\n", predicates.join(", "))
+        }
+    }
+}
+
+/// Writes the items the CLI and the client share, gated by the features of
+/// those emitted.
+pub(super) fn define_send_helpers(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut features = vec![];
+    if defines_client(config) {
+        features.push(Feature::Client);
+    }
+    if defines_cli(config) {
+        features.push(Feature::Cli);
+    }
+    let cfg = cfg(&features, config);
+    for item in SEND_HELPERS {
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes the [`CLI_HELPERS`].
+pub(super) fn define_cli_helpers(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Cli], config);
+    for item in CLI_HELPERS {
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes a clap `Cli` with a subcommand per instruction and `run_cli`,
+/// which builds the instruction, signs it with a keypair file and sends it.
+pub(super) fn define_cli(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let instructions: Vec<_> = instructions
+        .iter()
+        .filter(|ix| has_args_struct(ix, config))
+        .collect();
+    let cfg = cfg(&[Feature::Cli], config);
+    let vis = config.type_visibility.as_prefix();
+
+    if !config.shared_support {
+        define_cli_helpers(output, config)?;
+    }
+    output.write_fmt(format_args!(
+        "/// Sends an instruction of the program, see [`run_cli`].\n{0}#[derive(clap::Parser)]\n{1}struct Cli {{\n\t/// RPC endpoint\n\t#[arg(long, default_value = \"http://127.0.0.1:8899\")]\n\tpub url: String,\n\t/// Keypair paying for and signing the transaction, default: the solana\n\t/// CLI keypair\n\t#[arg(long)]\n\tpub keypair: Option<String>,\n\t/// Compute unit limit of the transaction\n\t#[arg(long)]\n\tpub compute_unit_limit: Option<u32>,\n\t/// Priority fee of the transaction, in micro-lamports per compute unit\n\t#[arg(long)]\n\tpub compute_unit_price: Option<u64>,\n\t/// Address lookup table to send a v0 transaction with, repeatable\n\t#[arg(long)]\n\tpub lookup_table: Vec<Pubkey>,\n\t#[command(subcommand)]\n\tpub command: CliCommand,\n}}\n",
+        cfg, vis
+    ))?;
+
+    output.write_fmt(format_args!(
+        "/// The instructions, signer accounts default to the keypair. Struct,\n/// enum, byte and nested option or vector args are hex encoded borsh.\n{}#[derive(clap::Subcommand)]\n{}enum CliCommand {{\n",
+        cfg, vis
+    ))?;
+    for ix in instructions.iter() {
+        output.write_fmt(format_args!(
+            "\t/// Sends `{}`.\n\t{} {{\n",
+            ix.name,
+            config.naming.instructions.apply(&ix.name)
+        ))?;
+        for (field, account) in account_fields(ix, config) {
+            let ty = account_type(account, config);
+            let ty = if account.is_signer || account.is_optional {
+                format!("Option<{}>", ty)
+            } else {
+                ty.to_string()
+            };
+            output.write_fmt(format_args!("\t\t#[arg(long)]\n\t\t{}: {},\n", field, ty))?;
+        }
+        for arg in ix.args.iter() {
+            let ty = match cli_arg(&arg.ty, &arg.name, config) {
+                CliArg::Flag(ty) => ty,
+                CliArg::Borsh(_) => "String".to_string(),
+            };
+            output.write_fmt(format_args!(
+                "\t\t#[arg(long)]\n\t\t{}: {},\n",
+                config.naming.fields.apply(&arg.name),
+                ty
+            ))?;
+        }
+        output.write_all(b"\t},\n")?;
+    }
+    output.write_all(b"}\n")?;
+
+    output.write_fmt(format_args!(
+        "/// Builds the instruction of the command, signs it with the keypair and\n/// sends it after the compute budget instructions, in a v0 transaction if\n/// lookup tables are given.\n{}{}fn run_cli(cli: Cli) -> std::result::Result<solana_sdk::signature::Signature, Box<dyn std::error::Error>> {{\n\tuse solana_sdk::signer::Signer;\n\tlet path = match cli.keypair {{\n\t\tSome(path) => path,\n\t\tNone => format!(\"{{}}/.config/solana/id.json\", std::env::var(\"HOME\")?),\n\t}};\n\tlet payer = solana_sdk::signature::read_keypair_file(&path)?;\n\tlet ix = match cli.command {{\n",
+        cfg, vis
+    ))?;
+    for ix in instructions.iter() {
+        let fields: Vec<_> = account_fields(ix, config)
+            .into_iter()
+            .map(|(field, _)| field)
+            .chain(ix.args.iter().map(|a| config.naming.fields.apply(&a.name)))
+            .collect();
+        output.write_fmt(format_args!(
+            "\t\tCliCommand::{} {{ {} }} => {{\n\t\t\tlet accounts = {} {{\n",
+            config.naming.instructions.apply(&ix.name),
+            fields.join(", "),
+            accounts_struct(ix, config)
+        ))?;
+        for (name, account) in account_fields(ix, config) {
+            if account.is_signer && !account.is_optional {
+                let into = if config.pubkey_newtypes.contains_key(&account.name) {
+                    ".into()"
+                } else {
+                    ""
+                };
+                output.write_fmt(format_args!(
+                    "\t\t\t\t{0}: {0}.unwrap_or(payer.pubkey(){1}),\n",
+                    name, into
+                ))?;
+            } else {
+                output.write_fmt(format_args!("\t\t\t\t{},\n", name))?;
+            }
+        }
+        output.write_fmt(format_args!(
+            "\t\t\t}};\n\t\t\taccounts.validate()?;\n\t\t\taccounts.check_signers(&[payer.pubkey()])?;\n\t\t\t{}(\n\t\t\t\t&accounts,\n",
+            builder_fn(ix, config)
+        ))?;
+        if !ix.args.is_empty() {
+            output.write_fmt(format_args!(
+                "\t\t\t\t&{} {{\n",
+                config.naming.instructions.apply(&ix.name)
+            ))?;
+            for arg in ix.args.iter() {
+                let name = config.naming.fields.apply(&arg.name);
+                match cli_arg(&arg.ty, &arg.name, config) {
+                    CliArg::Flag(_) => output.write_fmt(format_args!("\t\t\t\t\t{},\n", name))?,
+                    CliArg::Borsh(ty) => output.write_fmt(format_args!(
+                        "\t\t\t\t\t{0}: cli_borsh::<{1}>(&{0})?,\n",
+                        name, ty
+                    ))?,
+                }
+            }
+            if config.trailing_bytes {
+                output.write_all(b"\t\t\t\t\textra: Vec::new(),\n")?;
+            }
+            output.write_all(b"\t\t\t\t},\n")?;
+        }
+        output.write_all(b"\t\t\t)\n\t\t}\n")?;
+    }
+    output.write_all(
+        b"\t};\n\tlet budget = ComputeBudget {\n\t\tunit_limit: cli.compute_unit_limit,\n\t\tunit_price: cli.compute_unit_price,\n\t};\n\tlet rpc = solana_client::rpc_client::RpcClient::new(cli.url);\n\tlet ixs = budget.prepend([ix]);\n\tlet tables = cli\n\t\t.lookup_table\n\t\t.iter()\n\t\t.map(|address| fetch_lookup_table(&rpc, address))\n\t\t.collect::<std::result::Result<Vec<_>, _>>()?;\n\tlet blockhash = rpc.get_latest_blockhash()?;\n\tlet tx = versioned_transaction(&payer.pubkey(), &ixs, &tables, &[&payer], blockhash)?;\n\tOk(rpc.send_and_confirm_transaction(&tx)?)\n}\n",
+    )?;
+    Ok(())
+}
+
+/// Program independent part of the client, `Client` is replaced by the name
+/// of the client struct.
+const CLIENT: &[&str] = &[
+    r#"/// How the client sends and confirms transactions.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+	/// Commitment of the RPC calls and of the confirmation.
+	pub commitment: solana_sdk::commitment_config::CommitmentConfig,
+	/// Compute budget instructions prepended to every transaction.
+	pub compute_budget: ComputeBudget,
+	/// Lookup tables making transactions v0 ones, see [`fetch_lookup_table`].
+	pub lookup_tables: Vec<solana_sdk::address_lookup_table_account::AddressLookupTableAccount>,
+	/// Times a transaction is sent again after it failed to send or to
+	/// confirm. Preflight and program errors are not retried.
+	pub max_retries: usize,
+	/// Wait before the first retry, doubled after every one.
+	pub retry_backoff: std::time::Duration,
+	/// Sign a retry with a fresh blockhash once the blockhash of the
+	/// previous attempt expired, as until then that transaction can still
+	/// land and a new one would run the instructions twice. Otherwise every
+	/// attempt resends the first transaction, which can't land twice but
+	/// never lands after its blockhash expired.
+	pub refresh_blockhash: bool,
+	/// How long an attempt waits for the confirmation.
+	pub confirm_timeout: std::time::Duration,
+	/// Interval of the signature status polls while confirming.
+	pub poll_interval: std::time::Duration,
+}
+"#,
+    r#"impl Default for ClientConfig {
+	fn default() -> Self {
+		Self {
+			commitment: solana_sdk::commitment_config::CommitmentConfig::confirmed(),
+			compute_budget: ComputeBudget::default(),
+			lookup_tables: Vec::new(),
+			max_retries: 3,
+			retry_backoff: std::time::Duration::from_millis(500),
+			refresh_blockhash: true,
+			confirm_timeout: std::time::Duration::from_secs(60),
+			poll_interval: std::time::Duration::from_millis(500),
+		}
+	}
+}
+"#,
+    r#"/// Sends the instructions of the program over RPC.
+pub struct Client {
+	rpc: solana_client::rpc_client::RpcClient,
+	config: ClientConfig,
+}
+"#,
+    r#"impl Client {
+	pub fn new(url: impl ToString, config: ClientConfig) -> Self {
+		let rpc = solana_client::rpc_client::RpcClient::new_with_commitment(url.to_string(), config.commitment);
+		Self { rpc, config }
+	}
+
+	pub fn rpc(&self) -> &solana_client::rpc_client::RpcClient {
+		&self.rpc
+	}
+
+	pub fn config(&self) -> &ClientConfig {
+		&self.config
+	}
+
+	/// Sends `ixs` after the compute budget instructions, paid by the first
+	/// of `signers`, and waits for the confirmation, retrying as configured.
+{send_span}	pub fn send(
+		&self,
+		ixs: &[anchor_lang::solana_program::instruction::Instruction],
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {
+		let payer = signers.first().ok_or(BindingsError::MissingAccount { name: "payer" })?.pubkey();
+		let ixs = self.config.compute_budget.prepend(ixs.iter().cloned());
+		let send_config = solana_client::rpc_config::RpcSendTransactionConfig {
+			preflight_commitment: Some(self.config.commitment.commitment),
+			max_retries: Some(0),
+			..Default::default()
+		};
+		let mut backoff = self.config.retry_backoff;
+		// the last transaction sent and its blockhash
+		let mut sent: Option<(solana_sdk::transaction::VersionedTransaction, solana_sdk::hash::Hash)> = None;
+		for attempt in 0..=self.config.max_retries {
+			if attempt > 0 {
+				std::thread::sleep(backoff);
+				backoff *= 2;
+			}
+			if let Some((tx, blockhash)) = sent.take().filter(|_| self.config.refresh_blockhash) {
+				// the previous transaction can land until its blockhash expires
+				let signature = tx.signatures[0];
+				loop {
+					let valid = self.rpc.is_blockhash_valid(&blockhash, self.config.commitment)?;
+					match self.rpc.get_signature_status_with_commitment(&signature, self.config.commitment)? {
+						Some(Ok(())) => return Ok(signature),
+						Some(Err(e)) => return Err(e.into()),
+						None if valid => std::thread::sleep(self.config.poll_interval),
+						None => break,
+					}
+				}
+			}
+			if sent.is_none() {
+				let (blockhash, _) =
+					self.rpc.get_latest_blockhash_with_commitment(self.config.commitment)?;
+				let tx = versioned_transaction(&payer, &ixs, &self.config.lookup_tables, signers, blockhash)?;
+				sent = Some((tx, blockhash));
+			}
+			let (tx, _) = sent.as_ref().expect("built above");
+			let signature = match self.rpc.send_transaction_with_config(tx, send_config) {
+				Ok(signature) => signature,
+				// preflight and program errors fail the same way again
+				Err(e)
+					if attempt < self.config.max_retries
+						&& matches!(
+							e.get_transaction_error(),
+							None | Some(solana_sdk::transaction::TransactionError::BlockhashNotFound)
+						) =>
+				{
+{send_failed}					continue;
+				}
+				Err(e) => return Err(e.into()),
+			};
+{record_signature}			let started = std::time::Instant::now();
+			while started.elapsed() < self.config.confirm_timeout {
+				let status = self.rpc.get_signature_status_with_commitment(&signature, self.config.commitment)?;
+				match status {
+					Some(Ok(())) => return Ok(signature),
+					Some(Err(e)) => return Err(e.into()),
+					None => std::thread::sleep(self.config.poll_interval),
+				}
+			}
+{not_confirmed}		}
+		Err(BindingsError::NotConfirmed {
+			attempts: self.config.max_retries + 1,
+		})
+	}
+"#,
+];
+
+/// `simulate` of the client, `{error}` and `{events}` filled in.
+const SIMULATE: &str = r#"
+	/// Simulates `ixs` after the compute budget instructions, paid by the
+	/// first of `signers`, without sending them.
+{simulate_span}	pub fn simulate(
+		&self,
+		ixs: &[anchor_lang::solana_program::instruction::Instruction],
+		signers: &[&dyn solana_sdk::signer::Signer],
+	) -> std::result::Result<Simulation, BindingsError> {
+		let payer = signers.first().ok_or(BindingsError::MissingAccount { name: "payer" })?.pubkey();
+		let ixs = self.config.compute_budget.prepend(ixs.iter().cloned());
+		let blockhash = self.rpc.get_latest_blockhash()?;
+		let tx = versioned_transaction(&payer, &ixs, &self.config.lookup_tables, signers, blockhash)?;
+		let simulate_config = solana_client::rpc_config::RpcSimulateTransactionConfig {
+			commitment: Some(self.config.commitment),
+			..Default::default()
+		};
+		let result = self.rpc.simulate_transaction_with_config(&tx, simulate_config)?.value;
+		let logs = result.logs.unwrap_or_default();
+		Ok(Simulation {
+{error}{events}			units_consumed: result.units_consumed,
+			logs,
+			err: result.err,
+		})
+	}
+"#;
+
+/// Writes `ClientConfig` and a `<Module>Client` sending the instructions
+/// with it, a `send_` and a `simulate_` method per instruction so they
+/// can't collide with the inherent `new`, `send` or `rpc`. The simulations
+/// decode the program's error when `decode_errors` and its events when
+/// `decode_events`.
+pub(super) fn define_client(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    module_name: &str,
+    program: &str,
+    decode_errors: bool,
+    decode_events: bool,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Client], config);
+    let name = format!("{}Client", module_name.to_upper_camel_case());
+    let program_field = format!("program = {:?}", program);
+    for item in CLIENT {
+        let item = item
+            .replace("struct Client {", &format!("struct {} {{", name))
+            .replace("impl Client {", &format!("impl {} {{", name))
+            .replace(
+                "{send_span}",
+                &config.instrument(
+                    "\t",
+                    "debug",
+                    &format!("{}, signature = tracing::field::Empty", program_field),
+                ),
+            )
+            .replace(
+                "{send_failed}",
+                &config.traced(
+                    "\t\t\t\t\t",
+                    "tracing::warn!(attempt, error = %e, \"send failed, retrying\");",
+                ),
+            )
+            .replace(
+                "{record_signature}",
+                &config.traced(
+                    "\t\t\t",
+                    "tracing::Span::current().record(\"signature\", tracing::field::display(&signature));",
+                ),
+            )
+            .replace(
+                "{not_confirmed}",
+                &config.traced(
+                    "\t\t\t",
+                    "tracing::warn!(attempt, %signature, \"not confirmed, retrying\");",
+                ),
+            );
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    let error = if decode_errors {
+        // the `ErrorCode` of anchor's prelude is glob imported too
+        let path = match config.layout {
+            Layout::Flat => "ErrorCode",
+            Layout::Nested => "super::errors::ErrorCode",
+        };
+        "\t\t\terror: match &result.err {\n\t\t\t\t// errors of the instructions of the program only, not of those\n\t\t\t\t// it invoked\n\t\t\t\tSome(solana_sdk::transaction::TransactionError::InstructionError(\n\t\t\t\t\tindex,\n\t\t\t\t\tsolana_sdk::instruction::InstructionError::Custom(code),\n\t\t\t\t)) if ixs.get(*index as usize).map(|ix| ix.program_id) == Some(PROGRAM_ID) => {path}::from_code(*code),\n\t\t\t\t_ => None,\n\t\t\t},\n".replace("{path}", path)
+    } else {
+        String::new()
+    };
+    let events = if decode_events {
+        "\t\t\tevents: ProgramEvent::from_logs(&logs),\n"
+    } else {
+        ""
+    };
+    output.write_all(
+        SIMULATE
+            .replace(
+                "{simulate_span}",
+                &config.instrument("\t", "debug", &program_field),
+            )
+            .replace("{error}", &error)
+            .replace("{events}", events)
+            .as_bytes(),
+    )?;
+    for ix in instructions.iter().filter(|ix| has_args_struct(ix, config)) {
+        let (params, args) = if ix.args.is_empty() {
+            (String::new(), String::new())
+        } else {
+            let args = config.naming.instructions.apply(&ix.name);
+            (format!(", args: &{}", args), ", args".to_string())
+        };
+        output.write_fmt(format_args!(
+            "\n\t/// Sends `{}`, paid by the first of `signers`.\n{}\tpub fn send_{}(\n\t\t&self,\n\t\taccounts: &{}{},\n\t\tsigners: &[&dyn solana_sdk::signer::Signer],\n\t) -> std::result::Result<solana_sdk::signature::Signature, BindingsError> {{\n\t\taccounts.validate()?;\n\t\taccounts.check_signers(&signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>())?;\n\t\tlet ix = {}(accounts{});\n\t\t{}::check_metas(&ix.accounts)?;\n\t\tself.send(&[ix], signers)\n\t}}\n",
+            ix.name,
+            config.instrument(
+                "\t",
+                "debug",
+                &format!("{}, instruction = {:?}", program_field, ix.name)
+            ),
+            config.naming.fields.apply(&ix.name),
+            accounts_struct(ix, config),
+            params,
+            builder_fn(ix, config),
+            args,
+            accounts_struct(ix, config)
+        ))?;
+        output.write_fmt(format_args!(
+            "\n\t/// Simulates `{}`, paid by the first of `signers`.\n{}\tpub fn simulate_{}(\n\t\t&self,\n\t\taccounts: &{}{},\n\t\tsigners: &[&dyn solana_sdk::signer::Signer],\n\t) -> std::result::Result<Simulation, BindingsError> {{\n\t\taccounts.validate()?;\n\t\tlet ix = {}(accounts{});\n\t\t{}::check_metas(&ix.accounts)?;\n\t\tself.simulate(&[ix], signers)\n\t}}\n",
+            ix.name,
+            config.instrument(
+                "\t",
+                "debug",
+                &format!("{}, instruction = {:?}", program_field, ix.name)
+            ),
+            config.naming.fields.apply(&ix.name),
+            accounts_struct(ix, config),
+            params,
+            builder_fn(ix, config),
+            args,
+            accounts_struct(ix, config)
+        ))?;
+    }
+    output.write_all(b"}\n")?;
+
+    output.write_all(cfg.as_bytes())?;
+    output.write_all(
+        b"/// Outcome of a simulation by the client.\n#[derive(Debug, Clone)]\npub struct Simulation {\n\t/// Compute units the transaction consumed, if the node reports them.\n\tpub units_consumed: Option<u64>,\n\tpub logs: Vec<String>,\n\t/// Why the transaction failed, if it did.\n\tpub err: Option<solana_sdk::transaction::TransactionError>,\n",
+    )?;
+    if decode_errors {
+        output.write_all(
+            b"\t/// The error of the program the transaction failed with, if any.\n\tpub error: Option<",
+        )?;
+        output.write_all(match config.layout {
+            Layout::Flat => b"ErrorCode>,\n".as_slice(),
+            Layout::Nested => b"super::errors::ErrorCode>,\n".as_slice(),
+        })?;
+    }
+    if decode_events {
+        output.write_all(
+            b"\t/// The events the program logged.\n\tpub events: Vec<EmittedEvent>,\n",
+        )?;
+    }
+    output.write_all(b"}\n")
+}
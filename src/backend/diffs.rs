@@ -0,0 +1,78 @@
+//! Field by field diffs of account states, see
+//! [`GeneratorConfig::account_diffs`].
+
+use std::io::Write;
+
+use log::warn;
+
+use crate::config::GeneratorConfig;
+use crate::ir::{TypeDef, TypeDefKind};
+
+/// Program independent part of the diffs: the changed field.
+const FIELD_CHANGE: &[u8] = br#"
+/// A field of an account differing between two states of it, with its
+/// `Debug` rendering in both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+	pub field: &'static str,
+	pub old: String,
+	pub new: String,
+}
+"#;
+
+/// Whether the derives allow diffs, which render the fields with `Debug`.
+fn renders(config: &GeneratorConfig) -> bool {
+    config.derives.iter().any(|d| d.ends_with("Debug"))
+}
+
+/// Writes the [`FIELD_CHANGE`] struct.
+pub(super) fn define_field_change(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if !renders(config) {
+        return Ok(());
+    }
+    output.write_all(FIELD_CHANGE)
+}
+
+/// Writes [`FIELD_CHANGE`], unless it is in the support module, and a
+/// `diff` method per struct account among `emitted`, listing the fields
+/// whose rendering differs between two states of the account.
+pub(super) fn define_diffs(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter_map(|def| match &def.kind {
+            TypeDefKind::Struct { fields } if def.discriminator.is_some() => Some((def, fields)),
+            _ => None,
+        })
+        .collect();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    if !renders(config) {
+        warn!("the generated types don't derive Debug, no account diffs");
+        return Ok(());
+    }
+    if !config.shared_support {
+        define_field_change(output, config)?;
+    }
+    for (def, fields) in accounts {
+        output.write_fmt(format_args!(
+            "\nimpl {} {{\n\t/// The fields of `other` whose `Debug` rendering differs from that of\n\t/// the same field of `self`, in declaration order.\n\tpub fn diff(&self, other: &Self) -> Vec<FieldChange> {{\n\t\tlet mut changes = Vec::new();\n",
+            config.naming.types.apply(&def.name)
+        ))?;
+        for field in fields.iter() {
+            output.write_fmt(format_args!(
+                "\t\tlet (old, new) = (format!(\"{{:?}}\", self.{0}), format!(\"{{:?}}\", other.{0}));\n\t\tif old != new {{\n\t\t\tchanges.push(FieldChange {{ field: \"{0}\", old, new }});\n\t\t}}\n",
+                config.naming.fields.apply(&field.name)
+            ))?;
+        }
+        output.write_all(b"\t\tchanges\n\t}\n}\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,318 @@
+//! Decoding events out of transactions, see [`GeneratorConfig::event_decoder`].
+
+use std::io::Write;
+
+use super::client::cfg;
+use crate::config::{Feature, GeneratorConfig};
+use crate::ir::Event;
+
+/// Program independent part: the `emit_cpi!` tag, the attributed event and
+/// the base64 decoding of `Program data:` lines.
+const EVENT_HELPERS: &[u8] = br#"
+/// Instruction discriminator of the self-invocation `emit_cpi!` carries an
+/// event in, followed by the event's own discriminator and fields.
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// An event with the instruction of the transaction that emitted it.
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions the top-level instruction invoked of
+    /// the instruction executing when the event was emitted, `None` for the
+    /// top-level instruction itself.
+    pub inner_index: Option<usize>,
+    pub event: ProgramEvent,
+}
+
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in s.bytes().take_while(|c| *c != b'=') {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = ((acc << 6) | v as u32) & 0xffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+"#;
+
+/// Follows the `invoke`/`success`/`failed` log lines to tell which
+/// instruction logged each `Program data:` line.
+const FROM_LOGS: &str = r#"
+	/// Events the program logged with `emit!`, from the log messages of a
+	/// transaction, with the instruction executing when each was logged.
+	/// Nothing after a truncated log can be attributed.
+	pub fn from_logs<S: AsRef<str>>(logs: &[S]) -> Vec<EmittedEvent> {
+		let mut events = Vec::new();
+		// (program, inner index) of every instruction being executed
+		let mut stack: Vec<(&str, Option<usize>)> = Vec::new();
+		let mut index = None;
+		let mut invoked = 0;
+		for line in logs.iter().map(AsRef::as_ref) {
+			if line == "Log truncated" {
+				break;
+			}
+			let Some(rest) = line.strip_prefix("Program ") else {
+				continue;
+			};
+			if let Some(data) = rest.strip_prefix("data: ") {
+				let (Some(index), Some((program, inner_index))) = (index, stack.last()) else {
+					continue;
+				};
+				if *program != PROGRAM_ADDRESS {
+					continue;
+				}
+				// `sol_log_data` logs each slice separately
+				let data: Option<Vec<Vec<u8>>> = data.split_whitespace().map(decode_base64).collect();
+				if let Some(event) = data.and_then(|data| Self::decode(&data.concat())) {
+					events.push(EmittedEvent {
+						index,
+						inner_index: *inner_index,
+						event,
+					});
+				}
+				continue;
+			}
+			let Some((program, status)) = rest.split_once(' ') else {
+				continue;
+			};
+			if status == "invoke [1]" {
+				index = Some(index.map_or(0, |i: usize| i + 1));
+				invoked = 0;
+				stack = vec![(program, None)];
+			} else if status.starts_with("invoke [") {
+				stack.push((program, Some(invoked)));
+				invoked += 1;
+			} else if status == "success" || status.starts_with("failed") {
+				stack.pop();
+			}
+		}
+		events
+	}
+"#;
+
+/// Writes a `ProgramEvent` enum of the emitted event structs, decoding them
+/// by discriminator from log messages and from `emit_cpi!` inner
+/// instructions.
+pub(super) fn define_event_decoder(
+    output: &mut impl Write,
+    events: &[Event],
+    program: &str,
+    address: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    output.write_all(EVENT_HELPERS)?;
+    output.write_fmt(format_args!(
+        "\nconst PROGRAM_ADDRESS: &str = \"{}\";\n\n",
+        address
+    ))?;
+
+    let names: Vec<_> = events
+        .iter()
+        .map(|e| config.naming.types.apply(&e.name))
+        .collect();
+    output.write_all(
+        b"/// The events of the program.\n#[derive(Debug, Clone)]\npub enum ProgramEvent {\n",
+    )?;
+    for name in names.iter() {
+        output.write_fmt(format_args!("\t{0}({0}),\n", name))?;
+    }
+    output.write_all(b"}\nimpl ProgramEvent {\n")?;
+    output.write_fmt(format_args!(
+        "\t/// Decodes an event from its discriminator and fields, `None` if it\n\t/// fails, see [`Self::try_decode`].\n\tpub fn decode(data: &[u8]) -> Option<Self> {{\n\t\tSelf::try_decode(data).ok()\n\t}}\n\n\t/// Decodes an event from its discriminator and fields.\n{}\tpub fn try_decode(data: &[u8]) -> std::result::Result<Self, BindingsError> {{\n\t\tif data.len() < 8 {{\n\t\t\treturn Err(BindingsError::TruncatedData {{ expected: 8, got: data.len() }});\n\t\t}}\n\t\tlet (discriminator, mut data) = data.split_at(8);\n\t\tlet discriminator: [u8; 8] = discriminator.try_into().expect(\"split at 8\");\n",
+        config.instrument("\t", "trace", &format!("program = {:?}", program))
+    ))?;
+    if !config.tracing && !config.metrics {
+        output.write_all(b"\t\tmatch discriminator {\n")?;
+        for (event, name) in events.iter().zip(names.iter()) {
+            output.write_fmt(format_args!(
+                "\t\t\t{:?} => Ok(Self::{1}(borsh::BorshDeserialize::deserialize(&mut data)?)),\n",
+                event.discriminator, name
+            ))?;
+        }
+        output.write_all(
+            b"\t\t\t_ => Err(BindingsError::InvalidDiscriminator(discriminator)),\n\t\t}\n\t}\n",
+        )?;
+    } else {
+        // the metrics are reported by each arm, the events traced
+        let metrics = |indent: &str, call: &str| {
+            if config.metrics {
+                format!(
+                    "{0}if let Some(metrics) = decode_metrics() {{\n{0}\tmetrics.{1};\n{0}}}\n",
+                    indent, call
+                )
+            } else {
+                String::new()
+            }
+        };
+        if config.metrics {
+            output.write_all(b"\t\tlet started = std::time::Instant::now();\n")?;
+        }
+        output.write_all(b"\t\tmatch discriminator {\n")?;
+        for (event, name) in events.iter().zip(names.iter()) {
+            output.write_fmt(format_args!(
+                "\t\t\t{:?} => match <{1} as borsh::BorshDeserialize>::deserialize(&mut data) {{\n\t\t\t\tOk(event) => {{\n{2}\t\t\t\t\tOk(Self::{1}(event))\n\t\t\t\t}}\n\t\t\t\tErr(e) => {{\n{3}{4}\t\t\t\t\tErr(e.into())\n\t\t\t\t}}\n\t\t\t}},\n",
+                event.discriminator,
+                name,
+                metrics(
+                    "\t\t\t\t\t",
+                    &format!("decoded(\"event\", {:?}, started.elapsed())", event.name)
+                ),
+                config.traced(
+                    "\t\t\t\t\t",
+                    &format!(
+                        "tracing::debug!(event = {:?}, error = %e, \"event decode failed\");",
+                        event.name
+                    )
+                ),
+                metrics(
+                    "\t\t\t\t\t",
+                    &format!("decode_failed(\"event\", {:?})", event.name)
+                ),
+            ))?;
+        }
+        output.write_fmt(format_args!(
+            "\t\t\t_ => {{\n{}{}\t\t\t\tErr(BindingsError::InvalidDiscriminator(discriminator))\n\t\t\t}}\n\t\t}}\n\t}}\n",
+            config.traced(
+                "\t\t\t\t",
+                "tracing::trace!(?discriminator, \"unknown event discriminator\");"
+            ),
+            metrics(
+                "\t\t\t\t",
+                "unknown_discriminator(\"event\", &discriminator)"
+            ),
+        ))?;
+    }
+    output.write_all(
+        b"\n\t/// Decodes the event carried by an inner instruction of `program_id`,\n\t/// if it is an `emit_cpi!` self-invocation of the program.\n\tpub fn from_inner_instruction(program_id: &str, data: &[u8]) -> Option<Self> {\n\t\tif program_id != PROGRAM_ADDRESS {\n\t\t\treturn None;\n\t\t}\n\t\tSelf::decode(data.strip_prefix(&EVENT_IX_TAG)?)\n\t}\n",
+    )?;
+    output.write_all(FROM_LOGS.as_bytes())?;
+    output.write_all(b"}\n")
+}
+
+/// `EventContext` and `event_stream`, keeping a `logsSubscribe`
+/// subscription open from a spawned task.
+const EVENT_STREAM: &[&str] = &[
+    r#"/// Where a streamed event was logged.
+#[derive(Debug, Clone)]
+pub struct EventContext {
+	pub signature: solana_sdk::signature::Signature,
+	pub slot: u64,
+	/// Position of the top-level instruction in the message.
+	pub index: usize,
+	/// Position among the instructions the top-level instruction invoked,
+	/// `None` for the top-level instruction itself.
+	pub inner_index: Option<usize>,
+}
+"#,
+    r#"/// The events the program logs in successful transactions, from a
+/// `logsSubscribe` subscription at `ws_url` kept by a task spawned on the
+/// current tokio runtime. The task reconnects with a backoff when the
+/// subscription fails, events logged meanwhile are missed, and ends once
+/// the stream is dropped.
+pub fn event_stream(
+	ws_url: impl ToString,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+) -> impl futures::Stream<Item = (EventContext, ProgramEvent)> {
+	let ws_url = ws_url.to_string();
+	let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+	tokio::spawn(async move {
+		let mut backoff = EVENT_STREAM_BACKOFF;
+		while let Err(_e) = forward_events(&ws_url, commitment, &sender, &mut backoff).await {
+{reconnect}			tokio::time::sleep(backoff).await;
+			backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+		}
+	});
+	futures::stream::unfold(receiver, |mut receiver| async move {
+		receiver.recv().await.map(|item| (item, receiver))
+	})
+}
+"#,
+    r#"/// Wait before the first reconnection of [`event_stream`], doubled up to
+/// 30 seconds after every failed one.
+const EVENT_STREAM_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+"#,
+    r#"/// Sends the events of a new subscription to `sender` until nobody receives
+/// them, fails when the subscription does.
+async fn forward_events(
+	ws_url: &str,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+	sender: &tokio::sync::mpsc::UnboundedSender<(EventContext, ProgramEvent)>,
+	backoff: &mut std::time::Duration,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use futures::StreamExt;
+	let client = solana_client::nonblocking::pubsub_client::PubsubClient::new(ws_url).await?;
+	let (mut notifications, unsubscribe) = client
+		.logs_subscribe(
+			solana_client::rpc_config::RpcTransactionLogsFilter::Mentions(vec![PROGRAM_ADDRESS.to_string()]),
+			solana_client::rpc_config::RpcTransactionLogsConfig { commitment: Some(commitment) },
+		)
+		.await?;
+	*backoff = EVENT_STREAM_BACKOFF;
+	while let Some(notification) = notifications.next().await {
+		let logs = notification.value;
+		if logs.err.is_some() {
+			continue;
+		}
+		let Ok(signature) = logs.signature.parse() else {
+			continue;
+		};
+		for emitted in ProgramEvent::from_logs(&logs.logs) {
+			let context = EventContext {
+				signature,
+				slot: notification.context.slot,
+				index: emitted.index,
+				inner_index: emitted.inner_index,
+			};
+			if sender.send((context, emitted.event)).is_err() {
+				drop(notifications);
+				unsubscribe().await;
+				return Ok(());
+			}
+		}
+	}
+	Err("subscription closed".into())
+}
+"#,
+];
+
+/// Writes `event_stream`, streaming the `ProgramEvent`s the program logs
+/// over a websocket subscription.
+pub(super) fn define_event_stream(
+    output: &mut impl Write,
+    events: &[Event],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let cfg = cfg(&[Feature::Ws], config);
+    for item in EVENT_STREAM {
+        let item = item.replace(
+            "{reconnect}",
+            &config.traced(
+                "\t\t\t",
+                "tracing::warn!(error = %_e, ?backoff, \"event subscription failed, reconnecting\");",
+            ),
+        );
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
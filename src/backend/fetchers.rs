@@ -0,0 +1,304 @@
+//! Decoders of the accounts, and typed `getProgramAccounts` fetchers and
+//! filters of them, emitted with the client, see
+//! [`GeneratorConfig::client`], and the account scans, see
+//! [`GeneratorConfig::account_scans`].
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use heck::ToSnakeCase;
+
+use super::client::cfg;
+use super::rust::rust_type;
+use crate::config::{Feature, GeneratorConfig};
+use crate::ir::{TypeDef, TypeDefKind};
+
+/// Snake case plural of the account `name`, as in `fetch_all_<plural>`.
+fn plural(name: &str) -> String {
+    let snake = name.to_snake_case();
+    if snake.ends_with('s')
+        || snake.ends_with('x')
+        || snake.ends_with("sh")
+        || snake.ends_with("ch")
+    {
+        format!("{}es", snake)
+    } else {
+        format!("{}s", snake)
+    }
+}
+
+/// The `getProgramAccounts` filters selecting the accounts `def`: its
+/// discriminator and its size when static, one per line after `indent`.
+fn account_filters(def: &TypeDef, definitions: &[TypeDef], indent: &str) -> String {
+    let mut filters = format!(
+        "{}solana_client::rpc_filter::RpcFilterType::Memcmp(solana_client::rpc_filter::Memcmp::new_raw_bytes(0, vec!{:?})),\n",
+        indent,
+        def.discriminator.unwrap_or_default()
+    );
+    if let Some(size) = def.static_size(definitions) {
+        filters.push_str(&format!(
+            "{}solana_client::rpc_filter::RpcFilterType::DataSize({}),\n",
+            indent,
+            8 + size
+        ));
+    }
+    filters
+}
+
+/// Program independent part of the account decoders.
+const DECODE_ACCOUNT: &str = r#"
+/// Decodes a `T` from the data of an account, which starts with its
+/// `discriminator` and holds `size` bytes at least if `T` has a fixed size.
+fn decode_account<T: borsh::BorshDeserialize>(
+	data: &[u8],
+	discriminator: [u8; 8],
+	size: Option<usize>,
+) -> std::result::Result<T, BindingsError> {
+	if data.len() < 8 {
+		return Err(BindingsError::TruncatedData { expected: 8, got: data.len() });
+	}
+	let (found, mut fields) = data.split_at(8);
+	if found != discriminator {
+		return Err(BindingsError::InvalidDiscriminator(found.try_into().expect("split at 8")));
+	}
+	if let Some(expected) = size.filter(|size| data.len() < *size) {
+		return Err(BindingsError::TruncatedData { expected, got: data.len() });
+	}
+	Ok(T::deserialize(&mut fields)?)
+}
+"#;
+
+/// Writes the [`DECODE_ACCOUNT`] helper.
+pub(super) fn define_decode_account(output: &mut impl Write) -> std::io::Result<()> {
+    output.write_all(DECODE_ACCOUNT.as_bytes())
+}
+
+/// Writes a `from_account_data` function per account among `emitted`,
+/// decoding it from the data of the account, discriminator included.
+pub(super) fn define_account_decoders(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    definitions: &[TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some())
+        .collect();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    if !config.shared_support {
+        define_decode_account(output)?;
+    }
+    let vis = config.type_visibility.as_prefix();
+    for def in accounts {
+        let size = match def.static_size(definitions) {
+            Some(size) => format!("Some({})", 8 + size),
+            None => "None".to_string(),
+        };
+        output.write_fmt(format_args!(
+            "\nimpl {} {{\n\t/// Decodes the `{}` account from its data, discriminator included.\n\t{}fn from_account_data(data: &[u8]) -> std::result::Result<Self, BindingsError> {{\n\t\tdecode_account(data, {:?}, {})\n\t}}\n}}\n",
+            config.naming.types.apply(&def.name),
+            def.name,
+            vis,
+            def.discriminator.unwrap_or_default(),
+            size
+        ))?;
+    }
+    Ok(())
+}
+
+/// Writes a `fetch_all_<accounts>` function per account among `emitted`,
+/// fetching the accounts of the program with its discriminator, and its
+/// size when static, along with the caller's filters, decoded.
+pub(super) fn define_fetchers(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    definitions: &[TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Client], config);
+    let vis = config.type_visibility.as_prefix();
+    for def in emitted.iter().filter(|def| def.discriminator.is_some()) {
+        let name = config.naming.types.apply(&def.name);
+        output.write_fmt(format_args!(
+            "\n{}/// Fetches the `{}` accounts of the program matching `extra_filters`,\n/// decoded, through `getProgramAccounts` filtered by their discriminator.\n{}fn fetch_all_{}(\n\trpc: &solana_client::rpc_client::RpcClient,\n\textra_filters: Vec<solana_client::rpc_filter::RpcFilterType>,\n) -> std::result::Result<Vec<(Pubkey, {})>, BindingsError> {{\n\tlet mut filters = vec![\n{}",
+            cfg,
+            def.name,
+            vis,
+            plural(&def.name),
+            name,
+            account_filters(def, definitions, "\t\t")
+        ))?;
+        output.write_fmt(format_args!(
+            "\t];\n\tfilters.extend(extra_filters);\n\tlet config = solana_client::rpc_config::RpcProgramAccountsConfig {{\n\t\tfilters: Some(filters),\n\t\taccount_config: solana_client::rpc_config::RpcAccountInfoConfig {{\n\t\t\tencoding: Some(solana_account_decoder::UiAccountEncoding::Base64),\n\t\t\t..Default::default()\n\t\t}},\n\t\t..Default::default()\n\t}};\n\trpc.get_program_accounts_with_config(&PROGRAM_ID, config)?\n\t\t.into_iter()\n\t\t.map(|(address, account)| {{\n\t\t\tlet decoded = {}::from_account_data(&account.data)?;\n\t\t\tOk((address, decoded))\n\t\t}})\n\t\t.collect()\n}}\n",
+            name
+        ))?;
+    }
+    Ok(())
+}
+
+/// Writes a `<Account>Filters` per account among `emitted`, with a function
+/// per field returning the `memcmp` filter of the accounts holding a value
+/// in it, at the field's offset in the account data. The fields after one
+/// of varying size are left out, their offset varies too.
+pub(super) fn define_filters(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    definitions: &[TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Client], config);
+    let vis = config.type_visibility.as_prefix();
+    for def in emitted.iter().filter(|def| def.discriminator.is_some()) {
+        let TypeDefKind::Struct { fields } = &def.kind else {
+            continue;
+        };
+        let name = format!("{}Filters", config.naming.types.apply(&def.name));
+        output.write_fmt(format_args!(
+            "\n{cfg}/// `getProgramAccounts` filters on the fields of the `{}` accounts.\n{vis}struct {name};\n{cfg}impl {name} {{\n",
+            def.name,
+        ))?;
+        let mut offset = Some(8);
+        for field in fields.iter() {
+            let Some(at) = offset else {
+                break;
+            };
+            let mut unresolved = HashSet::new();
+            output.write_fmt(format_args!(
+                "\t/// The accounts whose `{}` is `value`, compared at byte {}.\n\t{}fn {}(value: &{}) -> solana_client::rpc_filter::RpcFilterType {{\n\t\tSelf::memcmp({}, value)\n\t}}\n\n",
+                field.name,
+                at,
+                vis,
+                config.naming.fields.apply(&field.name),
+                rust_type(&field.ty, Some(&field.name), config, &mut unresolved),
+                at
+            ))?;
+            offset = field.ty.static_size(definitions).map(|size| at + size);
+        }
+        output.write_all(
+            b"\tfn memcmp(offset: usize, value: &impl borsh::BorshSerialize) -> solana_client::rpc_filter::RpcFilterType {\n\t\tlet bytes = value.try_to_vec().expect(\"serializing into a Vec\");\n\t\tsolana_client::rpc_filter::RpcFilterType::Memcmp(solana_client::rpc_filter::Memcmp::new_raw_bytes(offset, bytes))\n\t}\n}\n",
+        )?;
+    }
+    Ok(())
+}
+
+/// Program independent part of the account scans.
+const SCAN_HELPERS: &[&str] = &[
+    r#"/// How the account scans fetch many accounts: `batch_size` addresses per
+/// `getMultipleAccounts` request, at most `concurrency` requests in flight,
+/// each waiting `pause` first to stay under the rate limit of the RPC.
+#[derive(Debug, Clone, Copy)]
+pub struct ScanConfig {
+	/// Addresses per request, the RPC accepts up to 100.
+	pub batch_size: usize,
+	pub concurrency: usize,
+	pub pause: std::time::Duration,
+}
+"#,
+    r#"impl Default for ScanConfig {
+	fn default() -> Self {
+		Self {
+			batch_size: 100,
+			concurrency: 4,
+			pause: std::time::Duration::ZERO,
+		}
+	}
+}
+"#,
+    r#"/// Streams the accounts at `addresses`, `None` for those that don't exist,
+/// in order, fetched in batches of `getMultipleAccounts` as `config` says.
+pub fn fetch_multiple_accounts<'a>(
+	rpc: &'a solana_client::nonblocking::rpc_client::RpcClient,
+	addresses: Vec<Pubkey>,
+	config: ScanConfig,
+) -> impl futures::Stream<Item = std::result::Result<(Pubkey, Option<solana_sdk::account::Account>), solana_client::client_error::ClientError>> + 'a {
+	use futures::StreamExt;
+	let batches: Vec<Vec<Pubkey>> = addresses
+		.chunks(config.batch_size.clamp(1, 100))
+		.map(<[Pubkey]>::to_vec)
+		.collect();
+	futures::stream::iter(batches)
+		.map(move |batch| async move {
+			tokio::time::sleep(config.pause).await;
+			let accounts = rpc.get_multiple_accounts(&batch).await?;
+			Ok(batch.into_iter().zip(accounts).map(Ok).collect::<Vec<_>>())
+		})
+		.buffered(config.concurrency.max(1))
+		.flat_map(|batch| {
+			futures::stream::iter(match batch {
+				Ok(accounts) => accounts,
+				Err(e) => vec![Err(e)],
+			})
+		})
+}
+"#,
+];
+
+/// Writes the [`SCAN_HELPERS`].
+pub(super) fn define_scan_helpers(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Ws], config);
+    for item in SCAN_HELPERS {
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes the [`SCAN_HELPERS`], unless they are in the support module, then
+/// per account among `emitted` a `fetch_<accounts>_batched` function
+/// streaming the accounts at given addresses decoded, and a `scan_<accounts>`
+/// function streaming all of them: their addresses listed by
+/// `getProgramAccounts` without their data, then fetched in batches.
+pub(super) fn define_scans(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    definitions: &[TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some())
+        .collect();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    if !config.shared_support {
+        define_scan_helpers(output, config)?;
+    }
+    let cfg = cfg(&[Feature::Ws], config);
+    let vis = config.type_visibility.as_prefix();
+    for def in accounts {
+        let name = config.naming.types.apply(&def.name);
+        let plural = plural(&def.name);
+        output.write_fmt(format_args!(
+            "\n{}/// Streams the `{}` accounts at `addresses`, decoded, fetched in batches.\n/// The missing accounts and those of another type are skipped.\n{}fn fetch_{}_batched<'a>(\n\trpc: &'a solana_client::nonblocking::rpc_client::RpcClient,\n\taddresses: Vec<Pubkey>,\n\tconfig: ScanConfig,\n) -> impl futures::Stream<Item = std::result::Result<(Pubkey, {}), BindingsError>> + 'a {{\n\tuse futures::StreamExt;\n\tfetch_multiple_accounts(rpc, addresses, config).filter_map(|fetched| async move {{\n\t\tmatch fetched {{\n\t\t\tOk((address, Some(account))) if account.data.starts_with(&{:?}) => Some(\n\t\t\t\t{}::from_account_data(&account.data).map(|decoded| (address, decoded)),\n\t\t\t),\n\t\t\tOk(_) => None,\n\t\t\tErr(e) => Some(Err(e.into())),\n\t\t}}\n\t}})\n}}\n",
+            cfg,
+            def.name,
+            vis,
+            plural,
+            name,
+            def.discriminator.unwrap_or_default(),
+            name
+        ))?;
+        output.write_fmt(format_args!(
+            "\n{}/// Streams the `{}` accounts of the program matching `extra_filters`,\n/// decoded: lists their addresses through `getProgramAccounts` without\n/// their data, then fetches them in batches.\n{}fn scan_{}<'a>(\n\trpc: &'a solana_client::nonblocking::rpc_client::RpcClient,\n\textra_filters: Vec<solana_client::rpc_filter::RpcFilterType>,\n\tconfig: ScanConfig,\n) -> impl futures::Stream<Item = std::result::Result<(Pubkey, {}), BindingsError>> + 'a {{\n\tuse futures::StreamExt;\n\tfutures::stream::once(async move {{\n\t\tlet mut filters = vec![\n{}",
+            cfg,
+            def.name,
+            vis,
+            plural,
+            name,
+            account_filters(def, definitions, "\t\t\t")
+        ))?;
+        output.write_fmt(format_args!(
+            "\t\t];\n\t\tfilters.extend(extra_filters);\n\t\tlet listing = solana_client::rpc_config::RpcProgramAccountsConfig {{\n\t\t\tfilters: Some(filters),\n\t\t\taccount_config: solana_client::rpc_config::RpcAccountInfoConfig {{\n\t\t\t\tencoding: Some(solana_account_decoder::UiAccountEncoding::Base64),\n\t\t\t\tdata_slice: Some(solana_account_decoder::UiDataSliceConfig {{ offset: 0, length: 0 }}),\n\t\t\t\t..Default::default()\n\t\t\t}},\n\t\t\t..Default::default()\n\t\t}};\n\t\trpc.get_program_accounts_with_config(&PROGRAM_ID, listing).await\n\t}})\n\t.flat_map(move |listed| match listed {{\n\t\tOk(listed) => {{\n\t\t\tlet addresses = listed.into_iter().map(|(address, _)| address).collect();\n\t\t\tfetch_{}_batched(rpc, addresses, config).left_stream()\n\t\t}}\n\t\tErr(e) => futures::stream::once(async move {{ Err(e.into()) }}).right_stream(),\n\t}})\n}}\n",
+            plural
+        ))?;
+    }
+    Ok(())
+}
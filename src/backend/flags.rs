@@ -0,0 +1,126 @@
+//! `bitflags` types of flag fields, see [`GeneratorConfig::flags`].
+
+use std::io::Write;
+
+use log::warn;
+
+use crate::config::{Feature, FlagSet, GeneratorConfig};
+use crate::ir::{EnumFields, Event, Instruction, Type, TypeDef, TypeDefKind};
+
+/// The flag set typing `field` and its integer, if `ty` is an unsigned
+/// integer and `field` has flags.
+pub(super) fn flag_type<'c>(
+    ty: &Type,
+    field: Option<&str>,
+    config: &'c GeneratorConfig,
+) -> Option<&'c FlagSet> {
+    let flags = config.flags.get(field?)?;
+    bits_type(ty).map(|_| flags)
+}
+
+fn bits_type(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::U8 => Some("u8"),
+        Type::U16 => Some("u16"),
+        Type::U32 => Some("u32"),
+        Type::U64 => Some("u64"),
+        Type::U128 => Some("u128"),
+        _ => None,
+    }
+}
+
+/// Integer type of every flag set the fields of the program use, in field
+/// order.
+fn used_flags<'c>(
+    instructions: &[Instruction],
+    definitions: &[TypeDef],
+    events: &[Event],
+    config: &'c GeneratorConfig,
+) -> Vec<(&'c FlagSet, &'static str)> {
+    let mut fields: Vec<(&str, &Type)> = vec![];
+    for ix in instructions {
+        fields.extend(ix.args.iter().map(|f| (f.name.as_str(), &f.ty)));
+    }
+    for def in definitions {
+        match &def.kind {
+            TypeDefKind::Struct { fields: defined } => {
+                fields.extend(defined.iter().map(|f| (f.name.as_str(), &f.ty)));
+            }
+            TypeDefKind::Enum { variants } => {
+                for variant in variants {
+                    if let Some(EnumFields::Named(named)) = &variant.fields {
+                        fields.extend(named.iter().map(|f| (f.name.as_str(), &f.ty)));
+                    }
+                }
+            }
+        }
+    }
+    for event in events {
+        fields.extend(event.fields.iter().map(|f| (f.name.as_str(), &f.ty)));
+    }
+
+    let mut used: Vec<(&FlagSet, &str)> = vec![];
+    for (name, mut ty) in fields {
+        while let Type::Option(inner) | Type::Vec(inner) | Type::Array(inner, _) = ty {
+            ty = inner;
+        }
+        let Some(flags) = config.flags.get(name) else {
+            continue;
+        };
+        let Some(bits) = bits_type(ty) else {
+            warn!("{} is not an unsigned integer, its flags are ignored", name);
+            continue;
+        };
+        match used.iter().find(|(used, _)| used.name == flags.name) {
+            Some((_, used_bits)) if *used_bits != bits => warn!(
+                "{} is a {} but {} holds {}",
+                name, bits, flags.name, used_bits
+            ),
+            Some(_) => {}
+            None => used.push((flags, bits)),
+        }
+    }
+    used
+}
+
+/// Writes a `bitflags` type per flag set the program uses, encoded by Borsh
+/// as its integer, unknown bits included.
+pub(super) fn define_flags(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    definitions: &[TypeDef],
+    events: &[Event],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut derives: Vec<&str> = vec!["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash"];
+    for derive in config.derives.iter() {
+        if !derive.contains("Borsh") && !derives.contains(&derive.as_str()) {
+            derives.push(derive);
+        }
+    }
+    let vis = config.type_visibility.as_prefix();
+    for (flags, bits) in used_flags(instructions, definitions, events, config) {
+        output.write_fmt(format_args!(
+            "bitflags::bitflags! {{\n\t#[derive({})]\n",
+            derives.join(", ")
+        ))?;
+        if config.feature_gates {
+            output.write_fmt(format_args!(
+                "\t#[cfg_attr({}, derive(serde::Serialize, serde::Deserialize))]\n",
+                Feature::Serde.predicate()
+            ))?;
+        }
+        output.write_fmt(format_args!(
+            "\t{}struct {}: {} {{\n",
+            vis, flags.name, bits
+        ))?;
+        for (flag, bit) in flags.bits.iter() {
+            output.write_fmt(format_args!("\t\tconst {} = 1 << {};\n", flag, bit))?;
+        }
+        output.write_fmt(format_args!(
+            "\t}}\n}}\nimpl borsh::BorshSerialize for {0} {{\n\tfn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {{\n\t\tborsh::BorshSerialize::serialize(&self.bits(), writer)\n\t}}\n}}\nimpl borsh::BorshDeserialize for {0} {{\n\tfn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {{\n\t\tOk(Self::from_bits_retain(<{1} as borsh::BorshDeserialize>::deserialize_reader(reader)?))\n\t}}\n}}\n",
+            flags.name, bits
+        ))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,561 @@
+//! Decoding over gRPC, see
+//! [`GeneratorConfig::grpc_service`](crate::GeneratorConfig::grpc_service).
+
+use std::io::Write;
+
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use log::warn;
+
+use super::client::cfg;
+use super::indexer::{hooked, indexed, structs_readable};
+use crate::config::{Artifact, Feature, GeneratorConfig};
+use crate::ir::{Event, Field, Instruction, Type, TypeDef, TypeDefKind};
+
+/// A field of a message.
+struct ProtoField {
+    /// Snake case name, in the schema and the prost struct.
+    name: String,
+    /// Type in the schema, with its label.
+    proto: String,
+    /// `#[prost(..)]` attribute arguments before the tag.
+    prost: String,
+    rust: String,
+    /// Expression converting the field of the generated struct.
+    convert: String,
+}
+
+/// Protobuf type, prost type, rust type and conversion of `{}` of the
+/// types with a protobuf type of their own.
+fn scalar(ty: &Type) -> Option<(&'static str, &'static str, &'static str, &'static str)> {
+    Some(match ty {
+        Type::Bool => ("bool", "bool", "bool", "{}"),
+        Type::U8 | Type::U16 => ("uint32", "uint32", "u32", "{} as u32"),
+        Type::U32 => ("uint32", "uint32", "u32", "{}"),
+        Type::I8 | Type::I16 => ("int32", "int32", "i32", "{} as i32"),
+        Type::I32 => ("int32", "int32", "i32", "{}"),
+        Type::U64 => ("uint64", "uint64", "u64", "{}"),
+        Type::I64 => ("int64", "int64", "i64", "{}"),
+        Type::U128 | Type::I128 => ("string", "string", "String", "{}.to_string()"),
+        Type::F32 => ("float", "float", "f32", "{}"),
+        Type::F64 => ("double", "double", "f64", "{}"),
+        Type::String => ("string", "string", "String", "{}.clone()"),
+        Type::PublicKey => ("string", "string", "String", "{}.to_string()"),
+        Type::Bytes => ("bytes", "bytes = \"vec\"", "Vec<u8>", "{}.clone()"),
+        _ => return None,
+    })
+}
+
+/// The message field of the field `name` of type `ty`, read from `expr`.
+/// Options of scalars are `optional`, vectors and arrays of them
+/// `repeated` and those of `u8` `bytes`, everything else is its borsh
+/// encoding.
+fn proto_field(name: &str, expr: &str, ty: &Type, config: &GeneratorConfig) -> ProtoField {
+    let hooked = |ty: &Type| hooked(ty, name, config);
+    let field = |proto: String, prost: String, rust: String, convert: String| ProtoField {
+        name: name.to_snake_case(),
+        proto,
+        prost,
+        rust,
+        convert,
+    };
+    let borsh = || {
+        field(
+            "bytes".to_string(),
+            "bytes = \"vec\"".to_string(),
+            "Vec<u8>".to_string(),
+            format!(
+                "borsh::BorshSerialize::try_to_vec(&{}).expect(\"writing to a Vec can't fail\")",
+                expr
+            ),
+        )
+    };
+    if hooked(ty) {
+        return borsh();
+    }
+    match ty {
+        Type::Option(inner) if !hooked(inner) => match scalar(inner) {
+            Some((proto, prost, rust, convert)) => field(
+                format!("optional {}", proto),
+                format!("{}, optional", prost),
+                format!("Option<{}>", rust),
+                // the options of the types converted as they are are copied
+                if convert == "{}" {
+                    expr.to_string()
+                } else {
+                    format!(
+                        "{}.as_ref().map(|v| {})",
+                        expr,
+                        convert.replace("{}", "(*v)")
+                    )
+                },
+            ),
+            None => borsh(),
+        },
+        Type::Vec(inner) | Type::Array(inner, _) if !hooked(inner) => {
+            match (&**inner, scalar(inner)) {
+                (Type::U8, _) => field(
+                    "bytes".to_string(),
+                    "bytes = \"vec\"".to_string(),
+                    "Vec<u8>".to_string(),
+                    format!("{}.to_vec()", expr),
+                ),
+                (_, Some((proto, prost, rust, convert))) => field(
+                    format!("repeated {}", proto),
+                    format!("{}, repeated", prost),
+                    format!("Vec<{}>", rust),
+                    if convert == "{}" {
+                        format!("{}.to_vec()", expr)
+                    } else {
+                        format!(
+                            "{}.iter().map(|v| {}).collect()",
+                            expr,
+                            convert.replace("{}", "(*v)")
+                        )
+                    },
+                ),
+                (_, None) => borsh(),
+            }
+        }
+        ty => match scalar(ty) {
+            Some((proto, prost, rust, convert)) => field(
+                proto.to_string(),
+                prost.to_string(),
+                rust.to_string(),
+                convert.replace("{}", expr),
+            ),
+            None => borsh(),
+        },
+    }
+}
+
+/// A message of a decoded item, with the name of its generated struct.
+struct Message {
+    /// Name of the message and of the generated struct.
+    name: String,
+    /// Field of the item in the oneof of `Decoded`.
+    item: String,
+    doc: String,
+    fields: Vec<ProtoField>,
+}
+
+impl Message {
+    fn new(
+        kind: &str,
+        name: String,
+        doc: String,
+        fields: &[Field],
+        config: &GeneratorConfig,
+    ) -> Self {
+        let fields = fields
+            .iter()
+            .map(|f| {
+                let expr = format!("value.{}", config.naming.fields.apply(&f.name));
+                proto_field(&f.name, &expr, &f.ty, config)
+            })
+            .collect();
+        Message {
+            item: format!("{}_{}", kind, name.to_snake_case()),
+            name,
+            doc,
+            fields,
+        }
+    }
+
+    fn write_schema(&self, schema: &mut String) {
+        schema.push_str(&format!("\n// {}\nmessage {} {{\n", self.doc, self.name));
+        for (i, field) in self.fields.iter().enumerate() {
+            let borsh = if field.convert.starts_with("borsh::") {
+                "  // borsh encoded\n"
+            } else {
+                ""
+            };
+            schema.push_str(&format!(
+                "{}  {} {} = {};\n",
+                borsh,
+                field.proto,
+                field.name,
+                i + 1
+            ));
+        }
+        schema.push_str("}\n");
+    }
+
+    fn write_struct(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_fmt(format_args!(
+            "\t/// {}\n\t#[derive(Clone, PartialEq, prost::Message)]\n\tpub struct {} {{\n",
+            self.doc, self.name
+        ))?;
+        for (i, field) in self.fields.iter().enumerate() {
+            output.write_fmt(format_args!(
+                "\t\t#[prost({}, tag = \"{}\")]\n\t\tpub {}: {},\n",
+                field.prost,
+                i + 1,
+                field.name,
+                field.rust
+            ))?;
+        }
+        output.write_all(b"\t}\n")
+    }
+
+    /// The `From` impl of the message, from the generated struct.
+    fn write_from(&self, output: &mut impl Write, cfg: &str) -> std::io::Result<()> {
+        let value = if self.fields.is_empty() { "_" } else { "value" };
+        output.write_fmt(format_args!(
+            "\n{0}impl From<&{1}> for proto::{1} {{\n\tfn from({2}: &{1}) -> Self {{\n\t\tSelf {{\n",
+            cfg, self.name, value
+        ))?;
+        for field in self.fields.iter() {
+            output.write_fmt(format_args!("\t\t\t{}: {},\n", field.name, field.convert))?;
+        }
+        output.write_all(b"\t\t}\n\t}\n}\n")
+    }
+}
+
+/// The instructions served, those whose args struct is emitted, with their
+/// messages.
+fn instruction_messages<'a>(
+    instructions: &'a [Instruction],
+    config: &GeneratorConfig,
+) -> Vec<(&'a Instruction, Message)> {
+    instructions
+        .iter()
+        .filter(|ix| indexed(ix, config))
+        .map(|ix| {
+            let name = config.naming.instructions.apply(&ix.name);
+            let doc = format!("The args of the `{}` instruction.", ix.name);
+            (ix, Message::new("instruction", name, doc, &ix.args, config))
+        })
+        .collect()
+}
+
+/// The messages of the events served, none without readable event structs.
+fn event_messages(events: &[Event], config: &GeneratorConfig) -> Vec<Message> {
+    if !config.emits(Artifact::Events) || !structs_readable(config) {
+        return vec![];
+    }
+    events
+        .iter()
+        .map(|event| {
+            let fields: Vec<_> = event
+                .fields
+                .iter()
+                .map(|f| Field {
+                    name: f.name.clone(),
+                    ty: f.ty.clone(),
+                })
+                .collect();
+            let name = config.naming.types.apply(&event.name);
+            let doc = format!("The `{}` event.", event.name);
+            Message::new("event", name, doc, &fields, config)
+        })
+        .collect()
+}
+
+/// The messages of the emitted struct accounts, with their discriminators.
+fn account_messages(accounts: &[&TypeDef], config: &GeneratorConfig) -> Vec<([u8; 8], Message)> {
+    if !config.emits(Artifact::Types) || !structs_readable(config) {
+        return vec![];
+    }
+    accounts
+        .iter()
+        .filter_map(|def| {
+            let TypeDefKind::Struct { fields } = &def.kind else {
+                return None;
+            };
+            let name = config.naming.types.apply(&def.name);
+            let doc = format!("The `{}` account.", def.name);
+            Some((
+                def.discriminator?,
+                Message::new("account", name, doc, fields, config),
+            ))
+        })
+        .collect()
+}
+
+/// Program independent part of the prost messages: the request.
+const REQUEST: &str = r#"	/// The data of an instruction, account or event to decode.
+	#[derive(Clone, PartialEq, prost::Message)]
+	pub struct DecodeRequest {
+		#[prost(enumeration = "decode_request::Kind", tag = "1")]
+		pub kind: i32,
+		/// The data, discriminator included.
+		#[prost(bytes = "vec", tag = "2")]
+		pub data: Vec<u8>,
+	}
+	pub mod decode_request {
+		/// What the data of a [`DecodeRequest`](super::DecodeRequest) is.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+		#[repr(i32)]
+		pub enum Kind {
+			Instruction = 0,
+			Account = 1,
+			Event = 2,
+		}
+	}
+"#;
+
+/// Program independent part of the service: the server, routing the calls
+/// of `{service}` to [`decode_proto`].
+const SERVER: &[&str] = &[
+    r#"/// The `{service}` service of [`PROTO_SCHEMA`], decoding the instructions,
+/// accounts and events of the program, to add to a `tonic` server:
+///
+/// - `Decode` decodes one of them
+/// - `DecodeStream` decodes a stream of them in order, ending at the first
+///   that fails with its status
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecoderServer;
+"#,
+    r#"impl tonic::server::NamedService for DecoderServer {
+	const NAME: &'static str = "{service}";
+}
+"#,
+    r#"impl<B> tonic::codegen::Service<tonic::codegen::http::Request<B>> for DecoderServer
+where
+	B: tonic::codegen::Body + Send + 'static,
+	B::Error: Into<tonic::codegen::StdError> + Send + 'static,
+{
+	type Response = tonic::codegen::http::Response<tonic::body::BoxBody>;
+	type Error = std::convert::Infallible;
+	type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+	fn poll_ready(
+		&mut self,
+		_cx: &mut std::task::Context<'_>,
+	) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+		std::task::Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, request: tonic::codegen::http::Request<B>) -> Self::Future {
+		match request.uri().path() {
+			"/{service}/Decode" => Box::pin(async move {
+				let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+				Ok(grpc.unary(DecodeCall, request).await)
+			}),
+			"/{service}/DecodeStream" => Box::pin(async move {
+				let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+				Ok(grpc.streaming(DecodeStreamCall, request).await)
+			}),
+			path => {
+				let status = tonic::Status::unimplemented(format!("no method {}", path));
+				Box::pin(async move { Ok(status.into_http()) })
+			}
+		}
+	}
+}
+"#,
+    r#"struct DecodeCall;
+"#,
+    r#"impl tonic::server::UnaryService<proto::DecodeRequest> for DecodeCall {
+	type Response = proto::Decoded;
+	type Future = tonic::codegen::BoxFuture<tonic::Response<proto::Decoded>, tonic::Status>;
+
+	fn call(&mut self, request: tonic::Request<proto::DecodeRequest>) -> Self::Future {
+		let decoded = decode_proto(request.get_ref()).map(tonic::Response::new);
+		Box::pin(async move { decoded })
+	}
+}
+"#,
+    r#"struct DecodeStreamCall;
+"#,
+    r#"impl tonic::server::StreamingService<proto::DecodeRequest> for DecodeStreamCall {
+	type Response = proto::Decoded;
+	type ResponseStream = tonic::codegen::BoxStream<proto::Decoded>;
+	type Future = tonic::codegen::BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+	fn call(&mut self, request: tonic::Request<tonic::Streaming<proto::DecodeRequest>>) -> Self::Future {
+		let decoded = futures::StreamExt::map(request.into_inner(), |request| decode_proto(&request?));
+		// the stream ends at the first error
+		let decoded = futures::StreamExt::scan(decoded, false, |failed, decoded| {
+			let item = (!*failed).then_some(decoded);
+			*failed = item.as_ref().is_some_and(|d| d.is_err());
+			futures::future::ready(item)
+		});
+		Box::pin(async move { Ok(tonic::Response::new(Box::pin(decoded) as Self::ResponseStream)) })
+	}
+}
+"#,
+    r#"/// Serves the [`DecoderServer`] on `addr`, until it fails.
+pub async fn serve_grpc_decoder(addr: std::net::SocketAddr) -> std::result::Result<(), tonic::transport::Error> {
+	tonic::transport::Server::builder()
+		.add_service(DecoderServer)
+		.serve(addr)
+		.await
+}
+"#,
+];
+
+/// Writes the gRPC decode service: `PROTO_SCHEMA`, the schema of a message
+/// per instruction, account and event and of the `Decoder` service, the
+/// prost messages in a `proto` module, `decode_proto` decoding a request
+/// into them, the `DecoderServer` and `serve_grpc_decoder`. The `From`
+/// impls of the messages are written next to the structs they read by
+/// [`define_proto_conversions`].
+pub(super) fn define_grpc(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    events: &[Event],
+    accounts: &[&TypeDef],
+    module: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Grpc], config);
+    for ix in instructions.iter().filter(|ix| !indexed(ix, config)) {
+        warn!("no args struct for {}, not served over gRPC", ix.name);
+    }
+    let instructions = instruction_messages(instructions, config);
+    let accounts = account_messages(accounts, config);
+    let events = event_messages(events, config);
+    let messages: Vec<&Message> = instructions
+        .iter()
+        .map(|(_, message)| message)
+        .chain(accounts.iter().map(|(_, message)| message))
+        .chain(events.iter())
+        .collect();
+    if messages.is_empty() {
+        warn!("nothing to serve over gRPC");
+        return Ok(());
+    }
+    let service = format!("{}.Decoder", module);
+
+    let mut schema = format!(
+        "syntax = \"proto3\";\n\npackage {};\n\n// Decodes the instructions, accounts and events of the program.\nservice Decoder {{\n  // Decodes one of them.\n  rpc Decode(DecodeRequest) returns (Decoded);\n  // Decodes a stream of them in order, ending at the first that fails.\n  rpc DecodeStream(stream DecodeRequest) returns (stream Decoded);\n}}\n\nmessage DecodeRequest {{\n  enum Kind {{\n    KIND_INSTRUCTION = 0;\n    KIND_ACCOUNT = 1;\n    KIND_EVENT = 2;\n  }}\n  Kind kind = 1;\n  // The data, discriminator included.\n  bytes data = 2;\n}}\n\nmessage Decoded {{\n  oneof item {{\n",
+        module
+    );
+    for (i, message) in messages.iter().enumerate() {
+        schema.push_str(&format!(
+            "    {} {} = {};\n",
+            message.name,
+            message.item,
+            i + 1
+        ));
+    }
+    schema.push_str("  }\n}\n");
+    for message in messages.iter() {
+        message.write_schema(&mut schema);
+    }
+    output.write_fmt(format_args!(
+        "\n{}/// The protobuf schema of the [`DecoderServer`] and its messages.\npub const PROTO_SCHEMA: &str = r#\"{}\"#;\n",
+        cfg, schema
+    ))?;
+
+    output.write_fmt(format_args!(
+        "\n{}/// The messages of [`PROTO_SCHEMA`], as prost generates them.\npub mod proto {{\n{}\t/// A decoded instruction, account or event.\n\t#[derive(Clone, PartialEq, prost::Message)]\n\tpub struct Decoded {{\n\t\t#[prost(oneof = \"decoded::Item\", tags = \"{}\")]\n\t\tpub item: Option<decoded::Item>,\n\t}}\n\tpub mod decoded {{\n\t\t#[derive(Clone, PartialEq, prost::Oneof)]\n\t\tpub enum Item {{\n",
+        cfg,
+        REQUEST,
+        (1..=messages.len())
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))?;
+    for (i, message) in messages.iter().enumerate() {
+        output.write_fmt(format_args!(
+            "\t\t\t#[prost(message, tag = \"{}\")]\n\t\t\t{}(super::{}),\n",
+            i + 1,
+            message.item.to_upper_camel_case(),
+            message.name
+        ))?;
+    }
+    output.write_all(b"\t\t}\n\t}\n")?;
+    for message in messages.iter() {
+        message.write_struct(output)?;
+    }
+    output.write_all(b"}\n")?;
+
+    output.write_fmt(format_args!(
+        "\n{}/// Decodes the instruction, account or event of `request`.\nfn decode_proto(request: &proto::DecodeRequest) -> std::result::Result<proto::Decoded, tonic::Status> {{\n\tuse proto::decode_request::Kind;\n\tuse proto::decoded::Item;\n\tlet data = request.data.as_slice();\n\tif data.len() < 8 {{\n\t\treturn Err(tonic::Status::invalid_argument(\"data shorter than a discriminator\"));\n\t}}\n\tlet (discriminator, {}) = data.split_at(8);\n\tlet item = match request.kind() {{\n\t\tKind::Instruction => match discriminator {{\n",
+        cfg,
+        if instructions.iter().any(|(ix, _)| !ix.args.is_empty()) {
+            "mut rest"
+        } else {
+            "_"
+        }
+    ))?;
+    for (ix, message) in instructions.iter() {
+        let decoded = if ix.args.is_empty() {
+            format!("proto::{} {{}}", message.name)
+        } else {
+            format!(
+                "(&<{} as borsh::BorshDeserialize>::deserialize(&mut rest).map_err(invalid)?).into()",
+                message.name
+            )
+        };
+        output.write_fmt(format_args!(
+            "\t\t\t{:?} => Item::{}({}),\n",
+            ix.discriminator,
+            message.item.to_upper_camel_case(),
+            decoded
+        ))?;
+    }
+    output.write_all(
+        b"\t\t\t_ => return Err(tonic::Status::not_found(\"none of the program's instructions\")),\n\t\t},\n\t\tKind::Account => match discriminator {\n",
+    )?;
+    for (discriminator, message) in accounts.iter() {
+        output.write_fmt(format_args!(
+            "\t\t\t{:?} => Item::{}((&{}::from_account_data(data).map_err(invalid)?).into()),\n",
+            discriminator,
+            message.item.to_upper_camel_case(),
+            message.name
+        ))?;
+    }
+    output.write_all(
+        b"\t\t\t_ => return Err(tonic::Status::not_found(\"none of the program's accounts\")),\n\t\t},\n",
+    )?;
+    if events.is_empty() {
+        output.write_all(
+            b"\t\tKind::Event => return Err(tonic::Status::not_found(\"none of the program's events\")),\n",
+        )?;
+    } else {
+        output.write_all(
+            b"\t\t// the events of `emit_cpi!` come in their instruction\n\t\tKind::Event => match ProgramEvent::try_decode(data.strip_prefix(&EVENT_IX_TAG).unwrap_or(data)).map_err(invalid)? {\n",
+        )?;
+        for message in events.iter() {
+            output.write_fmt(format_args!(
+                "\t\t\tProgramEvent::{}(event) => Item::{}((&event).into()),\n",
+                message.name,
+                message.item.to_upper_camel_case()
+            ))?;
+        }
+        output.write_all(b"\t\t},\n")?;
+    }
+    output.write_all(b"\t};\n\tOk(proto::Decoded { item: Some(item) })\n}\n")?;
+    output.write_fmt(format_args!(
+        "\n{}fn invalid(e: impl ToString) -> tonic::Status {{\n\ttonic::Status::invalid_argument(e.to_string())\n}}\n",
+        cfg
+    ))?;
+
+    for item in SERVER {
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.replace("{service}", &service).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes the `From` impls of the messages of the gRPC service, from the
+/// args structs into `instructions`, the accounts into `types` and the
+/// events into `events`, next to the structs whose fields they read.
+pub(super) fn define_proto_conversions(
+    instructions_output: &mut impl Write,
+    types_output: &mut impl Write,
+    events_output: &mut impl Write,
+    instructions: &[Instruction],
+    events: &[Event],
+    accounts: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Grpc], config);
+    for (ix, message) in instruction_messages(instructions, config) {
+        // the argless instructions have no struct to convert
+        if !ix.args.is_empty() {
+            message.write_from(instructions_output, &cfg)?;
+        }
+    }
+    for (_, message) in account_messages(accounts, config) {
+        message.write_from(types_output, &cfg)?;
+    }
+    for message in event_messages(events, config) {
+        message.write_from(events_output, &cfg)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,730 @@
+//! A Postgres indexer of the program, see
+//! [`GeneratorConfig::indexer`](crate::GeneratorConfig::indexer).
+//!
+//! There is no SQL backend to take the schema from: the tables are written
+//! here from the program model, with the column types of `scalar`, into
+//! a single `MIGRATION` run by `run_indexer` on every start, not migration
+//! files for `sqlx migrate`. Changing the IDL doesn't alter the tables
+//! already created.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use heck::ToSnakeCase;
+use log::warn;
+
+use super::client::cfg;
+use super::flags::flag_type;
+use crate::config::{Artifact, Feature, GeneratorConfig};
+use crate::ir::{Event, Instruction, Type, TypeDef, TypeDefKind};
+use crate::template::TemplateKind;
+
+/// A table column of a field.
+struct Column {
+    /// Quoted column name.
+    name: String,
+    sql: String,
+    /// Expression of the bound value.
+    bind: String,
+    /// Cast of the placeholder, the NUMERIC values are bound as text.
+    cast: &'static str,
+}
+
+/// SQL type, bind expression of `{}` and placeholder cast of the types
+/// with a column type of their own.
+fn scalar(ty: &Type) -> Option<(&'static str, &'static str, &'static str)> {
+    Some(match ty {
+        Type::Bool => ("BOOLEAN", "{}", ""),
+        Type::U8 | Type::I8 | Type::I16 => ("SMALLINT", "{} as i16", ""),
+        Type::U16 | Type::I32 => ("INTEGER", "{} as i32", ""),
+        Type::U32 | Type::I64 => ("BIGINT", "{} as i64", ""),
+        Type::U64 | Type::U128 | Type::I128 => ("NUMERIC", "{}.to_string()", "::NUMERIC"),
+        Type::F32 => ("REAL", "{}", ""),
+        Type::F64 => ("DOUBLE PRECISION", "{}", ""),
+        Type::String => ("TEXT", "{}.clone()", ""),
+        Type::PublicKey => ("TEXT", "{}.to_string()", ""),
+        Type::Bytes => ("BYTEA", "{}.clone()", ""),
+        _ => return None,
+    })
+}
+
+/// The column of the field `name` of type `ty`, read from `expr`. Options of
+/// scalars are nullable, everything else is stored as its borsh encoding.
+fn column(name: &str, expr: &str, ty: &Type, config: &GeneratorConfig) -> Column {
+    let hooked = |ty: &Type| hooked(ty, name, config);
+    let (sql, bind, cast) = match ty {
+        Type::Option(inner) if !hooked(ty) && !hooked(inner) => match scalar(inner) {
+            Some((sql, bind, cast)) => (
+                sql.to_string(),
+                format!("{}.as_ref().map(|v| {})", expr, bind.replace("{}", "(*v)")),
+                cast,
+            ),
+            None => borsh_column(expr),
+        },
+        ty if !hooked(ty) => match scalar(ty) {
+            Some((sql, bind, cast)) => {
+                (format!("{} NOT NULL", sql), bind.replace("{}", expr), cast)
+            }
+            None => borsh_column(expr),
+        },
+        _ => borsh_column(expr),
+    };
+    Column {
+        name: format!("{:?}", name.to_snake_case()),
+        sql,
+        bind,
+        cast,
+    }
+}
+
+/// Whether the rust type of `ty`, of the field `name`, is unknown: a type
+/// hook or the flags map it.
+pub(super) fn hooked(ty: &Type, name: &str, config: &GeneratorConfig) -> bool {
+    config
+        .type_hooks
+        .iter()
+        .any(|h| h.map(ty, Some(name)).is_some())
+        || flag_type(ty, Some(name), config).is_some()
+}
+
+fn borsh_column(expr: &str) -> (String, String, &'static str) {
+    (
+        "BYTEA NOT NULL".to_string(),
+        format!("borsh::BorshSerialize::try_to_vec(&{})?", expr),
+        "",
+    )
+}
+
+/// Renames `column` if it is taken by a column of `used`.
+fn unique(mut column: Column, prefix: &str, used: &mut HashSet<String>) -> Column {
+    if used.contains(&column.name) {
+        column.name = format!("\"{}_{}", prefix, &column.name[1..]);
+    }
+    used.insert(column.name.clone());
+    column
+}
+
+/// A table: the `CREATE TABLE` statement and the insert function of a
+/// decoded item.
+struct Table {
+    name: String,
+    /// Columns of the location of the item, bound by the caller.
+    key: Vec<(&'static str, &'static str)>,
+    columns: Vec<Column>,
+    /// Table constraint, if any.
+    constraint: String,
+}
+
+impl Table {
+    fn create(&self) -> String {
+        let mut lines: Vec<String> = self
+            .key
+            .iter()
+            .map(|(name, sql)| format!("\t{} {}", name, sql))
+            .collect();
+        lines.extend(
+            self.columns
+                .iter()
+                .map(|c| format!("\t{} {}", c.name, c.sql)),
+        );
+        if !self.constraint.is_empty() {
+            lines.push(format!("\t{}", self.constraint));
+        }
+        format!(
+            "CREATE TABLE IF NOT EXISTS {:?} (\n{}\n);\n",
+            self.name,
+            lines.join(",\n")
+        )
+    }
+
+    /// The insert statement, `conflict` being its `ON CONFLICT` clause.
+    fn insert(&self, conflict: &str) -> String {
+        let names: Vec<_> = self
+            .key
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .chain(self.columns.iter().map(|c| c.name.clone()))
+            .collect();
+        let placeholders: Vec<_> = (1..=self.key.len())
+            .map(|i| format!("${}", i))
+            .chain(
+                self.columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| format!("${}{}", self.key.len() + i + 1, c.cast)),
+            )
+            .collect();
+        format!(
+            "INSERT INTO {:?} ({}) VALUES ({}) ON CONFLICT {}",
+            self.name,
+            names.join(", "),
+            placeholders.join(", "),
+            conflict
+        )
+    }
+
+    /// The body of the insert function, binding `key` before the columns.
+    fn write_insert(
+        &self,
+        output: &mut impl Write,
+        conflict: &str,
+        key: &[&str],
+    ) -> std::io::Result<()> {
+        output.write_fmt(format_args!("\tsqlx::query({:?})\n", self.insert(conflict)))?;
+        for bind in key
+            .iter()
+            .map(|k| k.to_string())
+            .chain(self.columns.iter().map(|c| c.bind.clone()))
+        {
+            output.write_fmt(format_args!("\t\t.bind({})\n", bind))?;
+        }
+        output.write_all(b"\t\t.execute(executor)\n\t\t.await?;\n\tOk(())\n}\n")
+    }
+}
+
+const LOCATION_KEY: [(&str, &str); 4] = [
+    ("signature", "TEXT NOT NULL"),
+    ("slot", "BIGINT NOT NULL"),
+    ("ix_index", "INTEGER NOT NULL"),
+    ("inner_index", "INTEGER"),
+];
+
+const BIND_LOCATION: [&str; 4] = [
+    "location.signature.to_string()",
+    "location.slot as i64",
+    "location.index as i32",
+    "location.inner_index.map(|i| i as i32)",
+];
+
+fn instruction_table(ix: &Instruction, module: &str, config: &GeneratorConfig) -> Table {
+    let mut used: HashSet<_> = LOCATION_KEY
+        .iter()
+        .map(|(n, _)| format!("{:?}", n))
+        .collect();
+    let mut columns = vec![];
+    for (i, (name, account)) in ix.qualified_accounts().into_iter().enumerate() {
+        // accounts the caller left out are missing, optional ones
+        let bind = if account.is_optional {
+            // or passed as the program ID
+            format!(
+                "accounts.get({}).filter(|a| **a != PROGRAM_ID).map(|a| a.to_string())",
+                i
+            )
+        } else {
+            format!("accounts.get({}).map(|a| a.to_string())", i)
+        };
+        let column = Column {
+            name: format!("{:?}", name.replace('.', "_").to_snake_case()),
+            sql: "TEXT".to_string(),
+            bind,
+            cast: "",
+        };
+        columns.push(unique(column, "account", &mut used));
+    }
+    for arg in ix.args.iter() {
+        let expr = format!("args.{}", config.naming.fields.apply(&arg.name));
+        columns.push(unique(
+            column(&arg.name, &expr, &arg.ty, config),
+            "arg",
+            &mut used,
+        ));
+    }
+    Table {
+        name: format!("{}_ix_{}", module, ix.name.to_snake_case()),
+        key: LOCATION_KEY.to_vec(),
+        columns,
+        constraint: "UNIQUE NULLS NOT DISTINCT (signature, ix_index, inner_index)".to_string(),
+    }
+}
+
+fn event_table(event: &Event, module: &str, config: &GeneratorConfig) -> Table {
+    let mut key = vec![("event_index", "INTEGER NOT NULL")];
+    key.extend(LOCATION_KEY);
+    let mut used: HashSet<_> = key.iter().map(|(n, _)| format!("{:?}", n)).collect();
+    let columns = event
+        .fields
+        .iter()
+        .map(|f| {
+            let expr = format!("event.{}", config.naming.fields.apply(&f.name));
+            unique(column(&f.name, &expr, &f.ty, config), "field", &mut used)
+        })
+        .collect();
+    Table {
+        name: format!("{}_event_{}", module, event.name.to_snake_case()),
+        key,
+        columns,
+        constraint: "PRIMARY KEY (signature, event_index)".to_string(),
+    }
+}
+
+/// `None` for types, only accounts have a table.
+fn account_table(def: &TypeDef, module: &str, config: &GeneratorConfig) -> Option<Table> {
+    def.discriminator?;
+    let TypeDefKind::Struct { fields } = &def.kind else {
+        return None;
+    };
+    let key = vec![("address", "TEXT PRIMARY KEY"), ("slot", "BIGINT NOT NULL")];
+    let mut used: HashSet<_> = key.iter().map(|(n, _)| format!("{:?}", n)).collect();
+    let columns = fields
+        .iter()
+        .map(|f| {
+            let expr = format!("account.{}", config.naming.fields.apply(&f.name));
+            unique(column(&f.name, &expr, &f.ty, config), "field", &mut used)
+        })
+        .collect();
+    Some(Table {
+        name: format!("{}_account_{}", module, def.name.to_snake_case()),
+        key,
+        columns,
+        constraint: String::new(),
+    })
+}
+
+/// Whether the struct fields of accounts and events can be read.
+pub(super) fn structs_readable(config: &GeneratorConfig) -> bool {
+    !config.templates.contains_key(&TemplateKind::Struct)
+}
+
+/// Whether instruction `ix` is indexed: its args struct is emitted.
+pub(super) fn indexed(ix: &Instruction, config: &GeneratorConfig) -> bool {
+    ix.args.is_empty()
+        || (config.emits(Artifact::InstructionArgs)
+            && !config.templates.contains_key(&TemplateKind::Instruction))
+}
+
+/// Program independent part: the location of the indexed items and the
+/// sources of the transactions.
+const INDEXER: &[&str] = &[
+    r#"/// Where an indexed instruction or event comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+	pub signature: solana_sdk::signature::Signature,
+	pub slot: u64,
+	/// Position of the top-level instruction in the message.
+	pub index: usize,
+	/// Position among the instructions the top-level instruction invoked,
+	/// `None` for the top-level instruction itself.
+	pub inner_index: Option<usize>,
+}
+"#,
+    r#"/// Where [`run_indexer`] reads the program's transactions from.
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+	pub rpc_url: String,
+	pub ws_url: String,
+	pub commitment: solana_sdk::commitment_config::CommitmentConfig,
+	/// First slot of the backfill, `None` to index new transactions only.
+	pub from_slot: Option<u64>,
+}
+"#,
+    r#"/// Creates the tables, indexes the accounts of the program, backfills its
+/// transactions from `config.from_slot` on and then indexes the new ones as
+/// their logs are notified. Runs until a transaction can't be indexed.
+pub async fn run_indexer(
+	pool: &sqlx::PgPool,
+	config: &IndexerConfig,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	sqlx::Executor::execute(pool, MIGRATION).await?;
+	let rpc = solana_client::nonblocking::rpc_client::RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+	index_accounts(pool, &rpc).await?;
+
+	// subscribed first, so that nothing falls between the backfill and the
+	// notifications
+	let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+	let (ws_url, commitment) = (config.ws_url.clone(), config.commitment);
+	tokio::spawn(async move {
+		let mut backoff = std::time::Duration::from_millis(500);
+		while let Err(_e) = forward_signatures(&ws_url, commitment, &sender).await {
+{reconnect}			tokio::time::sleep(backoff).await;
+			backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+		}
+	});
+	if let Some(from_slot) = config.from_slot {
+		backfill(pool, &rpc, from_slot).await?;
+	}
+	while let Some(signature) = receiver.recv().await {
+		index_signature(pool, &rpc, signature).await?;
+	}
+	Err("the subscription task ended".into())
+}
+"#,
+    r#"/// Indexes the successful transactions of the program from `from_slot` on,
+/// oldest first.
+pub async fn backfill(
+	pool: &sqlx::PgPool,
+	rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+	from_slot: u64,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let mut signatures = Vec::new();
+	let mut before = None;
+	'pages: loop {
+		let config = solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config {
+			before,
+			until: None,
+			limit: None,
+			commitment: Some(rpc.commitment()),
+		};
+		let page = rpc.get_signatures_for_address_with_config(&PROGRAM_ID, config).await?;
+		let Some(last) = page.last() else {
+			break;
+		};
+		before = Some(last.signature.parse()?);
+		for info in page {
+			if info.slot < from_slot {
+				break 'pages;
+			}
+			if info.err.is_none() {
+				signatures.push(info.signature.parse()?);
+			}
+		}
+	}
+	for signature in signatures.into_iter().rev() {
+		index_signature(pool, rpc, signature).await?;
+	}
+	Ok(())
+}
+"#,
+    r#"/// Fetches and indexes the transaction `signature`, retrying while the
+/// node doesn't have it yet.
+pub async fn index_signature(
+	pool: &sqlx::PgPool,
+	rpc: &solana_client::nonblocking::rpc_client::RpcClient,
+	signature: solana_sdk::signature::Signature,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	let config = solana_client::rpc_config::RpcTransactionConfig {
+		encoding: Some(solana_transaction_status::UiTransactionEncoding::Base64),
+		commitment: Some(rpc.commitment()),
+		max_supported_transaction_version: Some(0),
+	};
+	let mut attempt = 0;
+	let tx = loop {
+		match rpc.get_transaction_with_config(&signature, config).await {
+			Ok(tx) => break tx,
+			Err(_e) if attempt < 5 => {
+{fetch_failed}				attempt += 1;
+				tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+			}
+			Err(e) => return Err(e.into()),
+		}
+	};
+	index_transaction(pool, signature, &tx).await
+}
+"#,
+    r#"/// Indexes the instructions of the program in `tx`, inner ones included,
+/// and the events it logged, in one database transaction. Failed
+/// transactions are skipped.
+pub async fn index_transaction(
+	pool: &sqlx::PgPool,
+	signature: solana_sdk::signature::Signature,
+	tx: &solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use solana_transaction_status::option_serializer::OptionSerializer;
+	let meta = tx.transaction.meta.as_ref().ok_or("transaction without status")?;
+	if meta.err.is_some() {
+		return Ok(());
+	}
+	let decoded = tx.transaction.transaction.decode().ok_or("transaction not in a binary encoding")?;
+	let mut keys = decoded.message.static_account_keys().to_vec();
+	if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+		for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+			keys.push(key.parse()?);
+		}
+	}
+
+	let mut db = pool.begin().await?;
+	for (index, ix) in decoded.message.instructions().iter().enumerate() {
+		let location = Location { signature, slot: tx.slot, index, inner_index: None };
+		index_instruction(&mut db, &location, &keys, ix.program_id_index, &ix.accounts, &ix.data).await?;
+	}
+	if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+		for group in inner {
+			for (inner_index, ix) in group.instructions.iter().enumerate() {
+				let solana_transaction_status::UiInstruction::Compiled(ix) = ix else {
+					continue;
+				};
+				let location = Location {
+					signature,
+					slot: tx.slot,
+					index: group.index as usize,
+					inner_index: Some(inner_index),
+				};
+				let data = solana_sdk::bs58::decode(&ix.data).into_vec()?;
+				index_instruction(&mut db, &location, &keys, ix.program_id_index, &ix.accounts, &data).await?;
+			}
+		}
+	}
+{index_events}	db.commit().await?;
+	Ok(())
+}
+"#,
+    r#"/// Fails when the subscription does, `Ok` once nobody receives the
+/// signatures anymore.
+async fn forward_signatures(
+	ws_url: &str,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+	sender: &tokio::sync::mpsc::UnboundedSender<solana_sdk::signature::Signature>,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use futures::StreamExt;
+	let client = solana_client::nonblocking::pubsub_client::PubsubClient::new(ws_url).await?;
+	let (mut notifications, unsubscribe) = client
+		.logs_subscribe(
+			solana_client::rpc_config::RpcTransactionLogsFilter::Mentions(vec![PROGRAM_ID.to_string()]),
+			solana_client::rpc_config::RpcTransactionLogsConfig { commitment: Some(commitment) },
+		)
+		.await?;
+	while let Some(notification) = notifications.next().await {
+		if notification.value.err.is_some() {
+			continue;
+		}
+		let Ok(signature) = notification.value.signature.parse() else {
+			continue;
+		};
+		if sender.send(signature).is_err() {
+			drop(notifications);
+			unsubscribe().await;
+			return Ok(());
+		}
+	}
+	Err("subscription closed".into())
+}
+"#,
+];
+
+/// Writes the indexer next to the args structs: the `MIGRATION` creating a
+/// table per instruction, event and account, an `insert_<name>` function
+/// per instruction, `index_transaction` decoding a transaction into them,
+/// and `run_indexer`, backfilling the program's transactions and following
+/// the new ones. The inserts of the events and accounts are written next to
+/// their structs by [`define_event_inserts`] and [`define_account_inserts`].
+pub(super) fn define_indexer(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    events: &[Event],
+    accounts: &[&TypeDef],
+    module: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Indexer], config);
+    let vis = config.type_visibility.as_prefix();
+    let instructions: Vec<_> = instructions
+        .iter()
+        .filter(|ix| {
+            let indexed = indexed(ix, config);
+            if !indexed {
+                warn!("no args struct for {}, not indexed", ix.name);
+            }
+            indexed
+        })
+        .collect();
+    let events = indexed_events(events, config);
+    let accounts = indexed_accounts(accounts, module, config);
+
+    let mut migration = String::new();
+    for ix in instructions.iter() {
+        migration.push_str(&instruction_table(ix, module, config).create());
+    }
+    for event in events.iter() {
+        migration.push_str(&event_table(event, module, config).create());
+    }
+    for (_, table) in accounts.iter() {
+        migration.push_str(&table.create());
+    }
+    output.write_fmt(format_args!(
+        "\n{}/// The tables of the indexer, created if missing by [`run_indexer`].\n{}const MIGRATION: &str = r#\"{}\"#;\n",
+        cfg, vis, migration
+    ))?;
+
+    for item in INDEXER {
+        let index_events = if events.is_empty() {
+            String::new()
+        } else {
+            let mut arms = String::new();
+            for event in events.iter() {
+                let name = config.naming.types.apply(&event.name);
+                arms.push_str(&format!(
+                    "\t\t\t\tProgramEvent::{0}(event) => insert_{1}(&mut *db, &location, event_index, event).await?,\n",
+                    name,
+                    event.name.to_snake_case()
+                ));
+            }
+            format!(
+                "\t// events of `emit_cpi!` are only seen in the logs when also `emit!`ed\n\tif let OptionSerializer::Some(logs) = &meta.log_messages {{\n\t\tfor (event_index, emitted) in ProgramEvent::from_logs(logs).into_iter().enumerate() {{\n\t\t\tlet location = Location {{\n\t\t\t\tsignature,\n\t\t\t\tslot: tx.slot,\n\t\t\t\tindex: emitted.index,\n\t\t\t\tinner_index: emitted.inner_index,\n\t\t\t}};\n\t\t\tmatch &emitted.event {{\n{}\t\t\t}}\n\t\t}}\n\t}}\n",
+                arms
+            )
+        };
+        let item = item
+            .replace("{index_events}", &index_events)
+            .replace(
+                "{reconnect}",
+                &config.traced(
+                    "\t\t\t",
+                    "tracing::warn!(error = %_e, ?backoff, \"log subscription failed, reconnecting\");",
+                ),
+            )
+            .replace(
+                "{fetch_failed}",
+                &config.traced(
+                    "\t\t\t\t",
+                    "tracing::debug!(%signature, attempt, error = %_e, \"transaction not fetched, retrying\");",
+                ),
+            );
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+
+    // the accounts, decoded by discriminator
+    output.write_fmt(format_args!(
+        "\n{}/// Indexes the accounts the program owns, at the slot they are fetched at.\n{}async fn index_accounts(\n\tpool: &sqlx::PgPool,\n\trpc: &solana_client::nonblocking::rpc_client::RpcClient,\n) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {{\n",
+        cfg, vis
+    ))?;
+    if accounts.is_empty() {
+        output.write_all(b"\tlet _ = (pool, rpc);\n\tOk(())\n}\n")?;
+    } else {
+        output.write_all(
+            b"\tlet slot = rpc.get_slot().await?;\n\tfor (address, account) in rpc.get_program_accounts(&PROGRAM_ID).await? {\n\t\tlet Some((discriminator, mut data)) = (account.data.len() >= 8).then(|| account.data.split_at(8)) else {\n\t\t\tcontinue;\n\t\t};\n\t\tmatch discriminator {\n",
+        )?;
+        for (def, _) in accounts.iter() {
+            output.write_fmt(format_args!(
+                "\t\t\t{:?} => {{\n\t\t\t\tlet account = <{} as borsh::BorshDeserialize>::deserialize(&mut data)?;\n\t\t\t\tinsert_{}(pool, &address, slot, &account).await?;\n\t\t\t}}\n",
+                def.discriminator.expect("accounts have one"),
+                config.naming.types.apply(&def.name),
+                def.name.to_snake_case()
+            ))?;
+        }
+        output.write_all(b"\t\t\t_ => {}\n\t\t}\n\t}\n\tOk(())\n}\n")?;
+    }
+
+    // the instructions, decoded by discriminator
+    output.write_fmt(format_args!(
+        "\n{}async fn index_instruction(\n\tdb: &mut sqlx::PgConnection,\n\tlocation: &Location,\n\tkeys: &[Pubkey],\n\tprogram_id_index: u8,\n\taccounts: &[u8],\n\tdata: &[u8],\n) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {{\n\tif keys.get(program_id_index as usize) != Some(&PROGRAM_ID) || data.len() < 8 {{\n\t\treturn Ok(());\n\t}}\n\tlet accounts: Vec<Pubkey> = accounts.iter().filter_map(|i| keys.get(*i as usize).copied()).collect();\n\tlet (discriminator, {}data) = data.split_at(8);\n\tmatch discriminator {{\n",
+        cfg,
+        if instructions.iter().any(|ix| !ix.args.is_empty()) {
+            "mut "
+        } else {
+            "_"
+        }
+    ))?;
+    for ix in instructions.iter() {
+        let insert = format!("insert_{}", ix.name.to_snake_case());
+        if ix.args.is_empty() {
+            output.write_fmt(format_args!(
+                "\t\t{:?} => {}(db, location, &accounts).await?,\n",
+                ix.discriminator, insert
+            ))?;
+        } else {
+            output.write_fmt(format_args!(
+                "\t\t{:?} => {{\n\t\t\tlet args = <{} as borsh::BorshDeserialize>::deserialize(&mut data)?;\n\t\t\t{}(db, location, &accounts, &args).await?;\n\t\t}}\n",
+                ix.discriminator,
+                config.naming.instructions.apply(&ix.name),
+                insert
+            ))?;
+        }
+    }
+    output.write_all(b"\t\t_ => {}\n\t}\n\tOk(())\n}\n")?;
+
+    for ix in instructions.iter() {
+        let table = instruction_table(ix, module, config);
+        let args = if ix.args.is_empty() {
+            String::new()
+        } else {
+            format!("\n\targs: &{},", config.naming.instructions.apply(&ix.name))
+        };
+        output.write_fmt(format_args!(
+            "\n{}/// Inserts a decoded `{}` instruction with its `accounts`, in order, unless it is indexed already.\n{}async fn insert_{}(\n\texecutor: impl sqlx::PgExecutor<'_>,\n\tlocation: &Location,\n\taccounts: &[Pubkey],{}\n) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {{\n",
+            cfg,
+            ix.name,
+            vis,
+            ix.name.to_snake_case(),
+            args
+        ))?;
+        table.write_insert(output, "DO NOTHING", &BIND_LOCATION)?;
+    }
+    Ok(())
+}
+
+/// The events the indexer stores, none without readable event structs.
+fn indexed_events<'a>(events: &'a [Event], config: &GeneratorConfig) -> Vec<&'a Event> {
+    if !config.emits(Artifact::Events) || !structs_readable(config) {
+        return vec![];
+    }
+    events.iter().collect()
+}
+
+/// The emitted accounts the indexer stores, with their tables.
+fn indexed_accounts<'a>(
+    accounts: &[&'a TypeDef],
+    module: &str,
+    config: &GeneratorConfig,
+) -> Vec<(&'a TypeDef, Table)> {
+    if !structs_readable(config) {
+        return vec![];
+    }
+    accounts
+        .iter()
+        .filter_map(|def| Some((*def, account_table(def, module, config)?)))
+        .collect()
+}
+
+/// Writes the `insert_<event>` functions of the indexer, next to the event
+/// structs.
+pub(super) fn define_event_inserts(
+    output: &mut impl Write,
+    events: &[Event],
+    module: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Indexer], config);
+    let vis = config.type_visibility.as_prefix();
+    for event in indexed_events(events, config) {
+        let table = event_table(event, module, config);
+        output.write_fmt(format_args!(
+            "\n{}/// Inserts a decoded `{}` event, the `event_index`th of its transaction, unless it is indexed already.\n{}async fn insert_{}(\n\texecutor: impl sqlx::PgExecutor<'_>,\n\tlocation: &Location,\n\tevent_index: usize,\n\tevent: &{},\n) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {{\n",
+            cfg,
+            event.name,
+            vis,
+            event.name.to_snake_case(),
+            config.naming.types.apply(&event.name)
+        ))?;
+        let mut key = vec!["event_index as i32"];
+        key.extend(BIND_LOCATION);
+        table.write_insert(output, "DO NOTHING", &key)?;
+    }
+    Ok(())
+}
+
+/// Writes the `insert_<account>` functions of the indexer, next to the
+/// account structs.
+pub(super) fn define_account_inserts(
+    output: &mut impl Write,
+    accounts: &[&TypeDef],
+    module: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Indexer], config);
+    let vis = config.type_visibility.as_prefix();
+    for (def, table) in indexed_accounts(accounts, module, config) {
+        let mut updates = vec!["slot = EXCLUDED.slot".to_string()];
+        updates.extend(
+            table
+                .columns
+                .iter()
+                .map(|c| format!("{0} = EXCLUDED.{0}", c.name)),
+        );
+        let conflict = format!(
+            "(address) DO UPDATE SET {} WHERE {:?}.slot <= EXCLUDED.slot",
+            updates.join(", "),
+            table.name
+        );
+        output.write_fmt(format_args!(
+            "\n{}/// Inserts or updates the `{}` account at `address`, unless a newer state is indexed.\n{}async fn insert_{}(\n\texecutor: impl sqlx::PgExecutor<'_>,\n\taddress: &Pubkey,\n\tslot: u64,\n\taccount: &{},\n) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {{\n",
+            cfg,
+            def.name,
+            vis,
+            def.name.to_snake_case(),
+            config.naming.types.apply(&def.name)
+        ))?;
+        table.write_insert(output, &conflict, &["address.to_string()", "slot as i64"])?;
+    }
+    Ok(())
+}
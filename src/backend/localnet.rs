@@ -0,0 +1,154 @@
+//! A local validator for integration tests of the bindings, see
+//! [`GeneratorConfig::localnet`](crate::GeneratorConfig::localnet).
+
+use std::io::Write;
+
+use heck::ToUpperCamelCase;
+
+use super::client::cfg;
+use crate::config::{Feature, GeneratorConfig};
+
+/// `Localnet`, `Client` being replaced by the name of the module's client.
+const LOCALNET: &[&str] = &[
+    r#"/// A local validator for integration tests, either started with the program
+/// loaded at [`PROGRAM_ID`] or an already running one.
+///
+/// ```ignore
+/// let localnet = Localnet::start("target/deploy/program.so")?;
+/// let payer = localnet.payer(1_000_000_000)?;
+/// let client = localnet.client(ClientConfig::default());
+/// ```
+pub struct Localnet {
+	url: String,
+	validator: Option<std::process::Child>,
+	ledger: Option<std::path::PathBuf>,
+}
+"#,
+    r#"impl Localnet {
+	/// How long [`Self::start`] waits for the validator to answer.
+	pub const STARTUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+	/// A validator running at `url`, which must have the program deployed.
+	pub fn connect(url: impl ToString) -> Self {
+		Self {
+			url: url.to_string(),
+			validator: None,
+			ledger: None,
+		}
+	}
+
+	/// Starts `solana-test-validator` with the program `so` loaded at
+	/// [`PROGRAM_ID`], on free ports and a fresh ledger in the temporary
+	/// directory, and waits until it answers. The validator is killed and
+	/// its ledger removed on drop.
+	pub fn start(so: impl AsRef<std::path::Path>) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+		let free_port = || -> std::io::Result<u16> {
+			Ok(std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+		};
+		// the websocket listens on the port after the rpc one
+		let rpc_port = free_port()?;
+		let ledger = std::env::temp_dir().join(format!("localnet-{}-{}", std::process::id(), rpc_port));
+		let validator = std::process::Command::new("solana-test-validator")
+			.arg("--reset")
+			.arg("--quiet")
+			.arg("--ledger")
+			.arg(&ledger)
+			.args(["--rpc-port", &rpc_port.to_string()])
+			.args(["--faucet-port", &free_port()?.to_string()])
+			.args(["--gossip-port", &free_port()?.to_string()])
+			.args(["--bpf-program", &PROGRAM_ID.to_string()])
+			.arg(so.as_ref())
+			.stdout(std::process::Stdio::null())
+			.stderr(std::process::Stdio::null())
+			.spawn()
+			.map_err(|e| format!("can't run solana-test-validator: {}", e))?;
+		let mut localnet = Self {
+			url: format!("http://127.0.0.1:{}", rpc_port),
+			validator: Some(validator),
+			ledger: Some(ledger),
+		};
+		let rpc = localnet.rpc();
+		let started = std::time::Instant::now();
+		while rpc.get_health().is_err() {
+			if let Some(status) = localnet.validator.as_mut().and_then(|v| v.try_wait().transpose()) {
+				return Err(format!("solana-test-validator exited with {}", status?).into());
+			}
+			if started.elapsed() > Self::STARTUP_TIMEOUT {
+				return Err("solana-test-validator didn't start in time".into());
+			}
+			std::thread::sleep(std::time::Duration::from_millis(200));
+		}
+		Ok(localnet)
+	}
+
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	/// An RPC client of the validator, with confirmed commitment.
+	pub fn rpc(&self) -> solana_client::rpc_client::RpcClient {
+		solana_client::rpc_client::RpcClient::new_with_commitment(
+			self.url.clone(),
+			solana_sdk::commitment_config::CommitmentConfig::confirmed(),
+		)
+	}
+
+	/// Airdrops `lamports` to `to` and waits for the confirmation.
+	pub fn airdrop(&self, to: &Pubkey, lamports: u64) -> std::result::Result<(), Box<dyn std::error::Error>> {
+		let rpc = self.rpc();
+		let signature = rpc.request_airdrop(to, lamports)?;
+		let started = std::time::Instant::now();
+		while !rpc.confirm_transaction(&signature)? {
+			if started.elapsed() > Self::STARTUP_TIMEOUT {
+				return Err(format!("airdrop {} not confirmed", signature).into());
+			}
+			std::thread::sleep(std::time::Duration::from_millis(200));
+		}
+		Ok(())
+	}
+
+	/// A new keypair funded with `lamports`.
+	pub fn payer(&self, lamports: u64) -> std::result::Result<solana_sdk::signer::keypair::Keypair, Box<dyn std::error::Error>> {
+		let payer = solana_sdk::signer::keypair::Keypair::new();
+		self.airdrop(&solana_sdk::signer::Signer::pubkey(&payer), lamports)?;
+		Ok(payer)
+	}
+
+	/// A client of the validator.
+	pub fn client(&self, config: ClientConfig) -> Client {
+		Client::new(&self.url, config)
+	}
+}
+"#,
+    r#"impl Drop for Localnet {
+	fn drop(&mut self) {
+		if let Some(mut validator) = self.validator.take() {
+			let _ = validator.kill();
+			let _ = validator.wait();
+		}
+		if let Some(ledger) = self.ledger.take() {
+			let _ = std::fs::remove_dir_all(ledger);
+		}
+	}
+}
+"#,
+];
+
+/// Writes `Localnet`, starting or connecting to a validator and handing out
+/// funded payers and clients of it.
+pub(super) fn define_localnet(
+    output: &mut impl Write,
+    module_name: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Localnet], config);
+    let client = format!("{}Client", module_name.to_upper_camel_case());
+    for item in LOCALNET {
+        let item = item
+            .replace("-> Client {", &format!("-> {} {{", client))
+            .replace("Client::new(", &format!("{}::new(", client));
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+//! Counters of the generated decoders, see
+//! [`GeneratorConfig::metrics`](crate::GeneratorConfig::metrics).
+
+use std::io::Write;
+
+/// The `DecodeMetrics` trait and its process wide registration.
+const METRICS: &[u8] = br#"
+/// Receives what the decoders of the module decode, to count decoded items
+/// by kind and name, unknown discriminators (a stale IDL) and failures.
+/// `kind` is `"instruction"` or `"event"`. See [`set_decode_metrics`].
+pub trait DecodeMetrics: Send + Sync {
+	fn decoded(&self, kind: &'static str, name: &'static str, elapsed: std::time::Duration) {
+		let _ = (kind, name, elapsed);
+	}
+
+	fn unknown_discriminator(&self, kind: &'static str, discriminator: &[u8]) {
+		let _ = (kind, discriminator);
+	}
+
+	fn decode_failed(&self, kind: &'static str, name: &'static str) {
+		let _ = (kind, name);
+	}
+}
+
+static DECODE_METRICS: std::sync::OnceLock<Box<dyn DecodeMetrics>> = std::sync::OnceLock::new();
+
+/// Registers the metrics the decoders of the module report to, `false` if
+/// some were registered already.
+pub fn set_decode_metrics(metrics: impl DecodeMetrics + 'static) -> bool {
+	DECODE_METRICS.set(Box::new(metrics)).is_ok()
+}
+
+/// The registered metrics, if any.
+pub fn decode_metrics() -> Option<&'static dyn DecodeMetrics> {
+	DECODE_METRICS.get().map(|m| m.as_ref())
+}
+"#;
+
+/// Writes the `DecodeMetrics` trait the decoders report to once
+/// registered.
+pub(super) fn define_metrics(output: &mut impl Write) -> std::io::Result<()> {
+    output.write_all(METRICS)
+}
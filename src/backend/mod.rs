@@ -0,0 +1,183 @@
+//! Code generation backends.
+//!
+//! The generator walks a lowered [`Program`] and hands each instruction,
+//! account, type and event to a [`Backend`], which turns them into output
+//! files.
+//! Backends are looked up by name in a [`BackendRegistry`], so alternative
+//! outputs can be plugged in without touching the walker.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::GeneratorConfig;
+use crate::error::{ParseIdlError, Result};
+use crate::ir::{Event, Instruction, Program, TypeDef};
+use crate::prune::prune;
+
+mod accessors;
+mod anchor;
+mod bench;
+mod big_arrays;
+mod bindings_error;
+mod borrowed;
+mod client;
+mod diffs;
+mod events;
+mod fetchers;
+mod flags;
+mod grpc;
+mod indexer;
+mod localnet;
+mod metrics;
+mod newtypes;
+mod prelude;
+mod rust;
+mod serde_human;
+mod service;
+mod sink;
+mod skeleton;
+mod streaming;
+mod support;
+mod watchers;
+
+pub use bench::BenchBackend;
+pub(crate) use rust::{
+    defines_bindings_error, defines_cli, defines_client, defines_localnet, uses_anchor,
+};
+pub use rust::{ty_to_rust_type, RustBackend};
+pub(crate) use sink::serializes;
+pub use skeleton::SkeletonBackend;
+pub(crate) use support::{support_module, SUPPORT_MODULE};
+
+/// A file produced by a backend, `path` is relative to the output directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// What a backend knows about the program being generated.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramContext<'a> {
+    pub program: &'a Program,
+    /// Name of the module (or file stem) the output is written as.
+    pub module_name: &'a str,
+    pub config: &'a GeneratorConfig,
+}
+
+/// Turns the visited IDL items into generated files.
+///
+/// `visit_program` is called first, then every instruction, account, type
+/// and event in IDL order, and finally `finish`.
+pub trait Backend {
+    fn visit_program(&mut self, program: &ProgramContext<'_>) -> Result<()>;
+
+    fn visit_instruction(&mut self, _ix: &Instruction) -> Result<()> {
+        Ok(())
+    }
+
+    fn visit_account(&mut self, _account: &TypeDef) -> Result<()> {
+        Ok(())
+    }
+
+    fn visit_type(&mut self, _ty: &TypeDef) -> Result<()> {
+        Ok(())
+    }
+
+    fn visit_event(&mut self, _event: &Event) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<Vec<GeneratedFile>>;
+}
+
+type BackendFactory = Box<dyn Fn() -> Box<dyn Backend> + Send + Sync>;
+
+/// Named backend constructors, selected through [`GeneratorConfig::backend`].
+pub struct BackendRegistry {
+    factories: BTreeMap<String, BackendFactory>,
+}
+
+impl BackendRegistry {
+    /// A registry without any backend.
+    pub fn new() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// A registry with the backends shipped by this crate.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register("rust", || Box::<RustBackend>::default());
+        registry.register("bench", || Box::<BenchBackend>::default());
+        registry.register("skeleton", || Box::<SkeletonBackend>::default());
+        registry
+    }
+
+    /// Registers a backend under `name`, replacing any previous one.
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn() -> Box<dyn Backend> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(|k| k.as_str())
+    }
+
+    /// Creates the backend selected by the config.
+    pub fn create(&self, config: &GeneratorConfig) -> Result<Box<dyn Backend>> {
+        match self.factories.get(&config.backend) {
+            Some(factory) => Ok(factory()),
+            None => Err(ParseIdlError::UnknownBackend(config.backend.clone())),
+        }
+    }
+
+    /// Runs the backend selected by the config over a program.
+    pub fn generate(
+        &self,
+        program: &Program,
+        module_name: &str,
+        config: &GeneratorConfig,
+    ) -> Result<Vec<GeneratedFile>> {
+        let mut backend = self.create(config)?;
+        run_backend(backend.as_mut(), program, module_name, config)
+    }
+}
+
+impl Default for BackendRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// Walks a program through a backend.
+pub fn run_backend(
+    backend: &mut dyn Backend,
+    program: &Program,
+    module_name: &str,
+    config: &GeneratorConfig,
+) -> Result<Vec<GeneratedFile>> {
+    let pruned = prune(program, config);
+    let program = pruned.as_ref().unwrap_or(program);
+    backend.visit_program(&ProgramContext {
+        program,
+        module_name,
+        config,
+    })?;
+    for ix in program.instructions.iter() {
+        backend.visit_instruction(ix)?;
+    }
+    for account in program.accounts.iter() {
+        backend.visit_account(account)?;
+    }
+    for ty in program.types.iter() {
+        backend.visit_type(ty)?;
+    }
+    for event in program.events.iter() {
+        backend.visit_event(event)?;
+    }
+    backend.finish()
+}
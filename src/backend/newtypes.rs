@@ -0,0 +1,114 @@
+//! `Pubkey` newtypes of the fields and accounts in a role, see
+//! [`GeneratorConfig::pubkey_newtypes`].
+
+use std::io::Write;
+
+use crate::config::{Feature, GeneratorConfig};
+use crate::ir::{EnumFields, Event, Instruction, Type, TypeDef, TypeDefKind};
+
+/// The newtypes the `Pubkey` fields of the program, and its accounts when
+/// `accounts` is set, are typed as, in order.
+fn used_newtypes<'c>(
+    instructions: &[Instruction],
+    definitions: &[TypeDef],
+    events: &[Event],
+    accounts: bool,
+    config: &'c GeneratorConfig,
+) -> Vec<&'c str> {
+    let mut fields: Vec<(&str, &Type)> = vec![];
+    for ix in instructions {
+        fields.extend(ix.args.iter().map(|f| (f.name.as_str(), &f.ty)));
+    }
+    for def in definitions {
+        match &def.kind {
+            TypeDefKind::Struct { fields: defined } => {
+                fields.extend(defined.iter().map(|f| (f.name.as_str(), &f.ty)));
+            }
+            TypeDefKind::Enum { variants } => {
+                for variant in variants {
+                    if let Some(EnumFields::Named(named)) = &variant.fields {
+                        fields.extend(named.iter().map(|f| (f.name.as_str(), &f.ty)));
+                    }
+                }
+            }
+        }
+    }
+    for event in events {
+        fields.extend(event.fields.iter().map(|f| (f.name.as_str(), &f.ty)));
+    }
+
+    let mut names: Vec<&str> = vec![];
+    for (name, mut ty) in fields {
+        while let Type::Option(inner) | Type::Vec(inner) | Type::Array(inner, _) = ty {
+            ty = inner;
+        }
+        if *ty == Type::PublicKey {
+            names.push(name);
+        }
+    }
+    if accounts {
+        for ix in instructions {
+            names.extend(ix.flat_accounts().iter().map(|a| a.name.as_str()));
+        }
+    }
+
+    let mut used: Vec<&str> = vec![];
+    for newtype in names
+        .iter()
+        .filter_map(|name| config.pubkey_newtypes.get(*name))
+    {
+        if !used.contains(&newtype.as_str()) {
+            used.push(newtype);
+        }
+    }
+    used
+}
+
+/// Writes a `Pubkey` newtype per role the program uses, encoded by Borsh
+/// as the key, dereferencing to it and converting from and to it.
+pub(super) fn define_newtypes(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    definitions: &[TypeDef],
+    events: &[Event],
+    accounts: bool,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut derives: Vec<&str> = vec![
+        "borsh::BorshSerialize",
+        "borsh::BorshDeserialize",
+        "Debug",
+        "Clone",
+        "Copy",
+        "PartialEq",
+        "Eq",
+        "Hash",
+        "PartialOrd",
+        "Ord",
+        "Default",
+    ];
+    for derive in config.derives.iter() {
+        if !derive.contains("Borsh") && !derives.contains(&derive.as_str()) {
+            derives.push(derive);
+        }
+    }
+    let vis = config.type_visibility.as_prefix();
+    for name in used_newtypes(instructions, definitions, events, accounts, config) {
+        output.write_fmt(format_args!(
+            "/// A `Pubkey` in the role of a `{}`.\n#[derive({})]\n",
+            name,
+            derives.join(", ")
+        ))?;
+        if config.feature_gates {
+            output.write_fmt(format_args!(
+                "#[cfg_attr({}, derive(serde::Serialize, serde::Deserialize))]\n",
+                Feature::Serde.predicate()
+            ))?;
+        }
+        output.write_fmt(format_args!(
+            "{0}struct {1}(pub Pubkey);\nimpl std::ops::Deref for {1} {{\n\ttype Target = Pubkey;\n\tfn deref(&self) -> &Pubkey {{\n\t\t&self.0\n\t}}\n}}\nimpl From<Pubkey> for {1} {{\n\tfn from(key: Pubkey) -> Self {{\n\t\tSelf(key)\n\t}}\n}}\nimpl From<{1}> for Pubkey {{\n\tfn from(key: {1}) -> Self {{\n\t\tkey.0\n\t}}\n}}\nimpl std::fmt::Display for {1} {{\n\tfn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{\n\t\tself.0.fmt(f)\n\t}}\n}}\nimpl std::str::FromStr for {1} {{\n\ttype Err = <Pubkey as std::str::FromStr>::Err;\n\tfn from_str(s: &str) -> std::result::Result<Self, Self::Err> {{\n\t\ts.parse().map(Self)\n\t}}\n}}\n",
+            vis, name
+        ))?;
+    }
+    Ok(())
+}
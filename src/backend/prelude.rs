@@ -0,0 +1,105 @@
+//! The `prelude` module of a program, see [`GeneratorConfig::prelude`].
+
+use std::io::Write;
+
+use super::rust::{decodes_events, defines_bindings_error, defines_program_id};
+use crate::config::{Artifact, GeneratorConfig, Layout};
+use crate::ir::{Event, Instruction, TypeDef};
+use crate::template::TemplateKind;
+
+/// Writes the `prelude` module re-exporting the args structs of the
+/// `instructions`, the accounts among `emitted`, the `events` and
+/// `ProgramEvent`, the `ErrorCode` of the program when it `has_errors`, the
+/// `BindingsError` and the `PROGRAM_ID` of the program at `address`, those
+/// of them which are generated.
+pub(super) fn define_prelude(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    emitted: &[&TypeDef],
+    events: &[Event],
+    has_errors: bool,
+    address: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut instruction_items = vec![];
+    if config.emits(Artifact::InstructionArgs)
+        && !config.templates.contains_key(&TemplateKind::Instruction)
+    {
+        instruction_items.extend(
+            instructions
+                .iter()
+                .filter(|ix| !ix.args.is_empty())
+                .map(|ix| config.naming.instructions.apply(&ix.name)),
+        );
+    }
+    // the support module's comes in through the glob of the module root
+    let mut root_items = vec![];
+    if defines_bindings_error(config) && config.shared_support {
+        root_items.push("BindingsError".to_string());
+    } else if defines_bindings_error(config) {
+        instruction_items.push("BindingsError".to_string());
+    }
+    // left out with an address that isn't a pubkey
+    let pubkey = matches!(bs58::decode(address).into_vec(), Ok(id) if id.len() == 32);
+    if defines_program_id(config) && pubkey {
+        instruction_items.push("PROGRAM_ID".to_string());
+    }
+    let mut event_items = vec![];
+    if config.emits(Artifact::Events) {
+        event_items.extend(events.iter().map(|e| config.naming.types.apply(&e.name)));
+        if decodes_events(config) && !events.is_empty() {
+            event_items.push("ProgramEvent".to_string());
+        }
+    }
+    let mut error_items = vec![];
+    if config.emits(Artifact::Errors) && has_errors {
+        error_items.push("ErrorCode".to_string());
+    }
+    let type_items: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some())
+        .map(|def| config.naming.types.apply(&def.name))
+        .collect();
+    let sections = [
+        ("super::instructions", instruction_items),
+        ("super::events", event_items),
+        ("super::errors", error_items),
+        ("super::types", type_items),
+        ("super", root_items),
+    ];
+
+    let vis = config.type_visibility.as_prefix();
+    output.write_fmt(format_args!(
+        "\n/// The instructions, accounts, events and errors of the program, for a\n/// glob import.\n{}mod prelude {{\n",
+        vis
+    ))?;
+    match config.layout {
+        Layout::Flat => {
+            let items: Vec<_> = sections.into_iter().flat_map(|(_, items)| items).collect();
+            if !items.is_empty() {
+                output.write_fmt(format_args!("\t{}use super::{};\n", vis, use_tree(&items)))?;
+            }
+        }
+        // by the path of their section, the globs of the module root are
+        // ambiguous for the names anchor_lang's prelude also has
+        Layout::Nested => {
+            for (section, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+                output.write_fmt(format_args!(
+                    "\t{}use {}::{};\n",
+                    vis,
+                    section,
+                    use_tree(items)
+                ))?;
+            }
+        }
+    }
+    output.write_all(b"}\n")
+}
+
+/// The names of a `use` path, in braces if there are several.
+fn use_tree(names: &[String]) -> String {
+    match names {
+        [name] => name.clone(),
+        names => format!("{{{}}}", names.join(", ")),
+    }
+}
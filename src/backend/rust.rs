@@ -0,0 +1,1413 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anchor_idl::IdlType;
+use heck::ToSnakeCase;
+use log::warn;
+
+use super::accessors::define_accessors;
+use super::anchor::define_anchor_traits;
+use super::big_arrays::{big_array_attribute, define_big_array, uses_big_arrays};
+use super::bindings_error::define_bindings_error;
+use super::borrowed::{define_borrowed_helpers, Borrowed};
+use super::client::{
+    define_builders, define_cli, define_client, define_program_id, define_send_helpers,
+};
+use super::diffs::define_diffs;
+use super::events::{define_event_decoder, define_event_stream};
+use super::fetchers::{define_account_decoders, define_fetchers, define_filters, define_scans};
+use super::flags::{define_flags, flag_type};
+use super::grpc::{define_grpc, define_proto_conversions};
+use super::indexer::{define_account_inserts, define_event_inserts, define_indexer};
+use super::localnet::define_localnet;
+use super::metrics::define_metrics;
+use super::newtypes::define_newtypes;
+use super::prelude::define_prelude;
+use super::serde_human::{define_serde_human, enum_attribute, field_attribute, human_readable};
+use super::service::define_service;
+use super::sink::{define_sink, serializes};
+use super::streaming::define_streaming;
+use super::watchers::define_watchers;
+use super::{Backend, GeneratedFile, ProgramContext};
+use crate::config::{Artifact, Case, Feature, GeneratorConfig, Layout};
+use crate::error::Result;
+use crate::frontend::legacy::lower_type;
+use crate::ir::{
+    EnumFields, EnumVariant, ErrorCode, Event, Field, Instruction, Program, Type, TypeDef,
+    TypeDefKind,
+};
+use crate::template::{
+    render, AccountContext, EnumContext, FieldContext, InstructionContext, StructContext,
+    TemplateKind, VariantContext,
+};
+
+/// The default backend, emitting one rust module per program.
+#[derive(Debug, Default)]
+pub struct RustBackend {
+    config: GeneratorConfig,
+    module_name: String,
+    program_name: String,
+    address: String,
+    output: Vec<u8>,
+    instructions: Vec<Instruction>,
+    // accounts first, then custom types, in IDL order
+    definitions: Vec<TypeDef>,
+    events: Vec<Event>,
+    errors: Vec<ErrorCode>,
+}
+
+impl Backend for RustBackend {
+    fn visit_program(&mut self, program: &ProgramContext<'_>) -> Result<()> {
+        self.config = program.config.clone();
+        self.module_name = program.module_name.to_string();
+        self.program_name = program.program.name.clone();
+        self.address = program.program.address.clone();
+        self.errors = program.program.errors.clone();
+
+        add_imports(&mut self.output, &self.config)?;
+        if self.config.emits(Artifact::ProgramId) {
+            add_program_id(&mut self.output, &program.program.address)?;
+        }
+        Ok(())
+    }
+
+    fn visit_instruction(&mut self, ix: &Instruction) -> Result<()> {
+        self.instructions.push(ix.clone());
+        Ok(())
+    }
+
+    fn visit_account(&mut self, account: &TypeDef) -> Result<()> {
+        self.definitions.push(account.clone());
+        Ok(())
+    }
+
+    fn visit_type(&mut self, ty: &TypeDef) -> Result<()> {
+        self.definitions.push(ty.clone());
+        Ok(())
+    }
+
+    fn visit_event(&mut self, event: &Event) -> Result<()> {
+        self.events.push(event.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<Vec<GeneratedFile>> {
+        let config = &self.config;
+        let mut unresolved = HashSet::new();
+        let mut instructions = vec![];
+        let mut events = vec![];
+        let mut errors = vec![];
+        let mut types = vec![];
+
+        if config.metrics {
+            define_metrics(&mut instructions)?;
+        }
+
+        if config.emits(Artifact::Discriminators) {
+            define_discriminator(&mut instructions, self.instructions.len())?;
+
+            // handle ix method and args
+            for ix in self.instructions.iter() {
+                add_discriminator(
+                    &mut instructions,
+                    ix.discriminator,
+                    &ix.name.to_snake_case(),
+                )?;
+            }
+            close_define_discriminator(&mut instructions, config)?;
+        }
+
+        // output ix args definition
+        if config.emits(Artifact::InstructionArgs) {
+            for ix in self.instructions.iter() {
+                if let Some(template) = config.templates.get(&TemplateKind::Instruction) {
+                    let context = InstructionContext {
+                        name: config.naming.instructions.apply(&ix.name),
+                        idl_name: &ix.name,
+                        discriminator: ix.discriminator,
+                        derives: &config.derives,
+                        visibility: config.type_visibility.as_prefix().trim_end(),
+                        args: field_contexts(&ix.args, config, &mut unresolved),
+                        accounts: ix
+                            .flat_accounts()
+                            .into_iter()
+                            .map(|a| AccountContext {
+                                name: &a.name,
+                                is_mut: a.is_mut,
+                                is_signer: a.is_signer,
+                                is_optional: a.is_optional,
+                            })
+                            .collect(),
+                    };
+                    instructions.write_all(render(template, context)?.as_bytes())?;
+                } else if !ix.args.is_empty() {
+                    define_struct_or_enum(
+                        &mut instructions,
+                        &config.naming.instructions.apply(&ix.name),
+                        "struct",
+                        config,
+                    )?;
+
+                    for arg in ix.args.iter() {
+                        add_struct_field(
+                            &mut instructions,
+                            &config.naming.fields.apply(&arg.name),
+                            &arg.ty,
+                            &arg.name,
+                            config,
+                            &mut unresolved,
+                        )?;
+                    }
+                    if config.trailing_bytes {
+                        add_extra_field(&mut instructions, config)?;
+                    }
+                    close_define_struct_or_enum(&mut instructions)?;
+                    define_accessors(
+                        &mut instructions,
+                        &config.naming.instructions.apply(&ix.name),
+                        ix.args.iter().map(|a| (a.name.as_str(), &a.ty)),
+                        config,
+                    )?;
+                    if config.trailing_bytes {
+                        add_extra_decode(&mut instructions, ix, &self.program_name, config)?;
+                    }
+                    if defines_constructors(config) {
+                        add_constructor(
+                            &mut instructions,
+                            &config.naming.instructions.apply(&ix.name),
+                            &format!("The args of `{}`", ix.name),
+                            &ix.args,
+                            config.trailing_bytes,
+                            config,
+                            &mut unresolved,
+                        )?;
+                    }
+                }
+            }
+        }
+
+        if defines_bindings_error(config) && !config.shared_support {
+            define_bindings_error(&mut instructions, defines_builders(config), config)?;
+        }
+        // next to the args structs, whose fields they may not see otherwise
+        if defines_program_id(config)
+            && define_program_id(&mut instructions, &self.address, config)?
+            && defines_builders(config)
+        {
+            define_builders(
+                &mut instructions,
+                &self.instructions,
+                &self.program_name,
+                config,
+            )?;
+        }
+        if (defines_cli(config) || defines_client(config)) && !config.shared_support {
+            define_send_helpers(&mut instructions, config)?;
+        }
+        if defines_cli(config) {
+            define_cli(&mut instructions, &self.instructions, config)?;
+        }
+        if defines_client(config) {
+            define_client(
+                &mut instructions,
+                &self.instructions,
+                &self.module_name,
+                &self.program_name,
+                config.emits(Artifact::Errors) && !self.errors.is_empty(),
+                decodes_events(config) && config.emits(Artifact::Events) && !self.events.is_empty(),
+                config,
+            )?;
+        }
+
+        if config.emits(Artifact::Events) {
+            for event in self.events.iter() {
+                define_struct_or_enum(
+                    &mut events,
+                    &config.naming.types.apply(&event.name),
+                    "struct",
+                    config,
+                )?;
+                for field in event.fields.iter() {
+                    add_struct_field(
+                        &mut events,
+                        &config.naming.fields.apply(&field.name),
+                        &field.ty,
+                        &field.name,
+                        config,
+                        &mut unresolved,
+                    )?;
+                }
+                close_define_struct_or_enum(&mut events)?;
+                define_accessors(
+                    &mut events,
+                    &config.naming.types.apply(&event.name),
+                    event.fields.iter().map(|f| (f.name.as_str(), &f.ty)),
+                    config,
+                )?;
+                if let Some(path) = &config.program_crate {
+                    add_program_crate_conversions(
+                        &mut events,
+                        &config.naming.types.apply(&event.name),
+                        path,
+                    )?;
+                }
+            }
+            if decodes_events(config) {
+                define_event_decoder(
+                    &mut events,
+                    &self.events,
+                    &self.program_name,
+                    &self.address,
+                    config,
+                )?;
+            }
+            if config.event_stream {
+                define_event_stream(&mut events, &self.events, config)?;
+            }
+        }
+
+        if config.emits(Artifact::Errors) && !self.errors.is_empty() {
+            add_error_codes(&mut errors, &self.errors, config)?;
+        }
+
+        if config.emits(Artifact::Types) && !config.pubkey_newtypes.is_empty() {
+            define_newtypes(
+                &mut types,
+                &self.instructions,
+                &self.definitions,
+                &self.events,
+                defines_builders(config),
+                config,
+            )?;
+        }
+        if config.emits(Artifact::Types) && !config.flags.is_empty() {
+            define_flags(
+                &mut types,
+                &self.instructions,
+                &self.definitions,
+                &self.events,
+                config,
+            )?;
+        }
+
+        let mut emitted = vec![];
+        if config.emits(Artifact::Types) {
+            // without the args structs nothing tells which definitions are
+            // needed, so all of them are emitted
+            let all = !config.emits(Artifact::InstructionArgs);
+            for custom_type in self.definitions.iter() {
+                let overridden = config.type_overrides.contains_key(&custom_type.name);
+                let root = all || (fetches_accounts(config) && custom_type.discriminator.is_some());
+                if (root && !overridden) || unresolved.contains(&custom_type.name) {
+                    define_type(&mut types, custom_type, config, &mut unresolved)?;
+                    if let Some(path) = &config.program_crate {
+                        add_program_crate_conversions(
+                            &mut types,
+                            &config.naming.types.apply(&custom_type.name),
+                            path,
+                        )?;
+                    }
+                    unresolved.remove(&custom_type.name);
+                    emitted.push(custom_type);
+                }
+            }
+        }
+
+        if config.emits(Artifact::Types) {
+            define_discriminant_test(&mut types, &emitted, config)?;
+        }
+        let big_arrays = serializes(config)
+            && uses_big_arrays(
+                if config.emits(Artifact::InstructionArgs) {
+                    &self.instructions
+                } else {
+                    &[]
+                },
+                if config.emits(Artifact::Events) {
+                    &self.events
+                } else {
+                    &[]
+                },
+                &emitted,
+                config,
+            )?;
+        if config.emits(Artifact::Types) && fetches_accounts(config) {
+            define_account_decoders(&mut types, &emitted, &self.definitions, config)?;
+        }
+        if config.emits(Artifact::Types) && config.anchor_traits {
+            define_anchor_traits(&mut types, &emitted, &self.address, config)?;
+        }
+        if config.emits(Artifact::Types) && config.account_diffs {
+            define_diffs(&mut types, &emitted, config)?;
+        }
+
+        if defines_client(config) {
+            define_fetchers(&mut types, &emitted, &self.definitions, config)?;
+            define_filters(&mut types, &emitted, &self.definitions, config)?;
+        }
+        if config.account_scans {
+            define_scans(&mut types, &emitted, &self.definitions, config)?;
+        }
+        if config.account_watchers {
+            define_watchers(&mut types, &emitted, config)?;
+        }
+
+        // each insert next to the struct whose fields it reads
+        if config.indexer {
+            define_indexer(
+                &mut instructions,
+                &self.instructions,
+                &self.events,
+                &emitted,
+                &self.module_name,
+                config,
+            )?;
+            define_event_inserts(&mut events, &self.events, &self.module_name, config)?;
+            define_account_inserts(&mut types, &emitted, &self.module_name, config)?;
+        }
+
+        let mut borrowed = vec![];
+        if config.borrowed {
+            define_borrowed(
+                &mut borrowed,
+                &self.instructions,
+                &self.events,
+                &emitted,
+                config,
+            )?;
+        }
+
+        let mut streaming = vec![];
+        if config.streaming {
+            define_streaming(&mut streaming, &emitted, config)?;
+        }
+
+        let mut localnet = vec![];
+        if defines_localnet(config) {
+            define_localnet(&mut localnet, &self.module_name, config)?;
+        }
+
+        let mut sink = vec![];
+        if config.kafka_sink {
+            define_sink(&mut sink, &self.instructions, &self.events, config)?;
+        }
+
+        let mut grpc = vec![];
+        if config.grpc_service {
+            define_grpc(
+                &mut grpc,
+                &self.instructions,
+                &self.events,
+                &emitted,
+                &self.module_name,
+                config,
+            )?;
+            define_proto_conversions(
+                &mut instructions,
+                &mut types,
+                &mut events,
+                &self.instructions,
+                &self.events,
+                &emitted,
+                config,
+            )?;
+        }
+
+        let mut service = vec![];
+        if config.http_service {
+            define_service(
+                &mut service,
+                &self.instructions,
+                &emitted,
+                &self.events,
+                config,
+            )?;
+        }
+
+        for unresolved in unresolved.iter() {
+            warn!("resolved type: {}", unresolved);
+        }
+
+        let sections = [
+            ("instructions", instructions),
+            ("events", events),
+            ("errors", errors),
+            ("types", types),
+            ("borrowed", borrowed),
+            ("streaming", streaming),
+            ("localnet", localnet),
+            ("sink", sink),
+            ("service", service),
+            ("grpc", grpc),
+        ];
+        let mut output = std::mem::take(&mut self.output);
+        if human_readable(config) && !config.shared_support {
+            define_serde_human(&mut output, config)?;
+        }
+        if big_arrays {
+            define_big_array(&mut output, config)?;
+        }
+        if config.prelude {
+            define_prelude(
+                &mut output,
+                &self.instructions,
+                &emitted,
+                &self.events,
+                !self.errors.is_empty(),
+                &self.address,
+                config,
+            )?;
+        }
+        match config.layout {
+            Layout::Flat => {
+                for (_, section) in sections {
+                    output.extend(section);
+                }
+                Ok(vec![GeneratedFile {
+                    path: PathBuf::from(format!("{}.rs", self.module_name)),
+                    contents: String::from_utf8(output)?,
+                }])
+            }
+            Layout::Nested => {
+                let dir = PathBuf::from(&self.module_name);
+                let mut files = vec![];
+                for (name, section) in sections {
+                    if section.is_empty() {
+                        continue;
+                    }
+                    output.write_fmt(format_args!("pub mod {0};\npub use {0}::*;\n", name))?;
+
+                    // the imports of mod.rs come in through the glob
+                    let mut contents = b"use super::*;\n\n".to_vec();
+                    contents.extend(section);
+                    files.push(GeneratedFile {
+                        path: dir.join(format!("{}.rs", name)),
+                        contents: String::from_utf8(contents)?,
+                    });
+                }
+                files.insert(
+                    0,
+                    GeneratedFile {
+                        path: dir.join("mod.rs"),
+                        contents: String::from_utf8(output)?,
+                    },
+                );
+                Ok(files)
+            }
+        }
+    }
+}
+
+/// Whether the `PROGRAM_ID` constant is generated, for the builders or
+/// the RPC code looking up the program's transactions and accounts.
+pub(super) fn defines_program_id(config: &GeneratorConfig) -> bool {
+    let sends = config.builders || config.cli || config.client || config.localnet;
+    (sends && config.emits(Artifact::InstructionArgs))
+        || config.account_scans
+        || config.indexer
+        || config.http_service
+}
+
+/// Whether the accounts structs and builders of the instructions are
+/// generated, for themselves or the code sending instructions. They take
+/// the args structs, so only when those are emitted.
+pub(super) fn defines_builders(config: &GeneratorConfig) -> bool {
+    defines_program_id(config) && config.emits(Artifact::InstructionArgs)
+}
+
+/// Whether the instruction CLI is generated, which sends the instructions
+/// of the builders.
+pub(crate) fn defines_cli(config: &GeneratorConfig) -> bool {
+    config.cli && config.emits(Artifact::InstructionArgs)
+}
+
+/// Whether the client is generated, for itself or the localnet, which
+/// sends the instructions of the builders.
+pub(crate) fn defines_client(config: &GeneratorConfig) -> bool {
+    (config.client || config.localnet) && config.emits(Artifact::InstructionArgs)
+}
+
+/// Whether the localnet of the integration tests is generated, which uses
+/// the client.
+pub(crate) fn defines_localnet(config: &GeneratorConfig) -> bool {
+    config.localnet && defines_client(config)
+}
+
+/// Whether the args structs get a constructor, for themselves or the
+/// callers of the builders, who can't build them otherwise.
+pub(super) fn defines_constructors(config: &GeneratorConfig) -> bool {
+    config.constructors || defines_builders(config)
+}
+
+/// Whether the generated code has decoders, builders or RPC calls failing
+/// with a `BindingsError`, which needs thiserror.
+pub(crate) fn defines_bindings_error(config: &GeneratorConfig) -> bool {
+    defines_builders(config) || fetches_accounts(config) || decodes_events(config)
+}
+
+/// Whether the events get a decoder, for themselves or the code decoding
+/// transactions.
+pub(super) fn decodes_events(config: &GeneratorConfig) -> bool {
+    config.event_decoder
+        || config.event_stream
+        || config.indexer
+        || config.kafka_sink
+        || config.http_service
+        || config.grpc_service
+}
+
+/// Whether the generated code fetches, stores or implements traits on the
+/// accounts, which are emitted then even if no instruction refers to them.
+pub(super) fn fetches_accounts(config: &GeneratorConfig) -> bool {
+    config.indexer
+        || defines_client(config)
+        || config.account_scans
+        || config.account_watchers
+        || config.anchor_traits
+        || config.account_diffs
+        || config.http_service
+        || config.grpc_service
+}
+
+fn define_borrowed(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    events: &[Event],
+    definitions: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let borrowed = Borrowed::new(config, definitions);
+    let mut structs = vec![];
+    if config.emits(Artifact::InstructionArgs)
+        && !config.templates.contains_key(&TemplateKind::Instruction)
+    {
+        for ix in instructions.iter() {
+            structs.push((config.naming.instructions.apply(&ix.name), ix.args.clone()));
+        }
+    }
+    if config.emits(Artifact::Events) {
+        for event in events.iter() {
+            let fields = event
+                .fields
+                .iter()
+                .map(|f| Field {
+                    name: f.name.clone(),
+                    ty: f.ty.clone(),
+                })
+                .collect();
+            structs.push((config.naming.types.apply(&event.name), fields));
+        }
+    }
+    for def in definitions.iter() {
+        if let TypeDefKind::Struct { fields } = &def.kind {
+            structs.push((config.naming.types.apply(&def.name), fields.clone()));
+        }
+    }
+
+    let structs: Vec<_> = structs
+        .into_iter()
+        .filter(|(_, fields)| borrowed.any_borrows(fields))
+        .collect();
+    if structs.is_empty() {
+        return Ok(());
+    }
+    if !config.shared_support {
+        define_borrowed_helpers(output)?;
+    }
+    for (name, fields) in structs.iter() {
+        borrowed.write_struct(output, name, fields)?;
+    }
+    Ok(())
+}
+
+fn define_type(
+    output: &mut impl Write,
+    custom_type: &TypeDef,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> Result<()> {
+    let name = config.naming.types.apply(&custom_type.name);
+    match &custom_type.kind {
+        TypeDefKind::Struct { fields } => {
+            if let Some(template) = config.templates.get(&TemplateKind::Struct) {
+                let context = StructContext {
+                    name,
+                    idl_name: &custom_type.name,
+                    derives: &config.derives,
+                    visibility: config.type_visibility.as_prefix().trim_end(),
+                    fields: field_contexts(fields, config, unresolved),
+                };
+                output.write_all(render(template, context)?.as_bytes())?;
+                return Ok(());
+            }
+        }
+        TypeDefKind::Enum { variants } => {
+            if let Some(template) = config.templates.get(&TemplateKind::Enum) {
+                let context = EnumContext {
+                    name,
+                    idl_name: &custom_type.name,
+                    derives: &config.derives,
+                    visibility: config.type_visibility.as_prefix().trim_end(),
+                    variants: variants
+                        .iter()
+                        .enumerate()
+                        .map(|(discriminant, v)| VariantContext {
+                            name: &v.name,
+                            discriminant,
+                        })
+                        .collect(),
+                };
+                output.write_all(render(template, context)?.as_bytes())?;
+                return Ok(());
+            }
+        }
+    }
+
+    match &custom_type.kind {
+        TypeDefKind::Struct { fields } => {
+            define_struct_or_enum(output, &name, "struct", config)?;
+            for field in fields.iter() {
+                add_struct_field(
+                    output,
+                    &config.naming.fields.apply(&field.name),
+                    &field.ty,
+                    &field.name,
+                    config,
+                    unresolved,
+                )?;
+            }
+            close_define_struct_or_enum(output)?;
+            define_accessors(
+                output,
+                &name,
+                fields.iter().map(|f| (f.name.as_str(), &f.ty)),
+                config,
+            )?;
+            // the args of the builders are built of them
+            if defines_constructors(config) {
+                add_constructor(
+                    output,
+                    &name,
+                    &format!("A `{}`", custom_type.name),
+                    fields,
+                    false,
+                    config,
+                    unresolved,
+                )?;
+            }
+        }
+        TypeDefKind::Enum { variants } => {
+            define_struct_or_enum(output, &name, "enum", config)?;
+            let unit = variants.iter().all(|v| v.fields.is_none());
+            for (discriminant, variant) in variants.iter().enumerate() {
+                add_enum_field(
+                    output,
+                    variant,
+                    unit.then_some(discriminant),
+                    config,
+                    unresolved,
+                )?;
+            }
+            close_define_struct_or_enum(output)?;
+        }
+    }
+    Ok(())
+}
+
+fn field_contexts(
+    fields: &[Field],
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> Vec<FieldContext> {
+    fields
+        .iter()
+        .map(|f| FieldContext {
+            name: config.naming.fields.apply(&f.name),
+            idl_name: f.name.clone(),
+            ty: rust_type(&f.ty, Some(&f.name), config, unresolved),
+            visibility: config.field_visibility.as_prefix().trim_end(),
+        })
+        .collect()
+}
+
+/// The anchor prelude of the default imports, which brings `Pubkey` in.
+const ANCHOR_PRELUDE: &str = "anchor_lang::prelude::*";
+
+/// Whether the generated code needs anchor_lang: the args structs are for
+/// instructions built with it, and the anchor traits are its own. The types
+/// and decoders alone don't.
+pub(crate) fn uses_anchor(config: &GeneratorConfig) -> bool {
+    config.emits(Artifact::InstructionArgs) || config.anchor_traits
+}
+
+/// The [`GeneratorConfig::imports`], with `Pubkey` taken from
+/// solana-program instead of the anchor prelude unless [`uses_anchor`].
+pub(super) fn imports(config: &GeneratorConfig) -> impl Iterator<Item = &str> {
+    config.imports.iter().map(|import| match import.as_str() {
+        ANCHOR_PRELUDE if !uses_anchor(config) => "solana_program::pubkey::Pubkey",
+        import => import,
+    })
+}
+
+fn add_imports(output: &mut impl Write, config: &GeneratorConfig) -> std::io::Result<()> {
+    for import in imports(config) {
+        output.write_fmt(format_args!("use {};\n", import))?;
+    }
+    for reexport in config.reexports.iter() {
+        output.write_fmt(format_args!("pub use {};\n", reexport))?;
+    }
+    output.write_all(b"\n")?;
+    Ok(())
+}
+
+fn add_program_id(output: &mut impl Write, id: &str) -> std::io::Result<()> {
+    output.write_fmt(format_args!("static ID: &str = \"{}\";\n", id))?;
+    Ok(())
+}
+
+fn define_discriminator(output: &mut impl Write, count: usize) -> std::io::Result<()> {
+    output.write_fmt(format_args!(
+        "\npub static INSTRUCTION_DISCRIMINATORS: [([u8; 8], &str); {}] = [\n",
+        count
+    ))?;
+    Ok(())
+}
+fn add_discriminator(
+    output: &mut impl Write,
+    bytes: [u8; 8],
+    ix_name: &str,
+) -> std::io::Result<()> {
+    output.write_fmt(format_args!("\t({:?}, \"{}\"),\n", bytes, ix_name))?;
+    Ok(())
+}
+fn close_define_discriminator(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if config.metrics {
+        output.write_all(
+            br#"];
+
+/// Name of the instruction whose discriminator starts `data`, reported to
+/// the [`DecodeMetrics`].
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let started = std::time::Instant::now();
+    let discriminator = data.get(..8)?;
+    let name = INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name);
+    if let Some(metrics) = decode_metrics() {
+        match name {
+            Some(name) => metrics.decoded("instruction", name, started.elapsed()),
+            None => metrics.unknown_discriminator("instruction", discriminator),
+        }
+    }
+    name
+}
+"#,
+        )?;
+    } else {
+        output.write_all(
+            br#"];
+
+/// Name of the instruction whose discriminator starts `data`.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    let discriminator = data.get(..8)?;
+    INSTRUCTION_DISCRIMINATORS
+        .iter()
+        .find(|(d, _)| d[..] == *discriminator)
+        .map(|(_, name)| *name)
+}
+"#,
+        )?;
+    }
+    output.write_all(
+        br#"
+pub struct Discriminator(pub HashMap<[u8; 8], String>);
+impl Discriminator {
+    pub fn new() -> Self {
+        Self(
+            INSTRUCTION_DISCRIMINATORS
+                .iter()
+                .map(|(d, name)| (*d, name.to_string()))
+                .collect(),
+        )
+    }
+
+    /// The table shared by the whole process, built on first use.
+    pub fn get() -> &'static Self {
+        static TABLE: std::sync::OnceLock<Discriminator> = std::sync::OnceLock::new();
+        TABLE.get_or_init(Self::new)
+    }
+}
+"#,
+    )?;
+    Ok(())
+}
+
+fn define_struct_or_enum(
+    output: &mut impl Write,
+    name: &str,
+    type_str: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if !config.derives.is_empty() {
+        output.write_fmt(format_args!("#[derive({})]\n", config.derives.join(", ")))?;
+    }
+    if config.feature_gates {
+        output.write_fmt(format_args!(
+            "#[cfg_attr({}, derive(serde::Serialize, serde::Deserialize))]\n",
+            Feature::Serde.predicate()
+        ))?;
+    }
+    if type_str == "enum" && human_readable(config) {
+        output.write_all(enum_attribute(config).as_bytes())?;
+    }
+    output.write_fmt(format_args!(
+        "{}{} {} {{\n",
+        config.type_visibility.as_prefix(),
+        type_str,
+        name
+    ))?;
+    Ok(())
+}
+
+fn add_struct_field(
+    output: &mut impl Write,
+    field_name: &str,
+    field_type: &Type,
+    idl_name: &str,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> std::io::Result<()> {
+    if let Some(attribute) = serde_field_attribute(field_type, idl_name, config) {
+        output.write_all(attribute.as_bytes())?;
+    }
+    output.write_fmt(format_args!(
+        "\t{}{}: ",
+        config.field_visibility.as_prefix(),
+        field_name,
+    ))?;
+    // the type goes straight into the output, without a String per field
+    let mut writer = IoWriter {
+        inner: &mut *output,
+        error: None,
+    };
+    let written = write_rust_type(&mut writer, field_type, Some(idl_name), config, unresolved);
+    if written.is_err() {
+        return Err(writer
+            .error
+            .unwrap_or_else(|| std::io::ErrorKind::Other.into()));
+    }
+    output.write_all(b",\n")?;
+    Ok(())
+}
+
+/// The serde attribute of a field: its human-readable representation, or
+/// the `big_array` one of a long array.
+fn serde_field_attribute(ty: &Type, field: &str, config: &GeneratorConfig) -> Option<String> {
+    if human_readable(config) {
+        if let Some(attribute) = field_attribute(ty, field, config) {
+            return Some(attribute);
+        }
+    }
+    if serializes(config) {
+        return big_array_attribute(ty, field, config);
+    }
+    None
+}
+
+/// `fmt::Write` over an `io::Write`, keeping the io error.
+struct IoWriter<'a, W> {
+    inner: &'a mut W,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> fmt::Write for IoWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+/// `extra` field of an args struct, holding the bytes after the known args.
+fn add_extra_field(output: &mut impl Write, config: &GeneratorConfig) -> std::io::Result<()> {
+    output.write_fmt(format_args!(
+        "\t/// Bytes after the known args, e.g. args appended by a newer program\n\t/// version. Not serialized.\n\t#[borsh_skip]\n\t{}extra: Vec<u8>,\n",
+        config.field_visibility.as_prefix()
+    ))
+}
+
+/// Writes a `new` function of the struct `name`, described by `doc` and
+/// the optional fields it leaves `None` if there are any, taking the
+/// non-optional `fields`, and a `with_<field>` setter per optional one.
+/// `extra` is the trailing bytes field of args structs.
+fn add_constructor(
+    output: &mut impl Write,
+    name: &str,
+    doc: &str,
+    fields: &[Field],
+    extra: bool,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> std::io::Result<()> {
+    let vis = config.type_visibility.as_prefix();
+    // (field, rust type, inner type if optional)
+    let args: Vec<_> = fields
+        .iter()
+        .map(|arg| {
+            let ty = rust_type(&arg.ty, Some(&arg.name), config, unresolved);
+            let inner = match &arg.ty {
+                Type::Option(inner) => Some(rust_type(inner, Some(&arg.name), config, unresolved))
+                    .filter(|inner| ty == format!("Option<{}>", inner)),
+                _ => None,
+            };
+            (config.naming.fields.apply(&arg.name), ty, inner)
+        })
+        .collect();
+    let params: Vec<_> = args
+        .iter()
+        .filter(|(_, _, inner)| inner.is_none())
+        .map(|(field, ty, _)| format!("{}: {}", field, ty))
+        .collect();
+    let mut fields: Vec<_> = args
+        .iter()
+        .map(|(field, _, inner)| match inner {
+            Some(_) => format!("\t\t\t{}: None,\n", field),
+            None => format!("\t\t\t{},\n", field),
+        })
+        .collect();
+    if extra {
+        fields.push("\t\t\textra: Vec::new(),\n".to_string());
+    }
+    let optional = if args.iter().any(|(_, _, inner)| inner.is_some()) {
+        ", the optional fields `None`"
+    } else {
+        ""
+    };
+    output.write_fmt(format_args!(
+        "impl {} {{\n\t/// {}{}.\n\t{}fn new({}) -> Self {{\n\t\tSelf {{\n{}\t\t}}\n\t}}\n",
+        name,
+        doc,
+        optional,
+        vis,
+        params.join(", "),
+        fields.concat()
+    ))?;
+    for (field, _, inner) in args.iter() {
+        if let Some(inner) = inner {
+            output.write_fmt(format_args!(
+                "\n\t/// Sets `{1}`.\n\t{0}fn with_{1}(mut self, {1}: {2}) -> Self {{\n\t\tself.{1} = Some({1});\n\t\tself\n\t}}\n",
+                vis, field, inner
+            ))?;
+        }
+    }
+    output.write_all(b"}\n")
+}
+
+fn add_extra_decode(
+    output: &mut impl Write,
+    ix: &Instruction,
+    program: &str,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let name = ix.name.to_snake_case();
+    let (deserialize, decoded) = if config.metrics {
+        (
+            format!(
+                "\t\tlet started = std::time::Instant::now();\n\t\tlet mut args = match <Self as borsh::BorshDeserialize>::deserialize(&mut data) {{\n\t\t\tOk(args) => args,\n\t\t\tErr(e) => {{\n\t\t\t\tif let Some(metrics) = decode_metrics() {{\n\t\t\t\t\tmetrics.decode_failed(\"instruction\", {0:?});\n\t\t\t\t}}\n\t\t\t\treturn Err(e);\n\t\t\t}}\n\t\t}};\n",
+                name
+            ),
+            format!(
+                "\t\tif let Some(metrics) = decode_metrics() {{\n\t\t\tmetrics.decoded(\"instruction\", {:?}, started.elapsed());\n\t\t}}\n",
+                name
+            ),
+        )
+    } else {
+        (
+            "\t\tlet mut args = <Self as borsh::BorshDeserialize>::deserialize(&mut data)?;\n"
+                .to_string(),
+            String::new(),
+        )
+    };
+    output.write_fmt(format_args!(
+        "impl {} {{\n\t/// Deserializes the args (without the discriminator), keeping trailing\n\t/// bytes in `extra` instead of failing on them.\n{}\t{}fn try_from_slice_with_extra(mut data: &[u8]) -> std::io::Result<Self> {{\n{}\t\targs.extra = data.to_vec();\n{}{}\t\tOk(args)\n\t}}\n}}\n",
+        config.naming.instructions.apply(&ix.name),
+        config.instrument(
+            "\t",
+            "trace",
+            &format!("program = {:?}, instruction = {:?}", program, ix.name)
+        ),
+        config.type_visibility.as_prefix(),
+        deserialize,
+        config.traced(
+            "\t\t",
+            "if !args.extra.is_empty() { tracing::debug!(extra = args.extra.len(), \"trailing args bytes\"); }"
+        ),
+        decoded
+    ))
+}
+
+/// `TryFrom` impls between `name` and the same named type of the program
+/// crate at `path`, through their borsh encoding.
+fn add_program_crate_conversions(
+    output: &mut impl Write,
+    name: &str,
+    path: &str,
+) -> std::io::Result<()> {
+    for (from, to) in [
+        (format!("{}::{}", path, name), name.to_string()),
+        (name.to_string(), format!("{}::{}", path, name)),
+    ] {
+        output.write_fmt(format_args!(
+            "impl TryFrom<&{0}> for {1} {{\n\ttype Error = std::io::Error;\n\n\tfn try_from(v: &{0}) -> std::io::Result<Self> {{\n\t\tlet mut data = Vec::new();\n\t\tborsh::BorshSerialize::serialize(v, &mut data)?;\n\t\t<Self as borsh::BorshDeserialize>::try_from_slice(&data)\n\t}}\n}}\n",
+            from, to
+        ))?;
+    }
+    Ok(())
+}
+
+/// Writes a variant with its fields. The variants of all-unit enums get
+/// their Borsh tag, the variant's index in the IDL, as explicit discriminant.
+fn add_enum_field(
+    output: &mut impl Write,
+    variant: &EnumVariant,
+    discriminant: Option<usize>,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> std::io::Result<()> {
+    match (&variant.fields, discriminant) {
+        (None, Some(discriminant)) => {
+            output.write_fmt(format_args!("\t{} = {},\n", variant.name, discriminant))?
+        }
+        (None, None) => output.write_fmt(format_args!("\t{},\n", variant.name))?,
+        (Some(EnumFields::Tuple(types)), _) => {
+            let types: Vec<_> = types
+                .iter()
+                .map(|ty| rust_type(ty, None, config, unresolved))
+                .collect();
+            output.write_fmt(format_args!("\t{}({}),\n", variant.name, types.join(", ")))?
+        }
+        (Some(EnumFields::Named(fields)), _) => {
+            output.write_fmt(format_args!("\t{} {{\n", variant.name))?;
+            for field in fields.iter() {
+                if let Some(attribute) = serde_field_attribute(&field.ty, &field.name, config) {
+                    output.write_fmt(format_args!("\t{}", attribute))?;
+                }
+                output.write_fmt(format_args!(
+                    "\t\t{}: {},\n",
+                    config.naming.fields.apply(&field.name),
+                    rust_type(&field.ty, Some(&field.name), config, unresolved)
+                ))?;
+            }
+            output.write_all(b"\t},\n")?
+        }
+    }
+    Ok(())
+}
+
+/// Writes a test asserting that the Borsh tags of the variants of the
+/// emitted enums are their discriminants, which are the IDL indices.
+fn define_discriminant_test(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let serializes = config.derives.iter().any(|d| d.ends_with("BorshSerialize"));
+    if !serializes || config.templates.contains_key(&TemplateKind::Enum) {
+        return Ok(());
+    }
+    let enums: Vec<_> = emitted
+        .iter()
+        .filter_map(|def| match &def.kind {
+            TypeDefKind::Enum { variants } => Some((def, variants)),
+            TypeDefKind::Struct { .. } => None,
+        })
+        .collect();
+    if enums.is_empty() {
+        return Ok(());
+    }
+    output.write_all(
+        b"\n#[cfg(test)]\nmod enum_discriminants {\n\tuse super::*;\n\n\t#[test]\n\tfn borsh_tags_are_discriminants() {\n",
+    )?;
+    for (def, variants) in enums {
+        let name = config.naming.types.apply(&def.name);
+        if variants.iter().all(|v| v.fields.is_none()) {
+            for (discriminant, variant) in variants.iter().enumerate() {
+                output.write_fmt(format_args!(
+                    "\t\tassert_eq!({0}::{1} as u8, {2});\n\t\tassert_eq!(borsh::BorshSerialize::try_to_vec(&{0}::{1}).unwrap(), [{2}]);\n",
+                    name, variant.name, discriminant
+                ))?;
+            }
+            continue;
+        }
+        // data variants round-trip, starting with their tag
+        for (discriminant, variant) in variants.iter().enumerate() {
+            let Some(value) = sample_variant(&name, variant, emitted, config) else {
+                continue;
+            };
+            output.write_fmt(format_args!(
+                "\t\tlet bytes = borsh::BorshSerialize::try_to_vec(&{1}).unwrap();\n\t\tassert_eq!(bytes[0], {2});\n\t\tlet decoded: {0} = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();\n\t\tassert_eq!(borsh::BorshSerialize::try_to_vec(&decoded).unwrap(), bytes);\n",
+                name, value, discriminant
+            ))?;
+        }
+    }
+    output.write_all(b"\t}\n}\n")
+}
+
+/// A value of `variant` built from default field values, `None` if one of
+/// its fields has none.
+fn sample_variant(
+    name: &str,
+    variant: &EnumVariant,
+    emitted: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> Option<String> {
+    let mut unresolved = HashSet::new();
+    match &variant.fields {
+        None => Some(format!("{}::{}", name, variant.name)),
+        Some(EnumFields::Tuple(types)) => {
+            let values: Option<Vec<_>> = types
+                .iter()
+                .map(|ty| sample_value(ty, emitted, config))
+                .collect();
+            Some(format!(
+                "{}::{}({})",
+                name,
+                variant.name,
+                values?.join(", ")
+            ))
+        }
+        Some(EnumFields::Named(fields)) => {
+            let values: Option<Vec<_>> = fields
+                .iter()
+                .map(|field| {
+                    // newtypes and hooks of the field don't take the plain value
+                    if rust_type(&field.ty, Some(&field.name), config, &mut unresolved)
+                        != rust_type(&field.ty, None, config, &mut unresolved)
+                    {
+                        return None;
+                    }
+                    let value = sample_value(&field.ty, emitted, config)?;
+                    Some(format!(
+                        "{}: {}",
+                        config.naming.fields.apply(&field.name),
+                        value
+                    ))
+                })
+                .collect();
+            Some(format!(
+                "{}::{} {{ {} }}",
+                name,
+                variant.name,
+                values?.join(", ")
+            ))
+        }
+    }
+}
+
+/// A value of `ty`: `Default::default()`, or the first variant of a unit
+/// enum.
+fn sample_value(ty: &Type, emitted: &[&TypeDef], config: &GeneratorConfig) -> Option<String> {
+    fn defaults(ty: &Type) -> bool {
+        match ty {
+            Type::Defined(_) => false,
+            Type::Array(inner, len) => *len <= 32 && defaults(inner),
+            _ => true,
+        }
+    }
+    if let Type::Defined(name) = ty {
+        let def = emitted.iter().find(|def| &def.name == name)?;
+        return match &def.kind {
+            TypeDefKind::Enum { variants } if variants.iter().all(|v| v.fields.is_none()) => {
+                Some(format!(
+                    "{}::{}",
+                    config.naming.types.apply(&def.name),
+                    variants.first()?.name
+                ))
+            }
+            _ => None,
+        };
+    }
+    defaults(ty).then(|| "Default::default()".to_string())
+}
+
+fn add_error_codes(
+    output: &mut impl Write,
+    errors: &[ErrorCode],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let vis = config.type_visibility.as_prefix();
+    output.write_fmt(format_args!(
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq)]\n#[repr(u32)]\n{}enum ErrorCode {{\n",
+        vis
+    ))?;
+    for error in errors.iter() {
+        output.write_fmt(format_args!("\t{} = {},\n", error.name, error.code))?;
+    }
+    output.write_fmt(format_args!(
+        "}}\nimpl ErrorCode {{\n\t{}fn from_code(code: u32) -> Option<Self> {{\n\t\tmatch code {{\n",
+        vis
+    ))?;
+    for error in errors.iter() {
+        output.write_fmt(format_args!(
+            "\t\t\t{} => Some(Self::{}),\n",
+            error.code, error.name
+        ))?;
+    }
+    output.write_fmt(format_args!(
+        "\t\t\t_ => None,\n\t\t}}\n\t}}\n\t{}fn msg(&self) -> &'static str {{\n\t\tmatch self {{\n",
+        vis
+    ))?;
+    for error in errors.iter() {
+        output.write_fmt(format_args!(
+            "\t\t\tSelf::{} => {:?},\n",
+            error.name,
+            error.msg.as_deref().unwrap_or(&error.name)
+        ))?;
+    }
+    output.write_all(b"\t\t}\n\t}\n}\n")?;
+    Ok(())
+}
+
+fn close_define_struct_or_enum(output: &mut impl Write) -> std::io::Result<()> {
+    output.write_all(b"}\n")?;
+    Ok(())
+}
+
+/// Names of the definitions the rust backend emits for `program`, following
+/// the same rules as [`RustBackend`].
+pub(crate) fn emitted_definitions(program: &Program, config: &GeneratorConfig) -> HashSet<String> {
+    let mut unresolved = HashSet::new();
+    let mut emitted = HashSet::new();
+    if !config.emits(Artifact::Types) {
+        return emitted;
+    }
+
+    if config.emits(Artifact::InstructionArgs) {
+        for arg in program.instructions.iter().flat_map(|ix| ix.args.iter()) {
+            rust_type(&arg.ty, Some(&arg.name), config, &mut unresolved);
+        }
+    }
+    if config.emits(Artifact::Events) {
+        for field in program.events.iter().flat_map(|e| e.fields.iter()) {
+            rust_type(&field.ty, Some(&field.name), config, &mut unresolved);
+        }
+    }
+
+    let all = !config.emits(Artifact::InstructionArgs);
+    for def in program.accounts.iter().chain(program.types.iter()) {
+        let overridden = config.type_overrides.contains_key(&def.name);
+        // the indexer stores the accounts, the client fetches them
+        let root = all || (fetches_accounts(config) && def.discriminator.is_some());
+        if (root && !overridden) || unresolved.contains(&def.name) {
+            if let TypeDefKind::Struct { fields } = &def.kind {
+                for field in fields.iter() {
+                    rust_type(&field.ty, Some(&field.name), config, &mut unresolved);
+                }
+            }
+            unresolved.remove(&def.name);
+            emitted.insert(def.name.clone());
+        }
+    }
+    emitted
+}
+
+/// Maps an IDL type to its rust spelling, recording every `Defined` name in `unresolved`.
+pub fn ty_to_rust_type(ty: &IdlType, unresolved: &mut HashSet<String>) -> String {
+    rust_type(
+        &lower_type(ty),
+        None,
+        &GeneratorConfig::default(),
+        unresolved,
+    )
+}
+
+/// `field` is the IDL name of the field `ty` belongs to, passed on to the
+/// type hooks.
+pub(crate) fn rust_type(
+    ty: &Type,
+    field: Option<&str>,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> String {
+    let mut out = String::new();
+    // writing into a String can't fail
+    let _ = write_rust_type(&mut out, ty, field, config, unresolved);
+    out
+}
+
+/// Writes the rust spelling of `ty`, see [`rust_type`].
+pub(crate) fn write_rust_type(
+    out: &mut impl fmt::Write,
+    ty: &Type,
+    field: Option<&str>,
+    config: &GeneratorConfig,
+    unresolved: &mut HashSet<String>,
+) -> fmt::Result {
+    if !config.type_hooks.is_empty() {
+        if let Some(rust_type) = config
+            .type_hooks
+            .iter()
+            .find_map(|hook| hook.map(ty, field))
+        {
+            return out.write_str(&rust_type);
+        }
+    }
+    if let Some(flags) = flag_type(ty, field, config) {
+        return out.write_str(&flags.name);
+    }
+    let primitive = match ty {
+        Type::Bool => "bool",
+        Type::U8 => "u8",
+        Type::I8 => "i8",
+        Type::U16 => "u16",
+        Type::I16 => "i16",
+        Type::U32 => "u32",
+        Type::I32 => "i32",
+        Type::F32 => "f32",
+        Type::U64 => "u64",
+        Type::I64 => "i64",
+        Type::F64 => "f64",
+        Type::U128 => "u128",
+        Type::I128 => "i128",
+        Type::Bytes => "Vec<u8>",
+        Type::String => "String",
+        Type::PublicKey => match field.and_then(|f| config.pubkey_newtypes.get(f)) {
+            Some(newtype) => newtype,
+            None => "Pubkey",
+        },
+        Type::Option(inner) => {
+            out.write_str("Option<")?;
+            write_rust_type(out, inner, field, config, unresolved)?;
+            return out.write_str(">");
+        }
+        Type::Vec(inner) => {
+            out.write_str("Vec<")?;
+            write_rust_type(out, inner, field, config, unresolved)?;
+            return out.write_str(">");
+        }
+        Type::Array(inner, size) => {
+            out.write_str("[")?;
+            write_rust_type(out, inner, field, config, unresolved)?;
+            return write!(out, "; {}]", size);
+        }
+        Type::Defined(name) => {
+            if let Some(rust_type) = config.type_overrides.get(name) {
+                return out.write_str(rust_type);
+            }
+            if !unresolved.contains(name) {
+                unresolved.insert(name.to_string());
+            }
+            return match config.naming.types {
+                Case::Preserve => out.write_str(name),
+                case => out.write_str(&case.apply(name)),
+            };
+        }
+    };
+    out.write_str(primitive)
+}
@@ -0,0 +1,216 @@
+//! Human-readable serde representations of the generated types, see
+//! [`GeneratorConfig::human_readable_serde`].
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use super::rust::rust_type;
+use crate::config::{Feature, GeneratorConfig};
+use crate::ir::Type;
+
+/// The `serde_human` module of the `#[serde(with)]` representations, where
+/// `string` is the `Display` string of a value, such as the base58 string
+/// of a pubkey, and `hex` the lowercase hex string of bytes.
+const SERDE_HUMAN: &str = r#"
+/// Human-readable serde representations of the fields.
+pub mod serde_human {
+	struct AsString<'a, T>(&'a T);
+
+	impl<T: std::fmt::Display> serde::Serialize for AsString<'_, T> {
+		fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.collect_str(self.0)
+		}
+	}
+
+	fn parse<T, E>(s: &str) -> std::result::Result<T, E>
+	where
+		T: std::str::FromStr,
+		T::Err: std::fmt::Display,
+		E: serde::de::Error,
+	{
+		s.parse().map_err(E::custom)
+	}
+
+	fn to_hex(bytes: &[u8]) -> String {
+		bytes.iter().map(|b| format!("{:02x}", b)).collect()
+	}
+
+	fn from_hex<T: TryFrom<Vec<u8>>, E: serde::de::Error>(hex: &str) -> std::result::Result<T, E> {
+		if hex.len() % 2 != 0 || !hex.is_ascii() {
+			return Err(E::custom(format!("invalid hex {:?}", hex)));
+		}
+		let bytes = (0..hex.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+			.collect::<std::result::Result<Vec<u8>, _>>()
+			.map_err(E::custom)?;
+		let len = bytes.len();
+		T::try_from(bytes).map_err(|_| E::invalid_length(len, &"the length of the field"))
+	}
+
+	/// A value as its `Display` string, parsed back with `FromStr`.
+	pub mod string {
+		pub fn serialize<T: std::fmt::Display, S: serde::Serializer>(value: &T, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.collect_str(value)
+		}
+
+		pub fn deserialize<'de, T, D>(deserializer: D) -> std::result::Result<T, D::Error>
+		where
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			super::parse(&<String as serde::Deserialize>::deserialize(deserializer)?)
+		}
+	}
+
+	/// An optional value as its [`string`] or null.
+	pub mod option_string {
+		pub fn serialize<T: std::fmt::Display, S: serde::Serializer>(value: &Option<T>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serde::Serialize::serialize(&value.as_ref().map(super::AsString), serializer)
+		}
+
+		pub fn deserialize<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+		where
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			<Option<String> as serde::Deserialize>::deserialize(deserializer)?
+				.map(|s| super::parse(&s))
+				.transpose()
+		}
+	}
+
+	/// A vector or array of values as a sequence of their [`string`]s.
+	pub mod seq_string {
+		pub fn serialize<C, T, S>(values: &C, serializer: S) -> std::result::Result<S::Ok, S::Error>
+		where
+			for<'a> &'a C: IntoIterator<Item = &'a T>,
+			T: std::fmt::Display,
+			S: serde::Serializer,
+		{
+			serializer.collect_seq(values.into_iter().map(super::AsString))
+		}
+
+		pub fn deserialize<'de, C, T, D>(deserializer: D) -> std::result::Result<C, D::Error>
+		where
+			C: TryFrom<Vec<T>>,
+			T: std::str::FromStr,
+			T::Err: std::fmt::Display,
+			D: serde::Deserializer<'de>,
+		{
+			let values = <Vec<String> as serde::Deserialize>::deserialize(deserializer)?
+				.iter()
+				.map(|s| super::parse(s))
+				.collect::<std::result::Result<Vec<T>, D::Error>>()?;
+			let len = values.len();
+			C::try_from(values).map_err(|_| serde::de::Error::invalid_length(len, &"the length of the field"))
+		}
+	}
+
+	/// Bytes as a lowercase hex string.
+	pub mod hex {
+		pub fn serialize<T: AsRef<[u8]>, S: serde::Serializer>(bytes: &T, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serializer.serialize_str(&super::to_hex(bytes.as_ref()))
+		}
+
+		pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<T, D::Error> {
+			super::from_hex(&<String as serde::Deserialize>::deserialize(deserializer)?)
+		}
+	}
+
+	/// Optional bytes as their [`hex`] string or null.
+	pub mod option_hex {
+		pub fn serialize<T: AsRef<[u8]>, S: serde::Serializer>(bytes: &Option<T>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+			serde::Serialize::serialize(&bytes.as_ref().map(|b| super::to_hex(b.as_ref())), serializer)
+		}
+
+		pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<T>, D::Error> {
+			<Option<String> as serde::Deserialize>::deserialize(deserializer)?
+				.map(|hex| super::from_hex(&hex))
+				.transpose()
+		}
+	}
+}
+"#;
+
+/// Whether the types get human-readable serde representations: they must
+/// derive the serde traits.
+pub(super) fn human_readable(config: &GeneratorConfig) -> bool {
+    config.human_readable_serde && super::sink::serializes(config)
+}
+
+/// An attribute of serde, gated by the serde feature with feature gates.
+pub(super) fn serde_attribute(attribute: &str, config: &GeneratorConfig) -> String {
+    if config.feature_gates {
+        format!(
+            "#[cfg_attr({}, serde({}))]",
+            Feature::Serde.predicate(),
+            attribute
+        )
+    } else {
+        format!("#[serde({})]", attribute)
+    }
+}
+
+/// Writes the `serde_human` module the field attributes refer to.
+pub(super) fn define_serde_human(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = if config.feature_gates {
+        format!("#[cfg({})]\n", Feature::Serde.predicate())
+    } else {
+        String::new()
+    };
+    let item = SERDE_HUMAN.replace(
+        "pub mod serde_human",
+        &format!(
+            "{}{}mod serde_human",
+            cfg,
+            config.type_visibility.as_prefix()
+        ),
+    );
+    output.write_all(item.as_bytes())
+}
+
+/// The serde attribute of the enums: adjacently tagged, by `kind` and
+/// `value`.
+pub(super) fn enum_attribute(config: &GeneratorConfig) -> String {
+    format!(
+        "{}\n",
+        serde_attribute("tag = \"kind\", content = \"value\"", config)
+    )
+}
+
+/// The `#[serde(with)]` attribute of the field `field` of type `ty`, if its
+/// type is a pubkey, a 128 bit integer or bytes, directly or in an option,
+/// vector or array, and isn't typed otherwise by the config.
+pub(super) fn field_attribute(ty: &Type, field: &str, config: &GeneratorConfig) -> Option<String> {
+    let stringified = |ty: &Type| matches!(ty, Type::PublicKey | Type::U128 | Type::I128);
+    let bytes = |ty: &Type| {
+        matches!(ty, Type::Bytes)
+            || matches!(ty, Type::Array(inner, _) if matches!(**inner, Type::U8))
+    };
+    let with = match ty {
+        ty if stringified(ty) => "string",
+        ty if bytes(ty) => "hex",
+        Type::Option(inner) if stringified(inner) => "option_string",
+        Type::Option(inner) if bytes(inner) => "option_hex",
+        Type::Vec(inner) | Type::Array(inner, _) if stringified(inner) => "seq_string",
+        _ => return None,
+    };
+    // newtypes, flags and hooks of the field are left to their own serde
+    // impls
+    let mut unresolved = HashSet::new();
+    if rust_type(ty, Some(field), config, &mut unresolved)
+        != rust_type(ty, None, config, &mut unresolved)
+    {
+        return None;
+    }
+    Some(format!(
+        "\t{}\n",
+        serde_attribute(&format!("with = \"serde_human::{}\"", with), config)
+    ))
+}
@@ -0,0 +1,341 @@
+//! Decoding over HTTP, see
+//! [`GeneratorConfig::http_service`](crate::GeneratorConfig::http_service).
+
+use std::io::Write;
+
+use log::warn;
+
+use super::client::cfg;
+use super::sink::serializes;
+use crate::config::{Artifact, Feature, GeneratorConfig};
+use crate::ir::{Event, Instruction, TypeDef};
+use crate::template::TemplateKind;
+
+/// Program independent part: the service, its routes and its errors.
+const SERVICE: &[&str] = &[
+    r#"/// A failed request of the [`DecodeService`], answered with its status and
+/// `{"error": message}`.
+#[derive(Debug)]
+pub struct ServiceError {
+	pub status: axum::http::StatusCode,
+	pub message: String,
+}
+"#,
+    r#"impl ServiceError {
+	pub fn new(status: axum::http::StatusCode, message: impl ToString) -> Self {
+		Self {
+			status,
+			message: message.to_string(),
+		}
+	}
+}
+"#,
+    r#"impl axum::response::IntoResponse for ServiceError {
+	fn into_response(self) -> axum::response::Response {
+		let body = axum::Json(serde_json::json!({ "error": self.message }));
+		(self.status, body).into_response()
+	}
+}
+"#,
+    r#"impl From<BindingsError> for ServiceError {
+	fn from(e: BindingsError) -> Self {
+		Self::new(axum::http::StatusCode::UNPROCESSABLE_ENTITY, e)
+	}
+}
+"#,
+    r#"impl From<std::io::Error> for ServiceError {
+	fn from(e: std::io::Error) -> Self {
+		Self::new(axum::http::StatusCode::UNPROCESSABLE_ENTITY, e)
+	}
+}
+"#,
+    r#"impl From<serde_json::Error> for ServiceError {
+	fn from(e: serde_json::Error) -> Self {
+		Self::new(axum::http::StatusCode::INTERNAL_SERVER_ERROR, e)
+	}
+}
+"#,
+    r#"impl From<solana_client::client_error::ClientError> for ServiceError {
+	fn from(e: solana_client::client_error::ClientError) -> Self {
+		Self::new(axum::http::StatusCode::BAD_GATEWAY, e)
+	}
+}
+"#,
+    r#"/// Serves the decoders of the program over HTTP, fetching the accounts and
+/// transactions from an RPC node. Every route answers JSON:
+///
+/// - `POST /decode/instruction` decodes the instruction data of a
+///   `{"data", "encoding"}` body, `encoding` being `"base58"`, the default,
+///   or `"base64"`, into `{"name", "data"}`
+/// - `GET /decode/account/:pubkey` fetches the account and decodes it into
+///   `{"address", "name", "data"}`
+/// - `GET /decode/tx/:signature` fetches the transaction and decodes the
+///   instructions of the program in it, inner ones included, and the events
+///   it logged
+#[derive(Clone)]
+pub struct DecodeService {
+	rpc: std::sync::Arc<solana_client::nonblocking::rpc_client::RpcClient>,
+}
+"#,
+    r#"impl DecodeService {
+	/// A service fetching from the node at `rpc_url`.
+	pub fn new(rpc_url: impl Into<String>) -> Self {
+		Self::with_rpc(solana_client::nonblocking::rpc_client::RpcClient::new(rpc_url.into()))
+	}
+
+	/// A service fetching through `rpc`, e.g. one with another commitment.
+	pub fn with_rpc(rpc: solana_client::nonblocking::rpc_client::RpcClient) -> Self {
+		Self {
+			rpc: std::sync::Arc::new(rpc),
+		}
+	}
+
+	/// The routes of the service, to serve or to nest in another router.
+	pub fn router(self) -> axum::Router {
+		axum::Router::new()
+			.route("/decode/instruction", axum::routing::post(decode_instruction_route))
+			.route("/decode/account/:pubkey", axum::routing::get(decode_account_route))
+			.route("/decode/tx/:signature", axum::routing::get(decode_tx_route))
+			.with_state(self)
+	}
+}
+"#,
+    r#"/// Serves the [`DecodeService`] of the node at `rpc_url` on `addr`, until
+/// it fails.
+pub async fn serve_decode_service(
+	addr: std::net::SocketAddr,
+	rpc_url: impl Into<String>,
+) -> std::io::Result<()> {
+	let listener = tokio::net::TcpListener::bind(addr).await?;
+	axum::serve(listener, DecodeService::new(rpc_url).router()).await
+}
+"#,
+    r#"async fn decode_instruction_route(
+	axum::Json(body): axum::Json<serde_json::Value>,
+) -> std::result::Result<axum::Json<serde_json::Value>, ServiceError> {
+	let bad_request = |message: &str| ServiceError::new(axum::http::StatusCode::BAD_REQUEST, message);
+	let data = body["data"].as_str().ok_or_else(|| bad_request("no data string in the body"))?;
+	let data = match body["encoding"].as_str().unwrap_or("base58") {
+		"base58" => solana_sdk::bs58::decode(data).into_vec().map_err(|e| bad_request(&e.to_string()))?,
+		"base64" => base64::Engine::decode(&base64::engine::general_purpose::STANDARD, data)
+			.map_err(|e| bad_request(&e.to_string()))?,
+		encoding => return Err(bad_request(&format!("unknown encoding {}", encoding))),
+	};
+	let decoded = decode_instruction_json(&data)?.ok_or_else(|| {
+		ServiceError::new(axum::http::StatusCode::UNPROCESSABLE_ENTITY, "none of the program's instructions")
+	})?;
+	Ok(axum::Json(decoded))
+}
+"#,
+    r#"async fn decode_account_route(
+	axum::extract::State(service): axum::extract::State<DecodeService>,
+	axum::extract::Path(pubkey): axum::extract::Path<String>,
+) -> std::result::Result<axum::Json<serde_json::Value>, ServiceError> {
+	let pubkey: Pubkey = pubkey
+		.parse()
+		.map_err(|_| ServiceError::new(axum::http::StatusCode::BAD_REQUEST, "invalid pubkey"))?;
+	let account = service.rpc.get_account(&pubkey).await?;
+	let not_decoded = || ServiceError::new(axum::http::StatusCode::UNPROCESSABLE_ENTITY, "none of the program's accounts");
+	if account.owner != PROGRAM_ID {
+		return Err(not_decoded());
+	}
+	let mut decoded = decode_account_json(&account.data)?.ok_or_else(not_decoded)?;
+	decoded["address"] = pubkey.to_string().into();
+	Ok(axum::Json(decoded))
+}
+"#,
+    r#"async fn decode_tx_route(
+	axum::extract::State(service): axum::extract::State<DecodeService>,
+	axum::extract::Path(signature): axum::extract::Path<String>,
+) -> std::result::Result<axum::Json<serde_json::Value>, ServiceError> {
+	use solana_transaction_status::option_serializer::OptionSerializer;
+	let bad_gateway = |message: &str| ServiceError::new(axum::http::StatusCode::BAD_GATEWAY, message);
+	let signature: solana_sdk::signature::Signature = signature
+		.parse()
+		.map_err(|_| ServiceError::new(axum::http::StatusCode::BAD_REQUEST, "invalid signature"))?;
+	let config = solana_client::rpc_config::RpcTransactionConfig {
+		encoding: Some(solana_transaction_status::UiTransactionEncoding::Base64),
+		commitment: Some(service.rpc.commitment()),
+		max_supported_transaction_version: Some(0),
+	};
+	let tx = service.rpc.get_transaction_with_config(&signature, config).await?;
+	let meta = tx.transaction.meta.as_ref().ok_or_else(|| bad_gateway("transaction without status"))?;
+	let decoded = tx.transaction.transaction.decode().ok_or_else(|| bad_gateway("transaction not in a binary encoding"))?;
+	let mut keys = decoded.message.static_account_keys().to_vec();
+	if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+		for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+			keys.push(key.parse().map_err(|_| bad_gateway("invalid loaded address"))?);
+		}
+	}
+
+	let mut instructions = Vec::new();
+	for (index, ix) in decoded.message.instructions().iter().enumerate() {
+		let decoded = decode_compiled_json(&keys, ix.program_id_index, &ix.accounts, &ix.data)?;
+		if let Some(mut decoded) = decoded {
+			decoded["index"] = index.into();
+			instructions.push(decoded);
+		}
+	}
+	if let OptionSerializer::Some(inner) = &meta.inner_instructions {
+		for group in inner {
+			for (inner_index, ix) in group.instructions.iter().enumerate() {
+				let solana_transaction_status::UiInstruction::Compiled(ix) = ix else {
+					continue;
+				};
+				let data = solana_sdk::bs58::decode(&ix.data)
+					.into_vec()
+					.map_err(|_| bad_gateway("invalid inner instruction data"))?;
+				let decoded = decode_compiled_json(&keys, ix.program_id_index, &ix.accounts, &data)?;
+				if let Some(mut decoded) = decoded {
+					decoded["index"] = group.index.into();
+					decoded["inner_index"] = inner_index.into();
+					instructions.push(decoded);
+				}
+			}
+		}
+	}
+	let events = match &meta.log_messages {
+		OptionSerializer::Some(logs) => decode_events_json(logs)?,
+		_ => Vec::new(),
+	};
+	Ok(axum::Json(serde_json::json!({
+		"signature": signature.to_string(),
+		"slot": tx.slot,
+		"err": meta.err.as_ref().map(|e| e.to_string()),
+		"instructions": instructions,
+		"events": events,
+	})))
+}
+"#,
+    r#"/// The instruction `data` of a transaction as `{"name", "data", "accounts"}`,
+/// `None` if its program isn't the program.
+fn decode_compiled_json(
+	keys: &[Pubkey],
+	program_id_index: u8,
+	accounts: &[u8],
+	data: &[u8],
+) -> std::result::Result<Option<serde_json::Value>, ServiceError> {
+	if keys.get(program_id_index as usize) != Some(&PROGRAM_ID) {
+		return Ok(None);
+	}
+	let Some(mut decoded) = decode_instruction_json(data)? else {
+		return Ok(None);
+	};
+	let accounts: Vec<_> = accounts
+		.iter()
+		.filter_map(|i| keys.get(*i as usize))
+		.map(|key| key.to_string())
+		.collect();
+	decoded["accounts"] = accounts.into();
+	Ok(Some(decoded))
+}
+"#,
+];
+
+/// Writes the `DecodeService` axum router and `serve_decode_service`, with
+/// `decode_instruction_json`, `decode_account_json` and `decode_events_json`
+/// rendering what the decoders of the program decode as JSON.
+pub(super) fn define_service(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    emitted: &[&TypeDef],
+    events: &[Event],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if !serializes(config) {
+        warn!("the service serializes the generated types with serde, derive serde::Serialize or use feature gates");
+    }
+    let cfg = cfg(&[Feature::Http], config);
+    for item in SERVICE {
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+
+    let args_structs = config.emits(Artifact::InstructionArgs)
+        && !config.templates.contains_key(&TemplateKind::Instruction);
+    let instructions: Vec<_> = instructions
+        .iter()
+        .filter(|ix| {
+            let decoded = ix.args.is_empty() || args_structs;
+            if !decoded {
+                warn!("no args struct for {}, not served", ix.name);
+            }
+            decoded
+        })
+        .collect();
+    output.write_fmt(format_args!(
+        "\n{}/// The instruction of the program `data` holds, discriminator included, as\n/// `{{\"name\", \"data\"}}`, `None` if it is none of them.\nfn decode_instruction_json(data: &[u8]) -> std::result::Result<Option<serde_json::Value>, ServiceError> {{\n\tif data.len() < 8 {{\n\t\treturn Ok(None);\n\t}}\n\tlet (discriminator, {}data) = data.split_at(8);\n\tlet (name, data) = match discriminator {{\n",
+        cfg,
+        if instructions.iter().any(|ix| !ix.args.is_empty()) {
+            "mut "
+        } else {
+            "_"
+        }
+    ))?;
+    for ix in instructions.iter() {
+        let data = if ix.args.is_empty() {
+            "serde_json::Value::Null".to_string()
+        } else {
+            format!(
+                "serde_json::to_value(<{} as borsh::BorshDeserialize>::deserialize(&mut data)?)?",
+                config.naming.instructions.apply(&ix.name)
+            )
+        };
+        output.write_fmt(format_args!(
+            "\t\t{:?} => ({:?}, {}),\n",
+            ix.discriminator, ix.name, data
+        ))?;
+    }
+    output.write_all(
+        b"\t\t_ => return Ok(None),\n\t};\n\tOk(Some(serde_json::json!({ \"name\": name, \"data\": data })))\n}\n",
+    )?;
+
+    // the accounts come from their decoders, emitted with the types
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some() && config.emits(Artifact::Types))
+        .collect();
+    output.write_fmt(format_args!(
+        "\n{}/// The account of the program `data` holds, discriminator included, as\n/// `{{\"name\", \"data\"}}`, `None` if it is none of them.\nfn decode_account_json({}data: &[u8]) -> std::result::Result<Option<serde_json::Value>, ServiceError> {{\n\tlet (name, account) = match data.get(..8) {{\n",
+        cfg,
+        if accounts.is_empty() { "_" } else { "" }
+    ))?;
+    for def in accounts {
+        output.write_fmt(format_args!(
+            "\t\tSome({:?}) => ({:?}, serde_json::to_value({}::from_account_data(data)?)?),\n",
+            def.discriminator.unwrap_or_default(),
+            def.name,
+            config.naming.types.apply(&def.name)
+        ))?;
+    }
+    output.write_all(
+        b"\t\t_ => return Ok(None),\n\t};\n\tOk(Some(serde_json::json!({ \"name\": name, \"data\": account })))\n}\n",
+    )?;
+
+    // the events come from the event decoder
+    let events = if config.emits(Artifact::Events) {
+        events
+    } else {
+        &[]
+    };
+    output.write_fmt(format_args!(
+        "\n{}/// The events the program logged in `logs`, as\n/// `{{\"name\", \"data\", \"index\", \"inner_index\"}}`.\nfn decode_events_json<S: AsRef<str>>({}logs: &[S]) -> std::result::Result<Vec<serde_json::Value>, ServiceError> {{\n",
+        cfg,
+        if events.is_empty() { "_" } else { "" }
+    ))?;
+    if events.is_empty() {
+        return output.write_all(b"\tOk(Vec::new())\n}\n");
+    }
+    output.write_all(b"\tlet mut decoded = Vec::new();\n\tfor emitted in ProgramEvent::from_logs(logs) {\n\t\tlet (name, data) = match &emitted.event {\n")?;
+    for event in events {
+        output.write_fmt(format_args!(
+            "\t\t\tProgramEvent::{}(event) => ({:?}, serde_json::to_value(event)?),\n",
+            config.naming.types.apply(&event.name),
+            event.name
+        ))?;
+    }
+    output.write_all(
+        b"\t\t};\n\t\tdecoded.push(serde_json::json!({\n\t\t\t\"name\": name,\n\t\t\t\"data\": data,\n\t\t\t\"index\": emitted.index,\n\t\t\t\"inner_index\": emitted.inner_index,\n\t\t}));\n\t}\n\tOk(decoded)\n}\n",
+    )
+}
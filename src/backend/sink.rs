@@ -0,0 +1,173 @@
+//! Publishing decoded instructions and events, see
+//! [`GeneratorConfig::kafka_sink`](crate::GeneratorConfig::kafka_sink).
+
+use std::io::Write;
+
+use log::warn;
+
+use super::client::cfg;
+use crate::config::{Artifact, Feature, GeneratorConfig};
+use crate::ir::{Event, Instruction};
+use crate::template::TemplateKind;
+
+/// Program independent part: the sink trait and its Kafka implementation.
+const SINK: &[&str] = &[
+    r#"/// Receives the decoded instructions and events of the program, as JSON
+/// envelopes `{"kind", "name", "signature", "data"}` keyed by the signature
+/// of their transaction. `kind` is `"instruction"` or `"event"`.
+pub trait DecodedSink {
+	fn publish(
+		&self,
+		key: &str,
+		payload: &[u8],
+	) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+"#,
+    r#"/// Publishes to a Kafka topic, from a producer polled in the background.
+pub struct KafkaSink {
+	producer: rdkafka::producer::ThreadedProducer<rdkafka::producer::DefaultProducerContext>,
+	topic: String,
+}
+"#,
+    r#"impl KafkaSink {
+	/// A sink producing to `topic` of the cluster at `brokers`, a comma
+	/// separated list of `host:port`.
+	pub fn new(brokers: &str, topic: impl Into<String>) -> std::result::Result<Self, rdkafka::error::KafkaError> {
+		let mut config = rdkafka::ClientConfig::new();
+		config.set("bootstrap.servers", brokers);
+		Self::with_config(&config, topic)
+	}
+
+	/// A sink producing to `topic` with a producer of `config`.
+	pub fn with_config(
+		config: &rdkafka::ClientConfig,
+		topic: impl Into<String>,
+	) -> std::result::Result<Self, rdkafka::error::KafkaError> {
+		Ok(Self {
+			producer: config.create()?,
+			topic: topic.into(),
+		})
+	}
+
+	/// Waits for the messages in flight to be delivered.
+	pub fn flush(&self, timeout: std::time::Duration) -> std::result::Result<(), rdkafka::error::KafkaError> {
+		rdkafka::producer::Producer::flush(&self.producer, timeout)
+	}
+}
+"#,
+    r#"impl DecodedSink for KafkaSink {
+	fn publish(
+		&self,
+		key: &str,
+		payload: &[u8],
+	) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+		let record = rdkafka::producer::BaseRecord::to(&self.topic).key(key).payload(payload);
+		self.producer.send(record).map_err(|(e, _)| e)?;
+		Ok(())
+	}
+}
+"#,
+    r#"fn envelope(
+	kind: &str,
+	name: &str,
+	signature: &solana_sdk::signature::Signature,
+	data: serde_json::Value,
+) -> std::result::Result<Vec<u8>, serde_json::Error> {
+	serde_json::to_vec(&serde_json::json!({
+		"kind": kind,
+		"name": name,
+		"signature": signature.to_string(),
+		"data": data,
+	}))
+}
+"#,
+];
+
+/// Whether the emitted types derive `serde::Serialize`.
+pub(crate) fn serializes(config: &GeneratorConfig) -> bool {
+    config.feature_gates
+        || config
+            .derives
+            .iter()
+            .any(|d| d.ends_with("Serialize") && !d.starts_with("Borsh"))
+}
+
+/// Writes the `DecodedSink` trait, its `KafkaSink` implementation and
+/// `publish_instruction` and `publish_events`, which decode an instruction
+/// or the events of a transaction's logs and publish them.
+pub(super) fn define_sink(
+    output: &mut impl Write,
+    instructions: &[Instruction],
+    events: &[Event],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    if !serializes(config) {
+        warn!("the sink serializes the generated types with serde, derive serde::Serialize or use feature gates");
+    }
+    let cfg = cfg(&[Feature::Kafka], config);
+    let vis = config.type_visibility.as_prefix();
+    for item in SINK {
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+
+    let args_structs = config.emits(Artifact::InstructionArgs)
+        && !config.templates.contains_key(&TemplateKind::Instruction);
+    let instructions: Vec<_> = instructions
+        .iter()
+        .filter(|ix| {
+            let decoded = ix.args.is_empty() || args_structs;
+            if !decoded {
+                warn!("no args struct for {}, not published", ix.name);
+            }
+            decoded
+        })
+        .collect();
+    output.write_fmt(format_args!(
+        "\n{}/// Decodes the data of an instruction of the program and publishes it to\n/// `sink`, `false` if it is none of the program's instructions.\n{}fn publish_instruction(\n\tsink: &dyn DecodedSink,\n\tsignature: &solana_sdk::signature::Signature,\n\tdata: &[u8],\n) -> std::result::Result<bool, Box<dyn std::error::Error + Send + Sync>> {{\n\tif data.len() < 8 {{\n\t\treturn Ok(false);\n\t}}\n\tlet (discriminator, {}data) = data.split_at(8);\n\tlet (name, data) = match discriminator {{\n",
+        cfg,
+        vis,
+        if instructions.iter().any(|ix| !ix.args.is_empty()) {
+            "mut "
+        } else {
+            "_"
+        }
+    ))?;
+    for ix in instructions.iter() {
+        let data = if ix.args.is_empty() {
+            "serde_json::Value::Null".to_string()
+        } else {
+            format!(
+                "serde_json::to_value(<{} as borsh::BorshDeserialize>::deserialize(&mut data)?)?",
+                config.naming.instructions.apply(&ix.name)
+            )
+        };
+        output.write_fmt(format_args!(
+            "\t\t{:?} => ({:?}, {}),\n",
+            ix.discriminator, ix.name, data
+        ))?;
+    }
+    output.write_all(
+        b"\t\t_ => return Ok(false),\n\t};\n\tsink.publish(&signature.to_string(), &envelope(\"instruction\", name, signature, data)?)?;\n\tOk(true)\n}\n",
+    )?;
+
+    // the events come from the event decoder
+    if !config.emits(Artifact::Events) || events.is_empty() {
+        return Ok(());
+    }
+    output.write_fmt(format_args!(
+        "\n{}/// Publishes the events the program logged in a transaction to `sink`,\n/// returns how many.\n{}fn publish_events<S: AsRef<str>>(\n\tsink: &dyn DecodedSink,\n\tsignature: &solana_sdk::signature::Signature,\n\tlogs: &[S],\n) -> std::result::Result<usize, Box<dyn std::error::Error + Send + Sync>> {{\n\tlet events = ProgramEvent::from_logs(logs);\n\tfor emitted in events.iter() {{\n\t\tlet (name, data) = match &emitted.event {{\n",
+        cfg, vis
+    ))?;
+    for event in events {
+        output.write_fmt(format_args!(
+            "\t\t\tProgramEvent::{}(event) => ({:?}, serde_json::to_value(event)?),\n",
+            config.naming.types.apply(&event.name),
+            event.name
+        ))?;
+    }
+    output.write_all(
+        b"\t\t};\n\t\tsink.publish(&signature.to_string(), &envelope(\"event\", name, signature, data)?)?;\n\t}\n\tOk(events.len())\n}\n",
+    )
+}
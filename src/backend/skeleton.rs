@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+use heck::ToSnakeCase;
+use log::warn;
+
+use super::rust::rust_type;
+use super::{Backend, GeneratedFile, ProgramContext};
+use crate::config::GeneratorConfig;
+use crate::decode::ERROR_CODE_OFFSET;
+use crate::error::Result;
+use crate::ir::{AccountItem, EnumFields, Program, Type, TypeDef, TypeDefKind};
+
+/// Emits the `lib.rs` of an Anchor program implementing the IDL: the
+/// `#[program]` module with a `todo!()` handler per instruction, a
+/// `#[derive(Accounts)]` struct per instruction, the accounts, types,
+/// events, errors and constants.
+///
+/// Accounts named like an account type of the IDL become `Account<'info,
+/// T>`, signers `Signer<'info>`, the system program, rent and clock their
+/// anchor types, everything else an `UncheckedAccount<'info>` to review.
+#[derive(Debug, Default)]
+pub struct SkeletonBackend {
+    config: GeneratorConfig,
+    module_name: String,
+    program: Option<Program>,
+}
+
+impl Backend for SkeletonBackend {
+    fn visit_program(&mut self, program: &ProgramContext<'_>) -> Result<()> {
+        self.config = program.config.clone();
+        self.module_name = program.module_name.to_string();
+        self.program = Some(program.program.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<Vec<GeneratedFile>> {
+        let Some(program) = self.program.take() else {
+            return Ok(vec![]);
+        };
+        let config = &self.config;
+        let mut out = vec![];
+
+        writeln!(
+            out,
+            "use anchor_lang::prelude::*;\n\ndeclare_id!(\"{}\");\n",
+            program.address
+        )?;
+
+        for constant in program.constants.iter() {
+            writeln!(
+                out,
+                "#[constant]\npub const {}: {} = {};",
+                constant.name,
+                rust_type(&constant.ty, None, config, &mut HashSet::new()),
+                constant.value
+            )?;
+        }
+        if !program.constants.is_empty() {
+            out.write_all(b"\n")?;
+        }
+
+        writeln!(
+            out,
+            "#[program]\npub mod {} {{\n\tuse super::*;",
+            program.name.to_snake_case()
+        )?;
+        for ix in program.instructions.iter() {
+            let mut params = format!(
+                "ctx: Context<{}>",
+                config.naming.instructions.apply(&ix.name)
+            );
+            for arg in ix.args.iter() {
+                params.push_str(&format!(
+                    ", {}: {}",
+                    config.naming.fields.apply(&arg.name),
+                    rust_type(&arg.ty, Some(&arg.name), config, &mut HashSet::new())
+                ));
+            }
+            let returns = match &ix.returns {
+                Some(ty) => rust_type(ty, None, config, &mut HashSet::new()),
+                None => "()".to_string(),
+            };
+            writeln!(
+                out,
+                "\n\tpub fn {}({}) -> Result<{}> {{\n\t\ttodo!()\n\t}}",
+                ix.name.to_snake_case(),
+                params,
+                returns
+            )?;
+        }
+        out.write_all(b"}\n")?;
+
+        for ix in program.instructions.iter() {
+            let name = config.naming.instructions.apply(&ix.name);
+            write_accounts(&mut out, &name, &ix.accounts, &program, config)?;
+        }
+
+        for account in program.accounts.iter() {
+            out.write_all(b"\n#[account]\n")?;
+            write_type(&mut out, account, config)?;
+        }
+        for ty in program.types.iter() {
+            out.write_all(b"\n#[derive(AnchorSerialize, AnchorDeserialize, Clone)]\n")?;
+            write_type(&mut out, ty, config)?;
+        }
+
+        for event in program.events.iter() {
+            writeln!(
+                out,
+                "\n#[event]\npub struct {} {{",
+                config.naming.types.apply(&event.name)
+            )?;
+            for field in event.fields.iter() {
+                if field.index {
+                    out.write_all(b"\t#[index]\n")?;
+                }
+                write_field(&mut out, &field.name, &field.ty, config)?;
+            }
+            out.write_all(b"}\n")?;
+        }
+
+        if let Some(first) = program.errors.first() {
+            if first.code == ERROR_CODE_OFFSET {
+                out.write_all(b"\n#[error_code]\n")?;
+            } else {
+                writeln!(out, "\n#[error_code(offset = {})]", first.code)?;
+            }
+            out.write_all(b"pub enum ErrorCode {\n")?;
+            for (i, error) in program.errors.iter().enumerate() {
+                if error.code != first.code + i as u32 {
+                    warn!(
+                        "error {} of {} is not numbered after the previous one, its code changes",
+                        error.name, program.name
+                    );
+                }
+                if let Some(msg) = &error.msg {
+                    writeln!(out, "\t#[msg({:?})]", msg)?;
+                }
+                writeln!(out, "\t{},", error.name)?;
+            }
+            out.write_all(b"}\n")?;
+        }
+
+        Ok(vec![GeneratedFile {
+            path: PathBuf::from(format!("{}.rs", self.module_name)),
+            contents: String::from_utf8(out)?,
+        }])
+    }
+}
+
+/// Writes the `#[derive(Accounts)]` struct `name`, then a struct per nested
+/// group, named after `name` and the group.
+fn write_accounts(
+    out: &mut Vec<u8>,
+    name: &str,
+    items: &[AccountItem],
+    program: &Program,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let mut groups = vec![];
+    writeln!(out, "\n#[derive(Accounts)]\npub struct {}<'info> {{", name)?;
+    for item in items {
+        match item {
+            AccountItem::Account(account) => {
+                let field = config.naming.fields.apply(&account.name);
+                let ty = if account.is_signer {
+                    "Signer<'info>".to_string()
+                } else {
+                    match account.name.to_snake_case().as_str() {
+                        "system_program" => "Program<'info, System>".to_string(),
+                        "rent" => "Sysvar<'info, Rent>".to_string(),
+                        "clock" => "Sysvar<'info, Clock>".to_string(),
+                        snake => match program
+                            .accounts
+                            .iter()
+                            .find(|a| a.name.to_snake_case() == snake)
+                        {
+                            Some(def) => {
+                                format!("Account<'info, {}>", config.naming.types.apply(&def.name))
+                            }
+                            None => {
+                                out.write_all(b"\t/// CHECK: not checked by the IDL\n")?;
+                                "UncheckedAccount<'info>".to_string()
+                            }
+                        },
+                    }
+                };
+                if account.is_mut {
+                    out.write_all(b"\t#[account(mut)]\n")?;
+                }
+                if account.is_optional {
+                    writeln!(out, "\tpub {}: Option<{}>,", field, ty)?;
+                } else {
+                    writeln!(out, "\tpub {}: {},", field, ty)?;
+                }
+            }
+            AccountItem::Group {
+                name: group,
+                accounts,
+            } => {
+                let group_struct = format!("{}{}", name, config.naming.instructions.apply(group));
+                writeln!(
+                    out,
+                    "\tpub {}: {}<'info>,",
+                    config.naming.fields.apply(group),
+                    group_struct
+                )?;
+                groups.push((group_struct, accounts));
+            }
+        }
+    }
+    out.write_all(b"}\n")?;
+    for (group_struct, accounts) in groups {
+        write_accounts(out, &group_struct, accounts, program, config)?;
+    }
+    Ok(())
+}
+
+fn write_type(out: &mut Vec<u8>, def: &TypeDef, config: &GeneratorConfig) -> std::io::Result<()> {
+    let name = config.naming.types.apply(&def.name);
+    match &def.kind {
+        TypeDefKind::Struct { fields } => {
+            writeln!(out, "pub struct {} {{", name)?;
+            for field in fields.iter() {
+                write_field(out, &field.name, &field.ty, config)?;
+            }
+        }
+        TypeDefKind::Enum { variants } => {
+            writeln!(out, "pub enum {} {{", name)?;
+            for variant in variants.iter() {
+                match &variant.fields {
+                    None => writeln!(out, "\t{},", variant.name)?,
+                    Some(EnumFields::Named(fields)) => {
+                        writeln!(out, "\t{} {{", variant.name)?;
+                        for field in fields.iter() {
+                            writeln!(
+                                out,
+                                "\t\t{}: {},",
+                                config.naming.fields.apply(&field.name),
+                                rust_type(
+                                    &field.ty,
+                                    Some(&field.name),
+                                    config,
+                                    &mut HashSet::new()
+                                )
+                            )?;
+                        }
+                        out.write_all(b"\t},\n")?;
+                    }
+                    Some(EnumFields::Tuple(types)) => {
+                        let types: Vec<_> = types
+                            .iter()
+                            .map(|ty| rust_type(ty, None, config, &mut HashSet::new()))
+                            .collect();
+                        writeln!(out, "\t{}({}),", variant.name, types.join(", "))?;
+                    }
+                }
+            }
+        }
+    }
+    out.write_all(b"}\n")?;
+    Ok(())
+}
+
+fn write_field(
+    out: &mut Vec<u8>,
+    name: &str,
+    ty: &Type,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "\tpub {}: {},",
+        config.naming.fields.apply(name),
+        rust_type(ty, Some(name), config, &mut HashSet::new())
+    )
+}
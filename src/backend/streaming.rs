@@ -0,0 +1,117 @@
+//! Lazy decoding of vector fields, see [`GeneratorConfig::streaming`].
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use super::rust::rust_type;
+use crate::config::GeneratorConfig;
+use crate::ir::{Field, Type, TypeDef, TypeDefKind};
+
+/// Iterator yielding the elements of a borsh encoded `Vec` one at a time.
+const DECODE_ITER: &[u8] = br#"
+/// Decodes the elements of a borsh encoded `Vec` lazily from the input
+/// buffer. Stops after the first error.
+pub struct DecodeIter<'a, T> {
+    data: &'a [u8],
+    remaining: usize,
+    _item: std::marker::PhantomData<T>,
+}
+impl<'a, T: borsh::BorshDeserialize> DecodeIter<'a, T> {
+    fn new(data: &mut &'a [u8]) -> std::io::Result<Self> {
+        let remaining = <u32 as borsh::BorshDeserialize>::deserialize(data)? as usize;
+        Ok(Self {
+            data,
+            remaining,
+            _item: std::marker::PhantomData,
+        })
+    }
+    /// Number of elements not yet decoded.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+impl<'a, T: borsh::BorshDeserialize> Iterator for DecodeIter<'a, T> {
+    type Item = std::io::Result<T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let item = T::deserialize(&mut self.data);
+        if item.is_err() {
+            self.remaining = 0;
+        }
+        Some(item)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+"#;
+
+/// Element type of a field decoded lazily, `None` for byte vectors and for
+/// fields whose rust type is replaced by a hook.
+fn element<'t>(field: &'t Field, config: &GeneratorConfig) -> Option<&'t Type> {
+    let Type::Vec(inner) = &field.ty else {
+        return None;
+    };
+    let hooked = config
+        .type_hooks
+        .iter()
+        .any(|h| h.map(&field.ty, Some(&field.name)).is_some());
+    (**inner != Type::U8 && !hooked).then_some(inner)
+}
+
+/// Writes a `<field>_iter` function for every vector field of the emitted
+/// structs, decoding the fields before it and returning a [`DecodeIter`]
+/// over its elements.
+pub(super) fn define_streaming(
+    output: &mut impl Write,
+    definitions: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let structs: Vec<_> = definitions
+        .iter()
+        .filter_map(|d| match &d.kind {
+            TypeDefKind::Struct { fields }
+                if fields.iter().any(|f| element(f, config).is_some()) =>
+            {
+                Some((d, fields))
+            }
+            _ => None,
+        })
+        .collect();
+    if structs.is_empty() {
+        return Ok(());
+    }
+
+    output.write_all(DECODE_ITER)?;
+    let vis = config.type_visibility.as_prefix();
+    for (def, fields) in structs {
+        output.write_fmt(format_args!(
+            "impl {} {{\n",
+            config.naming.types.apply(&def.name)
+        ))?;
+        for (i, field) in fields.iter().enumerate() {
+            let Some(inner) = element(field, config) else {
+                continue;
+            };
+            let name = config.naming.fields.apply(&field.name);
+            output.write_fmt(format_args!(
+                "\t/// Decodes `{0}` lazily from `data`, the encoded struct without\n\t/// the account discriminator.\n\t{1}fn {0}_iter(mut data: &[u8]) -> std::io::Result<DecodeIter<'_, {2}>> {{\n",
+                name,
+                vis,
+                rust_type(inner, Some(&field.name), config, &mut HashSet::new())
+            ))?;
+            for before in fields[..i].iter() {
+                output.write_fmt(format_args!(
+                    "\t\t<{} as borsh::BorshDeserialize>::deserialize(&mut data)?;\n",
+                    rust_type(&before.ty, Some(&before.name), config, &mut HashSet::new())
+                ))?;
+            }
+            output.write_all(b"\t\tDecodeIter::new(&mut data)\n\t}\n")?;
+        }
+        output.write_all(b"}\n")?;
+    }
+    Ok(())
+}
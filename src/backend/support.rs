@@ -0,0 +1,76 @@
+//! The support module of the program independent helpers, see
+//! [`GeneratorConfig::shared_support`].
+use super::bindings_error::define_bindings_error;
+use super::borrowed::define_borrowed_helpers;
+use super::client::{define_builder_helpers, define_cli_helpers, define_send_helpers};
+use super::diffs::define_field_change;
+use super::fetchers::{define_decode_account, define_scan_helpers};
+use super::rust::{
+    defines_bindings_error, defines_builders, defines_cli, defines_client, fetches_accounts,
+    imports,
+};
+use super::serde_human::{define_serde_human, human_readable};
+use super::watchers::define_watch_helpers;
+use crate::config::{Artifact, GeneratorConfig};
+
+/// Name of the support module, next to the program modules.
+pub(crate) const SUPPORT_MODULE: &str = "support";
+
+/// Keywords of the private items among the helpers, which are opened to the
+/// program modules.
+const PRIVATE_ITEMS: &[&str] = &["fn ", "async fn ", "struct ", "enum ", "const ", "static "];
+
+/// The support module of the helpers the generated code of `config` uses,
+/// empty if it uses none. A program module leaves out the helpers it gets
+/// from it.
+pub(crate) fn support_module(config: &GeneratorConfig) -> std::io::Result<Vec<u8>> {
+    let mut helpers = vec![];
+    if human_readable(config) {
+        define_serde_human(&mut helpers, config)?;
+    }
+    if defines_bindings_error(config) {
+        define_bindings_error(&mut helpers, defines_builders(config), config)?;
+    }
+    if defines_builders(config) {
+        define_builder_helpers(&mut helpers)?;
+    }
+    if defines_cli(config) || defines_client(config) {
+        define_send_helpers(&mut helpers, config)?;
+    }
+    if defines_cli(config) {
+        define_cli_helpers(&mut helpers, config)?;
+    }
+    if config.emits(Artifact::Types) && fetches_accounts(config) {
+        define_decode_account(&mut helpers)?;
+    }
+    if config.account_scans {
+        define_scan_helpers(&mut helpers, config)?;
+    }
+    if config.account_watchers {
+        define_watch_helpers(&mut helpers, config)?;
+    }
+    if config.borrowed {
+        define_borrowed_helpers(&mut helpers)?;
+    }
+    if config.emits(Artifact::Types) && config.account_diffs {
+        define_field_change(&mut helpers, config)?;
+    }
+    if helpers.is_empty() {
+        return Ok(helpers);
+    }
+
+    // the helpers of the config are written whether a program uses them or
+    // not, with the imports of the programs
+    let mut output = b"#![allow(dead_code, unused_imports)]\n\n".to_vec();
+    for import in imports(config) {
+        output.extend_from_slice(format!("use {};\n", import).as_bytes());
+    }
+    for line in String::from_utf8_lossy(&helpers).lines() {
+        if PRIVATE_ITEMS.iter().any(|item| line.starts_with(item)) {
+            output.extend_from_slice(b"pub(super) ");
+        }
+        output.extend_from_slice(line.as_bytes());
+        output.push(b'\n');
+    }
+    Ok(output)
+}
@@ -0,0 +1,177 @@
+//! `accountSubscribe` watchers of the accounts, see
+//! [`GeneratorConfig::account_watchers`].
+
+use std::io::Write;
+
+use heck::ToSnakeCase;
+
+use super::client::cfg;
+use crate::config::{Feature, GeneratorConfig};
+use crate::ir::TypeDef;
+
+/// Program independent part of the watchers: the subscription task and the
+/// slot the latest update waits in.
+const WATCH_ACCOUNT: &[&str] = &[
+    r#"/// The latest update of a watched account not streamed yet, replaced by
+/// the newer ones arriving before it is.
+struct LatestUpdate<T> {
+	update: std::sync::Mutex<Option<(u64, T)>>,
+	notify: tokio::sync::Notify,
+}
+"#,
+    r#"/// Streams the state of the account at `address` along with the slot of
+/// each update, decoded as a `T` if its data starts with `discriminator`,
+/// from an `accountSubscribe` subscription at `ws_url` kept by a task
+/// spawned on the current tokio runtime. A slow consumer only gets the
+/// latest state, the states it lags behind are skipped. The task reconnects
+/// with a backoff when the subscription fails, and ends once the stream is
+/// dropped.
+pub fn watch_account<T: borsh::BorshDeserialize + Send + 'static>(
+	ws_url: impl ToString,
+	address: Pubkey,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+	discriminator: [u8; 8],
+) -> impl futures::Stream<Item = (u64, T)> {
+	let ws_url = ws_url.to_string();
+	let latest = std::sync::Arc::new(LatestUpdate {
+		update: std::sync::Mutex::new(None),
+		notify: tokio::sync::Notify::new(),
+	});
+	let watched = std::sync::Arc::downgrade(&latest);
+	tokio::spawn(async move {
+		let mut backoff = WATCH_BACKOFF;
+		while let Err(_e) = forward_updates(&ws_url, &address, commitment, discriminator, &watched, &mut backoff).await {
+{reconnect}			tokio::time::sleep(backoff).await;
+			backoff = (backoff * 2).min(std::time::Duration::from_secs(30));
+		}
+	});
+	futures::stream::unfold(latest, |latest| async move {
+		loop {
+			latest.notify.notified().await;
+			let update = latest.update.lock().expect("not poisoned").take();
+			if let Some(update) = update {
+				return Some((update, latest));
+			}
+		}
+	})
+}
+"#,
+    r#"/// Wait before the first reconnection of [`watch_account`], doubled up to
+/// 30 seconds after every failed one.
+const WATCH_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+"#,
+    r#"/// Puts the updates of a new subscription in `watched` until the stream of
+/// it is dropped, fails when the subscription does.
+async fn forward_updates<T: borsh::BorshDeserialize>(
+	ws_url: &str,
+	address: &Pubkey,
+	commitment: solana_sdk::commitment_config::CommitmentConfig,
+	discriminator: [u8; 8],
+	watched: &std::sync::Weak<LatestUpdate<T>>,
+	backoff: &mut std::time::Duration,
+) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+	use futures::StreamExt;
+	let client = solana_client::nonblocking::pubsub_client::PubsubClient::new(ws_url).await?;
+	let config = solana_client::rpc_config::RpcAccountInfoConfig {
+		encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+		commitment: Some(commitment),
+		..Default::default()
+	};
+	let (mut notifications, unsubscribe) = client.account_subscribe(address, Some(config)).await?;
+	*backoff = WATCH_BACKOFF;
+	while let Some(notification) = notifications.next().await {
+		let Some(latest) = watched.upgrade() else {
+			drop(notifications);
+			unsubscribe().await;
+			return Ok(());
+		};
+		let Some(account) = notification.value.decode::<solana_sdk::account::Account>() else {
+			continue;
+		};
+		// closed or reassigned accounts hold no `T`
+		let Some(mut data) = account.data.strip_prefix(&discriminator) else {
+{skipped}			continue;
+		};
+		match T::deserialize(&mut data) {
+			Ok(decoded) => {
+				*latest.update.lock().expect("not poisoned") = Some((notification.context.slot, decoded));
+				latest.notify.notify_one();
+			}
+			Err(_e) => {
+{undecodable}			}
+		}
+	}
+	Err("subscription closed".into())
+}
+"#,
+];
+
+/// Writes [`WATCH_ACCOUNT`], unless it is in the support module, and a
+/// `watch_<account>` function per account among `emitted`, streaming its
+/// decoded states over a websocket subscription.
+pub(super) fn define_watchers(
+    output: &mut impl Write,
+    emitted: &[&TypeDef],
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let accounts: Vec<_> = emitted
+        .iter()
+        .filter(|def| def.discriminator.is_some())
+        .collect();
+    if accounts.is_empty() {
+        return Ok(());
+    }
+    if !config.shared_support {
+        define_watch_helpers(output, config)?;
+    }
+    let cfg = cfg(&[Feature::Ws], config);
+    let vis = config.type_visibility.as_prefix();
+    for def in accounts {
+        output.write_fmt(format_args!(
+            "\n{}/// Streams the state of the `{}` account at `address`, see [`watch_account`].\n{}fn watch_{}(\n\tws_url: impl ToString,\n\taddress: Pubkey,\n\tcommitment: solana_sdk::commitment_config::CommitmentConfig,\n) -> impl futures::Stream<Item = (u64, {})> {{\n\twatch_account(ws_url, address, commitment, {:?})\n}}\n",
+            cfg,
+            def.name,
+            vis,
+            def.name.to_snake_case(),
+            config.naming.types.apply(&def.name),
+            def.discriminator.unwrap_or_default()
+        ))?;
+    }
+    Ok(())
+}
+
+/// Writes the [`WATCH_ACCOUNT`] helpers.
+pub(super) fn define_watch_helpers(
+    output: &mut impl Write,
+    config: &GeneratorConfig,
+) -> std::io::Result<()> {
+    let cfg = cfg(&[Feature::Ws], config);
+    for item in WATCH_ACCOUNT {
+        let item = item
+            .replace(
+                "{reconnect}",
+                &config.traced(
+                    "\t\t\t",
+                    "tracing::warn!(error = %_e, ?backoff, %address, \"account subscription failed, reconnecting\");",
+                ),
+            )
+            .replace(
+                "{skipped}",
+                &config.traced(
+                    "\t\t\t",
+                    "tracing::debug!(%address, \"account update of another type, skipped\");",
+                ),
+            )
+            .replace(
+                "{undecodable}",
+                &config.traced(
+                    "\t\t\t\t",
+                    "tracing::warn!(error = %_e, %address, \"account update not decoded, skipped\");",
+                ),
+            );
+        output.write_all(b"\n")?;
+        output.write_all(cfg.as_bytes())?;
+        output.write_all(item.as_bytes())?;
+    }
+    Ok(())
+}
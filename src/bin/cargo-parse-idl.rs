@@ -0,0 +1,3 @@
+// same entry point as parse_idl, installed under the name cargo looks up
+// for `cargo parse-idl`
+include!("parse_idl.rs");
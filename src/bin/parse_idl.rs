@@ -0,0 +1,1122 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use log::warn;
+use parse_idl::decode::{
+    decode_account, decode_data, decode_instruction_with, decode_named, decode_transaction,
+    transaction_events, DecodeOptions, Decoded, Encoding, ProgramSet,
+};
+use parse_idl::ir::discriminator;
+use parse_idl::rpc::{http_url, LogsSubscription, LookupTables, RpcClient, MAINNET_URL};
+use parse_idl::{
+    find_cargo_bindings_dir, find_idl_json, generate_crates, generate_files, generate_workspace,
+    load_template_dir, read_program, Artifact, Case, Decimals, FlagSet, GeneratorConfig, Layout,
+    OptionalAccounts, Sighash, Visibility,
+};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(version, about = "Generate rust bindings from anchor IDL files")]
+struct Cli {
+    /// Report progress and per-file parse times (RUST_LOG overrides)
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate bindings for IDL files
+    Generate(Box<GenerateArgs>),
+    /// Decode binary data against an IDL
+    #[command(subcommand)]
+    Decode(DecodeCommand),
+    /// Stream the decoded instructions of a program's transactions as they
+    /// are confirmed
+    Monitor(MonitorArgs),
+    /// Decode the instructions and events of a program's past transactions
+    /// in a slot range, oldest first
+    Backfill(BackfillArgs),
+    /// Print the instructions, accounts, events and errors of an IDL with
+    /// their discriminators
+    List(ListArgs),
+    /// Print the discriminator of an instruction
+    Sighash(SighashArgs),
+    /// Print the discriminator of an account or event
+    Disc(DiscArgs),
+}
+
+#[derive(Subcommand)]
+enum DecodeCommand {
+    /// Identify an instruction by its discriminator and decode its args
+    Ix(DecodeIxArgs),
+    /// Fetch a transaction and decode the instructions of the programs with
+    /// a loaded IDL
+    Tx(DecodeTxArgs),
+    /// Fetch an account and decode it with the IDL of its owner
+    Account(DecodeAccountArgs),
+    /// Decode data of any kind: an instruction, account or event identified
+    /// by its discriminator, or a value of --type
+    Data(DecodeDataArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Indented, for reading
+    Pretty,
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl Format {
+    fn print(self, item: &(impl fmt::Display + Serialize)) -> anyhow::Result<()> {
+        match self {
+            Format::Pretty => println!("{}", item),
+            Format::Json => println!("{}", serde_json::to_string_pretty(item)?),
+            Format::Jsonl => println!("{}", serde_json::to_string(item)?),
+        }
+        Ok(())
+    }
+
+    /// Prints `items` one after the other, as one array for `json`.
+    fn print_all<T: fmt::Display + Serialize>(self, items: &[T]) -> anyhow::Result<()> {
+        if let Format::Json = self {
+            println!("{}", serde_json::to_string_pretty(items)?);
+            return Ok(());
+        }
+        for item in items {
+            self.print(item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Flags shared by the commands decoding instructions.
+#[derive(clap::Args)]
+struct DecodeFlags {
+    /// Keep the args that match the IDL and the bytes after them instead
+    /// of failing on data the IDL doesn't describe
+    #[arg(long)]
+    lenient: bool,
+    /// Show how transactions changed the lamport and token balances of
+    /// the accounts of their instructions
+    #[arg(long)]
+    balances: bool,
+}
+
+impl DecodeFlags {
+    fn options(&self) -> DecodeOptions {
+        DecodeOptions {
+            lenient: self.lenient,
+            balances: self.balances,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct DecodeIxArgs {
+    /// IDL of the program the instruction belongs to
+    #[arg(long)]
+    idl: PathBuf,
+    /// The instruction data, discriminator included
+    #[arg(long)]
+    data: String,
+    /// Encoding of --data: base58, base64 or hex
+    #[arg(long, default_value = "base58")]
+    encoding: Encoding,
+    #[arg(long, value_enum, default_value = "pretty")]
+    format: Format,
+    #[command(flatten)]
+    decode: DecodeFlags,
+}
+
+impl DecodeIxArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let program = read_program(&self.idl)?;
+        let data = self.encoding.decode(&self.data)?;
+        let ix = decode_instruction_with(&program, &data, self.decode.options())?;
+        self.format.print(&Record {
+            program: Some(&program.name),
+            ..Record::new("instruction", ix)
+        })
+    }
+}
+
+#[derive(Parser)]
+struct DecodeDataArgs {
+    /// IDL of the program the data belongs to
+    #[arg(long)]
+    idl: PathBuf,
+    #[arg(long)]
+    data: String,
+    /// Encoding of --data: base58, base64 or hex
+    #[arg(long, default_value = "base58")]
+    encoding: Encoding,
+    /// Decode the data, without a discriminator, as this type of the IDL
+    #[arg(long = "type")]
+    ty: Option<String>,
+    #[arg(long, value_enum, default_value = "pretty")]
+    format: Format,
+    #[command(flatten)]
+    decode: DecodeFlags,
+}
+
+impl DecodeDataArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let program = read_program(&self.idl)?;
+        let data = self.encoding.decode(&self.data)?;
+        let options = self.decode.options();
+        if let Some(ty) = &self.ty {
+            let value = decode_named(&program, ty, &data, options)?;
+            return self.format.print(&Record {
+                program: Some(&program.name),
+                ..Record::new("value", value)
+            });
+        }
+        let decoded = decode_data(&program, &data, options)?;
+        let kind = decoded.kind();
+        self.format.print(&Record {
+            // accounts and events name their program themselves
+            program: matches!(decoded, Decoded::Instruction(_)).then_some(program.name.as_str()),
+            ..Record::new(kind, decoded)
+        })
+    }
+}
+
+#[derive(Parser)]
+struct GenerateArgs {
+    /// Read Anchor.toml and generate bindings for every workspace program
+    #[arg(long)]
+    workspace: bool,
+    /// Crate the workspace bindings are written into, relative to the workspace root
+    #[arg(long = "crate", default_value = "bindings")]
+    bindings_crate: PathBuf,
+    /// Write a cargo workspace into the output directory (default: ./bindings)
+    /// with one bindings crate per IDL and a common crate of the shared types
+    #[arg(long, conflicts_with = "workspace")]
+    crates: bool,
+    /// Crate directory whose Cargo.toml gets path dependencies on the
+    /// bindings crates of --workspace or --crates
+    #[arg(long)]
+    consumer: Option<PathBuf>,
+    /// Directory with struct.jinja, enum.jinja and/or instruction.jinja
+    /// templates overriding the built-in emitter
+    #[arg(long)]
+    templates: Option<PathBuf>,
+    /// Only emit these layers: types, decoder, args, events, errors
+    /// (the program id is always emitted)
+    #[arg(long, value_delimiter = ',')]
+    emit: Option<Vec<Artifact>>,
+    /// Only generate these instructions, and the accounts and types they
+    /// use (the events and errors are kept)
+    #[arg(long, value_delimiter = ',')]
+    only_ix: Vec<String>,
+    /// Directory the IDL files are read from
+    #[arg(long, env = "PARSE_IDL_INPUT_DIR", default_value = "./")]
+    input_dir: PathBuf,
+    /// Directory the bindings are written into (default: ./src, or the
+    /// `bindings` member when run as `cargo parse-idl`)
+    #[arg(long, env = "PARSE_IDL_OUT_DIR")]
+    out_dir: Option<PathBuf>,
+    /// Also generate these, comma separated, as their flags do: builders,
+    /// cli, client, localnet, event-decoder, event-stream, account-scans,
+    /// account-watchers, indexer, kafka-sink, http-service, grpc-service,
+    /// tracing, metrics
+    #[arg(long, env = "PARSE_IDL_FEATURES", value_delimiter = ',')]
+    features: Vec<GenerateFeature>,
+    /// flat: one <program>.rs file, nested: <program>/mod.rs with a
+    /// submodule per layer
+    #[arg(long, default_value = "flat")]
+    layout: Layout,
+    /// Write every program into this one file of the output directory, as
+    /// a `pub mod <program> { .. }` each
+    #[arg(long, conflicts_with = "crates")]
+    bundle: Option<String>,
+    /// Namespace of the instruction discriminators,
+    /// sha256("<namespace>:<name>")
+    #[arg(long, default_value = "global")]
+    sighash_namespace: String,
+    /// Casing the instruction names are hashed in: snake (anchor's handler
+    /// names), preserve (the IDL names as written) or upper-camel
+    #[arg(long, default_value = "snake")]
+    sighash_case: Case,
+    /// Backend producing the output: rust (the bindings), bench (a
+    /// criterion harness for them) or skeleton (an Anchor program
+    /// implementing the IDL)
+    #[arg(long, default_value = "rust")]
+    backend: String,
+    /// Rust path of the crate holding the bindings, for outputs living
+    /// outside of it (bench)
+    #[arg(long, default_value = "bindings")]
+    crate_path: String,
+    /// Also generate <Name>Ref<'a> structs borrowing bytes and strings from
+    /// the decoded buffer
+    #[arg(long)]
+    borrowed: bool,
+    /// Also generate <field>_iter functions decoding the elements of vector
+    /// fields lazily
+    #[arg(long)]
+    streaming: bool,
+    /// Give args structs an `extra` field and a decode function accepting
+    /// trailing bytes
+    #[arg(long)]
+    trailing_bytes: bool,
+    /// Give args structs and custom structs a new function taking the
+    /// required fields and with_<field> setters for the optional ones
+    #[arg(long)]
+    constructors: bool,
+    /// Also generate a ProgramEvent enum decoding the events from
+    /// transaction logs and emit_cpi inner instructions
+    #[arg(long)]
+    event_decoder: bool,
+    /// Also generate an event_stream function streaming the decoded events
+    /// over a reconnecting websocket subscription, implies --event-decoder
+    #[arg(long)]
+    event_stream: bool,
+    /// Also generate an <Name>Accounts struct and a <name>_ix builder per
+    /// instruction, implies --constructors
+    #[arg(long)]
+    builders: bool,
+    /// What builders pass for optional accounts left as None: program-id
+    /// (the program ID in their place) or omit (a shorter account list)
+    #[arg(long, default_value = "program-id")]
+    optional_accounts: OptionalAccounts,
+    /// Also generate a clap Cli with a subcommand per instruction sending
+    /// it, implies --builders
+    #[arg(long)]
+    cli: bool,
+    /// Also generate a <Module>Client sending and simulating the
+    /// instructions over RPC, with retries, and a fetch_all_<accounts>
+    /// function per account, implies --builders
+    #[arg(long)]
+    client: bool,
+    /// Also generate a Localnet starting a test validator with the program
+    /// and handing out funded payers and clients, implies --client
+    #[arg(long)]
+    localnet: bool,
+    /// Also generate async scan_<accounts> and fetch_<accounts>_batched
+    /// functions streaming the accounts, fetched in batches of
+    /// getMultipleAccounts with bounded concurrency, implies --builders
+    #[arg(long)]
+    account_scans: bool,
+    /// Also generate a watch_<account> function per account streaming its
+    /// decoded states over a reconnecting accountSubscribe subscription
+    #[arg(long)]
+    account_watchers: bool,
+    /// Also generate an sqlx Postgres indexer: the table migration, an
+    /// insert function per instruction, event and account, and run_indexer
+    /// backfilling and following the program's transactions
+    #[arg(long)]
+    indexer: bool,
+    /// Also generate a DecodedSink trait with a Kafka implementation and
+    /// functions publishing decoded instructions and events to it as JSON,
+    /// keyed by signature
+    #[arg(long)]
+    kafka_sink: bool,
+    /// Also generate a DecodeService, an axum router decoding instruction
+    /// data, accounts and transactions fetched over RPC into JSON, implies
+    /// --builders and --event-decoder
+    #[arg(long)]
+    http_service: bool,
+    /// Also generate a tonic gRPC decode service: the protobuf schema,
+    /// prost messages of the instructions, accounts and events and a
+    /// DecoderServer with Decode and DecodeStream, implies --event-decoder
+    #[arg(long)]
+    grpc_service: bool,
+    /// Instrument the generated builders, client and decoders with tracing
+    /// spans behind the tracing cargo feature
+    #[arg(long)]
+    tracing: bool,
+    /// Generate a DecodeMetrics trait the generated decoders report
+    /// counters and latencies to
+    #[arg(long)]
+    metrics: bool,
+    /// Gate generated code behind the client, serde, ws, cli, tracing,
+    /// localnet, indexer, kafka, http and grpc cargo features of the consuming
+    /// crate, which must declare them
+    #[arg(long)]
+    feature_gates: bool,
+    /// Serialize pubkeys as base58 strings, 128 bit integers as strings and
+    /// bytes as hex with serde, and tag enums adjacently
+    #[arg(long)]
+    human_readable_serde: bool,
+    /// Emit a prelude module per program re-exporting its args structs,
+    /// accounts, events, errors and program id, and one per crate with --crates
+    #[arg(long)]
+    prelude: bool,
+    /// Emit types defined identically by several IDLs once, in a common
+    /// module
+    #[arg(long)]
+    dedupe: bool,
+    /// Directory of auxiliary IDLs the types the generated IDLs refer to
+    /// but don't define are taken from, emitted in the common module
+    #[arg(long)]
+    include: Vec<PathBuf>,
+    /// Emit From/TryFrom conversions between the compatible types of
+    /// several IDLs in a conversions module, makes the fields pub(crate)
+    #[arg(long)]
+    conversions: bool,
+    /// Emit the helpers every program would otherwise repeat, such as
+    /// BindingsError and the send helpers, once in a support module
+    #[arg(long)]
+    shared_support: bool,
+    /// Rust path of the program's own crate, to generate TryFrom
+    /// conversions between its types and the generated ones
+    #[arg(long)]
+    program_crate: Option<String>,
+    /// Implement anchor_lang's Discriminator, Owner, AccountDeserialize and
+    /// AccountSerialize on the accounts, for anchor-client and Account<T>
+    #[arg(long)]
+    anchor_traits: bool,
+    /// Give the accounts a diff method listing the fields changed between
+    /// two states of an account
+    #[arg(long)]
+    account_diffs: bool,
+    /// Leave out the types no instruction, account or event reaches, which
+    /// are reported with --verbose either way
+    #[arg(long)]
+    prune_unreferenced: bool,
+    /// File with a banner, e.g. a license, written below the header of every
+    /// generated file: a minijinja template of program, version and timestamp
+    #[arg(long)]
+    banner: Option<PathBuf>,
+    /// Type an unsigned integer field as flags, a bitflags type:
+    /// FIELD=Name:FLAG=bit,FLAG=bit, e.g. status=OrderStatus:OPEN=1,REDUCE_ONLY=2
+    #[arg(long, value_parser = parse_flags)]
+    flags: Vec<(String, FlagSet)>,
+    /// Type the Pubkey of an IDL field or account as a newtype: NAME=Type,
+    /// e.g. mint=Mint
+    #[arg(long, value_parser = parse_newtype)]
+    pubkey_newtype: Vec<(String, String)>,
+    /// Patterns of the i64/u64 fields holding Unix timestamps, e.g.
+    /// '*_ts,*_timestamp', given chrono <field>_datetime accessors
+    #[arg(long, value_delimiter = ',')]
+    timestamps: Vec<String>,
+    /// Mark a u64 field as a token amount, given a <field>_ui accessor:
+    /// FIELD=DECIMALS, a number or the field holding them, e.g. amount=6 or
+    /// supply=decimals
+    #[arg(long, value_parser = parse_amount)]
+    amount: Vec<(String, Decimals)>,
+    /// Regenerate files even if their IDL and options are unchanged
+    #[arg(long)]
+    force: bool,
+}
+
+fn parse_amount(s: &str) -> Result<(String, Decimals), String> {
+    let (field, decimals) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=DECIMALS, got '{}'", s))?;
+    Ok((field.to_string(), decimals.parse()?))
+}
+
+fn parse_newtype(s: &str) -> Result<(String, String), String> {
+    let (name, newtype) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=Type, got '{}'", s))?;
+    Ok((name.to_string(), newtype.to_string()))
+}
+
+fn parse_flags(s: &str) -> Result<(String, FlagSet), String> {
+    let (field, flags) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=Name:FLAG=bit,..., got '{}'", s))?;
+    Ok((field.to_string(), flags.parse()?))
+}
+
+/// A generation flag enabled through `--features`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GenerateFeature {
+    Builders,
+    Cli,
+    Client,
+    Localnet,
+    EventDecoder,
+    EventStream,
+    AccountScans,
+    AccountWatchers,
+    Indexer,
+    KafkaSink,
+    HttpService,
+    GrpcService,
+    Tracing,
+    Metrics,
+}
+
+impl GenerateArgs {
+    /// Whether `--features` enables `feature`.
+    fn enables(&self, feature: GenerateFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    fn config(&self) -> parse_idl::Result<GeneratorConfig> {
+        let mut builder = GeneratorConfig::builder()
+            .force(self.force)
+            .layout(self.layout)
+            .sighash_namespace(&self.sighash_namespace)
+            .sighash_case(self.sighash_case)
+            .backend(&self.backend)
+            .crate_path(&self.crate_path)
+            .borrowed(self.borrowed)
+            .streaming(self.streaming)
+            .trailing_bytes(self.trailing_bytes)
+            .constructors(self.constructors)
+            .event_decoder(self.event_decoder || self.enables(GenerateFeature::EventDecoder))
+            .event_stream(self.event_stream || self.enables(GenerateFeature::EventStream))
+            .builders(self.builders || self.enables(GenerateFeature::Builders))
+            .optional_accounts(self.optional_accounts)
+            .cli(self.cli || self.enables(GenerateFeature::Cli))
+            .client(self.client || self.enables(GenerateFeature::Client))
+            .localnet(self.localnet || self.enables(GenerateFeature::Localnet))
+            .account_scans(self.account_scans || self.enables(GenerateFeature::AccountScans))
+            .account_watchers(
+                self.account_watchers || self.enables(GenerateFeature::AccountWatchers),
+            )
+            .indexer(self.indexer || self.enables(GenerateFeature::Indexer))
+            .kafka_sink(self.kafka_sink || self.enables(GenerateFeature::KafkaSink))
+            .http_service(self.http_service || self.enables(GenerateFeature::HttpService))
+            .grpc_service(self.grpc_service || self.enables(GenerateFeature::GrpcService))
+            .tracing(self.tracing || self.enables(GenerateFeature::Tracing))
+            .metrics(self.metrics || self.enables(GenerateFeature::Metrics))
+            .feature_gates(self.feature_gates)
+            .human_readable_serde(self.human_readable_serde)
+            .prelude(self.prelude)
+            .dedupe(self.dedupe)
+            .conversions(self.conversions)
+            .shared_support(self.shared_support)
+            .anchor_traits(self.anchor_traits)
+            .account_diffs(self.account_diffs)
+            .prune_unreferenced(self.prune_unreferenced);
+        if self.conversions {
+            builder = builder.field_visibility(Visibility::Crate);
+        }
+        for dir in self.include.iter() {
+            builder = builder.include(dir);
+        }
+        for (name, newtype) in self.pubkey_newtype.iter() {
+            builder = builder.pubkey_newtype(name, newtype);
+        }
+        for (field, decimals) in self.amount.iter() {
+            builder = builder.amount(field, decimals.clone());
+        }
+        for name in self.only_ix.iter() {
+            builder = builder.only_instruction(name);
+        }
+        for pattern in self.timestamps.iter() {
+            builder = builder.timestamp(pattern);
+        }
+        for (field, flags) in self.flags.iter() {
+            builder = builder.flags(field, &flags.name, flags.bits.iter().cloned());
+        }
+        if let Some(path) = &self.program_crate {
+            builder = builder.program_crate(path);
+        }
+        if let Some(file_name) = &self.bundle {
+            builder = builder.bundle(file_name);
+        }
+        if let Some(emit) = &self.emit {
+            let artifacts = emit.iter().copied().chain([Artifact::ProgramId]);
+            builder = builder.artifacts(artifacts);
+        }
+        if let Some(dir) = &self.templates {
+            for (kind, source) in load_template_dir(dir)? {
+                builder = builder.template(kind, source);
+            }
+        }
+        if let Some(dir) = &self.consumer {
+            builder = builder.consumer_crate(dir);
+        }
+        if let Some(path) = &self.banner {
+            builder = builder.banner(std::fs::read_to_string(path)?);
+        }
+        Ok(builder.build())
+    }
+}
+
+#[derive(Parser)]
+struct DecodeTxArgs {
+    signature: String,
+    /// IDLs of the programs to decode, default: the IDL files found in the
+    /// current directory. `PATH@SLOT` registers a version of a program
+    /// that is in effect from SLOT on
+    #[arg(long)]
+    idl: Vec<IdlArg>,
+    /// RPC endpoint
+    #[arg(long, env = "PARSE_IDL_RPC_URL", default_value = MAINNET_URL)]
+    url: String,
+    #[arg(long, value_enum, default_value = "pretty")]
+    format: Format,
+    #[command(flatten)]
+    decode: DecodeFlags,
+}
+
+impl DecodeTxArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let programs = load_programs(&self.idl)?;
+        let rpc = RpcClient::new(&self.url);
+        let mut tables = LookupTables::new(rpc.clone());
+        let tx = fetch_transaction(&rpc, &mut tables, &self.signature)?;
+        let instructions = decode_transaction(&programs, &tx, self.decode.options())?;
+        self.format.print_all(&tx_records(
+            "instruction",
+            &self.signature,
+            tx["slot"].as_u64(),
+            &instructions,
+        ))
+    }
+}
+
+#[derive(Parser)]
+struct DecodeAccountArgs {
+    address: String,
+    /// IDLs to match the account against, default: the IDL files found in
+    /// the current directory. Of several versions of a program, the newest
+    /// is used
+    #[arg(long)]
+    idl: Vec<IdlArg>,
+    /// RPC endpoint
+    #[arg(long, env = "PARSE_IDL_RPC_URL", default_value = MAINNET_URL)]
+    url: String,
+    #[arg(long, value_enum, default_value = "pretty")]
+    format: Format,
+}
+
+impl DecodeAccountArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let programs = load_programs(&self.idl)?;
+        let account = RpcClient::new(&self.url).get_account(&self.address)?;
+        // the owner's IDL if it is loaded, otherwise whichever IDL knows the
+        // discriminator
+        let owner = programs.get(&account.owner, None);
+        let decoded = match owner {
+            Some(program) => decode_account(program, &account.data)?,
+            None => programs
+                .at(None)
+                .into_iter()
+                .find_map(|p| decode_account(p, &account.data).ok())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "no IDL for the owner {} and no loaded IDL decodes the account",
+                        account.owner
+                    )
+                })?,
+        };
+        self.format.print(&Record {
+            address: Some(&self.address),
+            ..Record::new("account", decoded)
+        })
+    }
+}
+
+#[derive(Parser)]
+struct ListArgs {
+    /// IDL to list the items of
+    idl: PathBuf,
+}
+
+impl ListArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let program = read_program(&self.idl)?;
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        print_table(
+            "Instructions",
+            &["NAME", "DISCRIMINATOR", "ARGS", "ACCOUNTS"],
+            program.instructions.iter().map(|ix| {
+                vec![
+                    ix.name.clone(),
+                    hex(&ix.discriminator),
+                    ix.args.len().to_string(),
+                    ix.flat_accounts().len().to_string(),
+                ]
+            }),
+        );
+        let definitions: Vec<_> = program
+            .accounts
+            .iter()
+            .chain(program.types.iter())
+            .cloned()
+            .collect();
+        print_table(
+            "Accounts",
+            &["NAME", "DISCRIMINATOR", "SIZE"],
+            program.accounts.iter().map(|account| {
+                vec![
+                    account.name.clone(),
+                    hex(&account.discriminator.unwrap_or_default()),
+                    // the discriminator included, as allocated
+                    account
+                        .static_size(&definitions)
+                        .map_or_else(|| "variable".to_string(), |size| (8 + size).to_string()),
+                ]
+            }),
+        );
+        print_table(
+            "Events",
+            &["NAME", "DISCRIMINATOR", "FIELDS"],
+            program.events.iter().map(|event| {
+                vec![
+                    event.name.clone(),
+                    hex(&event.discriminator),
+                    event.fields.len().to_string(),
+                ]
+            }),
+        );
+        print_table(
+            "Errors",
+            &["CODE", "NAME", "MESSAGE"],
+            program.errors.iter().map(|error| {
+                vec![
+                    error.code.to_string(),
+                    error.name.clone(),
+                    error.msg.clone().unwrap_or_default(),
+                ]
+            }),
+        );
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+struct SighashArgs {
+    /// Name of the instruction, as the IDL writes it
+    name: String,
+    /// Namespace of the discriminator, sha256("<namespace>:<name>")
+    #[arg(long, default_value = "global")]
+    namespace: String,
+    /// Casing the name is hashed in: snake (anchor's handler names),
+    /// preserve (as written) or upper-camel
+    #[arg(long, default_value = "snake")]
+    case: Case,
+}
+
+impl SighashArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let sighash = Sighash {
+            namespace: self.namespace.clone(),
+            case: self.case,
+        };
+        print_discriminator(
+            &format!("{}:{}", sighash.namespace, sighash.case.apply(&self.name)),
+            sighash.hash(&self.name),
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DiscKind {
+    Account,
+    Event,
+}
+
+#[derive(Parser)]
+struct DiscArgs {
+    /// Kind of the item: account or event
+    #[arg(value_enum)]
+    kind: DiscKind,
+    /// Name of the type, as the IDL writes it
+    name: String,
+}
+
+impl DiscArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let namespace = match self.kind {
+            DiscKind::Account => "account",
+            DiscKind::Event => "event",
+        };
+        print_discriminator(
+            &format!("{}:{}", namespace, self.name),
+            discriminator(namespace, &self.name),
+        );
+        Ok(())
+    }
+}
+
+/// Prints a discriminator and what it hashes: in hex, base58 (as RPC
+/// memcmp filters take it) and as a rust array.
+fn print_discriminator(preimage: &str, discriminator: [u8; 8]) {
+    println!("preimage  {}", preimage);
+    println!(
+        "hex       {}",
+        discriminator
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+    println!("base58    {}", bs58::encode(discriminator).into_string());
+    println!("rust      {:?}", discriminator);
+}
+
+/// Prints `rows` under `title` as columns padded to their widest cell,
+/// `(none)` without rows.
+fn print_table(title: &str, headers: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+    let rows: Vec<_> = rows.collect();
+    println!("{}", title);
+    if rows.is_empty() {
+        println!("  (none)\n");
+        return;
+    }
+    let widths: Vec<_> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([headers[i].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let header: Vec<_> = headers.iter().map(|h| h.to_string()).collect();
+    for row in [header].iter().chain(rows.iter()) {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("  {}", cells.join("  ").trim_end());
+    }
+    println!();
+}
+
+#[derive(Parser)]
+struct MonitorArgs {
+    /// Address of the program to watch
+    #[arg(long)]
+    program: String,
+    /// IDLs of the programs to decode, default: the IDL files found in the
+    /// current directory. `PATH@SLOT` registers a version of a program
+    /// that is in effect from SLOT on
+    #[arg(long)]
+    idl: Vec<IdlArg>,
+    /// Websocket endpoint
+    #[arg(
+        long,
+        env = "PARSE_IDL_WS_URL",
+        default_value = "wss://api.mainnet-beta.solana.com"
+    )]
+    url: String,
+    /// HTTP endpoint the transactions are fetched from, default: derived
+    /// from --url
+    #[arg(long, env = "PARSE_IDL_RPC_URL")]
+    rpc_url: Option<String>,
+    #[arg(long, value_enum, default_value = "pretty")]
+    format: Format,
+    #[command(flatten)]
+    decode: DecodeFlags,
+}
+
+/// A decoded instruction, event or account with where it comes from, what
+/// every command prints. The JSON form is one flat object.
+#[derive(Serialize)]
+struct Record<'a, T> {
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<u64>,
+    /// Set for items not naming their program themselves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program: Option<&'a str>,
+    /// Address of a decoded account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<&'a str>,
+    #[serde(flatten)]
+    item: T,
+}
+
+impl<'a, T> Record<'a, T> {
+    fn new(kind: &'static str, item: T) -> Self {
+        Self {
+            kind,
+            signature: None,
+            slot: None,
+            program: None,
+            address: None,
+            item,
+        }
+    }
+
+    fn in_tx(self, signature: &'a str, slot: Option<u64>) -> Self {
+        Self {
+            signature: Some(signature),
+            slot,
+            ..self
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Record<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(signature) = self.signature {
+            write!(f, "{} ", signature)?;
+        }
+        if let Some(slot) = self.slot {
+            write!(f, "slot {} ", slot)?;
+        }
+        if let Some(address) = self.address {
+            write!(f, "{} ", address)?;
+        }
+        write!(f, "{}", self.item)
+    }
+}
+
+/// `items` of the transaction `signature` as records.
+fn tx_records<'a, T>(
+    kind: &'static str,
+    signature: &'a str,
+    slot: Option<u64>,
+    items: &'a [T],
+) -> Vec<Record<'a, &'a T>> {
+    items
+        .iter()
+        .map(|item| Record::new(kind, item).in_tx(signature, slot))
+        .collect()
+}
+
+impl MonitorArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let programs = load_programs(&self.idl)?;
+        if programs.get(&self.program, None).is_none() {
+            warn!(
+                "no loaded IDL for {}, its instructions are skipped",
+                self.program
+            );
+        }
+        let rpc = RpcClient::new(self.rpc_url.clone().unwrap_or_else(|| http_url(&self.url)));
+        let mut tables = LookupTables::new(rpc.clone());
+        let mut subscription = LogsSubscription::new(&self.url, &self.program)?;
+        loop {
+            let notification = subscription.recv()?;
+            // the transaction can take a moment to be served after its logs
+            let mut tx = fetch_transaction(&rpc, &mut tables, &notification.signature);
+            for _ in 0..5 {
+                if tx.is_ok() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(500));
+                tx = fetch_transaction(&rpc, &mut tables, &notification.signature);
+            }
+            let instructions =
+                match tx.and_then(|tx| decode_transaction(&programs, &tx, self.decode.options())) {
+                    Ok(instructions) => instructions,
+                    Err(e) => {
+                        warn!("{}: {}", notification.signature, e);
+                        continue;
+                    }
+                };
+            self.format.print_all(&tx_records(
+                "instruction",
+                &notification.signature,
+                Some(notification.slot),
+                &instructions,
+            ))?;
+        }
+    }
+}
+
+#[derive(Parser)]
+struct BackfillArgs {
+    /// Address of the program whose transactions are decoded
+    #[arg(long)]
+    program: String,
+    #[arg(long)]
+    from_slot: u64,
+    /// Last slot included, default: the newest confirmed transaction
+    #[arg(long)]
+    to_slot: Option<u64>,
+    /// IDLs of the programs to decode, default: the IDL files found in the
+    /// current directory. `PATH@SLOT` registers a version of a program
+    /// that is in effect from SLOT on
+    #[arg(long)]
+    idl: Vec<IdlArg>,
+    /// RPC endpoint
+    #[arg(long, env = "PARSE_IDL_RPC_URL", default_value = MAINNET_URL)]
+    url: String,
+    #[arg(long, value_enum, default_value = "jsonl")]
+    format: Format,
+    #[command(flatten)]
+    decode: DecodeFlags,
+}
+
+impl BackfillArgs {
+    fn run(&self) -> anyhow::Result<()> {
+        let programs = load_programs(&self.idl)?;
+        let rpc = RpcClient::new(&self.url);
+
+        // walk back from the newest signature until before the range
+        let mut signatures = vec![];
+        let mut before: Option<String> = None;
+        'pages: loop {
+            let page = rpc.get_signatures_for_address(&self.program, before.as_deref(), 1000)?;
+            let Some(last) = page.last() else {
+                break;
+            };
+            before = Some(last.signature.clone());
+            for info in page {
+                if info.slot < self.from_slot {
+                    break 'pages;
+                }
+                if self.to_slot.is_none_or(|to| info.slot <= to) {
+                    signatures.push(info);
+                }
+            }
+        }
+        log::info!("{} transactions to decode", signatures.len());
+
+        let mut tables = LookupTables::new(rpc.clone());
+        for info in signatures.iter().rev() {
+            let tx = match fetch_transaction(&rpc, &mut tables, &info.signature) {
+                Ok(tx) => tx,
+                Err(e) => {
+                    warn!("{}: {}", info.signature, e);
+                    continue;
+                }
+            };
+            match decode_transaction(&programs, &tx, self.decode.options()) {
+                Ok(instructions) => self.format.print_all(&tx_records(
+                    "instruction",
+                    &info.signature,
+                    Some(info.slot),
+                    &instructions,
+                ))?,
+                Err(e) => warn!("{}: {}", info.signature, e),
+            }
+            match transaction_events(&programs, &tx) {
+                Ok(events) => self.format.print_all(&tx_records(
+                    "event",
+                    &info.signature,
+                    Some(info.slot),
+                    &events,
+                ))?,
+                Err(e) => warn!("{}: {}", info.signature, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The transaction `signature` with the addresses it loads from lookup
+/// tables resolved.
+fn fetch_transaction(
+    rpc: &RpcClient,
+    tables: &mut LookupTables,
+    signature: &str,
+) -> parse_idl::Result<serde_json::Value> {
+    let mut tx = rpc.get_transaction(signature)?;
+    tables.resolve(&mut tx)?;
+    Ok(tx)
+}
+
+/// An `--idl` of the decode commands: `PATH` or `PATH@ACTIVATION_SLOT`.
+#[derive(Clone)]
+struct IdlArg {
+    path: PathBuf,
+    activation_slot: u64,
+}
+
+impl std::str::FromStr for IdlArg {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // a path that merely contains an `@` is kept whole
+        let versioned = s
+            .rsplit_once('@')
+            .and_then(|(path, slot)| Some((path, slot.parse().ok()?)));
+        Ok(match versioned {
+            Some((path, activation_slot)) => IdlArg {
+                path: path.into(),
+                activation_slot,
+            },
+            None => IdlArg {
+                path: s.into(),
+                activation_slot: 0,
+            },
+        })
+    }
+}
+
+/// The programs of `idls`, or of the IDL files in the current directory.
+fn load_programs(idls: &[IdlArg]) -> anyhow::Result<ProgramSet> {
+    let idls = if idls.is_empty() {
+        find_idl_json(Path::new("./"))?
+            .into_iter()
+            .map(|path| IdlArg {
+                path,
+                activation_slot: 0,
+            })
+            .collect()
+    } else {
+        idls.to_vec()
+    };
+    let mut programs = ProgramSet::new();
+    for idl in idls.iter() {
+        programs.add_version(read_program(&idl.path)?, idl.activation_slot);
+    }
+    if programs.is_empty() {
+        anyhow::bail!("no IDL given and none found in the current directory");
+    }
+    Ok(programs)
+}
+
+fn main() -> anyhow::Result<()> {
+    // cargo runs `cargo-parse-idl parse-idl <args>` for `cargo parse-idl <args>`
+    let mut args: Vec<_> = std::env::args_os().collect();
+    let as_cargo_subcommand = args.get(1).is_some_and(|a| a == "parse-idl");
+    if as_cargo_subcommand {
+        args.remove(1);
+    }
+    let cli = Cli::parse_from(args);
+    env_logger::Builder::new()
+        .filter_level(if cli.verbose {
+            log::LevelFilter::Info
+        } else {
+            log::LevelFilter::Warn
+        })
+        .parse_default_env()
+        .init();
+
+    let args = match cli.command {
+        Some(Command::Generate(args)) => *args,
+        Some(Command::Decode(DecodeCommand::Ix(args))) => return args.run(),
+        Some(Command::Decode(DecodeCommand::Tx(args))) => return args.run(),
+        Some(Command::Decode(DecodeCommand::Account(args))) => return args.run(),
+        Some(Command::Decode(DecodeCommand::Data(args))) => return args.run(),
+        Some(Command::Monitor(args)) => return args.run(),
+        Some(Command::Backfill(args)) => return args.run(),
+        Some(Command::List(args)) => return args.run(),
+        Some(Command::Sighash(args)) => return args.run(),
+        Some(Command::Disc(args)) => return args.run(),
+        None => GenerateArgs::parse_from(["generate"]),
+    };
+    let config = args.config()?;
+
+    if args.workspace {
+        generate_workspace(&std::env::current_dir()?, &args.bindings_crate, &config)?;
+    } else if args.crates {
+        let files = find_idl_json(&args.input_dir)?;
+        let root = args.out_dir.unwrap_or_else(|| PathBuf::from("./bindings"));
+        std::fs::create_dir_all(&root)?;
+        generate_crates(&files, &root, &config)?;
+    } else {
+        let files = find_idl_json(&args.input_dir)?;
+        let out_dir = if let Some(out_dir) = args.out_dir {
+            out_dir
+        } else if as_cargo_subcommand {
+            find_cargo_bindings_dir(&std::env::current_dir()?)?
+                .unwrap_or_else(|| PathBuf::from("./src"))
+        } else {
+            PathBuf::from("./src")
+        };
+        std::fs::create_dir_all(&out_dir)?;
+        generate_files(&files, &out_dir, &config)?;
+    }
+
+    Ok(())
+}
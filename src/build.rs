@@ -0,0 +1,50 @@
+//! Helpers for driving the generator from a build script.
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out_dir = std::env::var("OUT_DIR").unwrap();
+//!     parse_idl::build::generate("idls", &out_dir).unwrap();
+//! }
+//!
+//! // src/lib.rs
+//! pub mod whirlpool {
+//!     include!(concat!(env!("OUT_DIR"), "/whirlpool.rs"));
+//! }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::codegen::generate_files;
+use crate::config::GeneratorConfig;
+use crate::error::Result;
+use crate::input::find_idl_json;
+
+/// Generates bindings for every IDL in `idl_dir` into `out_dir` with the
+/// default config, see [`generate_with_config`].
+pub fn generate(idl_dir: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    generate_with_config(idl_dir, out_dir, &GeneratorConfig::default())
+}
+
+/// Generates bindings for every IDL in `idl_dir` into `out_dir` and returns
+/// the written modules.
+///
+/// Prints the `cargo:rerun-if-changed` lines for the directory and each IDL
+/// so cargo reruns the build script when an IDL is added or edited.
+pub fn generate_with_config(
+    idl_dir: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    config: &GeneratorConfig,
+) -> Result<Vec<PathBuf>> {
+    let idl_dir = idl_dir.as_ref();
+    let out_dir = out_dir.as_ref();
+
+    println!("cargo:rerun-if-changed={}", idl_dir.display());
+    let files = find_idl_json(idl_dir)?;
+    for file in files.iter() {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+
+    std::fs::create_dir_all(out_dir)?;
+    generate_files(&files, out_dir, config)
+}
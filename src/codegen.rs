@@ -0,0 +1,520 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anchor_idl::Idl;
+use heck::ToSnakeCase;
+
+use crate::backend::{support_module, BackendRegistry, GeneratedFile, SUPPORT_MODULE};
+use crate::config::{GeneratorConfig, Sighash};
+use crate::conversions;
+use crate::dedupe;
+use crate::error::{ParseIdlError, Result};
+use crate::frontend;
+use crate::input::{idl_module_name, is_json, lower_file, parse_idl_value};
+use crate::ir::{Program, TypeDef};
+use crate::manual;
+use crate::output::write_atomic;
+use crate::provenance::{banner, Provenance};
+
+/// Generates one module per IDL (or per entry of an IDL array) into `out_dir`
+/// and returns the paths of the written modules.
+pub fn generate_files(
+    files: &[PathBuf],
+    out_dir: &Path,
+    config: &GeneratorConfig,
+) -> Result<Vec<PathBuf>> {
+    write_files(&BackendRegistry::with_defaults(), files, out_dir, config)
+}
+
+/// An IDL to generate: label for errors, module name, raw IDL, IDL file and
+/// its bytes. The raw IDL of a json file of one IDL is left to
+/// [`lower_module`].
+pub(crate) type Module<'a> = (
+    PathBuf,
+    String,
+    Option<serde_json::Value>,
+    &'a PathBuf,
+    Vec<u8>,
+);
+
+/// Lowers the IDL of a module, see [`Module`].
+pub(crate) fn lower_module(module: &Module, sighash: &Sighash) -> Result<Program> {
+    let (label, _, raw, path, bytes) = module;
+    let program = match raw {
+        Some(raw) => frontend::lower_with(raw.clone(), sighash),
+        None => lower_file(path, bytes, sighash),
+    };
+    program.map_err(|e| e.in_file(label))
+}
+
+/// Reads `files`, one module per IDL or per entry of an IDL array.
+pub(crate) fn read_modules(files: &[PathBuf]) -> Result<Vec<Module<'_>>> {
+    let mut modules = vec![];
+    for fullpath in files {
+        let file_name = fullpath
+            .file_stem()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let bytes = std::fs::read(fullpath)?;
+        let single = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+        if is_json(fullpath) && single {
+            modules.push((fullpath.clone(), file_name, None, fullpath, bytes));
+            continue;
+        }
+        let raw = parse_idl_value(fullpath, &bytes)?;
+        match raw {
+            // registry dumps ship many IDLs in one file
+            serde_json::Value::Array(entries) => {
+                for entry in entries {
+                    let Some(name) = idl_module_name(&entry) else {
+                        return Err(ParseIdlError::MissingName {
+                            path: fullpath.clone(),
+                        });
+                    };
+                    let label = PathBuf::from(format!("{} ({})", fullpath.display(), name));
+                    modules.push((label, name, Some(entry), fullpath, bytes.clone()));
+                }
+            }
+            raw => modules.push((fullpath.clone(), file_name, Some(raw), fullpath, bytes)),
+        }
+    }
+    Ok(modules)
+}
+
+/// The custom types of the common module: the ones `programs` share with
+/// [`GeneratorConfig::dedupe`], then the ones imported from
+/// [`GeneratorConfig::includes`].
+pub(crate) fn common_types(programs: &[Program], config: &GeneratorConfig) -> Result<Vec<TypeDef>> {
+    let mut shared = if config.dedupe {
+        dedupe::shared_types(programs)
+    } else {
+        vec![]
+    };
+    if !config.includes.is_empty() {
+        let included = dedupe::included_types(config)?;
+        dedupe::add_imported(&mut shared, dedupe::imported_types(programs, &included));
+    }
+    Ok(shared)
+}
+
+pub(crate) fn write_files(
+    registry: &BackendRegistry,
+    files: &[PathBuf],
+    out_dir: &Path,
+    config: &GeneratorConfig,
+) -> Result<Vec<PathBuf>> {
+    let modules = read_modules(files)?;
+    let mut bundle = config.bundle.as_ref().map(|_| Bundle::default());
+    let (config, support) = write_support(&mut bundle, out_dir, config)?;
+    let config = &config;
+
+    if !config.dedupe && !config.conversions && config.includes.is_empty() {
+        let mut written: Vec<_> = support.into_iter().collect();
+        for module in modules.iter() {
+            let (label, name, _, source, bytes) = module;
+            let provenance = Provenance::new(source, bytes);
+            let program = lower_module(module, &config.sighash)?;
+            written.extend(
+                emit(
+                    registry,
+                    &mut bundle,
+                    &program,
+                    name,
+                    out_dir,
+                    config,
+                    Some(&provenance),
+                )
+                .map_err(|e| e.in_file(label))?,
+            );
+        }
+        written.extend(write_bundle(bundle, out_dir, config)?);
+        return Ok(written);
+    }
+
+    let mut programs = vec![];
+    for module in modules.iter() {
+        programs.push(lower_module(module, &config.sighash)?);
+    }
+    let shared = common_types(&programs, config)?;
+
+    let mut written: Vec<_> = support.into_iter().collect();
+    if !shared.is_empty() {
+        let common = dedupe::common_program(&shared);
+        let signature = serde_json::to_vec(&common.types)?;
+        let provenance = Provenance::new(Path::new(dedupe::COMMON_MODULE), &signature);
+        written.extend(emit(
+            registry,
+            &mut bundle,
+            &common,
+            dedupe::COMMON_MODULE,
+            out_dir,
+            &dedupe::common_config(config),
+            Some(&provenance),
+        )?);
+    }
+    // (module name, program, config) of every generated program
+    let mut generated = vec![];
+    for ((label, name, _, source, mut bytes), mut program) in modules.into_iter().zip(programs) {
+        let common = format!("super::{}", dedupe::COMMON_MODULE);
+        let config = dedupe::program_config(config, &mut program, &shared, &common);
+        // a change of the shared set changes the module as well
+        bytes.extend(shared.iter().flat_map(|t| t.name.bytes()));
+        let provenance = Provenance::new(source, &bytes);
+        written.extend(
+            emit(
+                registry,
+                &mut bundle,
+                &program,
+                &name,
+                out_dir,
+                &config,
+                Some(&provenance),
+            )
+            .map_err(|e| e.in_file(label))?,
+        );
+        generated.push((name, program, config));
+    }
+
+    if config.conversions {
+        let generated: Vec<_> = generated
+            .iter()
+            .map(|(module, program, config)| conversions::Generated {
+                module,
+                program,
+                config,
+            })
+            .collect();
+        let contents = conversions::conversions(&generated);
+        if let Some(bundle) = bundle.as_mut().filter(|_| !contents.is_empty()) {
+            bundle.add_file(conversions::CONVERSIONS_MODULE, &contents)?;
+        } else if !contents.is_empty() {
+            let path = out_dir.join(format!("{}.rs", conversions::CONVERSIONS_MODULE));
+            let provenance = Provenance::new(Path::new(conversions::CONVERSIONS_MODULE), &contents)
+                .with_options(config);
+            if config.force || !provenance.is_current(&path) {
+                let mut file = provenance.header().into_bytes();
+                file.extend(banner(config, conversions::CONVERSIONS_MODULE, "")?.into_bytes());
+                file.extend(contents);
+                write_atomic(&path, &file)?;
+            }
+            written.push(path);
+        }
+    }
+    written.extend(write_bundle(bundle, out_dir, config)?);
+    Ok(written)
+}
+
+/// Writes the support module of [`GeneratorConfig::shared_support`] into
+/// `out_dir`, or adds it to the `bundle`, and returns the config of the
+/// modules, which re-export it, along with its path when written. Without
+/// helpers to share there is no support module.
+fn write_support(
+    bundle: &mut Option<Bundle>,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+) -> Result<(GeneratorConfig, Option<PathBuf>)> {
+    let mut config = config.clone();
+    if !config.shared_support {
+        return Ok((config, None));
+    }
+    let contents = support_module(&config)?;
+    if contents.is_empty() {
+        return Ok((config, None));
+    }
+    config
+        .reexports
+        .push(format!("super::{}::*", SUPPORT_MODULE));
+    if let Some(bundle) = bundle {
+        bundle.add_file(SUPPORT_MODULE, &contents)?;
+        return Ok((config, None));
+    }
+    let path = out_dir.join(format!("{}.rs", SUPPORT_MODULE));
+    let provenance = Provenance::new(Path::new(SUPPORT_MODULE), &contents).with_options(&config);
+    if config.force || !provenance.is_current(&path) {
+        let mut file = provenance.header().into_bytes();
+        file.extend(banner(&config, SUPPORT_MODULE, "")?.into_bytes());
+        file.extend(contents);
+        std::fs::create_dir_all(out_dir)?;
+        write_atomic(&path, &file)?;
+    }
+    Ok((config, Some(path)))
+}
+
+/// Writes the files of `program` into `out_dir` and returns the path of its
+/// module, or adds it to the `bundle` of [`GeneratorConfig::bundle`].
+fn emit(
+    registry: &BackendRegistry,
+    bundle: &mut Option<Bundle>,
+    program: &Program,
+    module_name: &str,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+    provenance: Option<&Provenance>,
+) -> Result<Option<PathBuf>> {
+    match bundle {
+        Some(bundle) => {
+            bundle.add(registry, program, module_name, config, provenance)?;
+            Ok(None)
+        }
+        None => {
+            write_program(registry, program, module_name, out_dir, config, provenance).map(Some)
+        }
+    }
+}
+
+/// The modules of [`GeneratorConfig::bundle`], collected as they are
+/// generated.
+#[derive(Default)]
+struct Bundle {
+    modules: String,
+    /// The IDL digests of the modules, the provenance of the bundle hashes
+    /// them.
+    digests: String,
+}
+
+impl Bundle {
+    fn add(
+        &mut self,
+        registry: &BackendRegistry,
+        program: &Program,
+        module_name: &str,
+        config: &GeneratorConfig,
+        provenance: Option<&Provenance>,
+    ) -> Result<()> {
+        let files = registry.generate(program, module_name, config)?;
+        self.modules.push_str(&inline_module(module_name, files)?);
+        if let Some(provenance) = provenance {
+            self.digests.push_str(&provenance.idl_sha256);
+        }
+        Ok(())
+    }
+
+    /// Adds the single file module `module_name`, such as the conversions.
+    fn add_file(&mut self, module_name: &str, contents: &[u8]) -> Result<()> {
+        self.modules.push_str(&format!(
+            "\npub mod {} {{\n{}}}\n",
+            module_name,
+            String::from_utf8(contents.to_vec())?
+        ));
+        self.digests
+            .push_str(&Provenance::new(Path::new(module_name), contents).idl_sha256);
+        Ok(())
+    }
+}
+
+/// `pub mod <module_name> { .. }` of the files a backend generated for a
+/// module: the module file, with the submodules it declares inlined in place
+/// of their `pub mod <name>;`.
+fn inline_module(module_name: &str, mut files: Vec<GeneratedFile>) -> Result<String> {
+    if files.is_empty() {
+        return Err(ParseIdlError::NoOutput);
+    }
+    let module = files.remove(0);
+    let dir = module.path.parent().unwrap_or(Path::new(""));
+    let mut contents = String::new();
+    for line in module.contents.lines() {
+        let declared = line
+            .strip_prefix("pub mod ")
+            .and_then(|l| l.strip_suffix(';'))
+            .and_then(|name| {
+                let path = dir.join(format!("{}.rs", name));
+                Some((name, files.iter().position(|f| f.path == path)?))
+            });
+        match declared {
+            Some((name, i)) => {
+                let submodule = files.remove(i);
+                contents.push_str(&format!("pub mod {} {{\n{}}}\n", name, submodule.contents));
+            }
+            None => {
+                contents.push_str(line);
+                contents.push('\n');
+            }
+        }
+    }
+    if let Some(file) = files.first() {
+        return Err(ParseIdlError::NotBundled(file.path.clone()));
+    }
+    Ok(format!("\npub mod {} {{\n{}}}\n", module_name, contents))
+}
+
+/// Writes the `bundle` into its file in `out_dir`, unless it is current,
+/// and returns its path.
+fn write_bundle(
+    bundle: Option<Bundle>,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+) -> Result<Option<PathBuf>> {
+    let (Some(bundle), Some(file_name)) = (bundle, &config.bundle) else {
+        return Ok(None);
+    };
+    let path = out_dir.join(file_name);
+    let provenance =
+        Provenance::new(Path::new(file_name), bundle.digests.as_bytes()).with_options(config);
+    if !config.force && provenance.is_current(&path) {
+        return Ok(Some(path));
+    }
+    let mut contents = provenance.header();
+    let module_name = file_name.strip_suffix(".rs").unwrap_or(file_name);
+    contents.push_str(&banner(config, module_name, "")?);
+    contents.push_str(bundle.modules.trim_start());
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        contents = manual::carry_over(&existing, contents);
+    }
+    std::fs::create_dir_all(out_dir)?;
+    write_atomic(&path, contents.as_bytes())?;
+    Ok(Some(path))
+}
+
+/// Generates `<out_dir>/<module_name>.rs` from a single raw IDL value and
+/// returns its path.
+///
+/// With a `provenance` every file starts with its header, and nothing is
+/// written when the existing module was generated from the same IDL with
+/// the same options by this version (unless [`GeneratorConfig::force`] is set). Manual regions of
+/// the files being replaced are kept.
+pub fn generate_module(
+    raw: serde_json::Value,
+    module_name: &str,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+    provenance: Option<&Provenance>,
+) -> Result<PathBuf> {
+    write_module(
+        &BackendRegistry::with_defaults(),
+        raw,
+        module_name,
+        out_dir,
+        config,
+        provenance,
+    )
+}
+
+pub(crate) fn write_module(
+    registry: &BackendRegistry,
+    raw: serde_json::Value,
+    module_name: &str,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+    provenance: Option<&Provenance>,
+) -> Result<PathBuf> {
+    let program = frontend::lower_with(raw, &config.sighash)?;
+    let config = without_support(config);
+    write_program(
+        registry,
+        &program,
+        module_name,
+        out_dir,
+        &config,
+        provenance,
+    )
+}
+
+/// `config` for a module written without a support module next to it,
+/// which keeps its own helpers.
+pub(crate) fn without_support(config: &GeneratorConfig) -> GeneratorConfig {
+    GeneratorConfig {
+        shared_support: false,
+        ..config.clone()
+    }
+}
+
+pub(crate) fn write_program(
+    registry: &BackendRegistry,
+    program: &Program,
+    module_name: &str,
+    out_dir: &Path,
+    config: &GeneratorConfig,
+    provenance: Option<&Provenance>,
+) -> Result<PathBuf> {
+    let files = registry.generate(program, module_name, config)?;
+    let provenance = provenance.map(|p| p.clone().with_options(config));
+    let provenance = provenance.as_ref();
+
+    if let (Some(provenance), Some(module)) = (provenance, files.first()) {
+        let path = out_dir.join(&module.path);
+        if !config.force && provenance.is_current(&path) {
+            return Ok(path);
+        }
+    }
+
+    let banner = banner(config, &program.name, &program.version)?;
+    let mut written = vec![];
+    for file in files {
+        let path = out_dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut contents = file.contents;
+        contents.insert_str(0, &banner);
+        if let Some(provenance) = provenance {
+            contents.insert_str(0, &provenance.header());
+        }
+        if let Ok(existing) = std::fs::read_to_string(&path) {
+            contents = manual::carry_over(&existing, contents);
+        }
+        write_atomic(&path, contents.as_bytes())?;
+        written.push(path);
+    }
+    // the first artifact is the module itself
+    written.into_iter().next().ok_or(ParseIdlError::NoOutput)
+}
+
+/// Generates the bindings of a raw JSON IDL.
+pub fn generate_from_json(json: &[u8], config: &GeneratorConfig) -> Result<String> {
+    generate_program(&frontend::lower_slice(json, &config.sighash)?, config)
+}
+
+/// Generates the bindings of an IDL and returns them as source, the
+/// artifacts of multi-file backends are concatenated.
+pub fn generate_from_idl(idl: &Idl, config: &GeneratorConfig) -> Result<String> {
+    generate_program(&frontend::legacy::lower_with(idl, &config.sighash)?, config)
+}
+
+/// Generates the bindings of an already lowered program.
+pub fn generate_program(program: &Program, config: &GeneratorConfig) -> Result<String> {
+    program_source(&BackendRegistry::with_defaults(), program, config)
+}
+
+pub(crate) fn program_source(
+    registry: &BackendRegistry,
+    program: &Program,
+    config: &GeneratorConfig,
+) -> Result<String> {
+    let config = without_support(config);
+    let files = registry.generate(program, &program.name.to_snake_case(), &config)?;
+    Ok(banner(&config, &program.name, &program.version)? + &join_files(files))
+}
+
+/// Generates the bindings of an IDL into any writer: a file, an in-memory
+/// buffer, stdout...
+pub fn generate_to_writer(
+    idl: &Idl,
+    config: &GeneratorConfig,
+    output: &mut impl Write,
+) -> Result<()> {
+    output.write_all(generate_from_idl(idl, config)?.as_bytes())?;
+    Ok(())
+}
+
+fn join_files(files: Vec<GeneratedFile>) -> String {
+    files
+        .into_iter()
+        .map(|f| f.contents)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Generates the bindings of an IDL as a token stream, ready to be composed
+/// with `quote!` based code.
+pub fn generate_tokens(idl: &Idl, config: &GeneratorConfig) -> Result<proc_macro2::TokenStream> {
+    let code = generate_from_idl(idl, config)?;
+    code.parse()
+        .map_err(|e: proc_macro2::LexError| ParseIdlError::Tokenize(e.to_string()))
+}
+
+/// Anchor instruction discriminator: first 8 bytes of `sha256("global:<name>")`.
+/// See [`Sighash`] for other namespaces and casings.
+pub fn build_sighash(fname: &str) -> [u8; 8] {
+    Sighash::default().hash(fname)
+}
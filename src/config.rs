@@ -0,0 +1,1105 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use heck::{ToSnakeCase, ToUpperCamelCase};
+
+use crate::ir::Type;
+use crate::template::TemplateKind;
+
+/// Visibility of generated items and fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Private,
+    Crate,
+    Public,
+}
+
+impl Visibility {
+    /// Prefix written in front of the item, including the trailing space.
+    pub fn as_prefix(&self) -> &'static str {
+        match self {
+            Visibility::Private => "",
+            Visibility::Crate => "pub(crate) ",
+            Visibility::Public => "pub ",
+        }
+    }
+}
+
+/// How IDL names are turned into rust identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// Keep the name exactly as written in the IDL.
+    Preserve,
+    Snake,
+    UpperCamel,
+}
+
+impl std::str::FromStr for Case {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(Case::Preserve),
+            "snake" => Ok(Case::Snake),
+            "upper-camel" => Ok(Case::UpperCamel),
+            _ => Err(format!(
+                "unknown case '{}', expected one of preserve, snake, upper-camel",
+                s
+            )),
+        }
+    }
+}
+
+impl Case {
+    pub fn apply(&self, name: &str) -> String {
+        match self {
+            Case::Preserve => name.to_string(),
+            Case::Snake => name.to_snake_case(),
+            Case::UpperCamel => name.to_upper_camel_case(),
+        }
+    }
+}
+
+/// How instruction discriminators are computed from the IDL names: the first
+/// 8 bytes of `sha256("<namespace>:<name>")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sighash {
+    /// `global` for anchor's instruction handlers.
+    pub namespace: String,
+    /// Anchor hashes the snake case name of the handler, which legacy IDLs
+    /// write in camel case. `Preserve` hashes the IDL name as written, for
+    /// IDLs keeping the handler's own casing.
+    pub case: Case,
+}
+
+impl Default for Sighash {
+    fn default() -> Self {
+        Self {
+            namespace: "global".to_string(),
+            case: Case::Snake,
+        }
+    }
+}
+
+impl Sighash {
+    /// Discriminator of the instruction named `name` in the IDL.
+    pub fn hash(&self, name: &str) -> [u8; 8] {
+        crate::ir::discriminator(&self.namespace, &self.case.apply(name))
+    }
+}
+
+/// Named bits of an unsigned integer field, generated as a `bitflags` type,
+/// see [`GeneratorConfig::flags`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlagSet {
+    /// Name of the generated type.
+    pub name: String,
+    /// Flag names and the index of their bit, `("REDUCE_ONLY", 2)` for
+    /// `0b100`.
+    pub bits: Vec<(String, u32)>,
+}
+
+impl std::str::FromStr for FlagSet {
+    type Err = String;
+
+    /// Parses `Name:FLAG=bit,FLAG=bit`, e.g. `OrderStatus:OPEN=1,REDUCE_ONLY=2`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, bits)) = s.split_once(':') else {
+            return Err(format!("expected Name:FLAG=bit,..., got '{}'", s));
+        };
+        let bits = bits
+            .split(',')
+            .map(|bit| {
+                let (flag, index) = bit
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected FLAG=bit, got '{}'", bit))?;
+                let index = index
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("bit of {} is not a number: '{}'", flag, index))?;
+                Ok((flag.trim().to_string(), index))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            name: name.trim().to_string(),
+            bits,
+        })
+    }
+}
+
+/// Where the decimals of an amount field come from, see
+/// [`GeneratorConfig::amounts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decimals {
+    Constant(u8),
+    /// The IDL name of an integer field of the same struct, e.g. the
+    /// `decimals` of a mint.
+    Field(String),
+}
+
+impl std::str::FromStr for Decimals {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(decimals) => Ok(Decimals::Constant(decimals)),
+            Err(_) if !s.is_empty() && !s.starts_with(|c: char| c.is_ascii_digit()) => {
+                Ok(Decimals::Field(s.to_string()))
+            }
+            Err(_) => Err(format!(
+                "expected a number of decimals or a field name, got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// Naming conventions of the generated code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    /// Names of the IDL `accounts` and `types` definitions.
+    pub types: Case,
+    /// Names of the per-instruction args structs.
+    pub instructions: Case,
+    /// Struct field names.
+    pub fields: Case,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Self {
+            types: Case::Preserve,
+            instructions: Case::UpperCamel,
+            fields: Case::Snake,
+        }
+    }
+}
+
+/// Pieces of the bindings that can be emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    /// The `ID` constant holding the program address.
+    ProgramId,
+    /// The instruction discriminator table.
+    Discriminators,
+    /// One args struct per instruction taking arguments.
+    InstructionArgs,
+    /// Account and custom type definitions referenced by the emitted code,
+    /// or all of them when the instruction args are not emitted.
+    Types,
+    /// One struct per event.
+    Events,
+    /// The `ErrorCode` enum of the program's custom errors.
+    Errors,
+}
+
+impl Artifact {
+    pub const ALL: [Artifact; 6] = [
+        Artifact::ProgramId,
+        Artifact::Discriminators,
+        Artifact::InstructionArgs,
+        Artifact::Types,
+        Artifact::Events,
+        Artifact::Errors,
+    ];
+
+    /// Name of the artifact on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Artifact::ProgramId => "program-id",
+            Artifact::Discriminators => "decoder",
+            Artifact::InstructionArgs => "args",
+            Artifact::Types => "types",
+            Artifact::Events => "events",
+            Artifact::Errors => "errors",
+        }
+    }
+}
+
+impl std::str::FromStr for Artifact {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Artifact::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Artifact::ALL.iter().map(|a| a.name()).collect();
+                format!(
+                    "unknown artifact '{}', expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Cargo features of the consuming crate gating generated code with heavy
+/// dependencies, see [`GeneratorConfig::feature_gates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// RPC client code, pulling in solana-client.
+    Client,
+    /// `serde::Serialize` and `serde::Deserialize` impls of the generated
+    /// types.
+    Serde,
+    /// Event and account subscriptions and the account scans, pulling in
+    /// tokio and futures.
+    Ws,
+    /// The instruction CLI, pulling in clap, solana-client and solana-sdk.
+    Cli,
+    /// `tracing` spans and events, see [`GeneratorConfig::tracing`].
+    Tracing,
+    /// The `Localnet` of integration tests, see [`GeneratorConfig::localnet`].
+    /// Uses the client, so it should enable the `client` feature.
+    Localnet,
+    /// The Postgres indexer, see [`GeneratorConfig::indexer`], pulling in
+    /// sqlx, tokio, futures, solana-client and solana-transaction-status.
+    Indexer,
+    /// The Kafka sink, see [`GeneratorConfig::kafka_sink`], pulling in
+    /// rdkafka and serde_json. It serializes the generated types, so it
+    /// should enable the `serde` feature.
+    Kafka,
+    /// The decode service, see [`GeneratorConfig::http_service`], pulling
+    /// in axum, tokio, serde_json, base64, solana-client and
+    /// solana-transaction-status. It serializes the generated types, so it
+    /// should enable the `serde` feature.
+    Http,
+    /// The gRPC decode service, see [`GeneratorConfig::grpc_service`],
+    /// pulling in tonic, prost and futures.
+    Grpc,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 10] = [
+        Feature::Client,
+        Feature::Serde,
+        Feature::Ws,
+        Feature::Cli,
+        Feature::Tracing,
+        Feature::Localnet,
+        Feature::Indexer,
+        Feature::Kafka,
+        Feature::Http,
+        Feature::Grpc,
+    ];
+
+    /// Name of the cargo feature.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Client => "client",
+            Feature::Serde => "serde",
+            Feature::Ws => "ws",
+            Feature::Cli => "cli",
+            Feature::Tracing => "tracing",
+            Feature::Localnet => "localnet",
+            Feature::Indexer => "indexer",
+            Feature::Kafka => "kafka",
+            Feature::Http => "http",
+            Feature::Grpc => "grpc",
+        }
+    }
+
+    /// `cfg` predicate of the feature, e.g. `feature = "client"`.
+    pub(crate) fn predicate(&self) -> String {
+        format!("feature = \"{}\"", self.name())
+    }
+}
+
+/// A user callback mapping an IDL type to a rust type, consulted before the
+/// type overrides and the built-in mapping.
+///
+/// It gets the type and the name of the field it belongs to (also for the
+/// inner types of `Option`, `Vec` and arrays, `None` outside of fields) and
+/// returns `None` to fall through to the next hook.
+#[derive(Clone)]
+pub struct TypeHook(Arc<TypeHookFn>);
+
+type TypeHookFn = dyn Fn(&Type, Option<&str>) -> Option<String> + Send + Sync;
+
+impl TypeHook {
+    pub fn new<F>(hook: F) -> Self
+    where
+        F: Fn(&Type, Option<&str>) -> Option<String> + Send + Sync + 'static,
+    {
+        Self(Arc::new(hook))
+    }
+
+    pub fn map(&self, ty: &Type, field: Option<&str>) -> Option<String> {
+        (self.0)(ty, field)
+    }
+}
+
+impl fmt::Debug for TypeHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TypeHook(..)")
+    }
+}
+
+/// How the files of a program are laid out in the output directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// A single `<program>.rs` module.
+    #[default]
+    Flat,
+    /// `<program>/mod.rs` with one submodule per layer: `instructions.rs`,
+    /// `events.rs`, `errors.rs` and `types.rs`.
+    Nested,
+}
+
+impl std::str::FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Layout::Flat),
+            "nested" => Ok(Layout::Nested),
+            _ => Err(format!(
+                "unknown layout '{}', expected one of flat, nested",
+                s
+            )),
+        }
+    }
+}
+
+/// How builders pass the optional accounts left as `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OptionalAccounts {
+    /// The program ID in their place, as anchor programs expect.
+    #[default]
+    ProgramId,
+    /// Left out of the account list, for programs taking a shorter list.
+    /// Only trailing optional accounts can be left out this way.
+    Omit,
+}
+
+impl std::str::FromStr for OptionalAccounts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "program-id" => Ok(OptionalAccounts::ProgramId),
+            "omit" => Ok(OptionalAccounts::Omit),
+            _ => Err(format!(
+                "unknown optional accounts convention '{}', expected one of program-id, omit",
+                s
+            )),
+        }
+    }
+}
+
+/// Options controlling the generated bindings.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    /// `use` lines written at the top of every generated file.
+    pub imports: Vec<String>,
+    /// Derives put on every generated struct and enum.
+    pub derives: Vec<String>,
+    pub type_visibility: Visibility,
+    pub field_visibility: Visibility,
+    pub naming: Naming,
+    /// How the instruction discriminators are computed when lowering the IDL.
+    pub sighash: Sighash,
+    /// IDL defined type name -> rust type used in its place. Overridden
+    /// types are not generated.
+    pub type_overrides: HashMap<String, String>,
+    /// Hooks tried in order, the first one returning a type wins. Types
+    /// mapped by a hook are not generated.
+    pub type_hooks: Vec<TypeHook>,
+    /// IDL field name -> the flags its unsigned integer holds. The fields
+    /// get a `bitflags` type with Borsh impls encoding it as the integer,
+    /// which needs bitflags 2 in the consuming crate (with its `serde`
+    /// feature for the serde derives).
+    pub flags: HashMap<String, FlagSet>,
+    /// IDL field or instruction account name -> newtype of its `Pubkey`,
+    /// e.g. `mint` -> `Mint`, in the fields and the builders' accounts
+    /// structs. The newtypes are encoded by Borsh as the key, dereference
+    /// to it and convert from and to it.
+    pub pubkey_newtypes: HashMap<String, String>,
+    /// Patterns of the `i64` and `u64` fields holding Unix timestamps, in
+    /// snake case with a leading or trailing `*`, e.g. `*_ts`. Their structs
+    /// get a `<field>_datetime` accessor returning a chrono
+    /// `DateTime<Utc>`, which needs chrono in the consuming crate.
+    pub timestamps: Vec<String>,
+    /// IDL field name -> decimals of the token amount it holds. Their
+    /// structs get a `<field>_ui` accessor returning it as an `f64` in UI
+    /// units.
+    pub amounts: HashMap<String, Decimals>,
+    pub artifacts: HashSet<Artifact>,
+    /// Generate only these instructions, by IDL or snake case name, and
+    /// the accounts and types they reach. The events and errors are kept,
+    /// as the IDL doesn't tell which instructions emit or return them.
+    pub only_instructions: Vec<String>,
+    /// Name of the registered backend producing the output.
+    pub backend: String,
+    /// User templates replacing the built-in emitter for their kind of item.
+    pub templates: HashMap<TemplateKind, String>,
+    /// Rewrite generated files even when their provenance header says they
+    /// are up to date.
+    pub force: bool,
+    pub layout: Layout,
+    /// Name of a single file in the output directory all the programs are
+    /// written into, as a `pub mod <program> { .. }` each with its
+    /// submodules inlined, instead of a file or directory per program.
+    pub bundle: Option<String>,
+    /// Rust path of the crate the bindings are compiled into, used by
+    /// outputs living outside of it such as the bench harness.
+    pub crate_path: String,
+    /// Also emit a `<Name>Ref<'a>` next to every struct with `Bytes`,
+    /// `String` or `Vec<u8>` fields, borrowing them from the input buffer
+    /// through its `decode` function. Vectors of borrowed elements become a
+    /// `RefSeq` decoding them as it is iterated, other vectors stay owned.
+    pub borrowed: bool,
+    /// Also emit a `<field>_iter` function for every `Vec` field of the
+    /// emitted structs, decoding the elements one at a time from the input
+    /// buffer instead of collecting them.
+    pub streaming: bool,
+    /// Give every args struct an `extra: Vec<u8>` field and a
+    /// `try_from_slice_with_extra` function accepting and keeping trailing
+    /// bytes, so args appended by a program upgrade don't break decoding.
+    pub trailing_bytes: bool,
+    /// Give every args struct and custom struct a `new` function taking its
+    /// required fields and a `with_<field>` setter per optional one, which
+    /// start out `None`. Implied by `builders`, whose callers build the args
+    /// so.
+    pub constructors: bool,
+    /// Also emit a `ProgramEvent` enum of the events, decoding them from
+    /// transaction logs and `emit_cpi!` inner instructions along with the
+    /// instruction that emitted them. Its `try_decode` tells why an event
+    /// doesn't decode with a `BindingsError`.
+    pub event_decoder: bool,
+    /// Also emit an `event_stream` function, a `futures::Stream` of the
+    /// decoded events and where they were logged from a `logsSubscribe`
+    /// subscription it reconnects, behind the `ws` feature. Needs tokio and
+    /// futures, implies `event_decoder`.
+    pub event_stream: bool,
+    /// Also emit a `PROGRAM_ID` constant, a `ComputeBudget` prepending the
+    /// compute unit limit and price instructions and, for every instruction,
+    /// a `<Name>Accounts` struct of its account addresses and a `<name>_ix`
+    /// function building the instruction from them and its args. The
+    /// accounts structs check account metas against the IDL with
+    /// `check_metas`: their count, signers, writable accounts and fixed
+    /// addresses. The client checks the instructions it sends so. The
+    /// generated code fails with a thiserror `BindingsError` of its decode,
+    /// account and RPC failures, the crate needs thiserror. Implies
+    /// `constructors`.
+    pub builders: bool,
+    /// How the builders pass optional accounts that are `None`.
+    pub optional_accounts: OptionalAccounts,
+    /// Also emit a clap `Cli` with a subcommand per instruction and a
+    /// `run_cli` function sending the instruction, along with helpers
+    /// building v0 transactions with address lookup tables. Implies
+    /// `builders`.
+    pub cli: bool,
+    /// Also emit a `<Module>Client` sending the instructions over RPC with
+    /// a `send_<instruction>` method each, configured by a `ClientConfig` of
+    /// the commitment, retries and confirmation polling. Its `simulate_` methods
+    /// return the compute units, logs and the decoded error and events of a
+    /// simulation. Also gives every account a `from_account_data` decoder
+    /// and emits a `fetch_all_<accounts>` function per account,
+    /// fetching the program's accounts of its type over `getProgramAccounts`
+    /// filtered by discriminator, and by size when it is static, and an
+    /// `<Account>Filters` of `memcmp` filters on its fields at their offset.
+    /// Implies `builders`.
+    pub client: bool,
+    /// Also emit async account scans behind the `ws` feature: a
+    /// `fetch_<accounts>_batched` function per account, streaming the
+    /// accounts at given addresses decoded, fetched with `getMultipleAccounts`
+    /// in batches, and a `scan_<accounts>` function streaming all of them,
+    /// listed without their data first. A `ScanConfig` sets the batch size,
+    /// the requests in flight and a pause before each, for rate limited RPCs.
+    /// Needs tokio and futures, implies `builders`.
+    pub account_scans: bool,
+    /// Also emit a `watch_<account>` function per account behind the `ws`
+    /// feature, streaming the decoded states of an account from an
+    /// `accountSubscribe` subscription it reconnects. A slow consumer skips
+    /// to the latest state, the updates of another type are skipped. Needs
+    /// tokio and futures.
+    pub account_watchers: bool,
+    /// Also emit a `Localnet` for integration tests, starting
+    /// `solana-test-validator` with the program loaded at its address or
+    /// connecting to a running validator, airdropping to payers and handing
+    /// out clients of it. Implies `client`.
+    pub localnet: bool,
+    /// Also emit a Postgres indexer built on sqlx: a `MIGRATION` creating a
+    /// table per instruction, event and account, an `insert_` function per
+    /// table, `index_transaction` decoding a transaction into them and
+    /// `run_indexer`, indexing the accounts and the transactions from a
+    /// backfill and a logs subscription. The tables use `NULLS NOT
+    /// DISTINCT`, which needs Postgres 15. The `MIGRATION` only creates
+    /// the missing tables, there are no migration files to evolve them.
+    /// Implies `builders` and `event_decoder`.
+    pub indexer: bool,
+    /// Also emit a `DecodedSink` trait with a `KafkaSink` implementation
+    /// built on rdkafka, and `publish_instruction` and `publish_events`
+    /// publishing what they decode to a sink as JSON, keyed by the
+    /// transaction signature. The builder makes the generated types derive
+    /// the serde traits if neither [`Self::feature_gates`] nor the derives
+    /// do. Implies `event_decoder`.
+    pub kafka_sink: bool,
+    /// Also emit a `DecodeService`, an axum router serving the decoders as
+    /// JSON: `POST /decode/instruction` decodes instruction data, `GET
+    /// /decode/account/:pubkey` and `GET /decode/tx/:signature` fetch an
+    /// account or a transaction over RPC and decode it, and
+    /// `serve_decode_service` serves it. The builder makes the generated
+    /// types derive the serde traits like for [`Self::kafka_sink`]. Implies
+    /// `builders` and `event_decoder`.
+    pub http_service: bool,
+    /// Also emit a tonic gRPC decode service: `PROTO_SCHEMA`, the protobuf
+    /// schema of a message per instruction, account and event and of a
+    /// `Decoder` service decoding them with `Decode` and `DecodeStream`,
+    /// the prost messages in a `proto` module, the `DecoderServer`
+    /// implementing the service and `serve_grpc_decoder` serving it. The
+    /// options of scalars are `optional` fields, the vectors and arrays of
+    /// them `repeated` ones, other fields their borsh encoding. Implies
+    /// `event_decoder`.
+    pub grpc_service: bool,
+    /// Instrument the builders, the client and the decoders with `tracing`
+    /// spans carrying the program, instruction and signature, and events on
+    /// retries and decode failures, behind the `tracing` feature of the
+    /// consuming crate.
+    pub tracing: bool,
+    /// Emit a `DecodeMetrics` trait the generated decoders report decoded
+    /// items, unknown discriminators, failures and decode latency to, once
+    /// registered with `set_decode_metrics`.
+    pub metrics: bool,
+    /// Gate the generated code needing heavy dependencies behind cargo
+    /// features of the consuming crate (see [`Feature`]), so a consumer
+    /// compiling only the types doesn't depend on them. Also derives the
+    /// serde traits on the generated types behind the `serde` feature.
+    pub feature_gates: bool,
+    /// Serialize the generated types human-readably where they derive the
+    /// serde traits, for JSON APIs and databases: pubkeys as base58 strings
+    /// and 128 bit integers as decimal strings, directly or in options,
+    /// vectors and arrays, bytes and byte arrays as hex strings, and enums
+    /// adjacently tagged, as `{"kind": <variant>}`. The fields typed by
+    /// [`Self::pubkey_newtypes`], [`Self::flags`] or type hooks keep their
+    /// own representation.
+    pub human_readable_serde: bool,
+    /// Emit a `prelude` module in each program module re-exporting its args
+    /// structs, accounts, events, errors and `PROGRAM_ID`, for
+    /// `use <program>::prelude::*`. With [`crate::generate_crates`] the lib.rs
+    /// of each program crate re-exports it as the crate's own prelude.
+    pub prelude: bool,
+    /// `pub use` lines written after the imports.
+    pub reexports: Vec<String>,
+    /// When generating several IDLs, emit the custom types they define
+    /// identically once in a `common` module, re-exported by each program.
+    pub dedupe: bool,
+    /// Directories of auxiliary IDLs, which aren't generated themselves, the
+    /// `Defined` types the generated IDLs leave undefined are looked up in.
+    /// The types found there, and those they refer to, are emitted once in
+    /// the `common` module and re-exported by the programs using them.
+    pub includes: Vec<PathBuf>,
+    /// When generating several IDLs, emit a `conversions` module of `From`
+    /// impls between the same named structs and enums of every two
+    /// programs, where the fields of the target are those of the source
+    /// (new option fields start out `None`) and the variants of the source
+    /// those of the target, `TryFrom` for enums losing variants. The fields
+    /// must not be private.
+    pub conversions: bool,
+    /// When generating IDLs into a directory, emit the program independent
+    /// helpers of the generated code, such as the `BindingsError`, the
+    /// compute budget, the send helpers and the account decoding, scan and
+    /// watch helpers, once in a `support` module the programs re-export,
+    /// instead of in every program. Not in crate mode, where every crate
+    /// keeps its own.
+    pub shared_support: bool,
+    /// Rust path of the program's own crate, e.g. `my_program`. Every
+    /// emitted type and event then converts to and from the same named type
+    /// at the root of that crate with `TryFrom` impls on references, going
+    /// through their borsh encoding. To use the program crate's types
+    /// instead of generating them, override them in
+    /// [`Self::type_overrides`].
+    pub program_crate: Option<String>,
+    /// Implement anchor_lang's `Discriminator`, `Owner` (the program's
+    /// address), `AccountDeserialize` and `AccountSerialize` on the
+    /// accounts, checking their discriminator as anchor does, so they can be
+    /// fetched with anchor-client and used as `Account<'info, T>` in other
+    /// programs. The accounts are emitted even if no instruction refers to
+    /// them.
+    pub anchor_traits: bool,
+    /// Give the struct accounts a `diff` method listing the fields that
+    /// changed between two states of an account as `FieldChange`s, with the
+    /// old and new `Debug` rendering of each, e.g. to monitor them. Needs the
+    /// `Debug` derive. The accounts are emitted even if no instruction refers
+    /// to them.
+    pub account_diffs: bool,
+    /// Leave out the types no instruction, account or event reaches, which
+    /// matters for the backends and artifacts emitting every type, e.g.
+    /// types without the instruction args. They are reported in the log
+    /// either way.
+    pub prune_unreferenced: bool,
+    /// Banner written below the provenance header of every generated file,
+    /// e.g. a license or ownership tags. A minijinja template of `program`,
+    /// `version`, the IDL version of the program, and `timestamp`, the UTC
+    /// time of the generation in RFC 3339. The modules shared by programs
+    /// get their module name as `program` and no version. Its lines are
+    /// written as line comments, unless they are comments already.
+    pub banner: Option<String>,
+    /// Directory of a crate depending on the bindings: generating a
+    /// workspace or crates adds path dependencies on the bindings crates to
+    /// its Cargo.toml, unless it has them.
+    pub consumer_crate: Option<PathBuf>,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            imports: vec![
+                "std::collections::HashMap".to_string(),
+                "anchor_lang::prelude::*".to_string(),
+                "borsh::{BorshDeserialize, BorshSerialize}".to_string(),
+            ],
+            derives: vec![
+                "BorshSerialize".to_string(),
+                "BorshDeserialize".to_string(),
+                "Debug".to_string(),
+                "Clone".to_string(),
+            ],
+            type_visibility: Visibility::Public,
+            field_visibility: Visibility::Private,
+            naming: Naming::default(),
+            sighash: Sighash::default(),
+            type_overrides: HashMap::new(),
+            type_hooks: Vec::new(),
+            flags: HashMap::new(),
+            pubkey_newtypes: HashMap::new(),
+            timestamps: Vec::new(),
+            amounts: HashMap::new(),
+            artifacts: Artifact::ALL.into_iter().collect(),
+            only_instructions: Vec::new(),
+            backend: "rust".to_string(),
+            templates: HashMap::new(),
+            force: false,
+            layout: Layout::Flat,
+            bundle: None,
+            crate_path: "bindings".to_string(),
+            borrowed: false,
+            streaming: false,
+            trailing_bytes: false,
+            constructors: false,
+            event_decoder: false,
+            event_stream: false,
+            builders: false,
+            optional_accounts: OptionalAccounts::default(),
+            cli: false,
+            client: false,
+            localnet: false,
+            account_scans: false,
+            account_watchers: false,
+            indexer: false,
+            kafka_sink: false,
+            http_service: false,
+            grpc_service: false,
+            tracing: false,
+            metrics: false,
+            feature_gates: false,
+            human_readable_serde: false,
+            prelude: false,
+            reexports: Vec::new(),
+            dedupe: false,
+            includes: Vec::new(),
+            conversions: false,
+            shared_support: false,
+            program_crate: None,
+            anchor_traits: false,
+            account_diffs: false,
+            prune_unreferenced: false,
+            banner: None,
+            consumer_crate: None,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn builder() -> GeneratorConfigBuilder {
+        GeneratorConfigBuilder::default()
+    }
+
+    pub fn emits(&self, artifact: Artifact) -> bool {
+        self.artifacts.contains(&artifact)
+    }
+
+    /// The `tracing::instrument` attribute of a generated function indented
+    /// by `indent`, behind the `tracing` feature, empty unless
+    /// [`Self::tracing`] is set.
+    pub(crate) fn instrument(&self, indent: &str, level: &str, fields: &str) -> String {
+        if !self.tracing {
+            return String::new();
+        }
+        format!(
+            "{}#[cfg_attr({}, tracing::instrument(level = \"{}\", skip_all, fields({})))]\n",
+            indent,
+            Feature::Tracing.predicate(),
+            level,
+            fields
+        )
+    }
+
+    /// The line `statement`, indented by `indent`, behind the `tracing`
+    /// feature, empty unless [`Self::tracing`] is set.
+    pub(crate) fn traced(&self, indent: &str, statement: &str) -> String {
+        if !self.tracing {
+            return String::new();
+        }
+        format!(
+            "{0}#[cfg({1})]\n{0}{2}\n",
+            indent,
+            Feature::Tracing.predicate(),
+            statement
+        )
+    }
+}
+
+/// Builder for [`GeneratorConfig`], starting from the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratorConfigBuilder {
+    config: GeneratorConfig,
+}
+
+impl GeneratorConfigBuilder {
+    /// Adds an import on top of the default ones.
+    pub fn import(mut self, import: impl Into<String>) -> Self {
+        self.config.imports.push(import.into());
+        self
+    }
+
+    /// Replaces all imports.
+    pub fn imports<I, S>(mut self, imports: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.imports = imports.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Adds a derive on top of the default ones.
+    pub fn derive(mut self, derive: impl Into<String>) -> Self {
+        let derive = derive.into();
+        if !self.config.derives.contains(&derive) {
+            self.config.derives.push(derive);
+        }
+        self
+    }
+
+    /// Replaces all derives.
+    pub fn derives<I, S>(mut self, derives: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.derives = derives.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn type_visibility(mut self, visibility: Visibility) -> Self {
+        self.config.type_visibility = visibility;
+        self
+    }
+
+    pub fn field_visibility(mut self, visibility: Visibility) -> Self {
+        self.config.field_visibility = visibility;
+        self
+    }
+
+    pub fn naming(mut self, naming: Naming) -> Self {
+        self.config.naming = naming;
+        self
+    }
+
+    pub fn sighash_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.config.sighash.namespace = namespace.into();
+        self
+    }
+
+    pub fn sighash_case(mut self, case: Case) -> Self {
+        self.config.sighash.case = case;
+        self
+    }
+
+    pub fn type_override(
+        mut self,
+        idl_name: impl Into<String>,
+        rust_type: impl Into<String>,
+    ) -> Self {
+        self.config
+            .type_overrides
+            .insert(idl_name.into(), rust_type.into());
+        self
+    }
+
+    /// Adds a [`TypeHook`], e.g. to map every `i64` field ending in `_ts`
+    /// to a timestamp newtype.
+    pub fn type_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Type, Option<&str>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.config.type_hooks.push(TypeHook::new(hook));
+        self
+    }
+
+    /// Types the IDL `field` as the flags named `bits`, pairs of a flag and
+    /// the index of its bit, in a `bitflags` type `name`.
+    pub fn flags<I, S>(mut self, field: impl Into<String>, name: impl Into<String>, bits: I) -> Self
+    where
+        I: IntoIterator<Item = (S, u32)>,
+        S: Into<String>,
+    {
+        let flags = FlagSet {
+            name: name.into(),
+            bits: bits
+                .into_iter()
+                .map(|(flag, bit)| (flag.into(), bit))
+                .collect(),
+        };
+        self.config.flags.insert(field.into(), flags);
+        self
+    }
+
+    /// Types the `Pubkey` of the IDL field or account `name` as `newtype`.
+    pub fn pubkey_newtype(mut self, name: impl Into<String>, newtype: impl Into<String>) -> Self {
+        self.config
+            .pubkey_newtypes
+            .insert(name.into(), newtype.into());
+        self
+    }
+
+    /// Adds a pattern of timestamp fields, see [`GeneratorConfig::timestamps`].
+    pub fn timestamp(mut self, pattern: impl Into<String>) -> Self {
+        self.config.timestamps.push(pattern.into());
+        self
+    }
+
+    /// Marks the IDL `field` as a token amount with `decimals`, see
+    /// [`GeneratorConfig::amounts`].
+    pub fn amount(mut self, field: impl Into<String>, decimals: Decimals) -> Self {
+        self.config.amounts.insert(field.into(), decimals);
+        self
+    }
+
+    /// Restricts the output to the given artifacts.
+    pub fn artifacts(mut self, artifacts: impl IntoIterator<Item = Artifact>) -> Self {
+        self.config.artifacts = artifacts.into_iter().collect();
+        self
+    }
+
+    /// Adds an instruction to generate, see
+    /// [`GeneratorConfig::only_instructions`].
+    pub fn only_instruction(mut self, name: impl Into<String>) -> Self {
+        self.config.only_instructions.push(name.into());
+        self
+    }
+
+    pub fn backend(mut self, backend: impl Into<String>) -> Self {
+        self.config.backend = backend.into();
+        self
+    }
+
+    /// Renders items of `kind` with a minijinja template instead of the
+    /// built-in emitter.
+    pub fn template(mut self, kind: TemplateKind, source: impl Into<String>) -> Self {
+        self.config.templates.insert(kind, source.into());
+        self
+    }
+
+    pub fn force(mut self, force: bool) -> Self {
+        self.config.force = force;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.config.layout = layout;
+        self
+    }
+
+    pub fn bundle(mut self, file_name: impl Into<String>) -> Self {
+        self.config.bundle = Some(file_name.into());
+        self
+    }
+
+    pub fn crate_path(mut self, crate_path: impl Into<String>) -> Self {
+        self.config.crate_path = crate_path.into();
+        self
+    }
+
+    pub fn borrowed(mut self, borrowed: bool) -> Self {
+        self.config.borrowed = borrowed;
+        self
+    }
+
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.config.streaming = streaming;
+        self
+    }
+
+    pub fn trailing_bytes(mut self, trailing_bytes: bool) -> Self {
+        self.config.trailing_bytes = trailing_bytes;
+        self
+    }
+
+    pub fn constructors(mut self, constructors: bool) -> Self {
+        self.config.constructors = constructors;
+        self
+    }
+
+    pub fn event_decoder(mut self, event_decoder: bool) -> Self {
+        self.config.event_decoder = event_decoder;
+        self
+    }
+
+    pub fn event_stream(mut self, event_stream: bool) -> Self {
+        self.config.event_stream = event_stream;
+        self
+    }
+
+    pub fn builders(mut self, builders: bool) -> Self {
+        self.config.builders = builders;
+        self
+    }
+
+    pub fn cli(mut self, cli: bool) -> Self {
+        self.config.cli = cli;
+        self
+    }
+
+    pub fn client(mut self, client: bool) -> Self {
+        self.config.client = client;
+        self
+    }
+
+    pub fn localnet(mut self, localnet: bool) -> Self {
+        self.config.localnet = localnet;
+        self
+    }
+
+    pub fn account_scans(mut self, account_scans: bool) -> Self {
+        self.config.account_scans = account_scans;
+        self
+    }
+
+    pub fn account_watchers(mut self, account_watchers: bool) -> Self {
+        self.config.account_watchers = account_watchers;
+        self
+    }
+
+    pub fn indexer(mut self, indexer: bool) -> Self {
+        self.config.indexer = indexer;
+        self
+    }
+
+    pub fn optional_accounts(mut self, optional_accounts: OptionalAccounts) -> Self {
+        self.config.optional_accounts = optional_accounts;
+        self
+    }
+
+    pub fn kafka_sink(mut self, kafka_sink: bool) -> Self {
+        self.config.kafka_sink = kafka_sink;
+        self
+    }
+
+    pub fn http_service(mut self, http_service: bool) -> Self {
+        self.config.http_service = http_service;
+        self
+    }
+
+    pub fn grpc_service(mut self, grpc_service: bool) -> Self {
+        self.config.grpc_service = grpc_service;
+        self
+    }
+
+    pub fn tracing(mut self, tracing: bool) -> Self {
+        self.config.tracing = tracing;
+        self
+    }
+
+    pub fn metrics(mut self, metrics: bool) -> Self {
+        self.config.metrics = metrics;
+        self
+    }
+
+    pub fn feature_gates(mut self, feature_gates: bool) -> Self {
+        self.config.feature_gates = feature_gates;
+        self
+    }
+
+    pub fn human_readable_serde(mut self, human_readable_serde: bool) -> Self {
+        self.config.human_readable_serde = human_readable_serde;
+        self
+    }
+
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.config.prelude = prelude;
+        self
+    }
+
+    pub fn reexport(mut self, path: impl Into<String>) -> Self {
+        self.config.reexports.push(path.into());
+        self
+    }
+
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.config.dedupe = dedupe;
+        self
+    }
+
+    pub fn include(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.includes.push(dir.into());
+        self
+    }
+
+    pub fn conversions(mut self, conversions: bool) -> Self {
+        self.config.conversions = conversions;
+        self
+    }
+
+    pub fn shared_support(mut self, shared_support: bool) -> Self {
+        self.config.shared_support = shared_support;
+        self
+    }
+
+    pub fn program_crate(mut self, path: impl Into<String>) -> Self {
+        self.config.program_crate = Some(path.into());
+        self
+    }
+
+    pub fn anchor_traits(mut self, anchor_traits: bool) -> Self {
+        self.config.anchor_traits = anchor_traits;
+        self
+    }
+
+    pub fn account_diffs(mut self, account_diffs: bool) -> Self {
+        self.config.account_diffs = account_diffs;
+        self
+    }
+
+    pub fn prune_unreferenced(mut self, prune_unreferenced: bool) -> Self {
+        self.config.prune_unreferenced = prune_unreferenced;
+        self
+    }
+
+    /// See [`GeneratorConfig::banner`].
+    pub fn banner(mut self, template: impl Into<String>) -> Self {
+        self.config.banner = Some(template.into());
+        self
+    }
+
+    /// See [`GeneratorConfig::consumer_crate`].
+    pub fn consumer_crate(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.consumer_crate = Some(dir.into());
+        self
+    }
+
+    pub fn build(mut self) -> GeneratorConfig {
+        // the sink and the service serialize the types they decode
+        if (self.config.kafka_sink || self.config.http_service)
+            && !crate::backend::serializes(&self.config)
+        {
+            self = self.derive("serde::Serialize").derive("serde::Deserialize");
+        }
+        self.config
+    }
+}
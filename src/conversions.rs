@@ -0,0 +1,369 @@
+//! Conversions between the types of programs generated side by side, see
+//! [`GeneratorConfig::conversions`].
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use log::warn;
+
+use crate::config::{Artifact, GeneratorConfig, Visibility};
+use crate::dedupe::references;
+use crate::ir::{Field, Program, Type, TypeDefKind};
+use crate::template::TemplateKind;
+
+/// Module holding the conversions, next to the program modules.
+pub(crate) const CONVERSIONS_MODULE: &str = "conversions";
+
+/// A generated program: its module, its IR and the config it was generated
+/// with.
+pub(crate) struct Generated<'a> {
+    pub module: &'a str,
+    pub program: &'a Program,
+    pub config: &'a GeneratorConfig,
+}
+
+/// The shape of an emitted struct or enum, as far as conversions see it.
+enum Shape {
+    Struct(Vec<Field>),
+    /// Variant names, the emitted enums have unit variants only.
+    Enum(Vec<String>),
+}
+
+/// Emitted structs and enums of `generated` by IDL name, with their rust
+/// names.
+fn emitted(generated: &Generated<'_>) -> HashMap<String, (String, Shape)> {
+    let (program, config) = (generated.program, generated.config);
+    let mut shapes = HashMap::new();
+    if config.emits(Artifact::Events) {
+        for event in program.events.iter() {
+            let fields = event
+                .fields
+                .iter()
+                .map(|f| Field {
+                    name: f.name.clone(),
+                    ty: f.ty.clone(),
+                })
+                .collect();
+            shapes.insert(
+                event.name.clone(),
+                (
+                    config.naming.types.apply(&event.name),
+                    Shape::Struct(fields),
+                ),
+            );
+        }
+    }
+    if !config.emits(Artifact::Types) {
+        return shapes;
+    }
+
+    // the same selection as the rust backend: everything without args
+    // structs, otherwise what the args and events refer to
+    let definitions: Vec<_> = program
+        .accounts
+        .iter()
+        .chain(program.types.iter())
+        .collect();
+    let mut needed: HashSet<String> = HashSet::new();
+    if config.emits(Artifact::InstructionArgs) {
+        let mut roots: Vec<&Type> = program
+            .instructions
+            .iter()
+            .flat_map(|ix| ix.args.iter().map(|a| &a.ty))
+            .collect();
+        if config.emits(Artifact::Events) {
+            roots.extend(
+                program
+                    .events
+                    .iter()
+                    .flat_map(|e| e.fields.iter().map(|f| &f.ty)),
+            );
+        }
+        let mut pending: Vec<String> = roots.into_iter().flat_map(defined).collect();
+        if config.indexer {
+            pending.extend(
+                definitions
+                    .iter()
+                    .filter(|d| d.discriminator.is_some())
+                    .map(|d| d.name.clone()),
+            );
+        }
+        while let Some(name) = pending.pop() {
+            if config.type_overrides.contains_key(&name) || !needed.insert(name.clone()) {
+                continue;
+            }
+            if let Some(def) = definitions.iter().find(|d| d.name == name) {
+                pending.extend(references(def));
+            }
+        }
+    } else {
+        needed.extend(
+            definitions
+                .iter()
+                .filter(|d| !config.type_overrides.contains_key(&d.name))
+                .map(|d| d.name.clone()),
+        );
+    }
+
+    for def in definitions {
+        if !needed.contains(&def.name) {
+            continue;
+        }
+        let shape = match &def.kind {
+            TypeDefKind::Struct { fields } => Shape::Struct(fields.clone()),
+            // only unit variants map by name
+            TypeDefKind::Enum { variants } if variants.iter().all(|v| v.fields.is_none()) => {
+                Shape::Enum(variants.iter().map(|v| v.name.clone()).collect())
+            }
+            TypeDefKind::Enum { .. } => continue,
+        };
+        shapes.insert(
+            def.name.clone(),
+            (config.naming.types.apply(&def.name), shape),
+        );
+    }
+    shapes
+}
+
+/// `Defined` names in `ty`.
+fn defined(ty: &Type) -> Vec<String> {
+    match ty {
+        Type::Defined(name) => vec![name.clone()],
+        Type::Option(inner) | Type::Vec(inner) | Type::Array(inner, _) => defined(inner),
+        _ => vec![],
+    }
+}
+
+/// How a conversion from one program to another is implemented.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Conversion {
+    From,
+    /// Some variants of the source enum are missing from the target.
+    TryFrom,
+}
+
+/// Converts one program's types into another's.
+struct Pair<'a> {
+    from: &'a Generated<'a>,
+    to: &'a Generated<'a>,
+    from_shapes: &'a HashMap<String, (String, Shape)>,
+    to_shapes: &'a HashMap<String, (String, Shape)>,
+}
+
+impl<'a> Pair<'a> {
+    /// The expression converting `expr` of IDL type `from` into `to`, if
+    /// the types are the same but for convertible definitions.
+    fn convert(
+        &self,
+        expr: &str,
+        from: &Type,
+        to: &Type,
+        convertible: &HashMap<&str, Conversion>,
+    ) -> Option<String> {
+        let same = |name: &str| {
+            // overridden on both sides by the same type, shared ones included
+            let from = self.from.config.type_overrides.get(name);
+            from.is_some() && from == self.to.config.type_overrides.get(name)
+        };
+        let into = |name: &str| convertible.get(name) == Some(&Conversion::From);
+        match (from, to) {
+            (Type::Defined(a), Type::Defined(b)) if a == b && same(a) => Some(expr.to_string()),
+            (Type::Defined(a), Type::Defined(b)) if a == b && into(a) => {
+                Some(format!("{}.into()", expr))
+            }
+            (Type::Option(a), Type::Option(b)) => match (a.as_ref(), b.as_ref()) {
+                (Type::Defined(x), Type::Defined(y)) if x == y && into(x) => {
+                    Some(format!("{}.map(Into::into)", expr))
+                }
+                (a, b) => self.convert(expr, a, b, convertible).filter(|e| e == expr),
+            },
+            (Type::Vec(a), Type::Vec(b)) => match (a.as_ref(), b.as_ref()) {
+                (Type::Defined(x), Type::Defined(y)) if x == y && into(x) => {
+                    Some(format!("{}.into_iter().map(Into::into).collect()", expr))
+                }
+                (a, b) => self.convert(expr, a, b, convertible).filter(|e| e == expr),
+            },
+            (Type::Array(a, n), Type::Array(b, m)) if n == m => {
+                self.convert(expr, a, b, convertible).filter(|e| e == expr)
+            }
+            (Type::Defined(_), _) | (_, Type::Defined(_)) => None,
+            (a, b) => (a == b).then(|| expr.to_string()),
+        }
+    }
+
+    /// How `name` converts, assuming the definitions in `convertible` do.
+    fn conversion(
+        &self,
+        name: &str,
+        convertible: &HashMap<&str, Conversion>,
+    ) -> Option<Conversion> {
+        let (_, from) = self.from_shapes.get(name)?;
+        let (_, to) = self.to_shapes.get(name)?;
+        match (from, to) {
+            (Shape::Struct(from), Shape::Struct(to)) => {
+                for field in to.iter() {
+                    match from.iter().find(|f| f.name == field.name) {
+                        Some(source) => {
+                            self.convert("v", &source.ty, &field.ty, convertible)?;
+                        }
+                        // fields added as options start out empty
+                        None if matches!(field.ty, Type::Option(_)) => {}
+                        None => return None,
+                    }
+                }
+                Some(Conversion::From)
+            }
+            (Shape::Enum(from), Shape::Enum(to)) => {
+                let kept = from.iter().filter(|v| to.contains(*v)).count();
+                match kept {
+                    0 => None,
+                    kept if kept == from.len() => Some(Conversion::From),
+                    _ => Some(Conversion::TryFrom),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The conversions of the definitions both programs emit. A definition
+    /// only converts if everything it refers to does.
+    fn conversions(&self) -> Vec<(&'a str, Conversion)> {
+        let mut convertible: HashMap<&str, Conversion> = self
+            .to_shapes
+            .keys()
+            .filter(|name| self.from_shapes.contains_key(*name))
+            .map(|name| (name.as_str(), Conversion::From))
+            .collect();
+        loop {
+            let mut next = HashMap::new();
+            for name in convertible.keys() {
+                if let Some(conversion) = self.conversion(name, &convertible) {
+                    next.insert(*name, conversion);
+                }
+            }
+            if next == convertible {
+                break;
+            }
+            convertible = next;
+        }
+        let mut conversions: Vec<_> = convertible.into_iter().collect();
+        conversions.sort();
+        conversions
+    }
+
+    fn write(
+        &self,
+        output: &mut impl Write,
+        name: &str,
+        conversion: Conversion,
+        convertible: &HashMap<&str, Conversion>,
+    ) -> std::io::Result<()> {
+        let (from_name, from) = &self.from_shapes[name];
+        let (to_name, to) = &self.to_shapes[name];
+        let from_path = format!("super::{}::{}", self.from.module, from_name);
+        let to_path = format!("super::{}::{}", self.to.module, to_name);
+        match (from, to, conversion) {
+            (Shape::Struct(from), Shape::Struct(to), _) => {
+                output.write_fmt(format_args!(
+                    "impl From<{}> for {} {{\n\tfn from(v: {}) -> Self {{\n\t\tSelf {{\n",
+                    from_path, to_path, from_path
+                ))?;
+                for field in to.iter() {
+                    let rust = self.to.config.naming.fields.apply(&field.name);
+                    let value = match from.iter().find(|f| f.name == field.name) {
+                        Some(source) => {
+                            let expr =
+                                format!("v.{}", self.from.config.naming.fields.apply(&source.name));
+                            self.convert(&expr, &source.ty, &field.ty, convertible)
+                                .expect("checked convertible")
+                        }
+                        None => "None".to_string(),
+                    };
+                    output.write_fmt(format_args!("\t\t\t{}: {},\n", rust, value))?;
+                }
+                output.write_all(b"\t\t}\n\t}\n}\n")?;
+            }
+            (Shape::Enum(from), Shape::Enum(_), Conversion::From) => {
+                output.write_fmt(format_args!(
+                    "impl From<{0}> for {1} {{\n\tfn from(v: {0}) -> Self {{\n\t\tmatch v {{\n",
+                    from_path, to_path
+                ))?;
+                for variant in from.iter() {
+                    output.write_fmt(format_args!(
+                        "\t\t\t{}::{2} => {}::{2},\n",
+                        from_path, to_path, variant
+                    ))?;
+                }
+                output.write_all(b"\t\t}\n\t}\n}\n")?;
+            }
+            (Shape::Enum(from), Shape::Enum(to), Conversion::TryFrom) => {
+                output.write_fmt(format_args!(
+                    "/// Fails with the value for the variants `{1}` lacks.\nimpl TryFrom<{0}> for {1} {{\n\ttype Error = {0};\n\n\tfn try_from(v: {0}) -> Result<Self, Self::Error> {{\n\t\tmatch v {{\n",
+                    from_path, to_path
+                ))?;
+                for variant in from.iter().filter(|v| to.contains(*v)) {
+                    output.write_fmt(format_args!(
+                        "\t\t\t{}::{2} => Ok({}::{2}),\n",
+                        from_path, to_path, variant
+                    ))?;
+                }
+                output.write_all(b"\t\t\tv => Err(v),\n\t\t}\n\t}\n}\n")?;
+            }
+            _ => unreachable!("only equal shapes convert"),
+        }
+        Ok(())
+    }
+}
+
+/// The conversions module: `From` impls between the structs and enums of
+/// every two programs whose fields or variants are compatible, `TryFrom`
+/// for enums losing variants. Empty if there are none.
+pub(crate) fn conversions(programs: &[Generated<'_>]) -> Vec<u8> {
+    let shapes: Vec<_> = programs.iter().map(emitted).collect();
+    let mut output = vec![];
+    for (i, from) in programs.iter().enumerate() {
+        for (j, to) in programs.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let mut skipped = vec![];
+            for generated in [from, to] {
+                if generated.config.field_visibility == Visibility::Private {
+                    skipped.push(format!("the fields of {} are private", generated.module));
+                }
+                let templates = &generated.config.templates;
+                if templates.contains_key(&TemplateKind::Struct)
+                    || templates.contains_key(&TemplateKind::Enum)
+                {
+                    skipped.push(format!("{} uses templates", generated.module));
+                }
+            }
+            if !skipped.is_empty() {
+                warn!(
+                    "no conversions from {} to {}: {}",
+                    from.module,
+                    to.module,
+                    skipped.join(", ")
+                );
+                continue;
+            }
+
+            let pair = Pair {
+                from,
+                to,
+                from_shapes: &shapes[i],
+                to_shapes: &shapes[j],
+            };
+            let conversions = pair.conversions();
+            let convertible: HashMap<_, _> = conversions.iter().copied().collect();
+            for (name, conversion) in conversions.iter() {
+                if !output.is_empty() {
+                    output.push(b'\n');
+                }
+                pair.write(&mut output, name, *conversion, &convertible)
+                    .expect("writing to a Vec can't fail");
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,1994 @@
+//! Decoding borsh encoded data against a [`Program`] at runtime, without
+//! generating and compiling bindings first.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use log::warn;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+
+use crate::error::{ParseIdlError, Result};
+use crate::ir::{EnumFields, ErrorCode, Field, Instruction, Program, Type, TypeDefKind};
+
+/// A decoded value, scalars keep the width of their IDL type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    F32(f32),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    U128(u128),
+    I128(i128),
+    Bytes(Vec<u8>),
+    String(String),
+    PublicKey([u8; 32]),
+    Option(Option<Box<Value>>),
+    /// A `Vec` or a fixed size array.
+    Array(Vec<Value>),
+    /// Struct fields in declaration order.
+    Struct(Vec<(String, Value)>),
+    /// `fields` is a [`Value::Struct`] for named fields and a
+    /// [`Value::Array`] for tuple variants.
+    Enum {
+        variant: String,
+        fields: Option<Box<Value>>,
+    },
+}
+
+/// JSON shape: 128 bit integers are strings (JSON numbers lose precision),
+/// public keys are base58, structs are objects, unit variants are their name
+/// and other variants `{"<variant>": <fields>}`.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::U128(v) => serializer.collect_str(v),
+            Value::I128(v) => serializer.collect_str(v),
+            Value::Bytes(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for b in v {
+                    seq.serialize_element(b)?;
+                }
+                seq.end()
+            }
+            Value::String(v) => serializer.serialize_str(v),
+            Value::PublicKey(v) => serializer.serialize_str(&bs58::encode(v).into_string()),
+            Value::Option(None) => serializer.serialize_none(),
+            Value::Option(Some(v)) => serializer.serialize_some(v),
+            Value::Array(v) => v.serialize(serializer),
+            Value::Struct(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, value) in fields {
+                    map.serialize_entry(name, value)?;
+                }
+                map.end()
+            }
+            Value::Enum {
+                variant,
+                fields: None,
+            } => serializer.serialize_str(variant),
+            Value::Enum {
+                variant,
+                fields: Some(fields),
+            } => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(variant, fields)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl Value {
+    /// The value on one line, `None` if it needs several.
+    fn inline(&self) -> Option<String> {
+        let s = match self {
+            Value::Bool(v) => v.to_string(),
+            Value::U8(v) => v.to_string(),
+            Value::I8(v) => v.to_string(),
+            Value::U16(v) => v.to_string(),
+            Value::I16(v) => v.to_string(),
+            Value::U32(v) => v.to_string(),
+            Value::I32(v) => v.to_string(),
+            Value::F32(v) => v.to_string(),
+            Value::U64(v) => v.to_string(),
+            Value::I64(v) => v.to_string(),
+            Value::F64(v) => v.to_string(),
+            Value::U128(v) => v.to_string(),
+            Value::I128(v) => v.to_string(),
+            Value::Bytes(v) => format!("{:?}", v),
+            Value::String(v) => format!("{:?}", v),
+            Value::PublicKey(v) => bs58::encode(v).into_string(),
+            Value::Option(None) => "None".to_string(),
+            Value::Option(Some(v)) => v.inline()?,
+            Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(Value::inline)
+                    .collect::<Option<Vec<_>>>()?;
+                format!("[{}]", items.join(", "))
+            }
+            Value::Struct(fields) if fields.is_empty() => "{}".to_string(),
+            Value::Enum {
+                variant,
+                fields: None,
+            } => variant.clone(),
+            Value::Struct(_) | Value::Enum { .. } => return None,
+        };
+        Some(s)
+    }
+
+    /// Writes a value that doesn't fit on one line, one line per field or
+    /// element.
+    fn write_block(&self, out: &mut String, indent: usize) -> fmt::Result {
+        match self {
+            Value::Option(Some(v)) => v.write_block(out, indent),
+            Value::Array(items) => {
+                for item in items {
+                    write!(out, "{:indent$}-", "")?;
+                    write_entry(out, item, indent + 2)?;
+                }
+                Ok(())
+            }
+            Value::Struct(fields) => {
+                for (name, value) in fields {
+                    write!(out, "{:indent$}{}:", "", name)?;
+                    write_entry(out, value, indent + 2)?;
+                }
+                Ok(())
+            }
+            Value::Enum {
+                variant,
+                fields: Some(fields),
+            } => {
+                write!(out, "{:indent$}{}:", "", variant)?;
+                write_entry(out, fields, indent + 2)
+            }
+            v => writeln!(out, "{:indent$}{}", "", v.inline().unwrap_or_default()),
+        }
+    }
+}
+
+/// Writes `value` after a `name:` or `-` prefix.
+fn write_entry(out: &mut String, value: &Value, indent: usize) -> fmt::Result {
+    match value.inline() {
+        Some(s) => writeln!(out, " {}", s),
+        None => {
+            out.push('\n');
+            value.write_block(out, indent)
+        }
+    }
+}
+
+/// Human readable form: scalars inline, nested structures indented below
+/// their name.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inline() {
+            Some(s) => f.write_str(&s),
+            None => {
+                let mut out = String::new();
+                self.write_block(&mut out, 0)?;
+                f.write_str(out.trim_end())
+            }
+        }
+    }
+}
+
+/// How strictly data has to match the IDL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// When the args don't match the IDL (program upgraded, IDL stale),
+    /// return the args that decoded and the remaining bytes instead of an
+    /// error. The discriminator still has to be known.
+    pub lenient: bool,
+    /// Annotate the accounts of decoded transaction instructions with how
+    /// the transaction changed their lamport and token balances.
+    pub balances: bool,
+}
+
+/// Instruction data decoded by [`decode_instruction`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedInstruction {
+    pub name: String,
+    /// A [`Value::Struct`] of the args, only the leading ones that decoded
+    /// when decoding leniently.
+    pub args: Value,
+    /// Bytes left undecoded by a lenient decode, from the first arg that
+    /// failed or after the last one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<u8>,
+    /// Why a lenient decode stopped before the last arg.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl fmt::Display for DecodedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = self.name.clone();
+        out.push('\n');
+        self.args.write_block(&mut out, 2)?;
+        if !self.remaining.is_empty() {
+            write!(out, "  <{} bytes not decoded", self.remaining.len())?;
+            if let Some(error) = &self.error {
+                write!(out, ": {}", error)?;
+            }
+            out.push('>');
+        }
+        f.write_str(out.trim_end())
+    }
+}
+
+/// Identifies the instruction by the discriminator starting `data` and
+/// decodes its args from the rest, which must be consumed entirely.
+pub fn decode_instruction(program: &Program, data: &[u8]) -> Result<DecodedInstruction> {
+    decode_instruction_with(program, data, DecodeOptions::default())
+}
+
+/// [`decode_instruction`] with `options`.
+pub fn decode_instruction_with(
+    program: &Program,
+    data: &[u8],
+    options: DecodeOptions,
+) -> Result<DecodedInstruction> {
+    let (discriminator, mut rest) = split_discriminator(data)?;
+    let ix = program
+        .instructions
+        .iter()
+        .find(|ix| ix.discriminator == discriminator)
+        .ok_or_else(|| {
+            log_unknown(program, &["instruction"], data);
+            ParseIdlError::UnknownDiscriminator(discriminator)
+        })?;
+
+    if !options.lenient {
+        let args = decode_fields(program, &ix.args, &mut rest)?;
+        if !rest.is_empty() {
+            return Err(ParseIdlError::Decode(format!(
+                "{} trailing bytes after the args of {}",
+                rest.len(),
+                ix.name
+            )));
+        }
+        return Ok(DecodedInstruction {
+            name: ix.name.clone(),
+            args,
+            remaining: vec![],
+            error: None,
+        });
+    }
+
+    let mut args = vec![];
+    let mut error = None;
+    for arg in ix.args.iter() {
+        // `rest` only advances past args that decoded entirely
+        let mut data = rest;
+        match decode_value(program, &arg.ty, &mut data) {
+            Ok(value) => {
+                args.push((arg.name.clone(), value));
+                rest = data;
+            }
+            Err(e) => {
+                error = Some(field_error(&arg.name, e));
+                break;
+            }
+        }
+    }
+    Ok(DecodedInstruction {
+        name: ix.name.clone(),
+        args: Value::Struct(args),
+        remaining: rest.to_vec(),
+        error,
+    })
+}
+
+/// Account data decoded by [`decode_account`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedAccount {
+    /// Name of the program in its IDL.
+    pub program: String,
+    /// Name of the account type.
+    pub name: String,
+    /// A [`Value::Struct`] of the fields.
+    pub fields: Value,
+}
+
+impl fmt::Display for DecodedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = self.name.clone();
+        out.push('\n');
+        self.fields.write_block(&mut out, 2)?;
+        f.write_str(out.trim_end())
+    }
+}
+
+/// The known discriminators of `program` of the `kinds` ("instruction",
+/// "account" or "event") closest to `discriminator`, by the bits they
+/// differ in, as `kind name`, nearest first.
+pub fn nearest_discriminators(
+    program: &Program,
+    kinds: &[&str],
+    discriminator: [u8; 8],
+    count: usize,
+) -> Vec<String> {
+    let known = program
+        .instructions
+        .iter()
+        .map(|ix| ("instruction", &ix.name, ix.discriminator))
+        .chain(
+            program
+                .accounts
+                .iter()
+                .filter_map(|a| Some(("account", &a.name, a.discriminator?))),
+        )
+        .chain(
+            program
+                .events
+                .iter()
+                .map(|e| ("event", &e.name, e.discriminator)),
+        );
+    let mut by_distance: Vec<(u32, String)> = known
+        .filter(|(kind, _, _)| kinds.contains(kind))
+        .map(|(kind, name, known)| {
+            let distance = known
+                .iter()
+                .zip(discriminator.iter())
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum();
+            (distance, format!("{} {}", kind, name))
+        })
+        .collect();
+    by_distance.sort();
+    by_distance
+        .into_iter()
+        .take(count)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Bytes of a logged hex dump, the rest is only counted.
+const DUMPED_BYTES: usize = 64;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Logs `data`, whose discriminator `program` has none of the `kinds` for,
+/// as a hex dump with the nearest known discriminators: it usually means
+/// the IDL is stale.
+fn log_unknown(program: &Program, kinds: &[&str], data: &[u8]) {
+    let Ok((discriminator, _)) = split_discriminator(data) else {
+        return;
+    };
+    let dump = &data[..data.len().min(DUMPED_BYTES)];
+    let elided = if data.len() > dump.len() { "..." } else { "" };
+    warn!(
+        "{} has no {} with discriminator {}, nearest: {}; {} bytes: {}{}",
+        program.name,
+        kinds.join(", "),
+        hex(&discriminator),
+        nearest_discriminators(program, kinds, discriminator, 3).join(", "),
+        data.len(),
+        hex(dump),
+        elided
+    );
+}
+
+fn split_discriminator(data: &[u8]) -> Result<([u8; 8], &[u8])> {
+    let discriminator = data
+        .get(..8)
+        .and_then(|d| d.try_into().ok())
+        .ok_or_else(|| ParseIdlError::Decode("data is shorter than a discriminator".into()))?;
+    Ok((discriminator, &data[8..]))
+}
+
+/// Identifies the account type by the discriminator starting `data` and
+/// decodes its fields. Accounts are often allocated larger than their
+/// content, bytes after the fields are ignored.
+pub fn decode_account(program: &Program, data: &[u8]) -> Result<DecodedAccount> {
+    let (discriminator, mut rest) = split_discriminator(data)?;
+    let account = program
+        .accounts
+        .iter()
+        .find(|a| a.discriminator == Some(discriminator))
+        .ok_or_else(|| {
+            log_unknown(program, &["account"], data);
+            ParseIdlError::UnknownAccount(discriminator)
+        })?;
+    let fields = match &account.kind {
+        TypeDefKind::Struct { fields } => decode_fields(program, fields, &mut rest)?,
+        TypeDefKind::Enum { .. } => {
+            decode_value(program, &Type::Defined(account.name.clone()), &mut rest)?
+        }
+    };
+    Ok(DecodedAccount {
+        program: program.name.clone(),
+        name: account.name.clone(),
+        fields,
+    })
+}
+
+/// An event decoded by [`decode_event`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedEvent {
+    /// Name of the program in its IDL.
+    pub program: String,
+    pub name: String,
+    /// A [`Value::Struct`] of the fields.
+    pub fields: Value,
+}
+
+impl fmt::Display for DecodedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = format!("{} event {}\n", self.program, self.name);
+        self.fields.write_block(&mut out, 2)?;
+        f.write_str(out.trim_end())
+    }
+}
+
+/// Identifies the event by the discriminator starting `data` and decodes
+/// its fields.
+pub fn decode_event(program: &Program, data: &[u8]) -> Result<DecodedEvent> {
+    let (discriminator, mut rest) = split_discriminator(data)?;
+    let event = program
+        .events
+        .iter()
+        .find(|e| e.discriminator == discriminator)
+        .ok_or_else(|| {
+            log_unknown(program, &["event"], data);
+            ParseIdlError::UnknownEvent(discriminator)
+        })?;
+    let fields: Vec<_> = event
+        .fields
+        .iter()
+        .map(|f| Field {
+            name: f.name.clone(),
+            ty: f.ty.clone(),
+        })
+        .collect();
+    Ok(DecodedEvent {
+        program: program.name.clone(),
+        name: event.name.clone(),
+        fields: decode_fields(program, &fields, &mut rest)?,
+    })
+}
+
+fn decode_fields(program: &Program, fields: &[Field], data: &mut &[u8]) -> Result<Value> {
+    let mut values = Vec::with_capacity(fields.len());
+    for field in fields {
+        let value = decode_value(program, &field.ty, data)
+            .map_err(|e| ParseIdlError::Decode(field_error(&field.name, e)))?;
+        values.push((field.name.clone(), value));
+    }
+    Ok(Value::Struct(values))
+}
+
+/// Message of a decode error prefixed with the field it happened in.
+fn field_error(name: &str, e: ParseIdlError) -> String {
+    match e {
+        ParseIdlError::Decode(msg) => format!("{}: {}", name, msg),
+        e => format!("{}: {}", name, e),
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(ParseIdlError::Decode("unexpected end of data".into()));
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+
+fn take_array<const N: usize>(data: &mut &[u8]) -> Result<[u8; N]> {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(take(data, N)?);
+    Ok(bytes)
+}
+
+fn take_len(data: &mut &[u8]) -> Result<usize> {
+    Ok(u32::from_le_bytes(take_array(data)?) as usize)
+}
+
+/// Decodes one value of `ty`, any type of `program`, from the start of
+/// `data` and advances past it.
+pub fn decode_value(program: &Program, ty: &Type, data: &mut &[u8]) -> Result<Value> {
+    let value = match ty {
+        Type::Bool => match take_array::<1>(data)?[0] {
+            0 => Value::Bool(false),
+            1 => Value::Bool(true),
+            b => return Err(ParseIdlError::Decode(format!("invalid bool {}", b))),
+        },
+        Type::U8 => Value::U8(take_array::<1>(data)?[0]),
+        Type::I8 => Value::I8(i8::from_le_bytes(take_array(data)?)),
+        Type::U16 => Value::U16(u16::from_le_bytes(take_array(data)?)),
+        Type::I16 => Value::I16(i16::from_le_bytes(take_array(data)?)),
+        Type::U32 => Value::U32(u32::from_le_bytes(take_array(data)?)),
+        Type::I32 => Value::I32(i32::from_le_bytes(take_array(data)?)),
+        Type::F32 => Value::F32(f32::from_le_bytes(take_array(data)?)),
+        Type::U64 => Value::U64(u64::from_le_bytes(take_array(data)?)),
+        Type::I64 => Value::I64(i64::from_le_bytes(take_array(data)?)),
+        Type::F64 => Value::F64(f64::from_le_bytes(take_array(data)?)),
+        Type::U128 => Value::U128(u128::from_le_bytes(take_array(data)?)),
+        Type::I128 => Value::I128(i128::from_le_bytes(take_array(data)?)),
+        Type::Bytes => {
+            let len = take_len(data)?;
+            Value::Bytes(take(data, len)?.to_vec())
+        }
+        Type::String => {
+            let len = take_len(data)?;
+            let s = std::str::from_utf8(take(data, len)?)
+                .map_err(|e| ParseIdlError::Decode(format!("invalid string: {}", e)))?;
+            Value::String(s.to_string())
+        }
+        Type::PublicKey => Value::PublicKey(take_array(data)?),
+        Type::Option(inner) => match take_array::<1>(data)?[0] {
+            0 => Value::Option(None),
+            1 => Value::Option(Some(Box::new(decode_value(program, inner, data)?))),
+            b => return Err(ParseIdlError::Decode(format!("invalid option tag {}", b))),
+        },
+        Type::Vec(inner) => {
+            let len = take_len(data)?;
+            // every element takes at least a byte, don't trust the length
+            let mut items = Vec::with_capacity(len.min(data.len()));
+            for _ in 0..len {
+                items.push(decode_value(program, inner, data)?);
+            }
+            Value::Array(items)
+        }
+        Type::Array(inner, len) => {
+            let mut items = Vec::with_capacity((*len).min(data.len()));
+            for _ in 0..*len {
+                items.push(decode_value(program, inner, data)?);
+            }
+            Value::Array(items)
+        }
+        Type::Defined(name) => {
+            let def = program
+                .find_type(name)
+                .ok_or_else(|| ParseIdlError::UnsupportedType(name.clone()))?;
+            match &def.kind {
+                TypeDefKind::Struct { fields } => decode_fields(program, fields, data)?,
+                TypeDefKind::Enum { variants } => {
+                    let tag = take_array::<1>(data)?[0];
+                    let variant = variants.get(tag as usize).ok_or_else(|| {
+                        ParseIdlError::Decode(format!("invalid {} variant {}", name, tag))
+                    })?;
+                    let fields = match &variant.fields {
+                        None => None,
+                        Some(EnumFields::Named(fields)) => {
+                            Some(Box::new(decode_fields(program, fields, data)?))
+                        }
+                        Some(EnumFields::Tuple(types)) => {
+                            let items = types
+                                .iter()
+                                .map(|ty| decode_value(program, ty, data))
+                                .collect::<Result<Vec<_>>>()?;
+                            Some(Box::new(Value::Array(items)))
+                        }
+                    };
+                    Value::Enum {
+                        variant: variant.name.clone(),
+                        fields,
+                    }
+                }
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// The programs transactions are decoded with. A program can be registered
+/// in several versions, each in effect from its activation slot until the
+/// next one, so transactions from before a redeployment that changed the
+/// layouts decode with the matching IDL.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramSet {
+    /// `(activation slot, program)` sorted by slot.
+    versions: Vec<(u64, Program)>,
+}
+
+impl ProgramSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `program` for all slots, unless later versions follow.
+    pub fn add(&mut self, program: Program) {
+        self.add_version(program, 0);
+    }
+
+    /// Registers a version of a program in effect from `activation_slot`.
+    pub fn add_version(&mut self, program: Program, activation_slot: u64) {
+        let at = self
+            .versions
+            .partition_point(|(slot, _)| *slot <= activation_slot);
+        self.versions.insert(at, (activation_slot, program));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// The version of the program at `address` in effect at `slot`, the
+    /// newest one when the slot is unknown. Slots before the first
+    /// activation get the oldest version.
+    pub fn get(&self, address: &str, slot: Option<u64>) -> Option<&Program> {
+        let mut versions = self.versions.iter().filter(|(_, p)| p.address == address);
+        let oldest = versions.next()?;
+        let in_effect = versions
+            .take_while(|(activation, _)| slot.is_none_or(|slot| *activation <= slot))
+            .last();
+        Some(&in_effect.unwrap_or(oldest).1)
+    }
+
+    /// One version of every program, the one in effect at `slot`.
+    pub fn at(&self, slot: Option<u64>) -> Vec<&Program> {
+        let mut programs: Vec<&Program> = vec![];
+        for (_, program) in self.versions.iter() {
+            if !programs.iter().any(|p| p.address == program.address) {
+                programs.extend(self.get(&program.address, slot));
+            }
+        }
+        programs
+    }
+}
+
+impl FromIterator<Program> for ProgramSet {
+    fn from_iter<I: IntoIterator<Item = Program>>(programs: I) -> Self {
+        let mut set = Self::new();
+        for program in programs {
+            set.add(program);
+        }
+        set
+    }
+}
+
+/// A value of a named type decoded by [`decode_named`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedValue {
+    /// Name of the type in the IDL.
+    #[serde(rename = "type")]
+    pub name: String,
+    pub value: Value,
+    /// Bytes after the value, kept by a lenient decode.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<u8>,
+}
+
+impl fmt::Display for DecodedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = self.name.clone();
+        write_entry(&mut out, &self.value, 2)?;
+        if !self.remaining.is_empty() {
+            write!(out, "  <{} bytes not decoded>", self.remaining.len())?;
+        }
+        f.write_str(out.trim_end())
+    }
+}
+
+/// Decodes `data`, which has no discriminator, as a value of the type
+/// `name` of `program`, consuming it entirely unless decoding leniently.
+pub fn decode_named(
+    program: &Program,
+    name: &str,
+    data: &[u8],
+    options: DecodeOptions,
+) -> Result<DecodedValue> {
+    let ty = program
+        .find_type(name)
+        .map(|def| Type::Defined(def.name.clone()))
+        .ok_or_else(|| ParseIdlError::UnsupportedType(name.to_string()))?;
+    let mut rest = data;
+    let value = decode_value(program, &ty, &mut rest)?;
+    if !rest.is_empty() && !options.lenient {
+        return Err(ParseIdlError::Decode(format!(
+            "{} trailing bytes after the {}",
+            rest.len(),
+            name
+        )));
+    }
+    Ok(DecodedValue {
+        name: name.to_string(),
+        value,
+        remaining: rest.to_vec(),
+    })
+}
+
+/// Data decoded by [`decode_data`], of the kind its discriminator belongs
+/// to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Decoded {
+    Instruction(DecodedInstruction),
+    Account(DecodedAccount),
+    Event(DecodedEvent),
+    /// Data whose discriminator the program has no instruction, account or
+    /// event for, which usually means the IDL is stale.
+    Unknown {
+        discriminator: [u8; 8],
+        data: Vec<u8>,
+    },
+}
+
+impl Decoded {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Decoded::Instruction(_) => "instruction",
+            Decoded::Account(_) => "account",
+            Decoded::Event(_) => "event",
+            Decoded::Unknown { .. } => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoded::Instruction(ix) => ix.fmt(f),
+            Decoded::Account(account) => account.fmt(f),
+            Decoded::Event(event) => event.fmt(f),
+            Decoded::Unknown {
+                discriminator,
+                data,
+            } => write!(
+                f,
+                "unknown discriminator {}, {} bytes",
+                hex(discriminator),
+                data.len()
+            ),
+        }
+    }
+}
+
+/// Decodes instruction data, account data or an event, whichever of them
+/// `program` has the discriminator starting `data` for, [`Decoded::Unknown`]
+/// (logged) if it has none.
+pub fn decode_data(program: &Program, data: &[u8], options: DecodeOptions) -> Result<Decoded> {
+    let (discriminator, _) = split_discriminator(data)?;
+    if program
+        .accounts
+        .iter()
+        .any(|a| a.discriminator == Some(discriminator))
+    {
+        return decode_account(program, data).map(Decoded::Account);
+    }
+    if program
+        .events
+        .iter()
+        .any(|e| e.discriminator == discriminator)
+    {
+        return decode_event(program, data).map(Decoded::Event);
+    }
+    if program
+        .instructions
+        .iter()
+        .any(|ix| ix.discriminator == discriminator)
+    {
+        return decode_instruction_with(program, data, options).map(Decoded::Instruction);
+    }
+    log_unknown(program, &["instruction", "account", "event"], data);
+    Ok(Decoded::Unknown {
+        discriminator,
+        data: data.to_vec(),
+    })
+}
+
+/// An instruction of a transaction fetched in the `json` encoding, with its
+/// account indices resolved to addresses.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransactionInstruction {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions invoked by the top-level instruction,
+    /// `None` for the top-level instruction itself.
+    pub inner_index: Option<usize>,
+    pub program_id: String,
+    pub accounts: Vec<String>,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// The addresses of the accounts of a `getTransaction` result, in the
+/// order the instructions and balances refer to them by.
+pub fn account_keys(tx: &serde_json::Value) -> Result<Vec<String>> {
+    let invalid = |what: &str| ParseIdlError::Decode(format!("invalid transaction: {}", what));
+    let message = &tx["transaction"]["message"];
+    let mut keys: Vec<String> = message["accountKeys"]
+        .as_array()
+        .ok_or_else(|| invalid("no account keys"))?
+        .iter()
+        .filter_map(|k| k.as_str().map(str::to_string))
+        .collect();
+    // v0 transactions: addresses loaded from lookup tables come after the
+    // static keys, writable ones first
+    let loaded = &tx["meta"]["loadedAddresses"];
+    let uses_lookups = message["addressTableLookups"]
+        .as_array()
+        .is_some_and(|l| !l.is_empty());
+    if uses_lookups && !loaded.is_object() {
+        return Err(invalid(
+            "addresses loaded from lookup tables are not resolved, see rpc::LookupTables",
+        ));
+    }
+    for kind in ["writable", "readonly"] {
+        if let Some(addresses) = loaded[kind].as_array() {
+            keys.extend(
+                addresses
+                    .iter()
+                    .filter_map(|k| k.as_str().map(str::to_string)),
+            );
+        }
+    }
+    Ok(keys)
+}
+
+/// All instructions of a `getTransaction` result, each top-level
+/// instruction followed by the instructions it invoked.
+pub fn transaction_instructions(tx: &serde_json::Value) -> Result<Vec<TransactionInstruction>> {
+    let invalid = |what: &str| ParseIdlError::Decode(format!("invalid transaction: {}", what));
+    let message = &tx["transaction"]["message"];
+    let keys = account_keys(tx)?;
+
+    let resolve = |ix: &serde_json::Value, index, inner_index| -> Result<_> {
+        let key = |i: &serde_json::Value| {
+            i.as_u64()
+                .and_then(|i| keys.get(i as usize))
+                .cloned()
+                .ok_or_else(|| invalid("account index out of range"))
+        };
+        Ok(TransactionInstruction {
+            index,
+            inner_index,
+            program_id: key(&ix["programIdIndex"])?,
+            accounts: ix["accounts"]
+                .as_array()
+                .ok_or_else(|| invalid("instruction without accounts"))?
+                .iter()
+                .map(key)
+                .collect::<Result<_>>()?,
+            data: Encoding::Base58.decode(ix["data"].as_str().unwrap_or_default())?,
+        })
+    };
+
+    let mut instructions = vec![];
+    let top_level = message["instructions"]
+        .as_array()
+        .ok_or_else(|| invalid("no instructions"))?;
+    let inner = tx["meta"]["innerInstructions"].as_array();
+    for (index, ix) in top_level.iter().enumerate() {
+        instructions.push(resolve(ix, index, None)?);
+        let invoked = inner
+            .into_iter()
+            .flatten()
+            .filter(|i| i["index"].as_u64() == Some(index as u64))
+            .filter_map(|i| i["instructions"].as_array())
+            .flatten();
+        for (inner_index, ix) in invoked.enumerate() {
+            instructions.push(resolve(ix, index, Some(inner_index))?);
+        }
+    }
+    Ok(instructions)
+}
+
+/// An instruction of a transaction decoded by [`decode_transaction`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DecodedTransactionInstruction {
+    pub index: usize,
+    pub inner_index: Option<usize>,
+    pub program_id: String,
+    /// Name of the program in its IDL.
+    pub program: String,
+    /// Serialized inline, its `name` and `args` next to the other fields.
+    #[serde(flatten)]
+    pub instruction: Option<DecodedInstruction>,
+    /// Why the instruction data could not be decoded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The accounts passed to the instruction, in order.
+    pub accounts: Vec<NamedAccount>,
+    /// The error the transaction failed with, on the instruction that
+    /// returned it, or on its top-level instruction if the failing program
+    /// isn't known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<ProgramError>,
+}
+
+/// An account passed to an instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NamedAccount {
+    /// Name of the account in the IDL's account list, `None` for accounts
+    /// past its end (remaining accounts) or when the instruction is unknown.
+    pub name: Option<String>,
+    pub address: String,
+    /// How the transaction changed the account's balances, when decoding
+    /// with [`DecodeOptions::balances`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<BalanceChange>,
+}
+
+/// Labels `addresses`, the accounts passed to `ix`, with the names of its
+/// account list. Lists leaving out trailing optional accounts are labeled
+/// up to their end.
+pub fn name_accounts(ix: &Instruction, addresses: &[String]) -> Vec<NamedAccount> {
+    let names = ix.qualified_accounts();
+    addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| NamedAccount {
+            name: names.get(i).map(|(name, _)| name.clone()),
+            address: address.clone(),
+            change: None,
+        })
+        .collect()
+}
+
+impl fmt::Display for DecodedTransactionInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)?;
+        if let Some(inner) = self.inner_index {
+            write!(f, ".{}", inner)?;
+        }
+        write!(f, " {} ", self.program)?;
+        match (&self.instruction, &self.error) {
+            (Some(ix), _) => write!(f, "{}", ix)?,
+            (None, error) => write!(f, "<{}>", error.as_deref().unwrap_or("unknown"))?,
+        }
+        if let Some(failure) = &self.failure {
+            write!(f, "\n  failed: {}", failure)?;
+        }
+        if !self.accounts.is_empty() {
+            f.write_str("\n  accounts:")?;
+        }
+        for (i, account) in self.accounts.iter().enumerate() {
+            match &account.name {
+                Some(name) => write!(f, "\n    {}: {}", name, account.address)?,
+                None => write!(f, "\n    #{}: {}", i, account.address)?,
+            }
+            if let Some(change) = &account.change {
+                write!(f, "  {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the instructions of a `getTransaction` result that belong to
+/// one of `programs`, top-level and inner, with the program versions in
+/// effect at the transaction's slot. Instructions that don't decode are
+/// kept with their error, `emit_cpi!` event invocations are left to
+/// [`transaction_events`].
+pub fn decode_transaction(
+    programs: &ProgramSet,
+    tx: &serde_json::Value,
+    options: DecodeOptions,
+) -> Result<Vec<DecodedTransactionInstruction>> {
+    let slot = tx["slot"].as_u64();
+    let failure = transaction_error(tx).map(|(index, code)| {
+        let program_id = failed_program(tx);
+        let program = program_id.as_deref().and_then(|id| programs.get(id, slot));
+        (index, program_error(program, program_id, code))
+    });
+    let changes = match options.balances {
+        true => balance_changes(tx)?,
+        false => Default::default(),
+    };
+    let mut decoded = vec![];
+    for ix in transaction_instructions(tx)? {
+        let Some(program) = programs.get(&ix.program_id, slot) else {
+            continue;
+        };
+        if ix.inner_index.is_some() && ix.data.starts_with(&EVENT_IX_TAG) {
+            continue;
+        }
+        let (instruction, error) = match decode_instruction_with(program, &ix.data, options) {
+            Ok(instruction) => (Some(instruction), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let idl_ix = instruction
+            .as_ref()
+            .and_then(|decoded| program.instructions.iter().find(|i| i.name == decoded.name));
+        let mut accounts = match idl_ix {
+            Some(idl_ix) => name_accounts(idl_ix, &ix.accounts),
+            None => ix
+                .accounts
+                .iter()
+                .map(|address| NamedAccount {
+                    name: None,
+                    address: address.clone(),
+                    change: None,
+                })
+                .collect(),
+        };
+        for account in accounts.iter_mut() {
+            account.change = changes.get(&account.address).cloned();
+        }
+        decoded.push(DecodedTransactionInstruction {
+            index: ix.index,
+            inner_index: ix.inner_index,
+            program_id: ix.program_id,
+            program: program.name.clone(),
+            failure: None,
+            instruction,
+            error,
+            accounts,
+        });
+    }
+    if let Some((index, failure)) = failure {
+        let failed = decoded
+            .iter()
+            .rposition(|d| d.index == index && Some(&d.program_id) == failure.program_id.as_ref())
+            .or_else(|| {
+                decoded
+                    .iter()
+                    .position(|d| d.index == index && d.inner_index.is_none())
+            });
+        if let Some(failed) = failed {
+            decoded[failed].failure = Some(failure);
+        }
+    }
+    Ok(decoded)
+}
+
+/// Instruction discriminator of the self-invocation `emit_cpi!` carries an
+/// event in, followed by the event's own discriminator and fields.
+pub const EVENT_IX_TAG: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+/// An event with the instruction of the transaction that emitted it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransactionEvent {
+    /// Position of the top-level instruction in the message.
+    pub index: usize,
+    /// Position among the instructions the top-level instruction invoked of
+    /// the instruction executing when the event was emitted, `None` for the
+    /// top-level instruction itself.
+    pub inner_index: Option<usize>,
+    /// The program that emitted the event.
+    pub program_id: String,
+    #[serde(flatten)]
+    pub event: DecodedEvent,
+}
+
+impl fmt::Display for TransactionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)?;
+        if let Some(inner) = self.inner_index {
+            write!(f, ".{}", inner)?;
+        }
+        write!(f, " {}", self.event)
+    }
+}
+
+/// An event logged by `emit!`, attributed to the instruction executing at
+/// the time.
+struct LoggedEvent<'a> {
+    index: usize,
+    inner_index: Option<usize>,
+    /// Number of instructions invoked by the top-level instruction so far.
+    invoked: usize,
+    program_id: &'a str,
+    data: Vec<u8>,
+}
+
+/// Follows the `invoke`/`success`/`failed` lines of transaction logs to
+/// tell which instruction wrote each `Program data:` line. Stops at a
+/// truncated log, after which nothing can be attributed.
+fn logged_events<'a>(logs: impl IntoIterator<Item = &'a str>) -> Vec<LoggedEvent<'a>> {
+    let mut events = vec![];
+    // (program, inner index) of every instruction being executed
+    let mut stack: Vec<(&str, Option<usize>)> = vec![];
+    let mut index = None;
+    let mut invoked = 0;
+    for line in logs {
+        if line == "Log truncated" {
+            break;
+        }
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            let (Some(index), Some((program_id, inner_index))) = (index, stack.last()) else {
+                continue;
+            };
+            // `sol_log_data` logs each slice separately
+            let data: Option<Vec<u8>> = data
+                .split_whitespace()
+                .map(|chunk| Encoding::Base64.decode(chunk).ok())
+                .collect::<Option<Vec<_>>>()
+                .map(|chunks| chunks.concat());
+            if let Some(data) = data {
+                events.push(LoggedEvent {
+                    index,
+                    inner_index: *inner_index,
+                    invoked,
+                    program_id,
+                    data,
+                });
+            }
+            continue;
+        }
+        let Some((program_id, status)) = rest.split_once(' ') else {
+            continue;
+        };
+        if status == "invoke [1]" {
+            index = Some(index.map_or(0, |i: usize| i + 1));
+            invoked = 0;
+            stack = vec![(program_id, None)];
+        } else if status.starts_with("invoke [") {
+            stack.push((program_id, Some(invoked)));
+            invoked += 1;
+        } else if status == "success" || status.starts_with("failed") {
+            stack.pop();
+        }
+    }
+    events
+}
+
+/// Events of `programs` a `getTransaction` result emitted, in the order
+/// they were emitted: those logged by `emit!` and those carried by the
+/// inner instructions of `emit_cpi!`. Each is decoded by the program that
+/// emitted it, in its version in effect at the transaction's slot.
+pub fn transaction_events(
+    programs: &ProgramSet,
+    tx: &serde_json::Value,
+) -> Result<Vec<TransactionEvent>> {
+    let slot = tx["slot"].as_u64();
+    // sorted by (index, instructions invoked before, logged first)
+    let mut events = vec![];
+    let logs = tx["meta"]["logMessages"].as_array().into_iter().flatten();
+    for logged in logged_events(logs.filter_map(|l| l.as_str())) {
+        let Some(program) = programs.get(logged.program_id, slot) else {
+            continue;
+        };
+        if let Ok(event) = decode_event(program, &logged.data) {
+            let event = TransactionEvent {
+                index: logged.index,
+                inner_index: logged.inner_index,
+                program_id: logged.program_id.to_string(),
+                event,
+            };
+            events.push(((logged.index, logged.invoked, 0), event));
+        }
+    }
+    for ix in transaction_instructions(tx)? {
+        let (Some(inner_index), Some(data)) = (ix.inner_index, ix.data.strip_prefix(&EVENT_IX_TAG))
+        else {
+            continue;
+        };
+        let Some(program) = programs.get(&ix.program_id, slot) else {
+            continue;
+        };
+        if let Ok(event) = decode_event(program, data) {
+            let event = TransactionEvent {
+                index: ix.index,
+                inner_index: Some(inner_index),
+                program_id: ix.program_id,
+                event,
+            };
+            events.push(((ix.index, inner_index, 1), event));
+        }
+    }
+    events.sort_by_key(|(key, _)| *key);
+    Ok(events.into_iter().map(|(_, event)| event).collect())
+}
+
+/// How a transaction changed the balances of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceChange {
+    /// Change of the SOL balance, in lamports.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub lamports: i64,
+    /// Change of the balance of a token account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<TokenChange>,
+}
+
+fn is_zero(lamports: &i64) -> bool {
+    *lamports == 0
+}
+
+/// Change of the balance of a token account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenChange {
+    pub mint: String,
+    /// Change in base units, a string in JSON.
+    #[serde(serialize_with = "serialize_display")]
+    pub amount: i128,
+    pub decimals: u8,
+    /// `amount` shifted by `decimals`, e.g. `-1.5`.
+    pub ui_amount: String,
+}
+
+fn serialize_display<S: Serializer>(
+    v: &impl fmt::Display,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_str(v)
+}
+
+/// Symbols of a few mints, other mints are shown by address.
+const KNOWN_MINTS: &[(&str, &str)] = &[
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"),
+    ("So11111111111111111111111111111111111111112", "wSOL"),
+];
+
+/// A signed amount in base units, shifted by `decimals`, without trailing
+/// zeros.
+fn ui_amount(amount: i128, decimals: u8) -> String {
+    let sign = if amount < 0 { "-" } else { "+" };
+    let digits = amount.unsigned_abs().to_string();
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}{}", sign, whole)
+    } else {
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+}
+
+impl fmt::Display for BalanceChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(token) = &self.token {
+            let symbol = KNOWN_MINTS
+                .iter()
+                .find(|(mint, _)| *mint == token.mint)
+                .map_or(token.mint.as_str(), |(_, symbol)| symbol);
+            parts.push(format!("{} {}", token.ui_amount, symbol));
+        }
+        if self.lamports != 0 {
+            parts.push(format!("{} SOL", ui_amount(self.lamports as i128, 9)));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// The changes of the lamport and token balances of the accounts of a
+/// `getTransaction` result, by address. Accounts whose balances didn't
+/// change are left out.
+pub fn balance_changes(tx: &serde_json::Value) -> Result<HashMap<String, BalanceChange>> {
+    let keys = account_keys(tx)?;
+    let meta = &tx["meta"];
+    let lamports = |kind: &str, i: usize| meta[kind][i].as_u64().unwrap_or_default() as i64;
+    let mut changes: Vec<BalanceChange> = (0..keys.len())
+        .map(|i| BalanceChange {
+            lamports: lamports("postBalances", i) - lamports("preBalances", i),
+            token: None,
+        })
+        .collect();
+
+    // accounts created or closed by the transaction only have one of them
+    for (kind, sign) in [("preTokenBalances", -1), ("postTokenBalances", 1)] {
+        for balance in meta[kind].as_array().into_iter().flatten() {
+            let Some(change) = balance["accountIndex"]
+                .as_u64()
+                .and_then(|i| changes.get_mut(i as usize))
+            else {
+                continue;
+            };
+            let amount = &balance["uiTokenAmount"];
+            let raw: i128 = amount["amount"]
+                .as_str()
+                .and_then(|a| a.parse().ok())
+                .unwrap_or_default();
+            let token = change.token.get_or_insert_with(|| TokenChange {
+                mint: balance["mint"].as_str().unwrap_or_default().to_string(),
+                amount: 0,
+                decimals: amount["decimals"].as_u64().unwrap_or_default() as u8,
+                ui_amount: String::new(),
+            });
+            token.amount += sign * raw;
+        }
+    }
+
+    Ok(keys
+        .into_iter()
+        .zip(changes)
+        .filter_map(|(address, mut change)| {
+            if let Some(token) = &mut change.token {
+                token.ui_amount = ui_amount(token.amount, token.decimals);
+            }
+            change.token = change.token.filter(|t| t.amount != 0);
+            (change.lamports != 0 || change.token.is_some()).then_some((address, change))
+        })
+        .collect())
+}
+
+/// A custom program error a transaction failed with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgramError {
+    pub code: u32,
+    /// The program that returned the error, from the transaction logs.
+    pub program_id: Option<String>,
+    /// Name of the error in the IDL or among Anchor's own errors, `None`
+    /// for codes neither knows.
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error {}", self.code)?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        if let Some(msg) = &self.msg {
+            write!(f, ": {}", msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// First code of the errors declared by programs with `#[error_code]`.
+pub const ERROR_CODE_OFFSET: u32 = 6000;
+
+/// Errors of the anchor-lang framework itself, returned before or after
+/// the program's handler runs.
+#[rustfmt::skip]
+const ANCHOR_ERRORS: &[(u32, &str, &str)] = &[
+    (100, "InstructionMissing", "8 byte instruction identifier not provided"),
+    (101, "InstructionFallbackNotFound", "Fallback functions are not supported"),
+    (102, "InstructionDidNotDeserialize", "The program could not deserialize the given instruction"),
+    (103, "InstructionDidNotSerialize", "The program could not serialize the given instruction"),
+    (1000, "IdlInstructionStub", "The program was compiled without idl instructions"),
+    (1001, "IdlInstructionInvalidProgram", "Invalid program given to the IDL instruction"),
+    (1002, "IdlAccountNotEmpty", "IDL account must be empty in order to resize, try closing first"),
+    (1500, "EventInstructionStub", "The program was compiled without `event-cpi` feature"),
+    (2000, "ConstraintMut", "A mut constraint was violated"),
+    (2001, "ConstraintHasOne", "A has one constraint was violated"),
+    (2002, "ConstraintSigner", "A signer constraint was violated"),
+    (2003, "ConstraintRaw", "A raw constraint was violated"),
+    (2004, "ConstraintOwner", "An owner constraint was violated"),
+    (2005, "ConstraintRentExempt", "A rent exemption constraint was violated"),
+    (2006, "ConstraintSeeds", "A seeds constraint was violated"),
+    (2007, "ConstraintExecutable", "An executable constraint was violated"),
+    (2008, "ConstraintState", "Deprecated Error, feel free to replace with something else"),
+    (2009, "ConstraintAssociated", "An associated constraint was violated"),
+    (2010, "ConstraintAssociatedInit", "An associated init constraint was violated"),
+    (2011, "ConstraintClose", "A close constraint was violated"),
+    (2012, "ConstraintAddress", "An address constraint was violated"),
+    (2013, "ConstraintZero", "Expected zero account discriminant"),
+    (2014, "ConstraintTokenMint", "A token mint constraint was violated"),
+    (2015, "ConstraintTokenOwner", "A token owner constraint was violated"),
+    (2016, "ConstraintMintMintAuthority", "A mint mint authority constraint was violated"),
+    (2017, "ConstraintMintFreezeAuthority", "A mint freeze authority constraint was violated"),
+    (2018, "ConstraintMintDecimals", "A mint decimals constraint was violated"),
+    (2019, "ConstraintSpace", "A space constraint was violated"),
+    (2020, "ConstraintAccountIsNone", "A required account for the constraint is None"),
+    (2500, "RequireViolated", "A require expression was violated"),
+    (2501, "RequireEqViolated", "A require_eq expression was violated"),
+    (2502, "RequireKeysEqViolated", "A require_keys_eq expression was violated"),
+    (2503, "RequireNeqViolated", "A require_neq expression was violated"),
+    (2504, "RequireKeysNeqViolated", "A require_keys_neq expression was violated"),
+    (2505, "RequireGtViolated", "A require_gt expression was violated"),
+    (2506, "RequireGteViolated", "A require_gte expression was violated"),
+    (3000, "AccountDiscriminatorAlreadySet", "The account discriminator was already set on this account"),
+    (3001, "AccountDiscriminatorNotFound", "No 8 byte discriminator was found on the account"),
+    (3002, "AccountDiscriminatorMismatch", "8 byte discriminator did not match what was expected"),
+    (3003, "AccountDidNotDeserialize", "Failed to deserialize the account"),
+    (3004, "AccountDidNotSerialize", "Failed to serialize the account"),
+    (3005, "AccountNotEnoughKeys", "Not enough account keys given to the instruction"),
+    (3006, "AccountNotMutable", "The given account is not mutable"),
+    (3007, "AccountOwnedByWrongProgram", "The given account is owned by a different program than expected"),
+    (3008, "InvalidProgramId", "Program ID was not as expected"),
+    (3009, "InvalidProgramExecutable", "Program account is not executable"),
+    (3010, "AccountNotSigner", "The given account did not sign"),
+    (3011, "AccountNotSystemOwned", "The given account is not owned by the system program"),
+    (3012, "AccountNotInitialized", "The program expected this account to be already initialized"),
+    (3013, "AccountNotProgramData", "The given account is not a program data account"),
+    (3014, "AccountNotAssociatedTokenAccount", "The given account is not the associated token account"),
+    (3015, "AccountSysvarMismatch", "The given public key does not match the required sysvar"),
+    (3016, "AccountReallocExceedsLimit", "The account reallocation exceeds the MAX_PERMITTED_DATA_INCREASE limit"),
+    (3017, "AccountDuplicateReallocs", "The account was duplicated for more than one reallocation"),
+    (4100, "DeclaredProgramIdMismatch", "The declared program id does not match the actual program id"),
+    (5000, "Deprecated", "The API being used is deprecated and should no longer be used"),
+];
+
+/// Names `code`, a custom error returned by `program_id`, from the errors
+/// of `program` or Anchor's own errors below [`ERROR_CODE_OFFSET`].
+pub fn program_error(
+    program: Option<&Program>,
+    program_id: Option<String>,
+    code: u32,
+) -> ProgramError {
+    let errors = program.map(|p| p.errors.as_slice()).unwrap_or_default();
+    // IDLs list the codes with the offset, very old ones without it
+    let declared = errors.iter().find(|e| e.code == code).or_else(|| {
+        let relative = code.checked_sub(ERROR_CODE_OFFSET)?;
+        let without_offset = errors.iter().all(|e| e.code < ERROR_CODE_OFFSET);
+        errors.iter().find(|e| without_offset && e.code == relative)
+    });
+    let (name, msg) = match declared {
+        Some(ErrorCode { name, msg, .. }) => (Some(name.clone()), msg.clone()),
+        None => match ANCHOR_ERRORS.iter().find(|e| e.0 == code) {
+            Some((_, name, msg)) => (Some(name.to_string()), Some(msg.to_string())),
+            None => (None, None),
+        },
+    };
+    ProgramError {
+        code,
+        program_id,
+        name,
+        msg,
+    }
+}
+
+/// Index of the instruction and code of the custom program error a
+/// `getTransaction` result failed with, `None` for successful
+/// transactions and other errors.
+pub fn transaction_error(tx: &serde_json::Value) -> Option<(usize, u32)> {
+    let error = tx["meta"]["err"]["InstructionError"].as_array()?;
+    let index = error.first()?.as_u64()? as usize;
+    let code = error.get(1)?["Custom"].as_u64()?;
+    Some((index, u32::try_from(code).ok()?))
+}
+
+/// The program whose failure the logs of a `getTransaction` result end
+/// with: the first to fail in the last top-level instruction, the
+/// innermost one, as the programs that invoked it fail after it.
+fn failed_program(tx: &serde_json::Value) -> Option<String> {
+    let logs: Vec<&str> = tx["meta"]["logMessages"]
+        .as_array()?
+        .iter()
+        .filter_map(|l| l.as_str())
+        .collect();
+    fn status(line: &str) -> Option<(&str, &str)> {
+        line.strip_prefix("Program ")?.split_once(' ')
+    }
+    let last = logs
+        .iter()
+        .rposition(|line| status(line).is_some_and(|(_, s)| s == "invoke [1]"))
+        .unwrap_or_default();
+    logs[last..].iter().find_map(|line| {
+        let (program_id, status) = status(line)?;
+        status.starts_with("failed").then(|| program_id.to_string())
+    })
+}
+
+/// Text encodings of binary data on the command line and in explorers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Base58,
+    Base64,
+    Hex,
+}
+
+impl Encoding {
+    pub fn decode(&self, s: &str) -> Result<Vec<u8>> {
+        let s = s.trim();
+        match self {
+            Encoding::Base58 => bs58::decode(s)
+                .into_vec()
+                .map_err(|e| ParseIdlError::Decode(format!("invalid base58: {}", e))),
+            Encoding::Base64 => {
+                use base64::Engine;
+                base64::engine::general_purpose::STANDARD
+                    .decode(s)
+                    .map_err(|e| ParseIdlError::Decode(format!("invalid base64: {}", e)))
+            }
+            Encoding::Hex => {
+                let s = s.strip_prefix("0x").unwrap_or(s);
+                if !s.len().is_multiple_of(2) {
+                    return Err(ParseIdlError::Decode("odd number of hex digits".into()));
+                }
+                (0..s.len())
+                    .step_by(2)
+                    .map(|i| {
+                        s.get(i..i + 2)
+                            .and_then(|b| u8::from_str_radix(b, 16).ok())
+                            .ok_or_else(|| ParseIdlError::Decode(format!("invalid hex: {}", s)))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "base58" => Ok(Encoding::Base58),
+            "base64" => Ok(Encoding::Base64),
+            "hex" => Ok(Encoding::Hex),
+            _ => Err(format!(
+                "unknown encoding '{}', expected base58, base64 or hex",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use base64::Engine;
+    use serde_json::json;
+
+    use crate::ir::{discriminator, EnumVariant, Event, EventField, TypeDef};
+
+    /// A program with one `run` instruction without args or accounts and
+    /// one error at the offset.
+    fn program(name: &str, error: &str) -> Program {
+        Program {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            address: name.to_string(),
+            instructions: vec![Instruction {
+                name: "run".to_string(),
+                discriminator: discriminator("global", "run"),
+                accounts: vec![],
+                args: vec![],
+                returns: None,
+            }],
+            accounts: vec![],
+            types: vec![],
+            events: vec![],
+            errors: vec![ErrorCode {
+                code: ERROR_CODE_OFFSET,
+                name: error.to_string(),
+                msg: None,
+            }],
+            constants: vec![],
+        }
+    }
+
+    /// [`program`] with a `Side` enum of every kind of variant, a
+    /// `swap(amount: u64, side: Side)` instruction and a `Swapped { amount: u64 }`
+    /// event.
+    fn demo(name: &str) -> Program {
+        let field = |name: &str, ty: Type| Field {
+            name: name.to_string(),
+            ty,
+        };
+        let mut program = program(name, "DemoError");
+        program.types.push(TypeDef {
+            name: "Side".to_string(),
+            discriminator: None,
+            kind: TypeDefKind::Enum {
+                variants: vec![
+                    EnumVariant {
+                        name: "Bid".to_string(),
+                        fields: None,
+                    },
+                    EnumVariant {
+                        name: "Ask".to_string(),
+                        fields: Some(EnumFields::Named(vec![field("price", Type::U64)])),
+                    },
+                    EnumVariant {
+                        name: "Custom".to_string(),
+                        fields: Some(EnumFields::Tuple(vec![Type::U8, Type::String])),
+                    },
+                ],
+            },
+        });
+        program.instructions.push(Instruction {
+            name: "swap".to_string(),
+            discriminator: discriminator("global", "swap"),
+            accounts: vec![],
+            args: vec![
+                field("amount", Type::U64),
+                field("side", Type::Defined("Side".to_string())),
+            ],
+            returns: None,
+        });
+        program.events.push(Event {
+            name: "Swapped".to_string(),
+            discriminator: discriminator("event", "Swapped"),
+            fields: vec![EventField {
+                name: "amount".to_string(),
+                ty: Type::U64,
+                index: false,
+            }],
+        });
+        program
+    }
+
+    /// Data of a `Swapped` event.
+    fn swapped(amount: u64) -> Vec<u8> {
+        [
+            &discriminator("event", "Swapped")[..],
+            &amount.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    fn base64(data: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+
+    #[test]
+    fn decodes_options_vecs_and_enums() {
+        let program = demo("Demo");
+        let decode = |ty: Type, mut data: &[u8]| {
+            let value = decode_value(&program, &ty, &mut data);
+            (value, data.len())
+        };
+        let side = || Type::Defined("Side".to_string());
+
+        let option = || Type::Option(Box::new(Type::U64));
+        let some = [&[1][..], &5u64.to_le_bytes()].concat();
+        assert_eq!(
+            decode(option(), &some).0.unwrap(),
+            Value::Option(Some(Box::new(Value::U64(5))))
+        );
+        assert_eq!(decode(option(), &[0, 9]).0.unwrap(), Value::Option(None));
+        assert_eq!(decode(option(), &[0, 9]).1, 1);
+        assert!(decode(option(), &[2]).0.is_err());
+
+        let strings = Type::Vec(Box::new(Type::String));
+        let data = [
+            &2u32.to_le_bytes()[..],
+            &1u32.to_le_bytes(),
+            b"a",
+            &2u32.to_le_bytes(),
+            b"bc",
+        ]
+        .concat();
+        assert_eq!(
+            decode(strings.clone(), &data).0.unwrap(),
+            Value::Array(vec![Value::String("a".into()), Value::String("bc".into())])
+        );
+        // a length beyond the data
+        assert!(decode(strings, &u32::MAX.to_le_bytes()).0.is_err());
+
+        assert_eq!(
+            decode(side(), &[0]).0.unwrap(),
+            Value::Enum {
+                variant: "Bid".into(),
+                fields: None
+            }
+        );
+        let ask = [&[1][..], &7u64.to_le_bytes()].concat();
+        assert_eq!(
+            decode(side(), &ask).0.unwrap(),
+            Value::Enum {
+                variant: "Ask".into(),
+                fields: Some(Box::new(Value::Struct(vec![(
+                    "price".into(),
+                    Value::U64(7)
+                )])))
+            }
+        );
+        let custom = [&[2, 3][..], &1u32.to_le_bytes(), b"x"].concat();
+        assert_eq!(
+            decode(side(), &custom).0.unwrap(),
+            Value::Enum {
+                variant: "Custom".into(),
+                fields: Some(Box::new(Value::Array(vec![
+                    Value::U8(3),
+                    Value::String("x".into())
+                ])))
+            }
+        );
+        assert!(decode(side(), &[3]).0.is_err());
+    }
+
+    #[test]
+    fn strict_decoding_rejects_trailing_bytes() {
+        let program = demo("Demo");
+        let swap = |rest: &[u8]| {
+            [
+                &discriminator("global", "swap")[..],
+                &5u64.to_le_bytes(),
+                rest,
+            ]
+            .concat()
+        };
+        let lenient = DecodeOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let args = Value::Struct(vec![
+            ("amount".into(), Value::U64(5)),
+            (
+                "side".into(),
+                Value::Enum {
+                    variant: "Bid".into(),
+                    fields: None,
+                },
+            ),
+        ]);
+
+        let decoded = decode_instruction(&program, &swap(&[0])).unwrap();
+        assert_eq!(decoded.name, "swap");
+        assert_eq!(decoded.args, args);
+
+        let err = decode_instruction(&program, &swap(&[0, 9, 9])).unwrap_err();
+        assert!(err.to_string().contains("2 trailing bytes"), "{}", err);
+        let decoded = decode_instruction_with(&program, &swap(&[0, 9, 9]), lenient).unwrap();
+        assert_eq!(decoded.args, args);
+        assert_eq!(decoded.remaining, [9, 9]);
+        assert_eq!(decoded.error, None);
+
+        // the args before the one that fails are kept
+        assert!(decode_instruction(&program, &swap(&[7])).is_err());
+        let decoded = decode_instruction_with(&program, &swap(&[7]), lenient).unwrap();
+        assert_eq!(
+            decoded.args,
+            Value::Struct(vec![("amount".into(), Value::U64(5))])
+        );
+        assert_eq!(decoded.remaining, [7]);
+        assert!(decoded.error.unwrap().contains("side"));
+
+        // the discriminator has to be known either way
+        let unknown = [0u8; 8];
+        assert!(decode_instruction_with(&program, &unknown, lenient).is_err());
+    }
+
+    #[test]
+    fn logged_events_follow_the_invocations() {
+        let data = |amount| format!("Program data: {}", base64(&swapped(amount)));
+        let logs = [
+            "Program Demo invoke [1]".to_string(),
+            data(1),
+            "Program Other invoke [2]".to_string(),
+            data(2),
+            "Program Other success".to_string(),
+            "Program Demo invoke [2]".to_string(),
+            "Program Demo success".to_string(),
+            data(3),
+            "Program Demo success".to_string(),
+            "Program Demo invoke [1]".to_string(),
+            data(4),
+            "Log truncated".to_string(),
+            data(5),
+        ];
+        let events: Vec<_> = logged_events(logs.iter().map(|l| l.as_str()))
+            .into_iter()
+            .map(|e| (e.index, e.inner_index, e.invoked, e.program_id, e.data))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (0, None, 0, "Demo", swapped(1)),
+                (0, Some(0), 1, "Other", swapped(2)),
+                (0, None, 2, "Demo", swapped(3)),
+                (1, None, 0, "Demo", swapped(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn transaction_events_are_in_emission_order() {
+        let programs: ProgramSet = [demo("Demo"), program("Other", "OtherError")]
+            .into_iter()
+            .collect();
+        let bs58 = |data: &[u8]| bs58::encode(data).into_string();
+        let run = bs58(&discriminator("global", "run"));
+        let logged = |amount| format!("Program data: {}", base64(&swapped(amount)));
+        let tx = json!({
+            "transaction": {"message": {
+                "accountKeys": ["Payer", "Demo", "Other"],
+                "instructions": [
+                    {"programIdIndex": 1, "accounts": [0], "data": run},
+                    {"programIdIndex": 1, "accounts": [0], "data": run},
+                ],
+            }},
+            "meta": {
+                "innerInstructions": [{"index": 0, "instructions": [
+                    {"programIdIndex": 2, "accounts": [0], "data": run},
+                    {
+                        "programIdIndex": 1,
+                        "accounts": [0],
+                        "data": bs58(&[&EVENT_IX_TAG[..], &swapped(2)].concat()),
+                    },
+                ]}],
+                "logMessages": [
+                    "Program Demo invoke [1]",
+                    logged(1),
+                    "Program Other invoke [2]",
+                    "Program Other success",
+                    "Program Demo invoke [2]",
+                    "Program Demo success",
+                    logged(3),
+                    "Program Demo success",
+                    "Program Demo invoke [1]",
+                    logged(4),
+                    "Program Demo success",
+                ],
+            },
+        });
+        let events: Vec<_> = transaction_events(&programs, &tx)
+            .unwrap()
+            .into_iter()
+            .map(|e| {
+                assert_eq!(
+                    (e.program_id.as_str(), e.event.name.as_str()),
+                    ("Demo", "Swapped")
+                );
+                (e.index, e.inner_index, e.event.fields)
+            })
+            .collect();
+        let amount = |amount| Value::Struct(vec![("amount".into(), Value::U64(amount))]);
+        assert_eq!(
+            events,
+            [
+                (0, None, amount(1)),
+                (0, Some(1), amount(2)),
+                (0, None, amount(3)),
+                (1, None, amount(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn ui_amounts_drop_trailing_zeros() {
+        assert_eq!(ui_amount(0, 9), "+0");
+        assert_eq!(ui_amount(-1_000_000_000, 9), "-1");
+        assert_eq!(ui_amount(5, 3), "+0.005");
+        assert_eq!(ui_amount(1_500_000, 6), "+1.5");
+        assert_eq!(ui_amount(120, 0), "+120");
+    }
+
+    #[test]
+    fn balance_changes_of_lamports_and_tokens() {
+        const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let balance = |index: u64, amount: &str| {
+            json!({
+                "accountIndex": index,
+                "mint": USDC,
+                "uiTokenAmount": {"amount": amount, "decimals": 6},
+            })
+        };
+        let tx = json!({
+            "transaction": {"message": {
+                "accountKeys": ["Payer", "Source", "Destination", "Unchanged"],
+                "instructions": [],
+            }},
+            "meta": {
+                "preBalances": [1_000_000_000, 2_039_280, 0, 1],
+                "postBalances": [997_955_720, 2_039_280, 2_039_280, 1],
+                "preTokenBalances": [balance(1, "2500000"), balance(3, "7")],
+                // the destination is created by the transaction
+                "postTokenBalances": [
+                    balance(1, "1000000"),
+                    balance(2, "1500000"),
+                    balance(3, "7"),
+                ],
+            },
+        });
+        let changes = balance_changes(&tx).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes["Payer"].lamports, -2_044_280);
+        assert_eq!(changes["Payer"].token, None);
+        assert_eq!(changes["Payer"].to_string(), "-0.00204428 SOL");
+
+        let source = &changes["Source"];
+        assert_eq!(source.lamports, 0);
+        let token = source.token.as_ref().unwrap();
+        assert_eq!((token.amount, token.decimals), (-1_500_000, 6));
+        assert_eq!(token.ui_amount, "-1.5");
+        assert_eq!(source.to_string(), "-1.5 USDC");
+
+        assert_eq!(
+            changes["Destination"].to_string(),
+            "+1.5 USDC, +0.00203928 SOL"
+        );
+    }
+
+    #[test]
+    fn program_errors_are_offset() {
+        let program = program("Demo", "DemoError");
+        let name = |program: &Program, code| program_error(Some(program), None, code).name;
+        assert_eq!(name(&program, 6000).as_deref(), Some("DemoError"));
+        // below the offset are Anchor's own errors
+        assert_eq!(name(&program, 0), None);
+        assert_eq!(name(&program, 2003).as_deref(), Some("ConstraintRaw"));
+        assert_eq!(name(&program, 6001), None);
+        assert_eq!(
+            program_error(None, None, 3012).name.as_deref(),
+            Some("AccountNotInitialized")
+        );
+
+        // very old IDLs list the codes without the offset
+        let mut old = program.clone();
+        old.errors[0].code = 0;
+        assert_eq!(name(&old, 6000).as_deref(), Some("DemoError"));
+        assert_eq!(name(&old, 0).as_deref(), Some("DemoError"));
+    }
+
+    #[test]
+    fn program_versions_by_activation_slot() {
+        let version = |version: &str| Program {
+            version: version.to_string(),
+            ..program("Demo", "DemoError")
+        };
+        let mut programs = ProgramSet::new();
+        programs.add_version(version("2"), 200);
+        programs.add_version(version("1"), 100);
+        programs.add(program("Other", "OtherError"));
+        let get = |slot| programs.get("Demo", slot).map(|p| p.version.as_str());
+
+        // slots before the first activation get the oldest version
+        assert_eq!(get(Some(50)), Some("1"));
+        assert_eq!(get(Some(100)), Some("1"));
+        assert_eq!(get(Some(199)), Some("1"));
+        assert_eq!(get(Some(200)), Some("2"));
+        assert_eq!(get(None), Some("2"));
+        assert!(programs.get("Unknown", None).is_none());
+
+        let at: Vec<_> = programs
+            .at(Some(150))
+            .iter()
+            .map(|p| (p.name.as_str(), p.version.as_str()))
+            .collect();
+        assert_eq!(at, [("Other", "0.1.0"), ("Demo", "1")]);
+    }
+
+    #[test]
+    fn failed_program_is_the_innermost() {
+        let tx = json!({"meta": {"logMessages": [
+            "Program Outer invoke [1]",
+            "Program Outer success",
+            "Program Outer invoke [1]",
+            "Program Middle invoke [2]",
+            "Program Inner invoke [3]",
+            "Program Inner failed: custom program error: 0x1770",
+            "Program Middle failed: custom program error: 0x1770",
+            "Program Outer failed: custom program error: 0x1770",
+        ]}});
+        assert_eq!(failed_program(&tx).as_deref(), Some("Inner"));
+    }
+
+    #[test]
+    fn failure_is_on_the_innermost_instruction() {
+        let programs: ProgramSet = [
+            program("Outer", "OuterError"),
+            program("Inner", "InnerError"),
+        ]
+        .into_iter()
+        .collect();
+        let run = bs58::encode(discriminator("global", "run")).into_string();
+        let tx = json!({
+            "transaction": {"message": {
+                "accountKeys": ["Payer", "Outer", "Inner"],
+                "instructions": [{"programIdIndex": 1, "accounts": [0], "data": run}],
+            }},
+            "meta": {
+                "err": {"InstructionError": [0, {"Custom": 6000}]},
+                "innerInstructions": [{"index": 0, "instructions": [
+                    {"programIdIndex": 2, "accounts": [0], "data": run},
+                ]}],
+                "logMessages": [
+                    "Program Outer invoke [1]",
+                    "Program Inner invoke [2]",
+                    "Program Inner failed: custom program error: 0x1770",
+                    "Program Outer failed: custom program error: 0x1770",
+                ],
+            },
+        });
+        let decoded = decode_transaction(&programs, &tx, DecodeOptions::default()).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].failure, None);
+        let failure = decoded[1].failure.as_ref().unwrap();
+        assert_eq!(failure.program_id.as_deref(), Some("Inner"));
+        assert_eq!(failure.name.as_deref(), Some("InnerError"));
+    }
+}
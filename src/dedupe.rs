@@ -0,0 +1,234 @@
+//! Types shared between programs, see [`GeneratorConfig::dedupe`] and
+//! [`GeneratorConfig::includes`].
+
+use std::collections::{HashMap, HashSet};
+
+use crate::config::{Artifact, GeneratorConfig};
+use crate::error::Result;
+use crate::frontend;
+use crate::input::{find_idl_json, parse_idl_value};
+use crate::ir::{Program, Type, TypeDef};
+use crate::prune::unreferenced;
+use crate::visit::{self, Visitor};
+
+/// Module holding the shared types, next to the program modules.
+pub(crate) const COMMON_MODULE: &str = "common";
+
+/// Custom types defined identically by at least two programs.
+///
+/// A type is only shared if all the types it refers to are shared as well,
+/// so the common module is self-contained. Accounts are never shared, nor
+/// are the types no instruction, account or event of a program reaches,
+/// which its own module doesn't emit either.
+pub(crate) fn shared_types(programs: &[Program]) -> Vec<TypeDef> {
+    // name -> (definition, number of programs, identical everywhere)
+    let mut seen: HashMap<&str, (&TypeDef, usize, bool)> = HashMap::new();
+    let mut order = vec![];
+    for program in programs.iter() {
+        let unreferenced = unreferenced(program);
+        for ty in program.types.iter() {
+            if unreferenced.binary_search(&ty.name).is_ok() {
+                continue;
+            }
+            match seen.get_mut(ty.name.as_str()) {
+                Some((def, count, same)) => {
+                    *count += 1;
+                    *same &= *def == ty;
+                }
+                None => {
+                    seen.insert(&ty.name, (ty, 1, true));
+                    order.push(ty.name.as_str());
+                }
+            }
+        }
+    }
+
+    let mut shared: HashSet<&str> = seen
+        .iter()
+        .filter(|(_, (_, count, same))| *count > 1 && *same)
+        .map(|(name, _)| *name)
+        .collect();
+    loop {
+        let before = shared.clone();
+        shared.retain(|name| {
+            references(seen[name].0)
+                .iter()
+                .all(|r| before.contains(r.as_str()))
+        });
+        if shared.len() == before.len() {
+            break;
+        }
+    }
+
+    order
+        .into_iter()
+        .filter(|name| shared.contains(name))
+        .map(|name| seen[name].0.clone())
+        .collect()
+}
+
+struct Refs(HashSet<String>);
+
+impl<'ast> Visitor<'ast> for Refs {
+    fn visit_type(&mut self, ty: &'ast Type) {
+        if let Type::Defined(name) = ty {
+            self.0.insert(name.clone());
+        }
+        visit::walk_type(self, ty);
+    }
+}
+
+/// `Defined` names a type definition refers to.
+pub(crate) fn references(def: &TypeDef) -> HashSet<String> {
+    let mut refs = Refs(HashSet::new());
+    refs.visit_type_def(def);
+    refs.0
+}
+
+/// `Defined` names `program` refers to without defining them.
+fn undefined_references(program: &Program) -> HashSet<String> {
+    let mut refs = Refs(HashSet::new());
+    refs.visit_program(program);
+    refs.0.retain(|name| program.find_type(name).is_none());
+    refs.0
+}
+
+/// The accounts and types of the IDLs in the [`GeneratorConfig::includes`]
+/// directories, accounts as plain types, in file order.
+pub(crate) fn included_types(config: &GeneratorConfig) -> Result<Vec<TypeDef>> {
+    let mut types = vec![];
+    for dir in config.includes.iter() {
+        let mut files = find_idl_json(dir)?;
+        files.sort();
+        for path in files {
+            let bytes = std::fs::read(&path)?;
+            let entries = match parse_idl_value(&path, &bytes)? {
+                serde_json::Value::Array(entries) => entries,
+                raw => vec![raw],
+            };
+            for raw in entries {
+                let program = frontend::lower(raw).map_err(|e| e.in_file(path.clone()))?;
+                types.extend(program.accounts.into_iter().map(|mut def| {
+                    def.discriminator = None;
+                    def
+                }));
+                types.extend(program.types);
+            }
+        }
+    }
+    Ok(types)
+}
+
+/// The `included` types `programs` refer to without defining them, and
+/// those these refer to, in `included` order. The first definition of a
+/// name wins.
+pub(crate) fn imported_types(programs: &[Program], included: &[TypeDef]) -> Vec<TypeDef> {
+    let mut by_name: HashMap<&str, &TypeDef> = HashMap::new();
+    for def in included.iter() {
+        by_name.entry(def.name.as_str()).or_insert(def);
+    }
+    let mut needed: HashSet<String> = HashSet::new();
+    let mut pending: Vec<String> = programs.iter().flat_map(undefined_references).collect();
+    while let Some(name) = pending.pop() {
+        let Some(def) = by_name.get(name.as_str()) else {
+            continue;
+        };
+        if needed.insert(name) {
+            pending.extend(references(def));
+        }
+    }
+    let mut imported: Vec<TypeDef> = vec![];
+    for def in included.iter() {
+        if needed.remove(&def.name) {
+            imported.push(def.clone());
+        }
+    }
+    imported
+}
+
+/// Adds the `imported` types to `shared`, whose definitions of the same
+/// names win.
+pub(crate) fn add_imported(shared: &mut Vec<TypeDef>, imported: Vec<TypeDef>) {
+    for def in imported {
+        if !shared.iter().any(|s| s.name == def.name) {
+            shared.push(def);
+        }
+    }
+}
+
+/// The program generated as the common module.
+pub(crate) fn common_program(shared: &[TypeDef]) -> Program {
+    Program {
+        name: COMMON_MODULE.to_string(),
+        version: String::new(),
+        address: String::new(),
+        instructions: vec![],
+        accounts: vec![],
+        types: shared.to_vec(),
+        events: vec![],
+        errors: vec![],
+        constants: vec![],
+    }
+}
+
+/// Config of the common module: nothing but all of its types.
+pub(crate) fn common_config(config: &GeneratorConfig) -> GeneratorConfig {
+    let mut config = config.clone();
+    config.artifacts = [Artifact::Types].into_iter().collect();
+    // the program code the common module has no program for
+    config.builders = false;
+    config.cli = false;
+    config.client = false;
+    config.localnet = false;
+    config.account_scans = false;
+    config.account_watchers = false;
+    config.indexer = false;
+    config.event_decoder = false;
+    config.event_stream = false;
+    config.kafka_sink = false;
+    config.http_service = false;
+    config.grpc_service = false;
+    config
+}
+
+/// Removes the shared types from `program` and returns the config that
+/// re-exports them, and the shared types it refers to without defining
+/// them, from the common module at `common` instead.
+pub(crate) fn program_config(
+    config: &GeneratorConfig,
+    program: &mut Program,
+    shared: &[TypeDef],
+    common: &str,
+) -> GeneratorConfig {
+    let mut config = config.clone();
+    let names: HashSet<&str> = shared.iter().map(|t| t.name.as_str()).collect();
+    let undefined = undefined_references(program);
+    let mut used = vec![];
+    // imported types
+    for t in shared.iter().filter(|t| undefined.contains(&t.name)) {
+        let rust_name = config.naming.types.apply(&t.name);
+        config
+            .type_overrides
+            .insert(t.name.clone(), rust_name.clone());
+        used.push(rust_name);
+    }
+    program.types.retain(|t| {
+        let is_shared = names.contains(t.name.as_str());
+        if is_shared {
+            // overridden types are referred to by the given name and not
+            // generated, the re-export brings the name into scope
+            let rust_name = config.naming.types.apply(&t.name);
+            config
+                .type_overrides
+                .insert(t.name.clone(), rust_name.clone());
+            used.push(rust_name);
+        }
+        !is_shared
+    });
+    if !used.is_empty() {
+        config
+            .reexports
+            .push(format!("{}::{{{}}}", common, used.join(", ")));
+    }
+    config
+}
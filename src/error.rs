@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+/// Errors returned by the generator.
+#[derive(Debug, thiserror::Error)]
+pub enum ParseIdlError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid IDL file {}", path.display())]
+    InvalidIdl {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("invalid IDL: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("IDL spec version {spec} is not supported, only legacy anchor IDL can be parsed (found: {})", constructs.join(", "))]
+    UnsupportedSpec {
+        spec: String,
+        constructs: Vec<String>,
+    },
+    #[error("IDL uses constructs of a newer spec that are not supported: {}", constructs.join(", "))]
+    UnsupportedConstructs { constructs: Vec<String> },
+    #[error("unsupported type: {0}")]
+    UnsupportedType(String),
+    #[error("metadata cannot be None!")]
+    MissingMetadata,
+    #[error("metadata should contain 'address'")]
+    MissingAddress,
+    #[error("address in metadata should be string format")]
+    InvalidAddress,
+    #[error("{}: IDL entry without a name in its metadata", path.display())]
+    MissingName { path: PathBuf },
+    #[error("failed to generate bindings for {}", path.display())]
+    InFile {
+        path: PathBuf,
+        #[source]
+        source: Box<ParseIdlError>,
+    },
+    #[error("invalid manifest {}", path.display())]
+    InvalidManifest {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("{0}")]
+    Workspace(String),
+    #[error("unknown backend '{0}'")]
+    UnknownBackend(String),
+    #[error("backend produced no output")]
+    NoOutput,
+    #[error("{} is no submodule of its module, it can't be bundled", .0.display())]
+    NotBundled(PathBuf),
+    #[error("template error: {0}")]
+    Template(String),
+    #[error("generated code is not valid rust tokens: {0}")]
+    Tokenize(String),
+    #[error("rpc error: {0}")]
+    Rpc(String),
+    #[error("cannot decode: {0}")]
+    Decode(String),
+    #[error("no instruction with discriminator {0:?}")]
+    UnknownDiscriminator([u8; 8]),
+    #[error("no account with discriminator {0:?}")]
+    UnknownAccount([u8; 8]),
+    #[error("no event with discriminator {0:?}")]
+    UnknownEvent([u8; 8]),
+    #[error("generated code is not valid utf-8")]
+    Utf8(#[from] std::string::FromUtf8Error),
+}
+
+pub type Result<T> = std::result::Result<T, ParseIdlError>;
+
+impl ParseIdlError {
+    pub(crate) fn in_file(self, path: impl Into<PathBuf>) -> Self {
+        ParseIdlError::InFile {
+            path: path.into(),
+            source: Box::new(self),
+        }
+    }
+}
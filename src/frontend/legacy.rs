@@ -0,0 +1,372 @@
+//! Legacy (pre-0.30) anchor IDLs, as modelled by `anchor_idl`.
+
+use std::borrow::Cow;
+
+use crate::config::Sighash;
+use crate::error::{ParseIdlError, Result};
+use crate::input::has_unsupported_types;
+use crate::ir::{
+    discriminator, AccountItem, Constant, EnumFields, EnumVariant, ErrorCode, Event, EventField,
+    Field, Instruction, InstructionAccount, Program, Type, TypeDef, TypeDefKind,
+};
+use anchor_idl::{
+    EnumFields as IdlEnumFields, Idl, IdlAccountItem, IdlField, IdlInstruction, IdlType,
+    IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+use heck::{ToSnakeCase, ToUpperCamelCase};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+
+/// Lowers a legacy IDL, the program address is read from `metadata.address`.
+///
+/// The methods of a `state` section become instructions hashed in the
+/// `state` namespace, prefixed with `state` if a global instruction has the
+/// same name, and its struct an account.
+pub fn lower(idl: &Idl) -> Result<Program> {
+    lower_with(idl, &Sighash::default())
+}
+
+/// [`lower`] computing the instruction discriminators with `sighash`.
+pub fn lower_with(idl: &Idl, sighash: &Sighash) -> Result<Program> {
+    let Some(metadata) = &idl.metadata else {
+        return Err(ParseIdlError::MissingMetadata);
+    };
+    let Some(address) = metadata.get("address") else {
+        return Err(ParseIdlError::MissingAddress);
+    };
+    let Some(address) = address.as_str() else {
+        return Err(ParseIdlError::InvalidAddress);
+    };
+
+    Ok(Program {
+        name: idl.name.clone(),
+        version: idl.version.clone(),
+        address: address.to_string(),
+        instructions: idl
+            .instructions
+            .iter()
+            .map(|ix| lower_instruction(ix, ix.name.clone(), sighash))
+            .chain(state_methods(idl, sighash))
+            .collect(),
+        accounts: idl
+            .state
+            .iter()
+            .map(|state| &state.strct)
+            .chain(idl.accounts.iter())
+            .map(|account| {
+                let mut def = lower_type_def(account);
+                def.discriminator = Some(discriminator("account", &account.name));
+                def
+            })
+            .collect(),
+        types: idl.types.iter().map(lower_type_def).collect(),
+        events: idl
+            .events
+            .iter()
+            .flatten()
+            .map(|event| Event {
+                name: event.name.clone(),
+                discriminator: discriminator("event", &event.name),
+                fields: event
+                    .fields
+                    .iter()
+                    .map(|f| EventField {
+                        name: f.name.clone(),
+                        ty: lower_type(&f.ty),
+                        index: f.index,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        errors: idl
+            .errors
+            .iter()
+            .flatten()
+            .map(|e| ErrorCode {
+                code: e.code,
+                name: e.name.clone(),
+                msg: e.msg.clone(),
+            })
+            .collect(),
+        constants: idl
+            .constants
+            .iter()
+            .map(|c| Constant {
+                name: c.name.clone(),
+                ty: lower_type(&c.ty),
+                value: c.value.clone(),
+            })
+            .collect(),
+    })
+}
+
+/// Marks the accounts flagged `isOptional` or with a fixed `address` in
+/// `raw`, which `anchor_idl` doesn't model, in `program` as lowered from it.
+pub fn mark_account_constraints(raw: &serde_json::Value, program: &mut Program) {
+    fn mark(raw: &serde_json::Value, items: &mut [AccountItem]) {
+        let Some(raw) = raw.get("accounts").and_then(|a| a.as_array()) else {
+            return;
+        };
+        for (raw, item) in raw.iter().zip(items.iter_mut()) {
+            match item {
+                AccountItem::Account(account) => {
+                    account.is_optional = raw
+                        .get("isOptional")
+                        .and_then(|o| o.as_bool())
+                        .unwrap_or(false);
+                    if let Some(address) = raw.get("address").and_then(|a| a.as_str()) {
+                        account.address = Some(address.to_string());
+                    }
+                }
+                AccountItem::Group { accounts, .. } => mark(raw, accounts),
+            }
+        }
+    }
+
+    fn list(value: Option<&serde_json::Value>) -> &[serde_json::Value] {
+        value
+            .and_then(|v| v.as_array())
+            .map_or(&[], |a| a.as_slice())
+    }
+    // in the order of `lower`
+    let methods = raw.get("state").and_then(|s| s.get("methods"));
+    let raw_ixs = list(raw.get("instructions")).iter().chain(list(methods));
+    for (raw, ix) in raw_ixs.zip(program.instructions.iter_mut()) {
+        mark(raw, &mut ix.accounts);
+    }
+}
+
+/// What the checks of the raw IDL and [`mark_account_constraints`] read of
+/// a legacy IDL, borrowed from its json, the rest skipped.
+#[derive(Deserialize)]
+struct Shape<'a> {
+    #[serde(default, deserialize_with = "present")]
+    address: bool,
+    #[serde(default, borrow)]
+    instructions: Vec<InstructionShape<'a>>,
+    #[serde(default, borrow)]
+    state: Option<StateShape<'a>>,
+    #[serde(default)]
+    accounts: Vec<DefinitionShape>,
+    #[serde(default)]
+    types: Vec<DefinitionShape>,
+}
+
+#[derive(Deserialize)]
+struct StateShape<'a> {
+    #[serde(default, borrow)]
+    methods: Vec<InstructionShape<'a>>,
+}
+
+#[derive(Deserialize)]
+struct InstructionShape<'a> {
+    #[serde(default, deserialize_with = "present")]
+    discriminator: bool,
+    #[serde(default, borrow)]
+    accounts: Vec<AccountShape<'a>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountShape<'a> {
+    #[serde(default, deserialize_with = "present")]
+    is_mut: bool,
+    #[serde(default)]
+    is_optional: bool,
+    #[serde(default, borrow)]
+    address: Option<Cow<'a, str>>,
+    #[serde(default, borrow)]
+    accounts: Option<Vec<AccountShape<'a>>>,
+}
+
+#[derive(Deserialize)]
+struct DefinitionShape {
+    #[serde(rename = "type", default, deserialize_with = "present")]
+    ty: bool,
+    #[serde(default, deserialize_with = "present")]
+    generics: bool,
+}
+
+/// Whether the field is there, whatever its value.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<bool, D::Error> {
+    IgnoredAny::deserialize(deserializer).map(|_| true)
+}
+
+impl Shape<'_> {
+    /// Whether the IDL has none of the constructs of a newer spec
+    /// [`check_idl_spec`] looks for outside of the types, which `Idl`
+    /// doesn't deserialize.
+    fn is_legacy(&self) -> bool {
+        !self.address
+            && self.instructions.iter().all(|ix| {
+                !ix.discriminator
+                    && ix
+                        .accounts
+                        .iter()
+                        .all(|account| account.is_mut || account.accounts.is_some())
+            })
+            && self.accounts.iter().all(|def| def.ty && !def.generics)
+            && self.types.iter().all(|def| !def.generics)
+    }
+
+    /// [`mark_account_constraints`] of the shape.
+    fn mark(&self, program: &mut Program) {
+        fn mark(raw: &[AccountShape], items: &mut [AccountItem]) {
+            for (raw, item) in raw.iter().zip(items.iter_mut()) {
+                match item {
+                    AccountItem::Account(account) => {
+                        account.is_optional = raw.is_optional;
+                        if let Some(address) = &raw.address {
+                            account.address = Some(address.to_string());
+                        }
+                    }
+                    AccountItem::Group { accounts, .. } => {
+                        mark(raw.accounts.as_deref().unwrap_or_default(), accounts)
+                    }
+                }
+            }
+        }
+
+        let methods = self.state.iter().flat_map(|s| s.methods.iter());
+        let raw_ixs = self.instructions.iter().chain(methods);
+        for (raw, ix) in raw_ixs.zip(program.instructions.iter_mut()) {
+            mark(&raw.accounts, &mut ix.accounts);
+        }
+    }
+}
+
+/// [`lower_with`] straight from the json of a legacy IDL, deserialized into
+/// `Idl` and a borrowed [`Shape`] without building a `serde_json::Value` of
+/// the whole IDL, several times faster on large IDLs. `None` when it can't
+/// tell the IDL is supported, which the checks of the raw value report.
+pub fn lower_json(json: &[u8], sighash: &Sighash) -> Option<Result<Program>> {
+    let shape: Shape = serde_json::from_slice(json).ok()?;
+    if !shape.is_legacy() {
+        return None;
+    }
+    // types `Idl` doesn't know fail to deserialize
+    let idl: Idl = serde_json::from_slice(json).ok()?;
+    if let Some(metadata) = &idl.metadata {
+        if metadata.get("spec").is_some() || has_unsupported_types(metadata) {
+            return None;
+        }
+    }
+    Some(lower_with(&idl, sighash).map(|mut program| {
+        shape.mark(&mut program);
+        program
+    }))
+}
+
+fn lower_instruction(ix: &IdlInstruction, name: String, sighash: &Sighash) -> Instruction {
+    Instruction {
+        name,
+        discriminator: sighash.hash(&ix.name),
+        accounts: lower_accounts(&ix.accounts),
+        args: lower_fields(&ix.args),
+        returns: ix.returns.as_ref().map(lower_type),
+    }
+}
+
+fn state_methods<'a>(idl: &'a Idl, sighash: &'a Sighash) -> impl Iterator<Item = Instruction> + 'a {
+    let state_sighash = Sighash {
+        namespace: "state".to_string(),
+        case: sighash.case,
+    };
+    idl.state
+        .iter()
+        .flat_map(|state| state.methods.iter())
+        .map(move |method| {
+            let global = idl
+                .instructions
+                .iter()
+                .any(|ix| ix.name.to_snake_case() == method.name.to_snake_case());
+            let name = if global {
+                format!("state{}", method.name.to_upper_camel_case())
+            } else {
+                method.name.clone()
+            };
+            lower_instruction(method, name, &state_sighash)
+        })
+}
+
+fn lower_accounts(items: &[IdlAccountItem]) -> Vec<AccountItem> {
+    items
+        .iter()
+        .map(|item| match item {
+            IdlAccountItem::IdlAccount(account) => AccountItem::Account(InstructionAccount {
+                name: account.name.clone(),
+                is_mut: account.is_mut,
+                is_signer: account.is_signer,
+                is_optional: false,
+                address: None,
+            }),
+            IdlAccountItem::IdlAccounts(group) => AccountItem::Group {
+                name: group.name.clone(),
+                accounts: lower_accounts(&group.accounts),
+            },
+        })
+        .collect()
+}
+
+fn lower_fields(fields: &[IdlField]) -> Vec<Field> {
+    fields
+        .iter()
+        .map(|f| Field {
+            name: f.name.clone(),
+            ty: lower_type(&f.ty),
+        })
+        .collect()
+}
+
+fn lower_type_def(def: &IdlTypeDefinition) -> TypeDef {
+    let kind = match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => TypeDefKind::Struct {
+            fields: lower_fields(fields),
+        },
+        IdlTypeDefinitionTy::Enum { variants } => TypeDefKind::Enum {
+            variants: variants
+                .iter()
+                .map(|v| EnumVariant {
+                    name: v.name.clone(),
+                    fields: v.fields.as_ref().map(|fields| match fields {
+                        IdlEnumFields::Named(fields) => EnumFields::Named(lower_fields(fields)),
+                        IdlEnumFields::Tuple(types) => {
+                            EnumFields::Tuple(types.iter().map(lower_type).collect())
+                        }
+                    }),
+                })
+                .collect(),
+        },
+    };
+    TypeDef {
+        name: def.name.clone(),
+        discriminator: None,
+        kind,
+    }
+}
+
+/// Lowers an `anchor_idl` type.
+pub fn lower_type(ty: &IdlType) -> Type {
+    match ty {
+        IdlType::Bool => Type::Bool,
+        IdlType::U8 => Type::U8,
+        IdlType::I8 => Type::I8,
+        IdlType::U16 => Type::U16,
+        IdlType::I16 => Type::I16,
+        IdlType::U32 => Type::U32,
+        IdlType::I32 => Type::I32,
+        IdlType::F32 => Type::F32,
+        IdlType::U64 => Type::U64,
+        IdlType::I64 => Type::I64,
+        IdlType::F64 => Type::F64,
+        IdlType::U128 => Type::U128,
+        IdlType::I128 => Type::I128,
+        IdlType::Bytes => Type::Bytes,
+        IdlType::String => Type::String,
+        IdlType::PublicKey => Type::PublicKey,
+        IdlType::Defined(name) => Type::Defined(name.clone()),
+        IdlType::Option(inner) => Type::Option(Box::new(lower_type(inner))),
+        IdlType::Vec(inner) => Type::Vec(Box::new(lower_type(inner))),
+        IdlType::Array(inner, len) => Type::Array(Box::new(lower_type(inner)), *len),
+    }
+}
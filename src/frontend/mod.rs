@@ -0,0 +1,30 @@
+//! Front-ends lowering IDL formats into the [`ir`](crate::ir) model.
+
+use crate::config::Sighash;
+use crate::error::Result;
+use crate::input::parse_idl_ref;
+use crate::ir::Program;
+
+pub mod legacy;
+
+/// Lowers a raw IDL value with the front-end matching its format.
+pub fn lower(raw: serde_json::Value) -> Result<Program> {
+    lower_with(raw, &Sighash::default())
+}
+
+/// [`lower`] computing the instruction discriminators with `sighash`.
+pub fn lower_with(raw: serde_json::Value, sighash: &Sighash) -> Result<Program> {
+    let idl = parse_idl_ref(&raw)?;
+    let mut program = legacy::lower_with(&idl, sighash)?;
+    legacy::mark_account_constraints(&raw, &mut program);
+    Ok(program)
+}
+
+/// [`lower_with`] from the bytes of a json IDL, straight into the program
+/// if [`legacy::lower_json`] can, through a `serde_json::Value` otherwise.
+pub fn lower_slice(json: &[u8], sighash: &Sighash) -> Result<Program> {
+    match legacy::lower_json(json, sighash) {
+        Some(program) => program,
+        None => lower_with(serde_json::from_slice(json)?, sighash),
+    }
+}
@@ -0,0 +1,85 @@
+//! A configured generator that can be shared between threads.
+
+use std::path::{Path, PathBuf};
+
+use crate::backend::BackendRegistry;
+use crate::codegen::{program_source, write_files, write_module};
+use crate::config::GeneratorConfig;
+use crate::error::Result;
+use crate::frontend;
+use crate::ir::Program;
+use crate::provenance::Provenance;
+use crate::workspace::write_crates;
+
+/// A config and a backend registry, built once and reused.
+///
+/// `Generator` is `Send + Sync` and keeps no state between calls, every
+/// path it touches is passed in explicitly. Put it in an `Arc` to generate
+/// bindings for many programs in parallel.
+#[derive(Default)]
+pub struct Generator {
+    config: GeneratorConfig,
+    registry: BackendRegistry,
+}
+
+impl Generator {
+    /// A generator with the built-in backends.
+    pub fn new(config: GeneratorConfig) -> Self {
+        Self::with_registry(config, BackendRegistry::with_defaults())
+    }
+
+    pub fn with_registry(config: GeneratorConfig, registry: BackendRegistry) -> Self {
+        Self { config, registry }
+    }
+
+    pub fn config(&self) -> &GeneratorConfig {
+        &self.config
+    }
+
+    pub fn registry(&self) -> &BackendRegistry {
+        &self.registry
+    }
+
+    /// See [`generate_program`](crate::generate_program).
+    pub fn generate_program(&self, program: &Program) -> Result<String> {
+        program_source(&self.registry, program, &self.config)
+    }
+
+    /// See [`generate_from_json`](crate::generate_from_json).
+    pub fn generate_json(&self, json: &[u8]) -> Result<String> {
+        self.generate_program(&frontend::lower_slice(json, &self.config.sighash)?)
+    }
+
+    /// See [`generate_module`](crate::generate_module).
+    pub fn generate_module(
+        &self,
+        raw: serde_json::Value,
+        module_name: &str,
+        out_dir: &Path,
+        provenance: Option<&Provenance>,
+    ) -> Result<PathBuf> {
+        write_module(
+            &self.registry,
+            raw,
+            module_name,
+            out_dir,
+            &self.config,
+            provenance,
+        )
+    }
+
+    /// See [`generate_files`](crate::generate_files).
+    pub fn generate_files(&self, files: &[PathBuf], out_dir: &Path) -> Result<Vec<PathBuf>> {
+        write_files(&self.registry, files, out_dir, &self.config)
+    }
+
+    /// See [`generate_crates`](crate::generate_crates).
+    pub fn generate_crates(&self, files: &[PathBuf], root: &Path) -> Result<Vec<PathBuf>> {
+        write_crates(&self.registry, files, root, &self.config)
+    }
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Generator>();
+};
@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anchor_idl::Idl;
+use heck::ToSnakeCase;
+use log::info;
+use serde::Deserialize;
+
+use crate::config::Sighash;
+use crate::error::{ParseIdlError, Result};
+use crate::frontend;
+use crate::ir::Program;
+
+/// Module name of an IDL, taken from `metadata.name` or the legacy `name`.
+pub fn idl_module_name(raw: &serde_json::Value) -> Option<String> {
+    raw.get("metadata")
+        .and_then(|m| m.get("name"))
+        .or_else(|| raw.get("name"))
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_snake_case())
+}
+
+/// File extensions recognised as IDL inputs.
+pub const IDL_EXTENSIONS: [&str; 4] = ["json", "json5", "yaml", "yml"];
+
+/// Reads an IDL file into a json value.
+///
+/// The input format is selected by file extension, everything is converted
+/// into a json value before the IDL model is built.
+pub fn read_idl_value(path: &Path) -> Result<serde_json::Value> {
+    parse_idl_value(path, &std::fs::read(path)?)
+}
+
+/// Parses the contents of the IDL file at `path`, see [`read_idl_value`].
+///
+/// The whole file is parsed from memory, json with [`parse_json`].
+/// `serde_json::from_reader` goes through the reader byte by byte and is
+/// several times slower on multi-megabyte IDLs.
+pub fn parse_idl_value(path: &Path, bytes: &[u8]) -> Result<serde_json::Value> {
+    let started = Instant::now();
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let invalid = |source: Box<dyn std::error::Error + Send + Sync>| ParseIdlError::InvalidIdl {
+        path: path.to_path_buf(),
+        source,
+    };
+    let value = match ext {
+        "yaml" | "yml" => serde_yaml::from_slice(bytes).map_err(|e| invalid(e.into()))?,
+        "json5" => {
+            let content = std::str::from_utf8(bytes).map_err(|e| invalid(e.into()))?;
+            json5::from_str(content).map_err(|e| invalid(e.into()))?
+        }
+        _ => parse_json(bytes).map_err(|e| invalid(e.into()))?,
+    };
+    info!(
+        "parsed {} ({} KiB) in {:.1?}",
+        path.display(),
+        bytes.len() / 1024,
+        started.elapsed()
+    );
+    Ok(value)
+}
+
+/// Parses json with simd-json, faster than serde_json on the large IDLs.
+/// What simd-json rejects is parsed again by serde_json, whose errors tell
+/// the line and column.
+fn parse_json(bytes: &[u8]) -> serde_json::Result<serde_json::Value> {
+    // simd-json parses in place
+    let mut buffer = bytes.to_vec();
+    match simd_json::serde::from_slice(&mut buffer) {
+        Ok(value) => Ok(value),
+        Err(_) => serde_json::from_slice(bytes),
+    }
+}
+
+/// Builds the IDL model from a raw value, rejecting specs and types the
+/// generator can't handle.
+pub fn parse_idl(raw: serde_json::Value) -> Result<Idl> {
+    parse_idl_ref(&raw)
+}
+
+/// [`parse_idl`] deserializing from the borrowed value, for the callers
+/// needing it after, without cloning it.
+pub(crate) fn parse_idl_ref(raw: &serde_json::Value) -> Result<Idl> {
+    check_idl_spec(raw)?;
+    Ok(Idl::deserialize(raw)?)
+}
+
+/// Reads an IDL file into the IDL model.
+pub fn read_idl(path: &Path) -> Result<Idl> {
+    parse_idl(read_idl_value(path)?)
+}
+
+/// Reads an IDL file and lowers it into the normalized [`Program`].
+pub fn read_program(path: &Path) -> Result<Program> {
+    lower_file(path, &std::fs::read(path)?, &Sighash::default())
+}
+
+/// Whether the IDL file at `path` is parsed as json, see
+/// [`parse_idl_value`].
+pub(crate) fn is_json(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    !matches!(ext, "yaml" | "yml" | "json5")
+}
+
+/// Lowers the contents of the IDL file at `path`, json straight from its
+/// bytes with [`frontend::legacy::lower_json`] when it can.
+pub(crate) fn lower_file(path: &Path, bytes: &[u8], sighash: &Sighash) -> Result<Program> {
+    let started = Instant::now();
+    if is_json(path) {
+        if let Some(program) = frontend::legacy::lower_json(bytes, sighash) {
+            info!(
+                "parsed {} ({} KiB) in {:.1?}",
+                path.display(),
+                bytes.len() / 1024,
+                started.elapsed()
+            );
+            return program;
+        }
+    }
+    frontend::lower_with(parse_idl_value(path, bytes)?, sighash)
+}
+
+/// Lists the IDL files directly inside `root_path`.
+pub fn find_idl_json(root_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut v = vec![];
+
+    for entry in root_path.read_dir()? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            let p = entry.path();
+            if let Some(e) = p.extension() {
+                if IDL_EXTENSIONS.iter().any(|ext| e == *ext) {
+                    v.push(entry.path());
+                }
+            }
+        }
+    }
+
+    Ok(v)
+}
+
+/// Rejects IDLs written against a spec the generator can't handle.
+///
+/// Legacy (pre-0.30) anchor IDLs carry no spec marker, anything else uses a
+/// layout anchor_idl can't deserialize.
+pub fn check_idl_spec(raw: &serde_json::Value) -> Result<()> {
+    let spec = raw
+        .get("metadata")
+        .and_then(|m| m.get("spec"))
+        .and_then(|s| s.as_str());
+
+    let mut constructs = vec![];
+    if raw.get("address").is_some() {
+        constructs.push("top-level 'address'");
+    }
+    if let Some(ixs) = raw.get("instructions").and_then(|v| v.as_array()) {
+        if ixs.iter().any(|ix| ix.get("discriminator").is_some()) {
+            constructs.push("explicit instruction 'discriminator'");
+        }
+        let new_style_accounts = ixs
+            .iter()
+            .filter_map(|ix| ix.get("accounts").and_then(|a| a.as_array()))
+            .flatten()
+            .any(|acc| acc.get("isMut").is_none() && acc.get("accounts").is_none());
+        if new_style_accounts {
+            constructs.push("'writable'/'signer' instruction accounts");
+        }
+    }
+    if let Some(accounts) = raw.get("accounts").and_then(|v| v.as_array()) {
+        if accounts.iter().any(|acc| acc.get("type").is_none()) {
+            constructs.push("accounts without inline 'type'");
+        }
+    }
+    scan_type_constructs(raw, &mut constructs);
+
+    let constructs: Vec<String> = constructs.into_iter().map(String::from).collect();
+    match spec {
+        None if constructs.is_empty() => check_types(raw),
+        Some(spec) => Err(ParseIdlError::UnsupportedSpec {
+            spec: spec.to_string(),
+            constructs: if constructs.is_empty() {
+                vec!["'metadata.spec'".to_string()]
+            } else {
+                constructs
+            },
+        }),
+        None => Err(ParseIdlError::UnsupportedConstructs { constructs }),
+    }
+}
+
+/// Whether `value`, a part of an IDL, has types or constructs of a newer
+/// spec [`check_idl_spec`] rejects.
+pub(crate) fn has_unsupported_types(value: &serde_json::Value) -> bool {
+    let mut constructs = vec![];
+    scan_type_constructs(value, &mut constructs);
+    !constructs.is_empty() || check_types(value).is_err()
+}
+
+const KNOWN_TYPES: [&str; 16] = [
+    "bool",
+    "u8",
+    "i8",
+    "u16",
+    "i16",
+    "u32",
+    "i32",
+    "f32",
+    "u64",
+    "i64",
+    "f64",
+    "u128",
+    "i128",
+    "bytes",
+    "string",
+    "publicKey",
+];
+
+// catch types unknown to anchor_idl here, serde would only report an
+// untagged enum mismatch somewhere in the file
+fn check_types(value: &serde_json::Value) -> Result<()> {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                match (k.as_str(), v) {
+                    ("type" | "option" | "vec", v) => check_type(v)?,
+                    ("array", serde_json::Value::Array(a)) => {
+                        if let Some(ty) = a.first() {
+                            check_type(ty)?;
+                        }
+                    }
+                    _ => {}
+                }
+                check_types(v)?;
+            }
+        }
+        serde_json::Value::Array(a) => {
+            for v in a {
+                check_types(v)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn check_type(ty: &serde_json::Value) -> Result<()> {
+    match ty {
+        serde_json::Value::String(name) if !KNOWN_TYPES.contains(&name.as_str()) => {
+            Err(ParseIdlError::UnsupportedType(name.clone()))
+        }
+        // type definitions carry a "kind", everything else is a single-key wrapper
+        serde_json::Value::Object(map) if !map.contains_key("kind") => {
+            match map
+                .keys()
+                .find(|k| !["defined", "option", "vec", "array"].contains(&k.as_str()))
+            {
+                Some(k) => Err(ParseIdlError::UnsupportedType(k.clone())),
+                None => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+fn scan_type_constructs(value: &serde_json::Value, constructs: &mut Vec<&'static str>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                let is_pubkey = match (k.as_str(), v) {
+                    ("type" | "option" | "vec" | "coption", v) => v.as_str() == Some("pubkey"),
+                    ("array", serde_json::Value::Array(a)) => {
+                        a.first().and_then(|t| t.as_str()) == Some("pubkey")
+                    }
+                    _ => false,
+                };
+                if is_pubkey && !constructs.contains(&"'pubkey' type") {
+                    constructs.push("'pubkey' type");
+                }
+                if k == "defined"
+                    && v.is_object()
+                    && !constructs.contains(&"'defined' type given as object")
+                {
+                    constructs.push("'defined' type given as object");
+                }
+                if k == "generics" && !constructs.contains(&"generic types") {
+                    constructs.push("generic types");
+                }
+                scan_type_constructs(v, constructs);
+            }
+        }
+        serde_json::Value::Array(a) => {
+            for v in a {
+                scan_type_constructs(v, constructs);
+            }
+        }
+        _ => {}
+    }
+}
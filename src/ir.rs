@@ -0,0 +1,263 @@
+//! Normalized program model shared by all front-ends and backends.
+//!
+//! Front-ends (see [`crate::frontend`]) lower their input format into a
+//! [`Program`], backends only ever see this model. Discriminators are
+//! computed during lowering, so backends don't need to know the hashing
+//! rules of the source format.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// A program and everything its IDL declares.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Program {
+    pub name: String,
+    pub version: String,
+    pub address: String,
+    pub instructions: Vec<Instruction>,
+    pub accounts: Vec<TypeDef>,
+    pub types: Vec<TypeDef>,
+    pub events: Vec<Event>,
+    pub errors: Vec<ErrorCode>,
+    pub constants: Vec<Constant>,
+}
+
+impl Program {
+    /// Looks up a `Defined` type name among the accounts and custom types.
+    pub fn find_type(&self, name: &str) -> Option<&TypeDef> {
+        self.accounts
+            .iter()
+            .chain(self.types.iter())
+            .find(|t| t.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Instruction {
+    pub name: String,
+    pub discriminator: [u8; 8],
+    pub accounts: Vec<AccountItem>,
+    pub args: Vec<Field>,
+    pub returns: Option<Type>,
+}
+
+impl Instruction {
+    /// Accounts in the order they are passed to the program, with nested
+    /// groups flattened.
+    pub fn flat_accounts(&self) -> Vec<&InstructionAccount> {
+        fn flatten<'a>(items: &'a [AccountItem], out: &mut Vec<&'a InstructionAccount>) {
+            for item in items {
+                match item {
+                    AccountItem::Account(account) => out.push(account),
+                    AccountItem::Group { accounts, .. } => flatten(accounts, out),
+                }
+            }
+        }
+        let mut out = vec![];
+        flatten(&self.accounts, &mut out);
+        out
+    }
+
+    /// [`Self::flat_accounts`] with their names qualified by the groups they
+    /// are nested in, `group.account`, so that the same name in two groups
+    /// stays apart.
+    pub fn qualified_accounts(&self) -> Vec<(String, &InstructionAccount)> {
+        fn flatten<'a>(
+            prefix: &str,
+            items: &'a [AccountItem],
+            out: &mut Vec<(String, &'a InstructionAccount)>,
+        ) {
+            for item in items {
+                match item {
+                    AccountItem::Account(account) => {
+                        out.push((format!("{}{}", prefix, account.name), account))
+                    }
+                    AccountItem::Group { name, accounts } => {
+                        flatten(&format!("{}{}.", prefix, name), accounts, out)
+                    }
+                }
+            }
+        }
+        let mut out = vec![];
+        flatten("", &self.accounts, &mut out);
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum AccountItem {
+    Account(InstructionAccount),
+    /// A named group of accounts (a nested `Accounts` struct).
+    Group {
+        name: String,
+        accounts: Vec<AccountItem>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstructionAccount {
+    pub name: String,
+    pub is_mut: bool,
+    pub is_signer: bool,
+    /// Marked `isOptional`, the program accepts it being left out.
+    pub is_optional: bool,
+    /// The only address the account can have, as declared by the IDL.
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+}
+
+/// An account or custom type definition.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeDef {
+    pub name: String,
+    /// Set for accounts only.
+    pub discriminator: Option<[u8; 8]>,
+    pub kind: TypeDefKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TypeDefKind {
+    Struct { fields: Vec<Field> },
+    Enum { variants: Vec<EnumVariant> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Option<EnumFields>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum EnumFields {
+    Named(Vec<Field>),
+    Tuple(Vec<Type>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub name: String,
+    pub discriminator: [u8; 8],
+    pub fields: Vec<EventField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventField {
+    pub name: String,
+    pub ty: Type,
+    pub index: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorCode {
+    pub code: u32,
+    pub name: String,
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Constant {
+    pub name: String,
+    pub ty: Type,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum Type {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    U128,
+    I128,
+    Bytes,
+    String,
+    PublicKey,
+    /// Reference to an account or custom type, see [`Program::find_type`].
+    Defined(String),
+    Option(Box<Type>),
+    Vec(Box<Type>),
+    Array(Box<Type>, usize),
+}
+
+impl Type {
+    /// Borsh size of the type, `None` if it varies or refers to a type
+    /// missing from `definitions`.
+    pub fn static_size(&self, definitions: &[TypeDef]) -> Option<usize> {
+        size_of(self, definitions, &mut vec![])
+    }
+}
+
+impl TypeDef {
+    /// Borsh size of the definition, discriminator excluded, see
+    /// [`Type::static_size`].
+    pub fn static_size(&self, definitions: &[TypeDef]) -> Option<usize> {
+        def_size(self, definitions, &mut vec![])
+    }
+}
+
+// `visiting` are the definitions being sized, which can't contain themselves
+fn size_of<'a>(
+    ty: &Type,
+    definitions: &'a [TypeDef],
+    visiting: &mut Vec<&'a str>,
+) -> Option<usize> {
+    match ty {
+        Type::Bool | Type::U8 | Type::I8 => Some(1),
+        Type::U16 | Type::I16 => Some(2),
+        Type::U32 | Type::I32 | Type::F32 => Some(4),
+        Type::U64 | Type::I64 | Type::F64 => Some(8),
+        Type::U128 | Type::I128 => Some(16),
+        Type::PublicKey => Some(32),
+        Type::Bytes | Type::String | Type::Option(_) | Type::Vec(_) => None,
+        Type::Array(ty, len) => size_of(ty, definitions, visiting)?.checked_mul(*len),
+        Type::Defined(name) => {
+            let def = definitions.iter().find(|d| &d.name == name)?;
+            def_size(def, definitions, visiting)
+        }
+    }
+}
+
+fn def_size<'a>(
+    def: &'a TypeDef,
+    definitions: &'a [TypeDef],
+    visiting: &mut Vec<&'a str>,
+) -> Option<usize> {
+    if visiting.contains(&def.name.as_str()) {
+        return None;
+    }
+    visiting.push(&def.name);
+    let size = match &def.kind {
+        TypeDefKind::Struct { fields } => fields
+            .iter()
+            .map(|f| size_of(&f.ty, definitions, visiting))
+            .sum(),
+        // the tag only, unless a variant has fields
+        TypeDefKind::Enum { variants } => variants.iter().all(|v| v.fields.is_none()).then_some(1),
+    };
+    visiting.pop();
+    size
+}
+
+/// First 8 bytes of `sha256("<namespace>:<name>")`, the anchor discriminator
+/// scheme.
+pub fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let preimage = format!("{}:{}", namespace, name);
+
+    let result = Sha256::digest(preimage.as_bytes());
+
+    let mut disc = [0u8; 8];
+    disc.copy_from_slice(&result[..8]);
+    disc
+}
@@ -1,2 +1,55 @@
-// put your generated contract.rs file here as mod
-// to check if any syntax error
+//! Generate rust bindings from anchor IDL files.
+//!
+//! The `parse_idl` binary is a thin CLI over this library, build scripts and
+//! other tools can drive the same pipeline directly.
+
+pub mod backend;
+pub mod build;
+mod codegen;
+mod config;
+mod conversions;
+pub mod decode;
+mod dedupe;
+mod error;
+pub mod frontend;
+mod generator;
+mod input;
+pub mod ir;
+mod manual;
+mod output;
+mod provenance;
+mod prune;
+pub mod rpc;
+mod template;
+pub mod visit;
+mod workspace;
+
+/// The anchor IDL model the legacy front-end parses, re-exported so callers
+/// don't need to depend on a matching `anchor_idl` version.
+pub use anchor_idl as idl;
+pub use backend::{
+    ty_to_rust_type, Backend, BackendRegistry, BenchBackend, GeneratedFile, RustBackend,
+    SkeletonBackend,
+};
+pub use codegen::{
+    build_sighash, generate_files, generate_from_idl, generate_from_json, generate_module,
+    generate_program, generate_to_writer, generate_tokens,
+};
+pub use config::{
+    Artifact, Case, Decimals, Feature, FlagSet, GeneratorConfig, GeneratorConfigBuilder, Layout,
+    Naming, OptionalAccounts, Sighash, TypeHook, Visibility,
+};
+pub use error::{ParseIdlError, Result};
+pub use generator::Generator;
+pub use input::{
+    check_idl_spec, find_idl_json, idl_module_name, parse_idl, parse_idl_value, read_idl,
+    read_idl_value, read_program, IDL_EXTENSIONS,
+};
+pub use ir::Program;
+pub use provenance::Provenance;
+pub use template::{load_template_dir, TemplateKind};
+pub use workspace::{
+    add_lib_modules, add_path_dependency, add_workspace_members, anchor_programs,
+    find_anchor_workspace, find_cargo_bindings_dir, find_cargo_workspace, generate_crates,
+    generate_workspace,
+};
@@ -0,0 +1,88 @@
+//! Hand written regions of generated files.
+//!
+//! Code between `// parse_idl:begin-manual` and `// parse_idl:end-manual`
+//! lines survives regeneration. A region can be named
+//! (`// parse_idl:begin-manual helpers`), a named region replaces the region
+//! of the same name if the new output has one (e.g. from a template), every
+//! other region is appended to the end of the file.
+
+const BEGIN: &str = "// parse_idl:begin-manual";
+const END: &str = "// parse_idl:end-manual";
+
+struct Region<'a> {
+    name: &'a str,
+    /// The region including its marker lines.
+    text: String,
+    /// Line range of the region in the file.
+    lines: std::ops::Range<usize>,
+}
+
+fn regions(contents: &str) -> Vec<Region<'_>> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut regions = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(name) = lines[i].trim().strip_prefix(BEGIN) else {
+            i += 1;
+            continue;
+        };
+        // an unterminated region runs to the end of the file
+        let end = (i + 1..lines.len())
+            .find(|&j| lines[j].trim() == END)
+            .map(|j| j + 1)
+            .unwrap_or(lines.len());
+        let mut text = lines[i..end].join("\n");
+        text.push('\n');
+        regions.push(Region {
+            name: name.trim(),
+            text,
+            lines: i..end,
+        });
+        i = end;
+    }
+    regions
+}
+
+/// Carries the manual regions of `existing` over into `generated`.
+pub(crate) fn carry_over(existing: &str, generated: String) -> String {
+    let carried = regions(existing);
+    if carried.is_empty() {
+        return generated;
+    }
+
+    let targets = regions(&generated);
+    let mut appended = vec![];
+    let mut replaced = vec![None; targets.len()];
+    for region in carried.iter() {
+        let target = targets
+            .iter()
+            .position(|t| !region.name.is_empty() && t.name == region.name);
+        match target {
+            Some(t) if replaced[t].is_none() => replaced[t] = Some(region.text.as_str()),
+            _ => appended.push(region.text.as_str()),
+        }
+    }
+
+    let lines: Vec<&str> = generated.lines().collect();
+    let mut out = String::with_capacity(generated.len() + existing.len());
+    let mut line = 0;
+    for (target, text) in targets.iter().zip(replaced) {
+        for l in &lines[line..target.lines.start] {
+            out.push_str(l);
+            out.push('\n');
+        }
+        out.push_str(text.unwrap_or(&target.text));
+        line = target.lines.end;
+    }
+    for l in &lines[line..] {
+        out.push_str(l);
+        out.push('\n');
+    }
+    for text in appended {
+        if !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+        out.push_str(text);
+    }
+    out
+}
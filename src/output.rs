@@ -0,0 +1,37 @@
+//! Writing generated files to disk.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Numbers the temporary files of the process, whose threads may write the
+/// same path at once, e.g. the support module of two generators.
+static TMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Replaces `path` with `contents` in one step: the data goes to a
+/// temporary file next to it, which is then renamed over `path`. Readers
+/// (editors, rust-analyzer, a concurrent build) never see a half written
+/// file, and a failed run leaves the previous file in place.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        TMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
@@ -0,0 +1,232 @@
+//! Provenance header written on top of every generated file.
+//!
+//! The header records the generator version, the source IDL with its
+//! SHA-256 and the SHA-256 of the options it was generated with, so an
+//! unchanged IDL generated the same way doesn't need to be regenerated. The
+//! banner of [`GeneratorConfig::banner`] follows it.
+
+use std::fmt::Debug;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::GeneratorConfig;
+use crate::error::Result;
+use crate::template::render;
+
+const GENERATED_BY: &str = "// @generated by parse_idl ";
+const SOURCE: &str = "// source: ";
+const IDL_SHA256: &str = "// idl-sha256: ";
+const OPTIONS_SHA256: &str = "// options-sha256: ";
+
+/// Where a generated file comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Version of the generator that wrote the file.
+    pub version: String,
+    pub source: String,
+    /// Hex encoded SHA-256 of the source IDL file.
+    pub idl_sha256: String,
+    /// Hex encoded SHA-256 of the options of [`Self::with_options`], empty
+    /// without.
+    pub options_sha256: String,
+}
+
+impl Provenance {
+    /// Provenance of a file generated by this version from `idl`, the bytes
+    /// of the IDL file at `source`.
+    pub fn new(source: &Path, idl: &[u8]) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.display().to_string(),
+            idl_sha256: sha256(idl),
+            options_sha256: String::new(),
+        }
+    }
+
+    /// The provenance of the file generated with `config`: its backend,
+    /// templates and every other option but [`GeneratorConfig::force`], so
+    /// changing them regenerates the file. Type hooks can't be compared and
+    /// are left out.
+    pub fn with_options(self, config: &GeneratorConfig) -> Self {
+        Self {
+            options_sha256: sha256(options_key(config).as_bytes()),
+            ..self
+        }
+    }
+
+    pub fn header(&self) -> String {
+        let mut header = format!(
+            "{}{}, do not edit.\n{}{}\n{}{}\n",
+            GENERATED_BY, self.version, SOURCE, self.source, IDL_SHA256, self.idl_sha256
+        );
+        if !self.options_sha256.is_empty() {
+            header.push_str(&format!("{}{}\n", OPTIONS_SHA256, self.options_sha256));
+        }
+        header.push('\n');
+        header
+    }
+
+    /// Reads the header back from the start of a generated file.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let version = lines.next()?.strip_prefix(GENERATED_BY)?;
+        let version = version.strip_suffix(", do not edit.").unwrap_or(version);
+        let source = lines.next()?.strip_prefix(SOURCE)?;
+        let idl_sha256 = lines.next()?.strip_prefix(IDL_SHA256)?;
+        let options_sha256 = lines
+            .next()
+            .and_then(|l| l.strip_prefix(OPTIONS_SHA256))
+            .unwrap_or_default();
+        Some(Self {
+            version: version.to_string(),
+            source: source.to_string(),
+            idl_sha256: idl_sha256.to_string(),
+            options_sha256: options_sha256.to_string(),
+        })
+    }
+
+    /// Whether `path` was generated from the same IDL with the same options
+    /// by this generator version, warns when it was written by an
+    /// incompatible version.
+    pub fn is_current(&self, path: &Path) -> bool {
+        let Some(existing) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|c| Self::parse(&c))
+        else {
+            return false;
+        };
+        if !compatible(&existing.version, &self.version) {
+            warn!(
+                "{} was generated by parse_idl {}, regenerating with {}",
+                path.display(),
+                existing.version,
+                self.version
+            );
+        }
+        existing.version == self.version
+            && existing.idl_sha256 == self.idl_sha256
+            && existing.options_sha256 == self.options_sha256
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The `Debug` of `config` without [`GeneratorConfig::force`], with the
+/// entries of its maps and sets sorted to not depend on their iteration
+/// order.
+fn options_key(config: &GeneratorConfig) -> String {
+    fn sorted<T: Debug>(items: impl IntoIterator<Item = T>) -> Vec<String> {
+        let mut items: Vec<_> = items.into_iter().map(|i| format!("{:?}", i)).collect();
+        items.sort();
+        items
+    }
+
+    let mut rest = config.clone();
+    rest.force = false;
+    let maps = [
+        sorted(std::mem::take(&mut rest.type_overrides)),
+        sorted(std::mem::take(&mut rest.flags)),
+        sorted(std::mem::take(&mut rest.pubkey_newtypes)),
+        sorted(std::mem::take(&mut rest.amounts)),
+        sorted(std::mem::take(&mut rest.artifacts)),
+        sorted(std::mem::take(&mut rest.templates)),
+    ];
+    format!("{:?} {:?}", rest, maps)
+}
+
+/// Semver compatibility: same major, or same minor for 0.x versions.
+fn compatible(a: &str, b: &str) -> bool {
+    let key = |v: &str| {
+        let mut parts = v.split('.');
+        let major = parts.next().unwrap_or("");
+        let minor = parts.next().unwrap_or("");
+        if major == "0" {
+            (major.to_string(), minor.to_string())
+        } else {
+            (major.to_string(), String::new())
+        }
+    };
+    key(a) == key(b)
+}
+
+#[derive(Debug, Serialize)]
+struct BannerContext<'a> {
+    program: &'a str,
+    version: &'a str,
+    timestamp: String,
+}
+
+/// The rendered [`GeneratorConfig::banner`] of a file of `program`, as line
+/// comments followed by a blank line, empty without a banner.
+pub(crate) fn banner(config: &GeneratorConfig, program: &str, version: &str) -> Result<String> {
+    let Some(template) = &config.banner else {
+        return Ok(String::new());
+    };
+    let rendered = render(
+        template,
+        BannerContext {
+            program,
+            version,
+            timestamp: timestamp(),
+        },
+    )?;
+    let mut banner = String::new();
+    for line in rendered.trim_end().lines() {
+        if line.starts_with("//") {
+            banner.push_str(line);
+        } else if line.is_empty() {
+            banner.push_str("//");
+        } else {
+            banner.push_str("// ");
+            banner.push_str(line);
+        }
+        banner.push('\n');
+    }
+    if !banner.is_empty() {
+        banner.push('\n');
+    }
+    Ok(banner)
+}
+
+/// The current UTC time in RFC 3339, or that of `SOURCE_DATE_EPOCH` for
+/// reproducible outputs.
+fn timestamp() -> String {
+    let secs: u64 = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // civil date of the days since the epoch, after Howard Hinnant
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
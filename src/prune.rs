@@ -0,0 +1,138 @@
+//! Pruning a program to the instructions it is generated for, see
+//! [`GeneratorConfig::only_instructions`], and to the types referenced, see
+//! [`GeneratorConfig::prune_unreferenced`].
+
+use std::collections::HashSet;
+
+use heck::ToSnakeCase;
+use log::{info, warn};
+
+use crate::config::GeneratorConfig;
+use crate::ir::{Program, Type};
+use crate::visit::{self, Visitor};
+
+struct Defined(HashSet<String>);
+
+impl<'ast> Visitor<'ast> for Defined {
+    fn visit_type(&mut self, ty: &'ast Type) {
+        if let Type::Defined(name) = ty {
+            self.0.insert(name.clone());
+        }
+        visit::walk_type(self, ty);
+    }
+}
+
+/// The types `program` defines that no instruction, account or event
+/// reaches, sorted, empty for a program of types only. The constants are
+/// left out, nothing in the IDL refers to them.
+pub(crate) fn unreferenced(program: &Program) -> Vec<String> {
+    if program.instructions.is_empty() && program.accounts.is_empty() && program.events.is_empty() {
+        return vec![];
+    }
+    let mut reached = Defined(
+        program
+            .accounts
+            .iter()
+            .map(|def| def.name.clone())
+            .collect(),
+    );
+    for ix in program.instructions.iter() {
+        reached.visit_instruction(ix);
+    }
+    for event in program.events.iter() {
+        reached.visit_event(event);
+    }
+    reach(program, &mut reached);
+    let mut unreferenced: Vec<_> = program
+        .types
+        .iter()
+        .filter(|def| !reached.0.contains(&def.name))
+        .map(|def| def.name.clone())
+        .collect();
+    unreferenced.sort();
+    unreferenced
+}
+
+/// Adds the types the reached ones refer to, until nothing is added.
+fn reach(program: &Program, reached: &mut Defined) {
+    loop {
+        let before = reached.0.len();
+        for def in program.accounts.iter().chain(program.types.iter()) {
+            if reached.0.contains(&def.name) {
+                reached.visit_type_def(def);
+            }
+        }
+        if reached.0.len() == before {
+            break;
+        }
+    }
+}
+
+/// `program` with only the selected instructions and the accounts and types
+/// they reach, without the unreferenced types if they are pruned, `None`
+/// if nothing is pruned. The unreferenced types are reported either way.
+///
+/// The events, the errors and the constants are kept, the IDL doesn't tell
+/// which instructions emit or return them, along with the types they reach.
+pub(crate) fn prune(program: &Program, config: &GeneratorConfig) -> Option<Program> {
+    let unreferenced = unreferenced(program);
+    if !unreferenced.is_empty() {
+        info!(
+            "{}: {} unreferenced types: {}",
+            program.name,
+            unreferenced.len(),
+            unreferenced.join(", ")
+        );
+    }
+    let pruned = select(program, config);
+    if !config.prune_unreferenced || unreferenced.is_empty() {
+        return pruned;
+    }
+    let mut pruned = pruned.unwrap_or_else(|| program.clone());
+    pruned
+        .types
+        .retain(|def| unreferenced.binary_search(&def.name).is_err());
+    Some(pruned)
+}
+
+/// `program` with only the selected instructions, `None` without a
+/// selection.
+fn select(program: &Program, config: &GeneratorConfig) -> Option<Program> {
+    if config.only_instructions.is_empty() {
+        return None;
+    }
+    let matches = |only: &str, name: &str| only == name || only == name.to_snake_case();
+    let selected = |name: &str| {
+        config
+            .only_instructions
+            .iter()
+            .any(|only| matches(only, name))
+    };
+    for only in config.only_instructions.iter() {
+        if !program
+            .instructions
+            .iter()
+            .any(|ix| matches(only, &ix.name))
+        {
+            warn!("{} has no instruction {}", program.name, only);
+        }
+    }
+
+    let mut pruned = program.clone();
+    pruned.instructions.retain(|ix| selected(&ix.name));
+
+    let mut reached = Defined(HashSet::new());
+    for ix in pruned.instructions.iter() {
+        reached.visit_instruction(ix);
+    }
+    for event in pruned.events.iter() {
+        reached.visit_event(event);
+    }
+    for constant in pruned.constants.iter() {
+        reached.visit_type(&constant.ty);
+    }
+    reach(program, &mut reached);
+    pruned.accounts.retain(|def| reached.0.contains(&def.name));
+    pruned.types.retain(|def| reached.0.contains(&def.name));
+    Some(pruned)
+}
@@ -0,0 +1,303 @@
+//! Minimal blocking JSON-RPC and pubsub clients for the few Solana RPC
+//! methods the decode, monitor and backfill commands need.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::decode::Encoding;
+use crate::error::{ParseIdlError, Result};
+
+pub const MAINNET_URL: &str = "https://api.mainnet-beta.solana.com";
+
+/// An account fetched by [`RpcClient::get_account`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub owner: String,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+/// A transaction listed by [`RpcClient::get_signatures_for_address`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub failed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Calls `method` and returns the `result` of the response.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(request)
+            .map_err(|e| ParseIdlError::Rpc(format!("{}: {}", method, e)))?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            return Err(ParseIdlError::Rpc(format!("{}: {}", method, error)));
+        }
+        response
+            .get_mut("result")
+            .map(Value::take)
+            .ok_or_else(|| ParseIdlError::Rpc(format!("{}: response without a result", method)))
+    }
+
+    /// Data and owner of the account at `address`.
+    pub fn get_account(&self, address: &str) -> Result<Account> {
+        let info = self.call(
+            "getAccountInfo",
+            json!([address, {"encoding": "base64", "commitment": "confirmed"}]),
+        )?;
+        let value = &info["value"];
+        if value.is_null() {
+            return Err(ParseIdlError::Rpc(format!("account {} not found", address)));
+        }
+        let invalid = || ParseIdlError::Rpc(format!("invalid account info: {}", value));
+        let data = value["data"][0].as_str().ok_or_else(invalid)?;
+        Ok(Account {
+            owner: value["owner"].as_str().ok_or_else(invalid)?.to_string(),
+            lamports: value["lamports"].as_u64().ok_or_else(invalid)?,
+            data: Encoding::Base64.decode(data)?,
+        })
+    }
+
+    /// Up to `limit` (at most 1000) signatures of confirmed transactions
+    /// mentioning `address`, newest first, starting before the `before`
+    /// signature.
+    pub fn get_signatures_for_address(
+        &self,
+        address: &str,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let mut config = json!({"limit": limit, "commitment": "confirmed"});
+        if let Some(before) = before {
+            config["before"] = json!(before);
+        }
+        let infos = self.call("getSignaturesForAddress", json!([address, config]))?;
+        let infos = infos
+            .as_array()
+            .ok_or_else(|| ParseIdlError::Rpc(format!("invalid signatures: {}", infos)))?;
+        Ok(infos
+            .iter()
+            .filter_map(|info| {
+                Some(SignatureInfo {
+                    signature: info["signature"].as_str()?.to_string(),
+                    slot: info["slot"].as_u64()?,
+                    failed: !info["err"].is_null(),
+                })
+            })
+            .collect())
+    }
+
+    /// The confirmed transaction with `signature` in the `json` encoding,
+    /// versioned transactions included.
+    pub fn get_transaction(&self, signature: &str) -> Result<Value> {
+        let tx = self.call(
+            "getTransaction",
+            json!([signature, {
+                "encoding": "json",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )?;
+        if tx.is_null() {
+            return Err(ParseIdlError::Rpc(format!(
+                "transaction {} not found",
+                signature
+            )));
+        }
+        Ok(tx)
+    }
+}
+
+/// Size of the address lookup table header before the addresses.
+const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// Resolves the addresses v0 transactions load from address lookup tables,
+/// for transactions whose `meta.loadedAddresses` is missing. Fetched tables
+/// are cached.
+#[derive(Debug)]
+pub struct LookupTables {
+    rpc: RpcClient,
+    tables: HashMap<String, Vec<String>>,
+}
+
+impl LookupTables {
+    pub fn new(rpc: RpcClient) -> Self {
+        Self {
+            rpc,
+            tables: HashMap::new(),
+        }
+    }
+
+    /// Addresses stored in the lookup table at `address`.
+    pub fn table(&mut self, address: &str) -> Result<&[String]> {
+        if !self.tables.contains_key(address) {
+            let account = self.rpc.get_account(address)?;
+            let addresses = account
+                .data
+                .get(LOOKUP_TABLE_META_SIZE..)
+                .ok_or_else(|| {
+                    ParseIdlError::Rpc(format!("{} is not an address lookup table", address))
+                })?
+                .chunks_exact(32)
+                .map(|key| bs58::encode(key).into_string())
+                .collect();
+            self.tables.insert(address.to_string(), addresses);
+        }
+        Ok(&self.tables[address])
+    }
+
+    /// Fills in `meta.loadedAddresses` of a `getTransaction` result from its
+    /// `addressTableLookups`, unless the node already did.
+    pub fn resolve(&mut self, tx: &mut Value) -> Result<()> {
+        let lookups = match tx["transaction"]["message"]["addressTableLookups"].as_array() {
+            Some(lookups) if !lookups.is_empty() => lookups.clone(),
+            _ => return Ok(()),
+        };
+        if tx["meta"]["loadedAddresses"].is_object() {
+            return Ok(());
+        }
+        let mut writable = vec![];
+        let mut readonly = vec![];
+        for lookup in lookups.iter() {
+            let key = lookup["accountKey"].as_str().unwrap_or_default();
+            let table = self.table(key)?;
+            for (kind, loaded) in [
+                ("writableIndexes", &mut writable),
+                ("readonlyIndexes", &mut readonly),
+            ] {
+                for index in lookup[kind].as_array().into_iter().flatten() {
+                    let address = index
+                        .as_u64()
+                        .and_then(|i| table.get(i as usize))
+                        .ok_or_else(|| {
+                            ParseIdlError::Rpc(format!(
+                                "index {} out of lookup table {}",
+                                index, key
+                            ))
+                        })?;
+                    loaded.push(address.clone());
+                }
+            }
+        }
+        tx["meta"]["loadedAddresses"] = json!({"writable": writable, "readonly": readonly});
+        Ok(())
+    }
+}
+
+/// A `logsSubscribe` subscription to the transactions mentioning a
+/// program, over the websocket endpoint.
+pub struct LogsSubscription {
+    socket: tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<std::net::TcpStream>>,
+}
+
+/// A transaction seen by a [`LogsSubscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogsNotification {
+    pub signature: String,
+    pub slot: u64,
+    /// Whether the transaction failed.
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+fn ws_error(e: tungstenite::Error) -> ParseIdlError {
+    ParseIdlError::Rpc(format!("websocket: {}", e))
+}
+
+impl LogsSubscription {
+    pub fn new(ws_url: &str, program: &str) -> Result<Self> {
+        let (socket, _) = tungstenite::connect(ws_url).map_err(ws_error)?;
+        let mut subscription = Self { socket };
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "logsSubscribe",
+            "params": [{"mentions": [program]}, {"commitment": "confirmed"}],
+        });
+        subscription
+            .socket
+            .send(tungstenite::Message::Text(request.to_string()))
+            .map_err(ws_error)?;
+        let response = subscription.read_json()?;
+        if let Some(error) = response.get("error") {
+            return Err(ParseIdlError::Rpc(format!("logsSubscribe: {}", error)));
+        }
+        Ok(subscription)
+    }
+
+    fn read_json(&mut self) -> Result<Value> {
+        loop {
+            match self.socket.read().map_err(ws_error)? {
+                tungstenite::Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                tungstenite::Message::Close(_) => {
+                    return Err(ParseIdlError::Rpc("websocket closed".to_string()))
+                }
+                // pings are answered by tungstenite
+                _ => continue,
+            }
+        }
+    }
+
+    /// Blocks until the next transaction.
+    pub fn recv(&mut self) -> Result<LogsNotification> {
+        loop {
+            let message = self.read_json()?;
+            let result = &message["params"]["result"];
+            let Some(signature) = result["value"]["signature"].as_str() else {
+                continue;
+            };
+            return Ok(LogsNotification {
+                signature: signature.to_string(),
+                slot: result["context"]["slot"].as_u64().unwrap_or_default(),
+                failed: !result["value"]["err"].is_null(),
+                logs: result["value"]["logs"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|l| l.as_str().map(str::to_string))
+                    .collect(),
+            });
+        }
+    }
+}
+
+/// The HTTP endpoint next to a websocket endpoint, following the solana
+/// CLI convention of the websocket port being one above the HTTP port.
+pub fn http_url(ws_url: &str) -> String {
+    let url = if let Some(rest) = ws_url.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = ws_url.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        ws_url.to_string()
+    };
+    url.replacen(":8900", ":8899", 1)
+}
@@ -0,0 +1,114 @@
+//! User templates overriding the fixed rust emitter.
+//!
+//! A template is a [minijinja](https://docs.rs/minijinja) source rendered
+//! once per item of its kind. The context variables are:
+//!
+//! - `struct`: `name`, `idl_name`, `derives`, `visibility`, `fields`
+//!   (each with `name`, `idl_name`, `ty`, `visibility`)
+//! - `enum`: `name`, `idl_name`, `derives`, `visibility`, `variants`
+//!   (each with `name` and `discriminant`, its Borsh tag)
+//! - `instruction`: `name`, `idl_name`, `discriminator`, `derives`,
+//!   `visibility`, `args` (like struct fields) and `accounts` (each with
+//!   `name`, `is_mut`, `is_signer`, `is_optional`)
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::{ParseIdlError, Result};
+
+/// Kind of item a template is rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateKind {
+    Struct,
+    Enum,
+    Instruction,
+}
+
+impl TemplateKind {
+    pub const ALL: [TemplateKind; 3] = [
+        TemplateKind::Struct,
+        TemplateKind::Enum,
+        TemplateKind::Instruction,
+    ];
+
+    /// File name the template is loaded from in a template directory.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            TemplateKind::Struct => "struct.jinja",
+            TemplateKind::Enum => "enum.jinja",
+            TemplateKind::Instruction => "instruction.jinja",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FieldContext {
+    pub name: String,
+    pub idl_name: String,
+    pub ty: String,
+    pub visibility: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct StructContext<'a> {
+    pub name: String,
+    pub idl_name: &'a str,
+    pub derives: &'a [String],
+    pub visibility: &'static str,
+    pub fields: Vec<FieldContext>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct VariantContext<'a> {
+    pub name: &'a str,
+    pub discriminant: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct EnumContext<'a> {
+    pub name: String,
+    pub idl_name: &'a str,
+    pub derives: &'a [String],
+    pub visibility: &'static str,
+    pub variants: Vec<VariantContext<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AccountContext<'a> {
+    pub name: &'a str,
+    pub is_mut: bool,
+    pub is_signer: bool,
+    pub is_optional: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InstructionContext<'a> {
+    pub name: String,
+    pub idl_name: &'a str,
+    pub discriminator: [u8; 8],
+    pub derives: &'a [String],
+    pub visibility: &'static str,
+    pub args: Vec<FieldContext>,
+    pub accounts: Vec<AccountContext<'a>>,
+}
+
+/// Renders a template source with the given context.
+pub(crate) fn render(source: &str, context: impl Serialize) -> Result<String> {
+    let mut env = minijinja::Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.render_str(source, context)
+        .map_err(|e| ParseIdlError::Template(e.to_string()))
+}
+
+/// Loads every `<kind>.jinja` template present in `dir`.
+pub fn load_template_dir(dir: &Path) -> Result<Vec<(TemplateKind, String)>> {
+    let mut templates = vec![];
+    for kind in TemplateKind::ALL {
+        let path = dir.join(kind.file_name());
+        if path.is_file() {
+            templates.push((kind, std::fs::read_to_string(path)?));
+        }
+    }
+    Ok(templates)
+}
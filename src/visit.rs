@@ -0,0 +1,152 @@
+//! Read-only traversal of a lowered [`Program`].
+//!
+//! Implement [`Visitor`] and override the methods for the items of interest,
+//! the default methods descend into the children through the `walk_*`
+//! functions. Overriding a method and not calling its `walk_*` function
+//! skips that subtree.
+
+use crate::ir::{
+    AccountItem, EnumFields, EnumVariant, Event, EventField, Field, Instruction,
+    InstructionAccount, Program, Type, TypeDef, TypeDefKind,
+};
+
+/// Where a visited field is declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldOwner<'a> {
+    /// An argument of the named instruction.
+    Instruction(&'a str),
+    /// A field of the named account or custom type.
+    TypeDef(&'a str),
+    /// A named field of a variant of the named enum: `(enum, variant)`.
+    Variant(&'a str, &'a str),
+}
+
+pub trait Visitor<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_instruction(&mut self, ix: &'ast Instruction) {
+        walk_instruction(self, ix);
+    }
+
+    fn visit_instruction_account(
+        &mut self,
+        _ix: &'ast Instruction,
+        _account: &'ast InstructionAccount,
+    ) {
+    }
+
+    /// Called for every entry of the `accounts` and `types` sections.
+    fn visit_type_def(&mut self, def: &'ast TypeDef) {
+        walk_type_def(self, def);
+    }
+
+    fn visit_variant(&mut self, def: &'ast TypeDef, variant: &'ast EnumVariant) {
+        walk_variant(self, def, variant);
+    }
+
+    fn visit_event(&mut self, event: &'ast Event) {
+        walk_event(self, event);
+    }
+
+    fn visit_field(&mut self, _owner: FieldOwner<'ast>, field: &'ast Field) {
+        self.visit_type(&field.ty);
+    }
+
+    fn visit_event_field(&mut self, _event: &'ast Event, field: &'ast EventField) {
+        self.visit_type(&field.ty);
+    }
+
+    /// Called for every type, including the inner types of `Option`, `Vec`
+    /// and arrays.
+    fn visit_type(&mut self, ty: &'ast Type) {
+        walk_type(self, ty);
+    }
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    for ix in program.instructions.iter() {
+        visitor.visit_instruction(ix);
+    }
+    for def in program.accounts.iter().chain(program.types.iter()) {
+        visitor.visit_type_def(def);
+    }
+    for event in program.events.iter() {
+        visitor.visit_event(event);
+    }
+}
+
+pub fn walk_instruction<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ix: &'ast Instruction) {
+    fn accounts<'ast, V: Visitor<'ast> + ?Sized>(
+        visitor: &mut V,
+        ix: &'ast Instruction,
+        items: &'ast [AccountItem],
+    ) {
+        for item in items {
+            match item {
+                AccountItem::Account(account) => visitor.visit_instruction_account(ix, account),
+                AccountItem::Group {
+                    accounts: inner, ..
+                } => accounts(visitor, ix, inner),
+            }
+        }
+    }
+
+    accounts(visitor, ix, &ix.accounts);
+    for arg in ix.args.iter() {
+        visitor.visit_field(FieldOwner::Instruction(&ix.name), arg);
+    }
+    if let Some(returns) = &ix.returns {
+        visitor.visit_type(returns);
+    }
+}
+
+pub fn walk_type_def<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, def: &'ast TypeDef) {
+    match &def.kind {
+        TypeDefKind::Struct { fields } => {
+            for field in fields.iter() {
+                visitor.visit_field(FieldOwner::TypeDef(&def.name), field);
+            }
+        }
+        TypeDefKind::Enum { variants } => {
+            for variant in variants.iter() {
+                visitor.visit_variant(def, variant);
+            }
+        }
+    }
+}
+
+pub fn walk_variant<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    def: &'ast TypeDef,
+    variant: &'ast EnumVariant,
+) {
+    match &variant.fields {
+        Some(EnumFields::Named(fields)) => {
+            for field in fields.iter() {
+                visitor.visit_field(FieldOwner::Variant(&def.name, &variant.name), field);
+            }
+        }
+        Some(EnumFields::Tuple(types)) => {
+            for ty in types.iter() {
+                visitor.visit_type(ty);
+            }
+        }
+        None => {}
+    }
+}
+
+pub fn walk_event<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, event: &'ast Event) {
+    for field in event.fields.iter() {
+        visitor.visit_event_field(event, field);
+    }
+}
+
+/// Visits the types nested in `ty`.
+pub fn walk_type<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, ty: &'ast Type) {
+    match ty {
+        Type::Option(inner) | Type::Vec(inner) | Type::Array(inner, _) => visitor.visit_type(inner),
+        _ => {}
+    }
+}
@@ -0,0 +1,852 @@
+use std::path::{Path, PathBuf};
+
+use heck::ToSnakeCase;
+
+use log::warn;
+
+use crate::backend::{
+    defines_bindings_error, defines_cli, defines_client, defines_localnet, serializes, uses_anchor,
+    BackendRegistry,
+};
+use crate::codegen::{
+    common_types, generate_files, lower_module, read_modules, without_support, write_program,
+};
+use crate::config::{Feature, GeneratorConfig};
+use crate::dedupe;
+use crate::error::{ParseIdlError, Result};
+use crate::output::write_atomic;
+use crate::provenance::Provenance;
+
+/// Generates bindings for every program of the Anchor workspace enclosing `start`.
+///
+/// `anchor build` leaves one IDL per program in target/idl, the generated
+/// modules are collected into the `bindings_crate` directory. Its manifest
+/// is written when missing and it is added to the members of the workspace,
+/// and to the dependencies of the [`GeneratorConfig::consumer_crate`].
+pub fn generate_workspace(
+    start: &Path,
+    bindings_crate: &Path,
+    config: &GeneratorConfig,
+) -> Result<()> {
+    let root = find_anchor_workspace(start)?;
+    let manifest = read_manifest(&root.join("Anchor.toml"))?;
+
+    let idl_dir = root.join("target").join("idl");
+    let mut files = vec![];
+    for program in anchor_programs(&manifest, &root)? {
+        let path = idl_dir.join(format!("{}.json", program));
+        if !path.is_file() {
+            return Err(ParseIdlError::Workspace(format!(
+                "no IDL for program '{}' at {}, run `anchor build` first",
+                program,
+                path.display()
+            )));
+        }
+        files.push(path);
+    }
+
+    let crate_dir = root.join(bindings_crate);
+    let out_dir = crate_dir.join("src");
+    std::fs::create_dir_all(&out_dir)?;
+    let written = generate_files(&files, &out_dir, config)?;
+
+    let modules: Vec<String> = written.iter().filter_map(|f| module_name(f)).collect();
+    add_lib_modules(&out_dir.join("lib.rs"), &modules)?;
+    let name = crate_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    write_crate(
+        &crate_dir,
+        &name,
+        "0.1.0",
+        &used_features(config),
+        config,
+        false,
+    )?;
+    let crates = [crate_dir];
+    register_crates(&root, &crates)?;
+    add_consumer_dependencies(&crates, config)
+}
+
+/// Generates a cargo workspace into `root` with one bindings crate per IDL
+/// (or per entry of an IDL array), `<module>/` named `<module>-bindings`,
+/// and returns the crate directories.
+///
+/// The types shared with [`GeneratorConfig::dedupe`] or imported with
+/// [`GeneratorConfig::includes`] go to a `common/` crate the program crates
+/// depend on by path. The crates are added to the `[workspace] members` of
+/// `root/Cargo.toml`, which is created if needed, or of the workspace
+/// enclosing `root` if it has no Cargo.toml, and the program crates to the
+/// dependencies of the [`GeneratorConfig::consumer_crate`]. A crate's
+/// manifest is only written when missing, so edits to it are kept.
+pub fn generate_crates(
+    files: &[PathBuf],
+    root: &Path,
+    config: &GeneratorConfig,
+) -> Result<Vec<PathBuf>> {
+    write_crates(&BackendRegistry::with_defaults(), files, root, config)
+}
+
+pub(crate) fn write_crates(
+    registry: &BackendRegistry,
+    files: &[PathBuf],
+    root: &Path,
+    config: &GeneratorConfig,
+) -> Result<Vec<PathBuf>> {
+    if config.conversions {
+        warn!("conversions between the programs are not generated in crate mode");
+    }
+    if config.shared_support {
+        warn!("the helpers are not shared between the crates in crate mode");
+    }
+    let config = &without_support(config);
+    let modules = read_modules(files)?;
+    let mut programs = vec![];
+    for module in modules.iter() {
+        programs.push(lower_module(module, &config.sighash)?);
+    }
+    let shared = common_types(&programs, config)?;
+
+    let mut crates = vec![];
+    // the crates of the programs, without the common one
+    let mut program_crates = vec![];
+    if !shared.is_empty() {
+        let dir = root.join(dedupe::COMMON_MODULE);
+        let common = dedupe::common_program(&shared);
+        let signature = serde_json::to_vec(&common.types)?;
+        let provenance = Provenance::new(Path::new(dedupe::COMMON_MODULE), &signature);
+        let common_config = dedupe::common_config(config);
+        let features: Vec<_> = used_features(&common_config)
+            .into_iter()
+            .filter(|f| *f == Feature::Serde)
+            .collect();
+        write_crate(
+            &dir,
+            &crate_name(dedupe::COMMON_MODULE),
+            "0.1.0",
+            &features,
+            config,
+            false,
+        )?;
+        write_program(
+            registry,
+            &common,
+            dedupe::COMMON_MODULE,
+            &dir.join("src"),
+            &common_config,
+            Some(&provenance),
+        )?;
+        add_lib_modules(
+            &dir.join("src").join("lib.rs"),
+            &[dedupe::COMMON_MODULE.to_string()],
+        )?;
+        crates.push(dir);
+    }
+    let common_path = format!(
+        "{}::{}",
+        crate_name(dedupe::COMMON_MODULE).replace('-', "_"),
+        dedupe::COMMON_MODULE
+    );
+    for ((label, name, _, source, mut bytes), mut program) in modules.into_iter().zip(programs) {
+        let dir = root.join(&name);
+        let program_config = dedupe::program_config(config, &mut program, &shared, &common_path);
+        let uses_common = program_config.reexports.len() > config.reexports.len();
+        let features = used_features(&program_config);
+        write_crate(
+            &dir,
+            &crate_name(&name),
+            &program.version,
+            &features,
+            &program_config,
+            uses_common,
+        )
+        .map_err(|e| e.in_file(label.clone()))?;
+        bytes.extend(shared.iter().flat_map(|t| t.name.bytes()));
+        let provenance = Provenance::new(source, &bytes);
+        write_program(
+            registry,
+            &program,
+            &name,
+            &dir.join("src"),
+            &program_config,
+            Some(&provenance),
+        )
+        .map_err(|e| e.in_file(label))?;
+        let lib_rs = dir.join("src").join("lib.rs");
+        add_lib_modules(&lib_rs, std::slice::from_ref(&name))?;
+        if config.prelude {
+            add_lib_lines(&lib_rs, &[format!("pub use {}::prelude;", name)])?;
+        }
+        program_crates.push(dir.clone());
+        crates.push(dir);
+    }
+
+    register_crates(root, &crates)?;
+    add_consumer_dependencies(&program_crates, config)?;
+    Ok(crates)
+}
+
+/// Adds `crates` to the members of the workspace they belong to: the one of
+/// `root/Cargo.toml` if there is one or no workspace encloses `root`, which
+/// is created then, else the enclosing one.
+fn register_crates(root: &Path, crates: &[PathBuf]) -> Result<()> {
+    let root = root.canonicalize()?;
+    let manifest = root.join("Cargo.toml");
+    let enclosing = match root.parent() {
+        Some(parent) if !manifest.is_file() => find_cargo_workspace(parent)?,
+        _ => None,
+    };
+    let manifest = enclosing.unwrap_or(manifest);
+    let dir = manifest.parent().unwrap_or(&root);
+    let mut members = vec![];
+    for krate in crates {
+        if let Ok(member) = krate.canonicalize()?.strip_prefix(dir) {
+            members.push(member.to_string_lossy().to_string());
+        }
+    }
+    add_workspace_members(&manifest, &members)
+}
+
+/// Adds path dependencies on the bindings `crates` to the
+/// [`GeneratorConfig::consumer_crate`].
+fn add_consumer_dependencies(crates: &[PathBuf], config: &GeneratorConfig) -> Result<()> {
+    let Some(consumer) = &config.consumer_crate else {
+        return Ok(());
+    };
+    for dir in crates {
+        add_path_dependency(consumer, dir)?;
+    }
+    Ok(())
+}
+
+/// Package name of the bindings crate of a module.
+fn crate_name(module: &str) -> String {
+    format!("{}-bindings", module.replace('_', "-"))
+}
+
+/// A dependency of the generated code: name, version and cargo features.
+type Dependency = (&'static str, &'static str, &'static [&'static str]);
+
+const BASE_DEPENDENCIES: &[Dependency] = &[("borsh", "0.10.3", &[])];
+
+/// Where `Pubkey` comes from, anchor-lang unless the generated code doesn't
+/// otherwise use it, see [`uses_anchor`].
+const ANCHOR: &[Dependency] = &[("anchor-lang", "0.28.0", &[])];
+const SOLANA_PROGRAM: &[Dependency] = &[("solana-program", "1.16", &[])];
+
+/// The dependency of the [`GeneratorConfig::flags`] types, with serde
+/// derives that aren't gated.
+const BITFLAGS: &[Dependency] = &[("bitflags", "2", &[])];
+const BITFLAGS_SERDE: &[Dependency] = &[("bitflags", "2", &["serde"])];
+
+/// The dependency of the [`GeneratorConfig::timestamps`] accessors.
+const CHRONO: &[Dependency] = &[("chrono", "0.4.31", &[])];
+
+/// The dependency of the `BindingsError` of the decoders, builders and RPC
+/// code, see [`defines_bindings_error`].
+const THISERROR: &[Dependency] = &[("thiserror", "1", &[])];
+
+/// The dependencies whose default features are turned off. Those of sqlx
+/// pull in the mysql driver, whose rsa needs a zeroize the solana crates
+/// don't resolve with.
+const NO_DEFAULT_FEATURES: &[&str] = &["sqlx"];
+
+const SOLANA: &[Dependency] = &[
+    ("solana-account-decoder", "1.16", &[]),
+    ("solana-client", "1.16", &[]),
+    ("solana-sdk", "1.16", &[]),
+];
+
+/// The dependencies of the code behind a feature.
+fn dependencies(feature: Feature) -> impl Iterator<Item = &'static Dependency> {
+    let solana = if uses_solana(feature) { SOLANA } else { &[] };
+    feature_dependencies(feature).iter().chain(solana)
+}
+
+/// [`dependencies`] besides the solana crates.
+fn feature_dependencies(feature: Feature) -> &'static [Dependency] {
+    match feature {
+        Feature::Client | Feature::Localnet => &[],
+        Feature::Serde => &[("serde", "1", &["derive"])],
+        Feature::Ws => &[
+            ("tokio", "1", &["rt-multi-thread", "macros", "time", "sync"]),
+            ("futures", "0.3", &[]),
+        ],
+        Feature::Cli => &[("clap", "4", &["derive"])],
+        Feature::Tracing => &[("tracing", "0.1", &[])],
+        Feature::Indexer => &[
+            ("sqlx", "0.7", &["postgres", "runtime-tokio"]),
+            ("solana-transaction-status", "1.16", &[]),
+            ("tokio", "1", &["rt-multi-thread", "macros", "time", "sync"]),
+            ("futures", "0.3", &[]),
+        ],
+        Feature::Kafka => &[("rdkafka", "0.36", &[]), ("serde_json", "1", &[])],
+        Feature::Http => &[
+            ("axum", "0.7", &[]),
+            ("tokio", "1", &["rt-multi-thread", "macros", "net"]),
+            ("serde_json", "1", &[]),
+            ("base64", "0.21", &[]),
+            ("solana-transaction-status", "1.16", &[]),
+        ],
+        Feature::Grpc => &[
+            ("tonic", "0.12", &[]),
+            ("prost", "0.13", &[]),
+            ("futures", "0.3", &[]),
+        ],
+    }
+}
+
+fn uses_solana(feature: Feature) -> bool {
+    !matches!(feature, Feature::Serde | Feature::Tracing | Feature::Grpc)
+}
+
+/// Whether the generated code of `config` puts the code using `feature`
+/// behind the cargo feature: all of it with feature gates, the tracing
+/// spans always.
+fn gated(feature: Feature, config: &GeneratorConfig) -> bool {
+    config.feature_gates || feature == Feature::Tracing
+}
+
+/// The features the generated code of `config` uses, by the conditions the
+/// backends emit their code under.
+fn used_features(config: &GeneratorConfig) -> Vec<Feature> {
+    Feature::ALL
+        .into_iter()
+        .filter(|feature| match feature {
+            Feature::Client => defines_client(config),
+            Feature::Serde => serializes(config),
+            Feature::Ws => config.event_stream || config.account_scans || config.account_watchers,
+            Feature::Cli => defines_cli(config),
+            Feature::Tracing => config.tracing,
+            Feature::Localnet => defines_localnet(config),
+            Feature::Indexer => config.indexer,
+            Feature::Kafka => config.kafka_sink,
+            Feature::Http => config.http_service,
+            Feature::Grpc => config.grpc_service,
+        })
+        .collect()
+}
+
+/// Writes the manifest of a bindings crate in `dir`, using `features`, if
+/// it has none.
+///
+/// The dependencies of the [gated](gated) code are optional and enabled by
+/// the crate's features of the same name.
+fn write_crate(
+    dir: &Path,
+    name: &str,
+    version: &str,
+    features: &[Feature],
+    config: &GeneratorConfig,
+    uses_common: bool,
+) -> Result<()> {
+    let manifest = dir.join("Cargo.toml");
+    if manifest.is_file() {
+        return Ok(());
+    }
+    let version = if version.split('.').count() == 3 {
+        version
+    } else {
+        "0.1.0"
+    };
+
+    let mut contents = format!(
+        "[package]\nname = {:?}\nversion = {:?}\nedition = \"2021\"\n\n[dependencies]\n",
+        name, version
+    );
+    let mut seen = vec![];
+    let bitflags = if config.flags.is_empty() {
+        &[]
+    } else if !gated(Feature::Serde, config) && features.contains(&Feature::Serde) {
+        BITFLAGS_SERDE
+    } else {
+        BITFLAGS
+    };
+    let chrono = CHRONO.iter().filter(|_| !config.timestamps.is_empty());
+    let thiserror = THISERROR.iter().filter(|_| defines_bindings_error(config));
+    let semantic = bitflags.iter().chain(chrono).chain(thiserror);
+    let pubkey = if uses_anchor(config) {
+        ANCHOR
+    } else {
+        SOLANA_PROGRAM
+    };
+    let required = pubkey
+        .iter()
+        .chain(BASE_DEPENDENCIES)
+        .chain(semantic)
+        .map(|dep| (dep, false));
+    // the dependencies of ungated code first, they aren't optional
+    let (gated_features, ungated_features): (Vec<Feature>, Vec<Feature>) = features
+        .iter()
+        .partition(|feature| gated(**feature, config));
+    let ungated = ungated_features
+        .iter()
+        .flat_map(|feature| dependencies(*feature))
+        .map(|dep| (dep, false));
+    let optional = gated_features
+        .iter()
+        .flat_map(|feature| dependencies(*feature))
+        .map(|dep| (dep, true));
+    for ((name, version, dep_features), optional) in required.chain(ungated).chain(optional) {
+        if seen.contains(name) {
+            continue;
+        }
+        seen.push(name);
+        let no_default = NO_DEFAULT_FEATURES.contains(name);
+        if dep_features.is_empty() && !optional && !no_default {
+            contents.push_str(&format!("{} = {:?}\n", name, version));
+            continue;
+        }
+        contents.push_str(&format!("{} = {{ version = {:?}", name, version));
+        if no_default {
+            contents.push_str(", default-features = false");
+        }
+        if !dep_features.is_empty() {
+            contents.push_str(&format!(", features = {:?}", dep_features));
+        }
+        if optional {
+            contents.push_str(", optional = true");
+        }
+        contents.push_str(" }\n");
+    }
+    if uses_common {
+        contents.push_str(&format!(
+            "{} = {{ path = \"../{}\" }}\n",
+            crate_name(dedupe::COMMON_MODULE),
+            dedupe::COMMON_MODULE
+        ));
+    }
+    if !gated_features.is_empty() {
+        contents.push_str("\n[features]\n");
+        for feature in gated_features.iter() {
+            let mut enables: Vec<String> = dependencies(*feature)
+                .map(|(name, _, _)| format!("dep:{}", name))
+                .collect();
+            match feature {
+                Feature::Localnet => enables.push("client".to_string()),
+                Feature::Kafka | Feature::Http => enables.push("serde".to_string()),
+                Feature::Serde => {
+                    if uses_common {
+                        enables.push(format!("{}/serde", crate_name(dedupe::COMMON_MODULE)));
+                    }
+                    if !config.flags.is_empty() {
+                        enables.push("bitflags/serde".to_string());
+                    }
+                }
+                _ => {}
+            }
+            contents.push_str(&format!("{} = {:?}\n", feature.name(), enables));
+        }
+    }
+    std::fs::create_dir_all(dir.join("src"))?;
+    write_atomic(&manifest, contents.as_bytes())?;
+    Ok(())
+}
+
+/// Adds the `members` missing from the `[workspace]` of a Cargo.toml,
+/// directly or through a `<dir>/*` member, creating it if needed. An
+/// existing members array is edited in place, keeping the formatting of the
+/// manifest.
+pub fn add_workspace_members(manifest: &Path, members: &[String]) -> Result<()> {
+    let mut table = if manifest.is_file() {
+        read_manifest(manifest)?
+    } else {
+        toml::Table::new()
+    };
+    let listed: Vec<_> = table
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|m| m.iter().filter_map(|m| m.as_str()).collect())
+        .unwrap_or_default();
+    let missing: Vec<_> = members
+        .iter()
+        .filter(|member| !listed.iter().any(|listed| covers(listed, member)))
+        .cloned()
+        .collect();
+    if manifest.is_file() && missing.is_empty() {
+        return Ok(());
+    }
+    if !listed.is_empty() {
+        let contents = std::fs::read_to_string(manifest)?;
+        if let Some(contents) = insert_members(&contents, &missing) {
+            write_atomic(manifest, contents.as_bytes())?;
+            return Ok(());
+        }
+    }
+    let workspace = table
+        .entry("workspace")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let Some(workspace) = workspace.as_table_mut() else {
+        return Err(ParseIdlError::Workspace(format!(
+            "`workspace` of {} is not a table",
+            manifest.display()
+        )));
+    };
+    workspace.entry("resolver").or_insert_with(|| "2".into());
+    let existing = workspace
+        .entry("members")
+        .or_insert_with(|| toml::Value::Array(vec![]));
+    let Some(existing) = existing.as_array_mut() else {
+        return Err(ParseIdlError::Workspace(format!(
+            "`workspace.members` of {} is not an array",
+            manifest.display()
+        )));
+    };
+    for member in missing {
+        existing.push(member.into());
+    }
+    write_atomic(manifest, table.to_string().as_bytes())?;
+    Ok(())
+}
+
+/// Whether the workspace member `listed`, a directory or `<dir>/*`, covers
+/// the crate directory `member`.
+fn covers(listed: &str, member: &str) -> bool {
+    match listed.strip_suffix("/*") {
+        Some(parent) => Path::new(member).parent() == Some(Path::new(parent)),
+        None => Path::new(listed) == Path::new(member),
+    }
+}
+
+/// `contents` of a manifest with `members` appended to the members array of
+/// its `[workspace]`, in the style of the array, `None` if it isn't found or
+/// the edit doesn't parse.
+fn insert_members(contents: &str, members: &[String]) -> Option<String> {
+    let mut offset = 0;
+    let mut in_workspace = false;
+    let mut open = None;
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('[') {
+            in_workspace = trimmed.starts_with("[workspace]");
+        } else if in_workspace && trimmed.starts_with("members") {
+            open = Some(offset + line.find('[')? + 1);
+            break;
+        }
+        offset += line.len();
+    }
+    let open = open?;
+    let close = open + array_end(&contents[open..])?;
+    let items = &contents[open..close];
+    let entries: Vec<_> = members.iter().map(|m| format!("{:?}", m)).collect();
+    let (at, inserted) = match items.rfind('\n') {
+        // one member per line, indented as the first one
+        Some(last_line) => {
+            let indent = items
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .map(|l| &l[..l.len() - l.trim_start().len()])
+                .unwrap_or("    ");
+            let lines: String = entries
+                .iter()
+                .map(|e| format!("{}{},\n", indent, e))
+                .collect();
+            (open + last_line + 1, lines)
+        }
+        None if items.trim().is_empty() => (close, entries.join(", ")),
+        None if items.trim_end().ends_with(',') => (close, format!(" {}", entries.join(", "))),
+        None => (
+            open + items.trim_end().len(),
+            format!(", {}", entries.join(", ")),
+        ),
+    };
+    let edited = format!("{}{}{}", &contents[..at], inserted, &contents[at..]);
+    toml::from_str::<toml::Table>(&edited).ok()?;
+    Some(edited)
+}
+
+/// Offset of the `]` closing the array whose items start `items`.
+fn array_end(items: &str) -> Option<usize> {
+    let (mut in_string, mut in_comment) = (false, false);
+    for (i, c) in items.char_indices() {
+        match c {
+            '\n' => in_comment = false,
+            _ if in_comment => {}
+            '"' => in_string = !in_string,
+            '#' if !in_string => in_comment = true,
+            ']' if !in_string => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Adds a path dependency on the crate in `dependency` to the Cargo.toml of
+/// the crate in `dir`, unless it depends on it already. The line is added
+/// at the top of its `[dependencies]`, keeping the formatting of the
+/// manifest.
+pub fn add_path_dependency(dir: &Path, dependency: &Path) -> Result<()> {
+    let manifest = dir.join("Cargo.toml");
+    let dependency_manifest = dependency.join("Cargo.toml");
+    let Some(name) = read_manifest(&dependency_manifest)?
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string)
+    else {
+        return Err(ParseIdlError::Workspace(format!(
+            "{} has no package name",
+            dependency_manifest.display()
+        )));
+    };
+    if read_manifest(&manifest)?
+        .get("dependencies")
+        .and_then(|d| d.as_table())
+        .is_some_and(|d| d.contains_key(&name))
+    {
+        return Ok(());
+    }
+    let path = relative_path(&dir.canonicalize()?, &dependency.canonicalize()?);
+    let line = format!("{} = {{ path = {:?} }}", name, path.to_string_lossy());
+    let contents = insert_dependency(&std::fs::read_to_string(&manifest)?, &line);
+    write_atomic(&manifest, contents.as_bytes())?;
+    Ok(())
+}
+
+/// The manifest `contents` with the dependency `line` added at the top of
+/// its `[dependencies]`, a table appended for it if there is none. The
+/// header is matched line by line, whatever its spacing, trailing comment
+/// or line endings, which the added lines follow.
+fn insert_dependency(contents: &str, line: &str) -> String {
+    let newline = if contents.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut contents = contents.to_string();
+    let mut end = 0;
+    for header in contents.split_inclusive('\n') {
+        end += header.len();
+        let table = header.split('#').next().unwrap_or_default().trim();
+        if table == "[dependencies]" {
+            let mut insert = format!("{}{}", line, newline);
+            if !header.ends_with('\n') {
+                insert.insert_str(0, newline);
+            }
+            contents.insert_str(end, &insert);
+            return contents;
+        }
+    }
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push_str(newline);
+    }
+    contents.push_str(&format!("{0}[dependencies]{0}{1}{0}", newline, line));
+    contents
+}
+
+/// `to` relative to `from`, both absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut path: PathBuf = from.components().skip(common).map(|_| "..").collect();
+    path.extend(to.components().skip(common));
+    path
+}
+
+/// Module name of a generated file, `<name>.rs` or `<name>/mod.rs`.
+fn module_name(path: &Path) -> Option<String> {
+    let path = match path.file_stem()? {
+        stem if stem == "mod" => path.parent()?,
+        _ => path,
+    };
+    Some(path.file_stem()?.to_string_lossy().to_string())
+}
+
+/// Nearest directory containing an `Anchor.toml`.
+pub fn find_anchor_workspace(start: &Path) -> Result<PathBuf> {
+    for dir in start.ancestors() {
+        if dir.join("Anchor.toml").is_file() {
+            return Ok(dir.to_path_buf());
+        }
+    }
+    Err(ParseIdlError::Workspace(format!(
+        "no Anchor.toml found in {} or its parents",
+        start.display()
+    )))
+}
+
+/// Names of the programs of an Anchor workspace.
+///
+/// Program names come from the `[programs.<cluster>]` tables, falling back to
+/// the directories listed in `[workspace] members`.
+pub fn anchor_programs(manifest: &toml::Table, root: &Path) -> Result<Vec<String>> {
+    let mut programs = vec![];
+
+    if let Some(clusters) = manifest.get("programs").and_then(|p| p.as_table()) {
+        for cluster in clusters.values().filter_map(|c| c.as_table()) {
+            for name in cluster.keys() {
+                let name = name.to_snake_case();
+                if !programs.contains(&name) {
+                    programs.push(name);
+                }
+            }
+        }
+    }
+
+    if programs.is_empty() {
+        let members = manifest
+            .get("workspace")
+            .and_then(|w| w.get("members"))
+            .and_then(|m| m.as_array())
+            .map(|m| m.iter().filter_map(|m| m.as_str()).collect::<Vec<_>>())
+            .unwrap_or_else(|| vec!["programs/*"]);
+        for member in members {
+            let dirs = match member.strip_suffix("/*") {
+                Some(parent) => root
+                    .join(parent)
+                    .read_dir()?
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .collect(),
+                None => vec![root.join(member)],
+            };
+            for dir in dirs {
+                if let Some(name) = dir.file_name().and_then(|n| n.to_str()) {
+                    programs.push(name.to_snake_case());
+                }
+            }
+        }
+    }
+
+    if programs.is_empty() {
+        return Err(ParseIdlError::Workspace(
+            "Anchor.toml does not list any program".to_string(),
+        ));
+    }
+    programs.sort();
+    Ok(programs)
+}
+
+/// Manifest of the cargo workspace enclosing `start`: the nearest Cargo.toml
+/// with a `[workspace]` in it or its parents.
+pub fn find_cargo_workspace(start: &Path) -> Result<Option<PathBuf>> {
+    for dir in start.ancestors() {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.is_file() && read_manifest(&manifest)?.contains_key("workspace") {
+            return Ok(Some(manifest));
+        }
+    }
+    Ok(None)
+}
+
+/// Source directory of the `bindings` member (e.g. crates/bindings) of the
+/// cargo workspace enclosing `start`, if there is one.
+pub fn find_cargo_bindings_dir(start: &Path) -> Result<Option<PathBuf>> {
+    for dir in start.ancestors() {
+        let manifest = dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            continue;
+        }
+        let manifest = read_manifest(&manifest)?;
+        let Some(members) = manifest
+            .get("workspace")
+            .and_then(|w| w.get("members"))
+            .and_then(|m| m.as_array())
+        else {
+            continue;
+        };
+        for member in members.iter().filter_map(|m| m.as_str()) {
+            let candidates = match member.strip_suffix("/*") {
+                Some(parent) => vec![dir.join(parent).join("bindings")],
+                None => vec![dir.join(member)],
+            };
+            for candidate in candidates {
+                if candidate.file_name().is_some_and(|n| n == "bindings")
+                    && candidate.join("Cargo.toml").is_file()
+                {
+                    return Ok(Some(candidate.join("src")));
+                }
+            }
+        }
+        return Ok(None);
+    }
+    Ok(None)
+}
+
+/// Appends the missing `pub mod` declarations to a lib.rs.
+pub fn add_lib_modules(lib_rs: &Path, modules: &[String]) -> Result<()> {
+    let decls: Vec<_> = modules.iter().map(|m| format!("pub mod {};", m)).collect();
+    add_lib_lines(lib_rs, &decls)
+}
+
+/// Appends the `lines` missing from `lib_rs`, creating it if needed.
+fn add_lib_lines(lib_rs: &Path, lines: &[String]) -> Result<()> {
+    let mut content = if lib_rs.is_file() {
+        std::fs::read_to_string(lib_rs)?
+    } else {
+        String::new()
+    };
+    for line in lines {
+        if !content.lines().any(|l| l.trim() == line) {
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+    write_atomic(lib_rs, content.as_bytes())?;
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<toml::Table> {
+    toml::from_str(&std::fs::read_to_string(path)?).map_err(|source| {
+        ParseIdlError::InvalidManifest {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = "demo-bindings = { path = \"../demo\" }";
+
+    #[test]
+    fn dependencies_go_at_the_top_of_their_table() {
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\nserde = \"1\"\n";
+        assert_eq!(
+            insert_dependency(manifest, LINE),
+            format!(
+                "[package]\nname = \"app\"\n\n[dependencies]\n{}\nserde = \"1\"\n",
+                LINE
+            )
+        );
+    }
+
+    #[test]
+    fn crlf_manifests_keep_their_line_endings() {
+        let manifest = "[package]\r\nname = \"app\"\r\n\r\n[dependencies]\r\nserde = \"1\"\r\n";
+        let expected = format!(
+            "[package]\r\nname = \"app\"\r\n\r\n[dependencies]\r\n{}\r\nserde = \"1\"\r\n",
+            LINE
+        );
+        let inserted = insert_dependency(manifest, LINE);
+        assert_eq!(inserted, expected);
+        let parsed: toml::Table = toml::from_str(&inserted).unwrap();
+        assert!(parsed["dependencies"].get("demo-bindings").is_some());
+    }
+
+    #[test]
+    fn headers_with_comments_are_found() {
+        let manifest = "[package]\nname = \"app\"\n\n [dependencies] # runtime\nserde = \"1\"";
+        let inserted = insert_dependency(manifest, LINE);
+        assert_eq!(inserted.matches("[dependencies]").count(), 1);
+        let parsed: toml::Table = toml::from_str(&inserted).unwrap();
+        assert!(parsed["dependencies"].get("demo-bindings").is_some());
+    }
+
+    #[test]
+    fn a_table_is_appended_without_one() {
+        let manifest = "[package]\nname = \"app\"";
+        assert_eq!(
+            insert_dependency(manifest, LINE),
+            format!("[package]\nname = \"app\"\n\n[dependencies]\n{}\n", LINE)
+        );
+    }
+}
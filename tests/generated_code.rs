@@ -0,0 +1,45 @@
+//! Checks that generated code compiles: the golden files of the types and
+//! of the builders are included as modules, building against the
+//! anchor-lang, borsh and thiserror of this crate. Unlike the crates of
+//! `generated_crates.rs` they need nothing fetched, so they always run.
+
+// most of the bindings are only compiled here
+#[allow(dead_code)]
+mod types {
+    include!("../fixtures/legacy.rs");
+}
+
+#[allow(dead_code)]
+mod builders {
+    include!("../fixtures/legacy.builders.rs");
+}
+
+use anchor_lang::prelude::Pubkey;
+
+#[test]
+fn builders_encode_instructions() {
+    use builders::*;
+
+    let accounts = SwapAccounts {
+        pool: Pubkey::new_unique(),
+        user: Pubkey::new_unique(),
+    };
+    let ix = swap_ix(&accounts, &Swap::new(5, 4, Side::Ask));
+    assert_eq!(ix.program_id, PROGRAM_ID);
+    assert_eq!(instruction_name(&ix.data), Some("swap"));
+    assert_eq!(
+        ix.data[8..],
+        [&5u64.to_le_bytes()[..], &4u64.to_le_bytes(), &[1]].concat()
+    );
+    assert_eq!(ix.accounts.len(), 2);
+    assert!(ix.accounts[0].is_writable && !ix.accounts[0].is_signer);
+    assert!(ix.accounts[1].is_signer);
+}
+
+#[test]
+fn types_decode_instructions() {
+    let data = [&[248, 198, 158, 145, 225, 117, 135, 200][..], &[0; 17]].concat();
+    assert_eq!(types::instruction_name(&data), Some("swap"));
+    let args: types::Swap = borsh::BorshDeserialize::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(borsh::BorshSerialize::try_to_vec(&args).unwrap(), data[8..]);
+}
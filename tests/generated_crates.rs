@@ -0,0 +1,176 @@
+//! Checks that generated crates build: the crates of a fixture generated
+//! with a config go through `cargo check --all-features`. They fetch and
+//! build the solana crates, so they are ignored by default, run them with
+//! `cargo test -- --ignored`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use parse_idl::{
+    add_lib_modules, generate_crates, generate_files, Artifact, Decimals, GeneratorConfig,
+    Visibility,
+};
+
+#[test]
+#[ignore = "fetches and builds the solana crates"]
+fn indexer_crate_checks() {
+    check(
+        "legacy",
+        "indexer",
+        GeneratorConfig::builder().indexer(true).build(),
+    );
+}
+
+#[test]
+#[ignore = "fetches and builds the solana crates"]
+fn grpc_crate_checks() {
+    check(
+        "legacy",
+        "grpc",
+        GeneratorConfig::builder()
+            .grpc_service(true)
+            .feature_gates(true)
+            .build(),
+    );
+}
+
+#[test]
+#[ignore = "fetches and builds the solana crates"]
+fn client_crate_checks() {
+    check(
+        "legacy",
+        "client",
+        GeneratorConfig::builder()
+            .builders(true)
+            .constructors(true)
+            .event_decoder(true)
+            .client(true)
+            .cli(true)
+            .localnet(true)
+            .account_scans(true)
+            .account_watchers(true)
+            .event_stream(true)
+            .kafka_sink(true)
+            .http_service(true)
+            .tracing(true)
+            .metrics(true)
+            .prelude(true)
+            .anchor_traits(true)
+            .account_diffs(true)
+            .flags("feeRate", "FeeFlags", [("DISCOUNTED", 0), ("CAPPED", 1)])
+            .timestamp("*_out")
+            .pubkey_newtype("authority", "Authority")
+            .amount("amountIn", Decimals::Constant(6))
+            .feature_gates(true)
+            .build(),
+    );
+}
+
+#[test]
+#[ignore = "fetches and builds the solana crates"]
+fn decoder_crate_checks() {
+    check(
+        "legacy",
+        "decoder",
+        GeneratorConfig::builder()
+            .artifacts([
+                Artifact::ProgramId,
+                Artifact::Discriminators,
+                Artifact::Types,
+                Artifact::Events,
+                Artifact::Errors,
+            ])
+            .event_decoder(true)
+            .indexer(true)
+            .account_scans(true)
+            .account_watchers(true)
+            .kafka_sink(true)
+            .http_service(true)
+            .feature_gates(true)
+            .build(),
+    );
+}
+
+#[test]
+#[ignore = "fetches and builds the solana crates"]
+fn side_by_side_modules_check() {
+    check_modules(
+        "legacy",
+        "side_by_side",
+        GeneratorConfig::builder()
+            .builders(true)
+            .client(true)
+            .event_decoder(true)
+            .field_visibility(Visibility::Public)
+            .conversions(true)
+            .shared_support(true)
+            .prelude(true)
+            .build(),
+    );
+}
+
+/// Generates the crates of `fixtures/<fixture>.json` with `config` into a
+/// workspace of its own, named `name`, and checks them.
+fn check(fixture: &str, name: &str, config: GeneratorConfig) {
+    let root = workspace(name);
+    generate_crates(&[fixture_idl(fixture)], &root, &config).unwrap();
+    cargo_check(&root);
+}
+
+/// Generates the modules of two copies of `fixtures/<fixture>.json`, `v1`
+/// and `v2`, side by side with `config` into the crate of the fixture, in
+/// place of its module, and checks it. Covers what is only generated next
+/// to other modules, such as the conversions and the support module.
+fn check_modules(fixture: &str, name: &str, config: GeneratorConfig) {
+    let root = workspace(name);
+    let idl = fixture_idl(fixture);
+    // the crate of a single program for its manifest
+    let crates = generate_crates(std::slice::from_ref(&idl), &root, &config).unwrap();
+    let src = crates[0].join("src");
+    std::fs::remove_dir_all(&src).unwrap();
+    let idls: Vec<_> = ["v1", "v2"]
+        .iter()
+        .map(|version| {
+            let copy = root.join(version).with_extension("json");
+            std::fs::copy(&idl, &copy).unwrap();
+            copy
+        })
+        .collect();
+    let modules: Vec<_> = generate_files(&idls, &src, &config)
+        .unwrap()
+        .iter()
+        .map(|path| path.file_stem().unwrap().to_string_lossy().to_string())
+        .collect();
+    add_lib_modules(&src.join("lib.rs"), &modules).unwrap();
+    cargo_check(&root);
+}
+
+/// An empty workspace of its own named `name`, not a member of the
+/// enclosing one.
+fn workspace(name: &str) -> PathBuf {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("generated_crates")
+        .join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Cargo.toml"), "[workspace]\nresolver = \"2\"\n").unwrap();
+    root
+}
+
+fn fixture_idl(fixture: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(fixture)
+        .with_extension("json")
+}
+
+fn cargo_check(root: &Path) {
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let status = Command::new(env!("CARGO"))
+        .args(["check", "--workspace", "--all-features"])
+        .current_dir(root)
+        .env("CARGO_TARGET_DIR", tmp.join("generated_crates_target"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "cargo check failed in {}", root.display());
+}
@@ -0,0 +1,172 @@
+//! Golden-file tests: every `fixtures/<name>.json` is generated with the
+//! default config and compared with `fixtures/<name>.rs`, or with
+//! `fixtures/<name>.err` for IDLs the generator rejects. The [`CONFIGS`]
+//! generate their fixture into `fixtures/<name>.<config>.rs`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the expected files after an
+//! intended output change.
+
+use std::path::{Path, PathBuf};
+
+use parse_idl::{generate_from_json, Artifact, GeneratorConfig};
+
+/// A fixture, the name of a config and the config it is generated with.
+type Config = (&'static str, &'static str, fn() -> GeneratorConfig);
+
+/// The non-default configs by name, with the fixture they generate. Those
+/// emitting only the types emit all of them.
+const CONFIGS: &[Config] = &[
+    ("zero_copy", "human_readable", || {
+        GeneratorConfig::builder()
+            .artifacts([Artifact::Types])
+            .feature_gates(true)
+            .human_readable_serde(true)
+            .build()
+    }),
+    ("legacy", "types_only", || {
+        GeneratorConfig::builder()
+            .artifacts([Artifact::ProgramId, Artifact::Types])
+            .builders(true)
+            .client(true)
+            .build()
+    }),
+    ("legacy", "decoder_only", || {
+        GeneratorConfig::builder()
+            .artifacts([Artifact::ProgramId, Artifact::Discriminators])
+            .cli(true)
+            .build()
+    }),
+    ("legacy", "builders", || {
+        GeneratorConfig::builder().builders(true).build()
+    }),
+    ("legacy", "event_decoder", || {
+        GeneratorConfig::builder().event_decoder(true).build()
+    }),
+    ("legacy", "borrowed", || {
+        GeneratorConfig::builder().borrowed(true).build()
+    }),
+    ("legacy", "streaming", || {
+        GeneratorConfig::builder().streaming(true).build()
+    }),
+    ("legacy", "client", || {
+        GeneratorConfig::builder().client(true).build()
+    }),
+    ("legacy", "indexer", || {
+        GeneratorConfig::builder().indexer(true).build()
+    }),
+    ("legacy", "grpc", || {
+        GeneratorConfig::builder().grpc_service(true).build()
+    }),
+];
+
+#[test]
+fn golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+    let mut failures = vec![];
+    for fixture in fixtures.iter() {
+        compare(
+            fixture,
+            "",
+            &GeneratorConfig::default(),
+            update,
+            &mut failures,
+        );
+    }
+    for (name, config_name, config) in CONFIGS {
+        let fixture = dir.join(name).with_extension("json");
+        let suffix = format!("{}.", config_name);
+        compare(&fixture, &suffix, &config(), update, &mut failures);
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// Generates `fixture` with `config` and compares it with the expected file,
+/// `<name>.<suffix>rs` or `<name>.<suffix>err`.
+fn compare(
+    fixture: &Path,
+    suffix: &str,
+    config: &GeneratorConfig,
+    update: bool,
+    failures: &mut Vec<String>,
+) {
+    let idl = std::fs::read(fixture).unwrap();
+    let (actual, expected_path) = match generate_from_json(&idl, config) {
+        Ok(code) => (code, fixture.with_extension(format!("{}rs", suffix))),
+        Err(e) => (
+            error_chain(&e) + "\n",
+            fixture.with_extension(format!("{}err", suffix)),
+        ),
+    };
+
+    if update {
+        std::fs::write(&expected_path, &actual).unwrap();
+        return;
+    }
+    match std::fs::read_to_string(&expected_path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) => failures.push(format!(
+            "{} changed:\n{}",
+            expected_path.display(),
+            diff(&expected, &actual)
+        )),
+        Err(_) => failures.push(format!(
+            "{} is missing, run with UPDATE_GOLDEN=1 to create it",
+            expected_path.display()
+        )),
+    }
+}
+
+fn error_chain(e: &dyn std::error::Error) -> String {
+    let mut msg = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        msg.push_str(": ");
+        msg.push_str(&e.to_string());
+        source = e.source();
+    }
+    msg
+}
+
+/// Line diff of the longest common subsequence, `-` expected, `+` actual.
+fn diff(expected: &str, actual: &str) -> String {
+    let a: Vec<&str> = expected.lines().collect();
+    let b: Vec<&str> = actual.lines().collect();
+
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("{:>5} - {}\n", i + 1, a[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("{:>5} + {}\n", j + 1, b[j]));
+            j += 1;
+        }
+    }
+    out
+}